@@ -0,0 +1,267 @@
+// src/core/audio.rs
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+/// Min/max sample pairs for an audio file, used to draw waveforms without
+/// touching the samples every frame
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AudioPeaks {
+    pub sample_rate: u32,
+    /// (min, max) per bucket of `FRAMES_PER_BUCKET` frames, all channels mixed
+    pub buckets: Vec<(f32, f32)>,
+    /// Frames where the mixed signal changes sign, in order
+    pub zero_crossings: Vec<usize>,
+}
+
+impl AudioPeaks {
+    /// Fine enough for deep zoom; coarser views merge buckets when drawing
+    pub const FRAMES_PER_BUCKET: usize = 256;
+
+    pub fn from_wav(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let AudioBuffer {
+            sample_rate,
+            channels,
+            samples,
+        } = AudioBuffer::from_wav(path)?;
+
+        let buckets = samples
+            .chunks(Self::FRAMES_PER_BUCKET * channels)
+            .map(|chunk| {
+                chunk.iter().fold((f32::MAX, f32::MIN), |(min, max), &s| {
+                    (min.min(s), max.max(s))
+                })
+            })
+            .collect();
+
+        let mut zero_crossings = Vec::new();
+        let mut previous = 0.0;
+        for (frame, samples) in samples.chunks(channels).enumerate() {
+            let mixed: f32 = samples.iter().sum();
+            if frame > 0 && (previous < 0.0) != (mixed < 0.0) {
+                zero_crossings.push(frame);
+            }
+            previous = mixed;
+        }
+
+        Ok(Self {
+            sample_rate,
+            buckets,
+            zero_crossings,
+        })
+    }
+
+    /// Zero-crossing closest to `time` (seconds from the file start), if one lies within
+    /// `max_distance` seconds
+    pub fn nearest_zero_crossing(&self, time: f64, max_distance: f64) -> Option<f64> {
+        let sample_rate = self.sample_rate.max(1) as f64;
+        let frame = (time.max(0.0) * sample_rate).round() as usize;
+        let index = self.zero_crossings.partition_point(|&f| f < frame);
+
+        [index.checked_sub(1), Some(index)]
+            .into_iter()
+            .flatten()
+            .filter_map(|i| self.zero_crossings.get(i))
+            .map(|&f| f as f64 / sample_rate)
+            .min_by(|a, b| (a - time).abs().total_cmp(&(b - time).abs()))
+            .filter(|crossing| (crossing - time).abs() <= max_distance)
+    }
+
+    pub fn seconds_per_bucket(&self) -> f64 {
+        Self::FRAMES_PER_BUCKET as f64 / self.sample_rate.max(1) as f64
+    }
+
+    /// Combined min/max of the buckets covering a time range (seconds from the file start)
+    pub fn range(&self, start: f64, end: f64) -> Option<(f32, f32)> {
+        let bucket_duration = self.seconds_per_bucket();
+        let first = (start.max(0.0) / bucket_duration).floor() as usize;
+        let last = ((end / bucket_duration).ceil() as usize)
+            .max(first + 1)
+            .min(self.buckets.len());
+
+        self.buckets.get(first..last).and_then(|buckets| {
+            buckets
+                .iter()
+                .copied()
+                .reduce(|(min, max), (b_min, b_max)| (min.min(b_min), max.max(b_max)))
+        })
+    }
+}
+
+/// Builds waveform overviews on background threads, so opening a long recording
+/// doesn't freeze the UI while its samples are decoded
+#[derive(Default)]
+pub struct PeakLoader {
+    pending: HashMap<PathBuf, Receiver<Result<AudioPeaks, String>>>,
+    loaded: HashMap<PathBuf, Arc<AudioPeaks>>,
+}
+
+impl PeakLoader {
+    /// Peaks for a file, once they are built. A file that failed to load gets empty peaks.
+    pub fn get(&self, path: &Path) -> Option<Arc<AudioPeaks>> {
+        self.loaded.get(path).cloned()
+    }
+
+    /// Start building peaks for a file, unless they are built or being built already
+    pub fn request(&mut self, path: &Path) {
+        if self.loaded.contains_key(path) || self.pending.contains_key(path) {
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let file = path.to_path_buf();
+        thread::spawn(move || {
+            // The receiver is gone if the loader was dropped; nobody needs the result then
+            let _ = sender.send(AudioPeaks::from_wav(&file).map_err(|e| e.to_string()));
+        });
+        self.pending.insert(path.to_path_buf(), receiver);
+    }
+
+    /// Collect finished builds, returning the files that failed with their errors
+    pub fn poll(&mut self) -> Vec<(PathBuf, String)> {
+        let mut failed = Vec::new();
+        let mut finished = Vec::new();
+        for (path, receiver) in &self.pending {
+            let peaks = match receiver.try_recv() {
+                Ok(Ok(peaks)) => peaks,
+                Ok(Err(e)) => {
+                    failed.push((path.clone(), e));
+                    AudioPeaks::default()
+                }
+                Err(TryRecvError::Empty) => continue,
+                Err(TryRecvError::Disconnected) => {
+                    failed.push((path.clone(), "Waveform loader stopped".to_string()));
+                    AudioPeaks::default()
+                }
+            };
+            finished.push((path.clone(), peaks));
+        }
+
+        for (path, peaks) in finished {
+            self.pending.remove(&path);
+            self.loaded.insert(path, Arc::new(peaks));
+        }
+        failed
+    }
+
+    /// Whether any file is still being read
+    pub fn is_busy(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+/// Decoded samples of an audio file, interleaved and scaled to -1.0..1.0
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AudioBuffer {
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub samples: Vec<f32>,
+}
+
+impl AudioBuffer {
+    pub fn from_wav(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut reader = hound::WavReader::open(path)?;
+        let spec = reader.spec();
+
+        let samples: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1_i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|s| s.map(|s| s as f32 * scale))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+
+        Ok(Self {
+            sample_rate: spec.sample_rate,
+            channels: spec.channels.max(1) as usize,
+            samples,
+        })
+    }
+
+    /// Sample of `channel` at a time in seconds from the file start, or silence outside
+    /// the file. Mono files feed every channel.
+    pub fn sample_at(&self, time: f64, channel: usize) -> f32 {
+        if time < 0.0 {
+            return 0.0;
+        }
+        let frame = (time * self.sample_rate as f64) as usize;
+        let channel = channel.min(self.channels.saturating_sub(1));
+        self.samples
+            .get(frame * self.channels + channel)
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("supersaw-{}-{}", uuid::Uuid::new_v4(), name))
+    }
+
+    fn write_wav(path: &Path, samples: &[f32]) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 44_100,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    fn wait_for(loader: &mut PeakLoader) -> Vec<(PathBuf, String)> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut failed = Vec::new();
+        while loader.is_busy() {
+            assert!(Instant::now() < deadline, "peaks never finished");
+            failed.extend(loader.poll());
+            thread::sleep(Duration::from_millis(5));
+        }
+        failed
+    }
+
+    #[test]
+    fn background_peaks_match_a_direct_read() {
+        let path = temp_path("sine.wav");
+        let samples: Vec<f32> = (0..10_000).map(|i| (i as f32 * 0.05).sin()).collect();
+        write_wav(&path, &samples);
+
+        let mut loader = PeakLoader::default();
+        loader.request(&path);
+        assert!(loader.get(&path).is_none());
+        let failed = wait_for(&mut loader);
+
+        let expected = AudioPeaks::from_wav(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(failed.is_empty());
+        assert_eq!(*loader.get(&path).unwrap(), expected);
+    }
+
+    #[test]
+    fn unreadable_files_fail_once_with_empty_peaks() {
+        let path = temp_path("missing.wav");
+        let mut loader = PeakLoader::default();
+        loader.request(&path);
+        let failed = wait_for(&mut loader);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, path);
+        assert!(loader.get(&path).unwrap().buckets.is_empty());
+
+        // Asking again doesn't retry the read
+        loader.request(&path);
+        assert!(!loader.is_busy());
+    }
+}
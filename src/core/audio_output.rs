@@ -0,0 +1,205 @@
+// src/core/audio_output.rs
+use crate::core::Synth;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "audio")]
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+#[cfg(feature = "audio")]
+use std::time::Instant;
+
+/// How quickly the reported callback load follows the latest buffer
+#[cfg(feature = "audio")]
+const LOAD_SMOOTHING: f32 = 0.1;
+
+/// Seconds for the peak and RMS readings to fall by a factor of e
+#[cfg(feature = "audio")]
+const LEVEL_FALLOFF: f32 = 0.3;
+
+/// Peak and RMS level of the left and right output channels, written by the audio
+/// thread and read by the UI
+#[derive(Debug, Default)]
+pub struct OutputLevels {
+    peak: [AtomicU32; 2],
+    rms: [AtomicU32; 2],
+    clipped: AtomicBool,
+}
+
+impl OutputLevels {
+    pub fn peak(&self, channel: usize) -> f32 {
+        f32::from_bits(self.peak[channel].load(Ordering::Relaxed))
+    }
+
+    pub fn rms(&self, channel: usize) -> f32 {
+        f32::from_bits(self.rms[channel].load(Ordering::Relaxed))
+    }
+
+    /// Whether any sample exceeded full scale since the last `reset_clip`
+    pub fn clipped(&self) -> bool {
+        self.clipped.load(Ordering::Relaxed)
+    }
+
+    pub fn reset_clip(&self) {
+        self.clipped.store(false, Ordering::Relaxed);
+    }
+
+    /// Fold an interleaved buffer into the readings, letting older levels decay
+    #[cfg(feature = "audio")]
+    fn measure(&self, buffer: &[f32], channels: usize, sample_rate: f32) {
+        let channels = channels.max(1);
+        let frames = buffer.len() / channels;
+        if frames == 0 {
+            return;
+        }
+        let decay = (-(frames as f32 / sample_rate) / LEVEL_FALLOFF).exp();
+
+        for side in 0..2 {
+            // Mono output feeds both sides of the meter
+            let channel = side.min(channels - 1);
+            let (peak, sum_squares) = buffer
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .fold((0.0f32, 0.0f32), |(peak, sum), s| {
+                    (peak.max(s.abs()), sum + s * s)
+                });
+            let rms = (sum_squares / frames as f32).sqrt();
+
+            if peak > 1.0 {
+                self.clipped.store(true, Ordering::Relaxed);
+            }
+            let peak = peak.max(self.peak(side) * decay);
+            let rms = rms.max(self.rms(side) * decay);
+            self.peak[side].store(peak.to_bits(), Ordering::Relaxed);
+            self.rms[side].store(rms.to_bits(), Ordering::Relaxed);
+        }
+    }
+}
+
+/// Plays the internal synth through the default audio output device
+pub struct AudioOutput {
+    synth: Arc<Mutex<Synth>>,
+    /// Smoothed share of the buffer time spent rendering, stored as `f32` bits
+    load: Arc<AtomicU32>,
+    levels: Arc<OutputLevels>,
+    #[cfg(feature = "audio")]
+    _stream: cpal::Stream,
+}
+
+impl AudioOutput {
+    #[cfg(feature = "audio")]
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let host = cpal::default_host();
+        let device = host
+            .default_output_device()
+            .ok_or("No audio output device available")?;
+        let config = device.default_output_config()?;
+        let sample_rate = config.sample_rate().0 as f32;
+        let synth = Arc::new(Mutex::new(Synth::new(sample_rate)));
+        let load = Arc::new(AtomicU32::new(0));
+        let levels = Arc::new(OutputLevels::default());
+
+        let format = config.sample_format();
+        let config = config.into();
+        let stream = match format {
+            cpal::SampleFormat::F32 => build_stream::<f32>(
+                &device,
+                &config,
+                synth.clone(),
+                load.clone(),
+                levels.clone(),
+            )?,
+            cpal::SampleFormat::I16 => build_stream::<i16>(
+                &device,
+                &config,
+                synth.clone(),
+                load.clone(),
+                levels.clone(),
+            )?,
+            cpal::SampleFormat::U16 => build_stream::<u16>(
+                &device,
+                &config,
+                synth.clone(),
+                load.clone(),
+                levels.clone(),
+            )?,
+            format => return Err(format!("Unsupported sample format: {}", format).into()),
+        };
+        stream.play()?;
+
+        Ok(Self {
+            synth,
+            load,
+            levels,
+            _stream: stream,
+        })
+    }
+
+    #[cfg(not(feature = "audio"))]
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        Err("Audio output is not enabled in this build".into())
+    }
+
+    pub fn synth(&self) -> &Arc<Mutex<Synth>> {
+        &self.synth
+    }
+
+    pub fn active_voices(&self) -> usize {
+        self.synth
+            .lock()
+            .map(|synth| synth.active_voices())
+            .unwrap_or(0)
+    }
+
+    pub fn levels(&self) -> &OutputLevels {
+        &self.levels
+    }
+
+    /// Fraction of each audio buffer's duration spent rendering it (1.0 means overload)
+    pub fn callback_load(&self) -> f32 {
+        f32::from_bits(self.load.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(feature = "audio")]
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    synth: Arc<Mutex<Synth>>,
+    load: Arc<AtomicU32>,
+    levels: Arc<OutputLevels>,
+) -> Result<cpal::Stream, Box<dyn Error>>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    let sample_rate = config.sample_rate.0 as f32;
+    let mut mix = Vec::new();
+
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            let started = Instant::now();
+            mix.resize(data.len(), 0.0);
+            match synth.lock() {
+                Ok(mut synth) => synth.render(&mut mix, channels),
+                Err(_) => mix.fill(0.0),
+            }
+            for (out, sample) in data.iter_mut().zip(&mix) {
+                *out = T::from_sample(*sample);
+            }
+            levels.measure(&mix, channels, sample_rate);
+
+            let buffer_duration = data.len() as f32 / channels.max(1) as f32 / sample_rate;
+            let current = started.elapsed().as_secs_f32() / buffer_duration;
+            let previous = f32::from_bits(load.load(Ordering::Relaxed));
+            let smoothed = previous + (current - previous) * LOAD_SMOOTHING;
+            load.store(smoothed.to_bits(), Ordering::Relaxed);
+        },
+        |err| eprintln!("Audio output error: {}", err),
+        None,
+    )?;
+
+    Ok(stream)
+}
@@ -1,6 +1,12 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// Share of a lane's range within which a snapped value is pulled onto a quarter mark
+const VALUE_SNAP_MAGNET: f64 = 0.03;
+
+/// Seconds between the times a CC lane is checked for a changed value during playback
+const CC_SAMPLE_INTERVAL: f64 = 0.01;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct AutomationPoint {
     pub id: String,
@@ -194,6 +200,130 @@ impl AutomationLane {
         }
     }
 
+    /// Controller number, for MIDI CC lanes
+    pub fn cc_number(&self) -> Option<u8> {
+        match self.parameter {
+            AutomationParameter::MidiCC { cc_number, .. } => Some(cc_number),
+            _ => None,
+        }
+    }
+
+    /// The lane's value at `time`, rounded to a CC value
+    pub fn cc_value_at(&self, time: f64) -> u8 {
+        self.get_value_at_time(time).round().clamp(0.0, 127.0) as u8
+    }
+
+    /// Times in [start, end) where the lane's value, rounded to a CC value, steps to a new
+    /// value, paired with that value. Checked on a fixed grid so consecutive playback
+    /// windows agree on where the steps are; the value at time zero is always included.
+    pub fn cc_steps_in_range(&self, start: f64, end: f64) -> Vec<(f64, u8)> {
+        if self.points.is_empty() || end <= start {
+            return Vec::new();
+        }
+
+        let cc_value = |time: f64| self.cc_value_at(time);
+        let mut steps = Vec::new();
+        let mut sample = (start.max(0.0) / CC_SAMPLE_INTERVAL).ceil() as u64;
+        loop {
+            let time = sample as f64 * CC_SAMPLE_INTERVAL;
+            if time >= end {
+                break;
+            }
+            let value = cc_value(time);
+            if sample == 0 || cc_value(time - CC_SAMPLE_INTERVAL) != value {
+                steps.push((time, value));
+            }
+            sample += 1;
+        }
+        steps
+    }
+
+    /// Snap a value to the nearest quarter of the lane's range when it's close to one,
+    /// and to whole numbers on lanes carrying MIDI data
+    pub fn snap_value(&self, value: f64) -> f64 {
+        let range = self.max_value - self.min_value;
+        if range <= 0.0 {
+            return self.min_value;
+        }
+        let quarter = range / 4.0;
+        let nearest_quarter =
+            self.min_value + ((value - self.min_value) / quarter).round() * quarter;
+
+        let value = if (value - nearest_quarter).abs() <= range * VALUE_SNAP_MAGNET {
+            nearest_quarter
+        } else {
+            value
+        };
+        let value = match self.parameter {
+            AutomationParameter::MidiCC { .. }
+            | AutomationParameter::Velocity
+            | AutomationParameter::PitchBend => value.round(),
+            _ => value,
+        };
+        value.clamp(self.min_value, self.max_value)
+    }
+
+    /// Ids of points that can be dropped without changing the curve by more than
+    /// `tolerance` (a share of the lane's range). A point goes when it and every point
+    /// already dropped since the last kept one lie on the straight line from that kept
+    /// point to the following one.
+    pub fn redundant_points(&self, tolerance: f64) -> Vec<String> {
+        let max_error = tolerance * (self.max_value - self.min_value);
+        let mut redundant = Vec::new();
+        let mut kept = 0;
+
+        for index in 1..self.points.len().saturating_sub(1) {
+            let anchor = &self.points[kept];
+            let next = &self.points[index + 1];
+            // Only straight segments can be merged without changing their shape
+            let straight = self.points[kept..=index]
+                .iter()
+                .all(|p| p.curve_type == CurveType::Linear);
+            let span = next.time - anchor.time;
+
+            let on_line = straight
+                && span > 0.0
+                && self.points[kept + 1..=index].iter().all(|p| {
+                    let t = (p.time - anchor.time) / span;
+                    let expected = anchor.value + (next.value - anchor.value) * t;
+                    (p.value - expected).abs() <= max_error
+                });
+
+            if on_line {
+                redundant.push(self.points[index].id.clone());
+            } else {
+                kept = index;
+            }
+        }
+
+        redundant
+    }
+
+    /// Copy another lane's points, shifted later by `offset` seconds
+    pub fn append_shifted(&mut self, other: &AutomationLane, offset: f64) {
+        self.points.extend(other.points.iter().map(|p| AutomationPoint {
+            id: Uuid::new_v4().to_string(),
+            time: p.time + offset,
+            ..p.clone()
+        }));
+        self.sort_points();
+    }
+
+    /// Move every point at or after `time` later by `duration` seconds
+    pub fn insert_gap(&mut self, time: f64, duration: f64) {
+        for point in self.points.iter_mut().filter(|p| p.time >= time) {
+            point.time += duration;
+        }
+    }
+
+    /// Drop the points in `start..end` and move later ones left to close the gap
+    pub fn remove_span(&mut self, start: f64, end: f64) {
+        self.points.retain(|p| p.time < start || p.time >= end);
+        for point in self.points.iter_mut().filter(|p| p.time >= end) {
+            point.time -= end - start;
+        }
+    }
+
     fn sort_points(&mut self) {
         self.points.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
     }
@@ -3,12 +3,12 @@ use super::DawState;
 use std::time::{Duration, Instant};
 
 pub struct CommandManager {
-    undo_stack: Vec<DawCommand>,
-    redo_stack: Vec<DawCommand>,
-    state_snapshots: Vec<StateSnapshot>,
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
     max_snapshot_count: usize,
-    last_snapshot_time: Instant,
-    time_between_snapshots: Duration,
+    /// How soon a command has to follow the last one to share its undo step
+    coalesce_window: Duration,
+    last_execute_time: Instant,
 }
 
 pub struct StateSnapshot {
@@ -17,6 +17,21 @@ pub struct StateSnapshot {
     command: DawCommand,
 }
 
+/// How to take back a command in the history
+enum UndoAction {
+    /// Command that reverses the original
+    Inverse(DawCommand),
+    /// Whole-state fallback for commands without an inverse
+    Snapshot(Box<StateSnapshot>),
+    /// Nothing was captured (snapshot evicted)
+    None,
+}
+
+struct HistoryEntry {
+    command: DawCommand,
+    undo: UndoAction,
+}
+
 impl StateSnapshot {
     pub fn from_state(state: DawState) -> Self {
         Self {
@@ -40,21 +55,19 @@ impl CommandManager {
         Self {
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
-            state_snapshots: Vec::new(),
-            last_snapshot_time: Instant::now(),
+            last_execute_time: Instant::now(),
             max_snapshot_count: 50,
-            time_between_snapshots: Duration::from_millis(120),
+            coalesce_window: Duration::from_millis(120),
         }
     }
 
-    pub fn new(max_snapshot_count: usize, time_between_snapshots: Duration) -> Self {
+    pub fn new(max_snapshot_count: usize, coalesce_window: Duration) -> Self {
         Self {
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
-            state_snapshots: Vec::new(),
-            last_snapshot_time: Instant::now(),
+            last_execute_time: Instant::now(),
             max_snapshot_count,
-            time_between_snapshots,
+            coalesce_window,
         }
     }
 
@@ -63,16 +76,39 @@ impl CommandManager {
         command: DawCommand,
         state: &mut DawState,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let name = command.name();
-
-        // Save current state before executing the command
-        self.save_snapshot(state);
+        if !command.records_history() {
+            return command.execute(state);
+        }
+        // Nothing to do, so no undo step and the redo stack stays
+        if matches!(&command, DawCommand::Batch { commands } if commands.is_empty()) {
+            return Ok(());
+        }
 
-        // Execute the command
-        command.execute(state)?;
+        let now = Instant::now();
+        let coalesce = now.duration_since(self.last_execute_time) < self.coalesce_window
+            && self.undo_stack.last().is_some_and(|entry| {
+                command.continues(&entry.command)
+                    // A step that can't be inverted can't be folded into an inverse either
+                    && (!matches!(entry.undo, UndoAction::Inverse(_))
+                        || command.has_inverse(state))
+            });
+        self.last_execute_time = now;
 
-        // Add to undo stack
-        self.undo_stack.push(command);
+        if coalesce {
+            let step_inverse = command.execute_reversible(state)?;
+            if let Some(entry) = self.undo_stack.last_mut() {
+                // A snapshot from the start of the gesture already covers every step
+                if let (UndoAction::Inverse(first), Some(step)) = (&mut entry.undo, step_inverse) {
+                    let first_inverse = std::mem::replace(first, DawCommand::NoOp);
+                    *first = first_inverse.fold(step);
+                }
+                let previous = std::mem::replace(&mut entry.command, DawCommand::NoOp);
+                entry.command = command.fold(previous);
+            }
+        } else {
+            let entry = self.run(command, state)?;
+            self.push_undo(entry);
+        }
 
         // Clear redo stack as we have a new command
         self.redo_stack.clear();
@@ -81,29 +117,51 @@ impl CommandManager {
     }
 
     pub fn undo(&mut self, state: &mut DawState) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(command) = self.undo_stack.pop() {
-            // Restore the previous state
-            if let Some(snapshot) = self.state_snapshots.pop() {
-                *state = snapshot.state;
-            }
+        if let Some(entry) = self.undo_stack.pop() {
+            let redo_entry = match entry.undo {
+                // Running the inverse gives back a command that re-applies the original
+                // exactly, including ids generated the first time around
+                UndoAction::Inverse(inverse) => match inverse.execute_reversible(state)? {
+                    Some(reapply) => HistoryEntry {
+                        command: reapply,
+                        undo: UndoAction::Inverse(inverse),
+                    },
+                    None => HistoryEntry {
+                        command: entry.command,
+                        undo: UndoAction::None,
+                    },
+                },
+                UndoAction::Snapshot(snapshot) => {
+                    // Preferences and the message log aren't part of the edit history
+                    let mut restored = snapshot.state;
+                    std::mem::swap(&mut restored.settings, &mut state.settings);
+                    std::mem::swap(&mut restored.status, &mut state.status);
+                    *state = restored;
+                    HistoryEntry {
+                        command: entry.command,
+                        undo: UndoAction::None,
+                    }
+                }
+                UndoAction::None => HistoryEntry {
+                    command: entry.command,
+                    undo: UndoAction::None,
+                },
+            };
 
-            self.redo_stack.push(command);
+            self.redo_stack.push(redo_entry);
         }
         Ok(())
     }
 
     pub fn redo(&mut self, state: &mut DawState) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(command) = self.redo_stack.pop() {
-            // Save current state before re-executing the command
-            self.save_snapshot(state);
-
-            // Re-execute the command
-            command.execute(state)?;
+        if let Some(entry) = self.redo_stack.pop() {
+            // Re-execute the command, capturing fresh undo information
+            let entry = self.run(entry.command, state)?;
 
             // Log the redo action
-            println!("Redo: {}", command.name());
+            println!("Redo: {}", entry.command.name());
 
-            self.undo_stack.push(command);
+            self.push_undo(entry);
         }
         Ok(())
     }
@@ -116,17 +174,1256 @@ impl CommandManager {
         !self.redo_stack.is_empty()
     }
 
-    fn save_snapshot(&mut self, state: &DawState) {
-        let now = Instant::now();
+    fn run(
+        &mut self,
+        command: DawCommand,
+        state: &mut DawState,
+    ) -> Result<HistoryEntry, Box<dyn std::error::Error>> {
+        // Only commands without an inverse need a copy of the whole state, and they always
+        // get one: skipping it would leave an undo step that does nothing
+        let snapshot = if command.has_inverse(state) {
+            None
+        } else {
+            Some(Self::take_snapshot(state))
+        };
+
+        let undo = match command.execute_reversible(state)? {
+            Some(inverse) => UndoAction::Inverse(inverse),
+            None => snapshot.map_or(UndoAction::None, UndoAction::Snapshot),
+        };
+
+        Ok(HistoryEntry { command, undo })
+    }
+
+    fn push_undo(&mut self, entry: HistoryEntry) {
+        let is_snapshot = matches!(entry.undo, UndoAction::Snapshot(_));
+        self.undo_stack.push(entry);
+
+        if is_snapshot {
+            // Evict the oldest snapshot once we hold too many full copies of the state
+            let snapshot_count = self
+                .undo_stack
+                .iter()
+                .filter(|e| matches!(e.undo, UndoAction::Snapshot(_)))
+                .count();
+            if snapshot_count > self.max_snapshot_count {
+                if let Some(oldest) = self
+                    .undo_stack
+                    .iter_mut()
+                    .find(|e| matches!(e.undo, UndoAction::Snapshot(_)))
+                {
+                    oldest.undo = UndoAction::None;
+                }
+            }
+        }
+    }
+
+    fn take_snapshot(state: &DawState) -> Box<StateSnapshot> {
+        Box::new(StateSnapshot::from_state(state.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::*;
+    use serde_json::Value;
+    use std::path::PathBuf;
+
+    /// Ids of the things `test_state` puts in the project
+    struct Fixture {
+        lead: String,
+        bass: String,
+        drums: String,
+        clip: String,
+        next_clip: String,
+        audio_clip: String,
+        notes: Vec<String>,
+        marker: String,
+        section: String,
+    }
+
+    fn track(name: &str, track_type: TrackType) -> Track {
+        Track {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            track_type,
+            clips: Vec::new(),
+            is_muted: false,
+            is_soloed: false,
+            is_armed: false,
+            color: "#fde047".to_string(),
+            instrument: None,
+            is_collapsed: false,
+            is_monophonic: false,
+            input_velocity_curve: VelocityCurve::Linear,
+        }
+    }
+
+    fn midi_clip(start_time: f64, length: f64, notes: &[(u8, f64)]) -> (Clip, Vec<String>) {
+        let mut clip = Clip::empty_midi(start_time, length, 480);
+        let mut ids = Vec::new();
+        if let Clip::Midi {
+            midi_data: Some(store),
+            ..
+        } = &mut clip
+        {
+            for &(key, start) in notes {
+                let id = uuid::Uuid::new_v4().to_string();
+                store.add_note(Note {
+                    id: id.clone(),
+                    channel: 0,
+                    key,
+                    velocity: 90,
+                    start_time: start,
+                    duration: 0.5,
+                    start_tick: store.time_to_tick(start),
+                    duration_ticks: store.time_to_tick(0.5),
+                });
+                ids.push(id);
+            }
+        }
+        (clip, ids)
+    }
+
+    fn test_state() -> (DawState, Fixture) {
+        let mut state = DawState::new();
+
+        let midi = || TrackType::Midi {
+            channel: 0,
+            device_name: None,
+        };
+        let mut lead = track("Lead", midi());
+        let (clip, notes) = midi_clip(0.0, 2.0, &[(60, 0.0), (64, 0.5), (67, 1.0)]);
+        let (next_clip, _) = midi_clip(2.0, 2.0, &[(72, 0.25)]);
+        lead.clips.push(clip);
+        lead.clips.push(next_clip);
+
+        let mut bass = track("Bass", midi());
+        bass.is_soloed = true;
+        bass.is_muted = true;
+
+        let mut drums = track("Drums", TrackType::Audio);
+        drums.clips.push(Clip::Audio {
+            id: uuid::Uuid::new_v4().to_string(),
+            start_time: 1.3,
+            length: 3.0,
+            file_path: PathBuf::from("missing.wav"),
+            start_offset: 0.0,
+            end_offset: 3.0,
+            peaks: None,
+            is_muted: false,
+            color: None,
+        });
+
+        let fixture = Fixture {
+            lead: lead.id.clone(),
+            bass: bass.id.clone(),
+            drums: drums.id.clone(),
+            clip: lead.clips[0].id().to_string(),
+            next_clip: lead.clips[1].id().to_string(),
+            audio_clip: drums.clips[0].id().to_string(),
+            notes,
+            marker: String::new(),
+            section: String::new(),
+        };
+        state.project.tracks = vec![lead, bass, drums];
+
+        let marker = Marker::new("Verse", 4.0);
+        let section = Section::new("Intro", 0.0, 8.0, "#93c5fd");
+        let fixture = Fixture {
+            marker: marker.id.clone(),
+            section: section.id.clone(),
+            ..fixture
+        };
+        state.project.markers.push(marker);
+        state.project.sections.push(section);
+
+        (state, fixture)
+    }
 
-        if now.duration_since(self.last_snapshot_time) >= self.time_between_snapshots {
-            if self.state_snapshots.len() >= self.max_snapshot_count {
-                self.state_snapshots.remove(0);
+    /// Everything undo is responsible for, in a form where ids queued at the same
+    /// instant compare equal regardless of the order they were re-added in
+    fn fingerprint(state: &DawState) -> Value {
+        fn normalize(value: &mut Value) {
+            match value {
+                Value::Array(items) => {
+                    if items.iter().all(Value::is_string) {
+                        items.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+                    }
+                    items.iter_mut().for_each(normalize);
+                }
+                Value::Object(map) => map.values_mut().for_each(normalize),
+                _ => {}
             }
+        }
+
+        let mut value = serde_json::json!({
+            "project": state.project,
+            "current_time": state.current_time,
+        });
+        normalize(&mut value);
+        value
+    }
+
+    fn manager() -> CommandManager {
+        // No coalescing, so every command gets its own undo step
+        CommandManager::new(50, Duration::ZERO)
+    }
+
+    /// Execute, undo and redo `command`, checking each step lands on the right state
+    fn assert_round_trip(command: DawCommand, state: &mut DawState) {
+        let name = command.name();
+        let mut manager = manager();
+        let before = fingerprint(state);
+
+        manager.execute(command, state).unwrap();
+        let after = fingerprint(state);
+        assert_ne!(before, after, "{} changed nothing", name);
+
+        manager.undo(state).unwrap();
+        assert_eq!(fingerprint(state), before, "undo of {}", name);
+
+        manager.redo(state).unwrap();
+        assert_eq!(fingerprint(state), after, "redo of {}", name);
+
+        manager.undo(state).unwrap();
+        assert_eq!(fingerprint(state), before, "second undo of {}", name);
+    }
+
+    fn reversible_commands(f: &Fixture) -> Vec<DawCommand> {
+        vec![
+            DawCommand::DeleteTrack {
+                track_id: f.bass.clone(),
+            },
+            DawCommand::ReorderTracks {
+                from_index: 0,
+                to_index: 2,
+            },
+            DawCommand::SetTrackMidiChannel {
+                track_id: f.lead.clone(),
+                channel: 9,
+            },
+            DawCommand::MuteTrack {
+                track_id: f.lead.clone(),
+            },
+            DawCommand::UnmuteTrack {
+                track_id: f.bass.clone(),
+            },
+            DawCommand::ArmTrack {
+                track_id: f.lead.clone(),
+            },
+            DawCommand::SoloTrack {
+                track_id: f.lead.clone(),
+            },
+            DawCommand::UnsoloTrack {
+                track_id: f.bass.clone(),
+            },
+            DawCommand::SetSoloStates {
+                states: vec![(f.lead.clone(), true), (f.drums.clone(), true)],
+            },
+            DawCommand::SetTrackColor {
+                track_id: f.lead.clone(),
+                color: "#ff0000".to_string(),
+            },
+            DawCommand::SetTrackCollapsed {
+                track_id: f.lead.clone(),
+                collapsed: true,
+            },
+            DawCommand::SetTrackMonophonic {
+                track_id: f.lead.clone(),
+                monophonic: true,
+            },
+            DawCommand::SetTrackInputVelocityCurve {
+                track_id: f.lead.clone(),
+                curve: VelocityCurve::Exponential,
+            },
+            DawCommand::SetTrackInstrument {
+                track_id: f.lead.clone(),
+                instrument: Some(BuiltinInstrument::Saw),
+            },
+            DawCommand::AddTrack {
+                track_type: TrackType::Audio,
+                name: "Vox".to_string(),
+            },
+            DawCommand::AddNote {
+                clip_id: f.clip.clone(),
+                start_time: 1.5,
+                duration: 0.25,
+                pitch: 48,
+                velocity: 100,
+            },
+            DawCommand::DeleteNotes {
+                clip_id: f.clip.clone(),
+                note_ids: f.notes[..2].to_vec(),
+            },
+            DawCommand::UpdateNoteVelocity {
+                clip_id: f.clip.clone(),
+                note_id: f.notes[0].clone(),
+                velocity: 30,
+            },
+            DawCommand::SetNoteChannel {
+                clip_id: f.clip.clone(),
+                note_ids: f.notes.clone(),
+                channel: 9,
+            },
+            DawCommand::ResizeNote {
+                clip_id: f.clip.clone(),
+                note_id: f.notes[1].clone(),
+                new_start_time: 0.75,
+                new_duration: 1.0,
+            },
+            DawCommand::MoveNotes {
+                clip_id: f.clip.clone(),
+                note_ids: f.notes.clone(),
+                delta_time: 0.25,
+                delta_pitch: 2,
+            },
+            DawCommand::AddClip {
+                track_id: f.lead.clone(),
+                start_time: 8.0,
+                length: 2.0,
+                file_path: PathBuf::new(),
+            },
+            DawCommand::AddClip {
+                track_id: f.drums.clone(),
+                start_time: 8.0,
+                length: 2.0,
+                file_path: PathBuf::from("missing.wav"),
+            },
+            DawCommand::DeleteClip {
+                track_id: f.lead.clone(),
+                clip_id: f.clip.clone(),
+            },
+            DawCommand::MoveClip {
+                clip_id: f.next_clip.clone(),
+                track_id: f.lead.clone(),
+                new_start_time: 3.0,
+            },
+            DawCommand::SnapClipToGrid {
+                clip_id: f.audio_clip.clone(),
+            },
+            DawCommand::ResizeClip {
+                clip_id: f.clip.clone(),
+                new_length: 1.0,
+            },
+            DawCommand::SetClipMuted {
+                clip_id: f.clip.clone(),
+                muted: true,
+            },
+            DawCommand::SetClipColor {
+                clip_id: f.audio_clip.clone(),
+                color: Some("#00ff00".to_string()),
+            },
+            DawCommand::ClearClip {
+                clip_id: f.clip.clone(),
+            },
+            // Clamps two of the three notes onto 127
+            DawCommand::TransposeClip {
+                clip_id: f.clip.clone(),
+                semitones: 64,
+            },
+            DawCommand::SetClipProcessors {
+                clip_id: f.clip.clone(),
+                processors: vec![ClipProcessor::Transpose { semitones: -12 }],
+            },
+            DawCommand::SetBpm { bpm: 90.0 },
+            DawCommand::SetTimeSignature {
+                time_signature: (3, 4),
+            },
+            DawCommand::AddMarker {
+                marker: Marker::new("Chorus", 12.0),
+            },
+            DawCommand::DeleteMarker {
+                marker_id: f.marker.clone(),
+            },
+            DawCommand::MoveMarker {
+                marker_id: f.marker.clone(),
+                time: 6.0,
+            },
+            DawCommand::RenameMarker {
+                marker_id: f.marker.clone(),
+                name: "Bridge".to_string(),
+            },
+            DawCommand::AddSection {
+                section: Section::new("Outro", 16.0, 24.0, "#fca5a5"),
+            },
+            DawCommand::DeleteSection {
+                section_id: f.section.clone(),
+            },
+            DawCommand::SetSectionRange {
+                section_id: f.section.clone(),
+                start: 2.0,
+                end: 10.0,
+            },
+            DawCommand::RenameSection {
+                section_id: f.section.clone(),
+                name: "Build".to_string(),
+            },
+            DawCommand::Batch {
+                commands: vec![
+                    DawCommand::MuteTrack {
+                        track_id: f.lead.clone(),
+                    },
+                    DawCommand::AddNote {
+                        clip_id: f.next_clip.clone(),
+                        start_time: 0.5,
+                        duration: 0.5,
+                        pitch: 74,
+                        velocity: 80,
+                    },
+                ],
+            },
+        ]
+    }
+
+    #[test]
+    fn reversible_commands_round_trip() {
+        let (base, fixture) = test_state();
+        for command in reversible_commands(&fixture) {
+            let mut state = base.clone();
+            assert!(command.has_inverse(&state), "{} has no inverse", command.name());
+            assert_round_trip(command, &mut state);
+        }
+    }
+
+    #[test]
+    fn snapshot_commands_round_trip() {
+        let (base, fixture) = test_state();
+        let commands = vec![
+            // The first note can't move back from before the clip start
+            DawCommand::MoveNotes {
+                clip_id: fixture.clip.clone(),
+                note_ids: fixture.notes.clone(),
+                delta_time: -0.25,
+                delta_pitch: 2,
+            },
+            DawCommand::RemoveTime {
+                start: 0.25,
+                end: 0.75,
+            },
+            DawCommand::InsertTime {
+                time: 1.0,
+                duration: 2.0,
+            },
+        ];
+        for command in commands {
+            let mut state = base.clone();
+            assert!(!command.has_inverse(&state), "{} has an inverse", command.name());
+            assert_round_trip(command, &mut state);
+        }
+    }
+
+    #[test]
+    fn only_commands_without_an_inverse_keep_a_snapshot() {
+        let (base, fixture) = test_state();
+        for command in reversible_commands(&fixture) {
+            let name = command.name();
+            let mut state = base.clone();
+            let mut manager = manager();
+            manager.execute(command, &mut state).unwrap();
+            assert!(
+                matches!(manager.undo_stack[0].undo, UndoAction::Inverse(_)),
+                "{} kept a snapshot",
+                name
+            );
+        }
 
-            let snapshot = StateSnapshot::from_state(state.clone());
-            self.last_snapshot_time = now;
-            self.state_snapshots.push(snapshot);
+        let mut state = base.clone();
+        let mut manager = manager();
+        manager
+            .execute(
+                DawCommand::RemoveTime {
+                    start: 0.25,
+                    end: 0.75,
+                },
+                &mut state,
+            )
+            .unwrap();
+        assert!(matches!(manager.undo_stack[0].undo, UndoAction::Snapshot(_)));
+    }
+
+    #[test]
+    fn quick_commands_without_an_inverse_still_undo_one_at_a_time() {
+        let (mut state, _) = test_state();
+        let mut manager = CommandManager::default();
+        let before = fingerprint(&state);
+
+        for time in [0.5, 1.0] {
+            manager
+                .execute(
+                    DawCommand::InsertTime {
+                        time,
+                        duration: 1.0,
+                    },
+                    &mut state,
+                )
+                .unwrap();
         }
+        let after_both = fingerprint(&state);
+
+        manager.undo(&mut state).unwrap();
+        let after_first = fingerprint(&state);
+        assert_ne!(after_first, after_both, "undoing the second insert did nothing");
+        assert_ne!(after_first, before);
+
+        manager.undo(&mut state).unwrap();
+        assert_eq!(fingerprint(&state), before);
+    }
+
+    #[test]
+    fn restoring_a_snapshot_keeps_settings_and_messages() {
+        let (mut state, _) = test_state();
+        let mut manager = manager();
+        manager
+            .execute(
+                DawCommand::InsertTime {
+                    time: 1.0,
+                    duration: 1.0,
+                },
+                &mut state,
+            )
+            .unwrap();
+        assert!(matches!(manager.undo_stack[0].undo, UndoAction::Snapshot(_)));
+
+        // Changed after the snapshot was taken
+        state.settings.snap_threshold = 42.0;
+        state.status.info("Inserted time");
+
+        manager.undo(&mut state).unwrap();
+        assert_eq!(state.settings.snap_threshold, 42.0);
+        assert_eq!(state.status.history().len(), 1);
+    }
+
+    fn note_starts(state: &DawState) -> Vec<(u8, f64)> {
+        let Clip::Midi {
+            midi_data: Some(store),
+            ..
+        } = &state.project.tracks[0].clips[0]
+        else {
+            unreachable!()
+        };
+        let mut starts: Vec<(u8, f64)> = store.get_notes().map(|n| (n.key, n.start_time)).collect();
+        starts.sort_by_key(|start| start.0);
+        starts
+    }
+
+    #[test]
+    fn a_note_drag_undoes_in_one_step() {
+        let (mut state, fixture) = test_state();
+        let mut manager = CommandManager::default();
+        let before = note_starts(&state);
+
+        // A drag sends the distance moved since the previous frame
+        for (delta_time, delta_pitch) in [(0.25, 1), (0.25, 0), (0.0, 1), (-0.125, -1)] {
+            manager
+                .execute(
+                    DawCommand::MoveNotes {
+                        clip_id: fixture.clip.clone(),
+                        note_ids: fixture.notes.clone(),
+                        delta_time,
+                        delta_pitch,
+                    },
+                    &mut state,
+                )
+                .unwrap();
+        }
+        let dragged = note_starts(&state);
+        assert_eq!(dragged, vec![(61, 0.375), (65, 0.875), (68, 1.375)]);
+
+        manager.undo(&mut state).unwrap();
+        assert_eq!(note_starts(&state), before);
+        assert!(!manager.can_undo(), "the drag left more than one undo step");
+
+        manager.redo(&mut state).unwrap();
+        assert_eq!(note_starts(&state), dragged);
+    }
+
+    #[test]
+    fn undoing_add_clip_removes_the_added_clip() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        let existing: Vec<String> = state.project.tracks[0]
+            .clips
+            .iter()
+            .map(|c| c.id().to_string())
+            .collect();
+
+        manager
+            .execute(
+                DawCommand::AddClip {
+                    track_id: fixture.lead.clone(),
+                    start_time: 8.0,
+                    length: 2.0,
+                    file_path: PathBuf::new(),
+                },
+                &mut state,
+            )
+            .unwrap();
+        // Something else reorders the clip list before the undo
+        state.project.tracks[0].clips.reverse();
+        manager.undo(&mut state).unwrap();
+
+        let mut remaining: Vec<String> = state.project.tracks[0]
+            .clips
+            .iter()
+            .map(|c| c.id().to_string())
+            .collect();
+        remaining.sort();
+        let mut existing = existing;
+        existing.sort();
+        assert_eq!(remaining, existing);
+    }
+
+    fn soloed(state: &DawState) -> Vec<bool> {
+        state.project.tracks.iter().map(|t| t.is_soloed).collect()
+    }
+
+    #[test]
+    fn undoing_an_exclusive_solo_restores_the_previous_solos() {
+        let (mut state, fixture) = test_state();
+        state.project.tracks[2].is_soloed = true;
+        let mut manager = manager();
+        assert_eq!(soloed(&state), [false, true, true]);
+
+        manager
+            .execute(
+                DawCommand::SoloTrack {
+                    track_id: fixture.lead.clone(),
+                },
+                &mut state,
+            )
+            .unwrap();
+        assert_eq!(soloed(&state), [true, false, false]);
+
+        manager.undo(&mut state).unwrap();
+        assert_eq!(soloed(&state), [false, true, true]);
+
+        manager.redo(&mut state).unwrap();
+        assert_eq!(soloed(&state), [true, false, false]);
+    }
+
+    #[test]
+    fn undoing_a_track_delete_puts_it_back_in_place() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        manager
+            .execute(
+                DawCommand::DeleteTrack {
+                    track_id: fixture.bass.clone(),
+                },
+                &mut state,
+            )
+            .unwrap();
+        assert_eq!(state.project.tracks.len(), 2);
+
+        manager.undo(&mut state).unwrap();
+        let ids: Vec<&str> = state.project.tracks.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, [&fixture.lead, &fixture.bass, &fixture.drums]);
+        assert!(state.project.tracks[1].is_soloed);
+    }
+
+    #[test]
+    fn clearing_a_clip_keeps_it_and_undo_brings_the_notes_back() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        if let Clip::Midi {
+            automation_lanes, ..
+        } = &mut state.project.tracks[0].clips[0]
+        {
+            let mut lane = AutomationLane::new(AutomationParameter::Volume);
+            lane.add_point(0.5, 0.3);
+            automation_lanes.push(lane);
+        }
+        let notes = note_ids(&state, 0);
+
+        manager
+            .execute(
+                DawCommand::ClearClip {
+                    clip_id: fixture.clip.clone(),
+                },
+                &mut state,
+            )
+            .unwrap();
+        let clip = &state.project.tracks[0].clips[0];
+        assert_eq!(clip.id(), fixture.clip);
+        assert_eq!(clip.start_time(), 0.0);
+        assert_eq!(clip.length(), 2.0);
+        assert!(note_ids(&state, 0).is_empty());
+        match clip {
+            Clip::Midi {
+                automation_lanes, ..
+            } => {
+                assert_eq!(automation_lanes.len(), 1, "the lane itself stays");
+                assert!(automation_lanes[0].points.is_empty());
+            }
+            Clip::Audio { .. } => unreachable!(),
+        }
+
+        manager.undo(&mut state).unwrap();
+        assert_eq!(note_ids(&state, 0), notes);
+        assert_eq!(state.project.tracks[0].clips[0].id(), fixture.clip);
+    }
+
+    fn velocity_of(state: &DawState, note_id: &str) -> u8 {
+        match &state.project.tracks[0].clips[0] {
+            Clip::Midi {
+                midi_data: Some(store),
+                ..
+            } => store.get_note(note_id).unwrap().velocity,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn quick_velocity_edits_to_one_note_undo_one_at_a_time() {
+        let (mut state, fixture) = test_state();
+        // A velocity drag sends one command when released, so two quick drags on the
+        // same note have to stay two steps even inside the coalescing window
+        let mut manager = CommandManager::new(50, Duration::from_secs(600));
+        let note = &fixture.notes[0];
+        let edit = |velocity| DawCommand::UpdateNoteVelocity {
+            clip_id: fixture.clip.clone(),
+            note_id: note.clone(),
+            velocity,
+        };
+
+        manager.execute(edit(110), &mut state).unwrap();
+        manager.execute(edit(127), &mut state).unwrap();
+        assert_eq!(velocity_of(&state, note), 127);
+
+        manager.undo(&mut state).unwrap();
+        assert_eq!(velocity_of(&state, note), 110);
+        manager.undo(&mut state).unwrap();
+        assert_eq!(velocity_of(&state, note), 90);
+        assert!(!manager.can_undo());
+
+        manager.redo(&mut state).unwrap();
+        assert_eq!(velocity_of(&state, note), 110);
+    }
+
+    #[test]
+    fn a_velocity_lane_drag_undoes_in_one_step() {
+        let (mut state, fixture) = test_state();
+        let mut manager = CommandManager::default();
+        let note = &fixture.notes[0];
+
+        // A slow drag up a 127 pixel lane, then back down a little, a frame at a time
+        let mut drag = VelocityDrag::new(note.clone(), velocity_of(&state, note));
+        for _ in 0..40 {
+            drag.drag_by(0.5, 127.0);
+        }
+        for _ in 0..10 {
+            drag.drag_by(-0.5, 127.0);
+        }
+        assert_eq!(drag.velocity(), 105);
+        // Nothing changes until the bar is released
+        assert_eq!(velocity_of(&state, note), 90);
+
+        let command = drag.finish(&fixture.clip).unwrap();
+        manager.execute(command, &mut state).unwrap();
+        assert_eq!(velocity_of(&state, note), 105);
+
+        manager.undo(&mut state).unwrap();
+        assert_eq!(velocity_of(&state, note), 90);
+        assert!(!manager.can_undo());
+
+        // Letting go where it started is no edit at all
+        let mut drag = VelocityDrag::new(note.clone(), 90);
+        drag.drag_by(10.0, 127.0);
+        drag.drag_by(-10.0, 127.0);
+        assert!(drag.finish(&fixture.clip).is_none());
+    }
+
+    /// What the lead track plays over the whole fixture, in a comparable form
+    fn played_notes(state: &DawState) -> Vec<(u64, MidiMessage)> {
+        let mut played: Vec<(u64, MidiMessage)> = state.project.tracks[0]
+            .get_events_in_time_range(0.0, 8.0)
+            .into_iter()
+            .map(|event| ((event.time * 1000.0).round() as u64, event.message))
+            .collect();
+        played.sort_by_key(|(time, message)| (*time, format!("{:?}", message)));
+        played
+    }
+
+    #[test]
+    fn freezing_a_clip_keeps_its_sound_and_undo_brings_back_the_processors() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        let processors = vec![
+            ClipProcessor::Transpose { semitones: 5 },
+            ClipProcessor::VelocityScale { factor: 0.5 },
+        ];
+        let unprocessed = played_notes(&state);
+        manager
+            .execute(
+                DawCommand::SetClipProcessors {
+                    clip_id: fixture.clip.clone(),
+                    processors: processors.clone(),
+                },
+                &mut state,
+            )
+            .unwrap();
+        let processed = played_notes(&state);
+        assert_ne!(processed, unprocessed);
+
+        let stored = fingerprint(&state);
+        manager
+            .execute(
+                DawCommand::FreezeClip {
+                    clip_id: fixture.clip.clone(),
+                },
+                &mut state,
+            )
+            .unwrap();
+        assert_eq!(played_notes(&state), processed);
+        let Clip::Midi {
+            midi_data: Some(store),
+            processors: left,
+            ..
+        } = &state.project.tracks[0].clips[0]
+        else {
+            unreachable!()
+        };
+        assert!(left.is_empty());
+        let mut keys: Vec<(u8, u8)> = store.get_notes().map(|n| (n.key, n.velocity)).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, vec![(65, 45), (69, 45), (72, 45)]);
+
+        manager.undo(&mut state).unwrap();
+        assert_eq!(fingerprint(&state), stored);
+        assert_eq!(played_notes(&state), processed);
+    }
+
+    /// Channel of each note, and of its NoteOn and NoteOff events, in the order given
+    fn note_channels(state: &DawState, note_ids: &[String]) -> Vec<(u8, u8, u8)> {
+        let Clip::Midi {
+            midi_data: Some(store),
+            ..
+        } = &state.project.tracks[0].clips[0]
+        else {
+            panic!("lead clip isn't loaded");
+        };
+        let event_channel = |event_id: String| {
+            store
+                .get_events()
+                .find(|event| event.id == event_id)
+                .and_then(|event| event.message.channel())
+                .unwrap()
+        };
+        note_ids
+            .iter()
+            .map(|id| {
+                (
+                    store.get_note(id).unwrap().channel,
+                    event_channel(format!("{}_on", id)),
+                    event_channel(format!("{}_off", id)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn setting_the_channel_moves_notes_and_undo_restores_mixed_channels() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        let notes = &fixture.notes;
+
+        // Channel 10 in the UI
+        manager
+            .execute(
+                DawCommand::SetNoteChannel {
+                    clip_id: fixture.clip.clone(),
+                    note_ids: notes[..2].to_vec(),
+                    channel: 9,
+                },
+                &mut state,
+            )
+            .unwrap();
+        assert_eq!(
+            note_channels(&state, notes),
+            vec![(9, 9, 9), (9, 9, 9), (0, 0, 0)]
+        );
+
+        manager
+            .execute(
+                DawCommand::SetNoteChannel {
+                    clip_id: fixture.clip.clone(),
+                    note_ids: notes.clone(),
+                    channel: 3,
+                },
+                &mut state,
+            )
+            .unwrap();
+        assert_eq!(
+            note_channels(&state, notes),
+            vec![(3, 3, 3), (3, 3, 3), (3, 3, 3)]
+        );
+
+        // Each note goes back to the channel it had, not one shared channel
+        manager.undo(&mut state).unwrap();
+        assert_eq!(
+            note_channels(&state, notes),
+            vec![(9, 9, 9), (9, 9, 9), (0, 0, 0)]
+        );
+        manager.undo(&mut state).unwrap();
+        assert_eq!(
+            note_channels(&state, notes),
+            vec![(0, 0, 0), (0, 0, 0), (0, 0, 0)]
+        );
+
+        let out_of_range = DawCommand::SetNoteChannel {
+            clip_id: fixture.clip.clone(),
+            note_ids: notes.clone(),
+            channel: 16,
+        };
+        assert!(manager.execute(out_of_range, &mut state).is_err());
+    }
+
+    fn note_ids(state: &DawState, clip_index: usize) -> Vec<String> {
+        let mut ids: Vec<String> = match &state.project.tracks[0].clips[clip_index] {
+            Clip::Midi {
+                midi_data: Some(store),
+                ..
+            } => store.get_notes().map(|n| n.id.clone()).collect(),
+            _ => Vec::new(),
+        };
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn a_batched_paste_undoes_in_one_step() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        let before = note_ids(&state, 1);
+
+        // Pasting three notes into the second clip
+        let paste = DawCommand::Batch {
+            commands: [(0.0, 60), (0.5, 62), (1.0, 64)]
+                .into_iter()
+                .map(|(start_time, pitch)| DawCommand::AddNote {
+                    clip_id: fixture.next_clip.clone(),
+                    start_time,
+                    duration: 0.25,
+                    pitch,
+                    velocity: 100,
+                })
+                .collect(),
+        };
+        manager.execute(paste, &mut state).unwrap();
+        let pasted = note_ids(&state, 1);
+        assert_eq!(pasted.len(), before.len() + 3);
+
+        manager.undo(&mut state).unwrap();
+        assert_eq!(note_ids(&state, 1), before);
+        assert!(!manager.can_undo());
+
+        manager.redo(&mut state).unwrap();
+        assert_eq!(note_ids(&state, 1), pasted);
+    }
+
+    #[test]
+    fn a_failing_batch_leaves_nothing_behind() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        let before = fingerprint(&state);
+
+        let batch = DawCommand::Batch {
+            commands: vec![
+                DawCommand::MuteTrack {
+                    track_id: fixture.lead.clone(),
+                },
+                DawCommand::SetClipMuted {
+                    clip_id: "missing".to_string(),
+                    muted: true,
+                },
+            ],
+        };
+        assert!(manager.execute(batch, &mut state).is_err());
+        assert_eq!(fingerprint(&state), before);
+        assert!(!manager.can_undo());
+    }
+
+    #[test]
+    fn a_failing_batch_rolls_back_commands_without_an_inverse() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        let before = fingerprint(&state);
+
+        let batch = DawCommand::Batch {
+            commands: vec![
+                DawCommand::InsertTime {
+                    time: 1.0,
+                    duration: 2.0,
+                },
+                DawCommand::MuteTrack {
+                    track_id: fixture.lead.clone(),
+                },
+                DawCommand::SetClipMuted {
+                    clip_id: "missing".to_string(),
+                    muted: true,
+                },
+            ],
+        };
+        assert!(manager.execute(batch, &mut state).is_err());
+        assert_eq!(fingerprint(&state), before);
+        assert!(!manager.can_undo());
+    }
+
+    #[test]
+    fn an_empty_batch_leaves_the_history_alone() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        manager
+            .execute(
+                DawCommand::MuteTrack {
+                    track_id: fixture.lead.clone(),
+                },
+                &mut state,
+            )
+            .unwrap();
+        manager.undo(&mut state).unwrap();
+
+        manager
+            .execute(DawCommand::Batch { commands: vec![] }, &mut state)
+            .unwrap();
+        assert!(!manager.can_undo());
+        assert!(manager.can_redo(), "an empty batch threw away the redo stack");
+    }
+
+    #[test]
+    fn seeking_stays_out_of_the_history() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        manager
+            .execute(
+                DawCommand::MuteTrack {
+                    track_id: fixture.lead.clone(),
+                },
+                &mut state,
+            )
+            .unwrap();
+        manager.undo(&mut state).unwrap();
+
+        manager
+            .execute(DawCommand::SeekTime { time: 3.0 }, &mut state)
+            .unwrap();
+        assert_eq!(state.current_time, 3.0);
+        assert!(!manager.can_undo());
+        assert!(manager.can_redo(), "a seek threw away the redo stack");
+
+        manager.redo(&mut state).unwrap();
+        assert!(state.project.tracks[0].is_muted);
+        assert_eq!(state.current_time, 3.0);
+    }
+
+    #[test]
+    fn selecting_stays_out_of_the_history() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        manager
+            .execute(
+                DawCommand::MuteTrack {
+                    track_id: fixture.lead.clone(),
+                },
+                &mut state,
+            )
+            .unwrap();
+        manager.undo(&mut state).unwrap();
+
+        for command in [
+            DawCommand::SelectTrack {
+                track_id: fixture.bass.clone(),
+            },
+            DawCommand::SelectClip {
+                clip_id: fixture.clip.clone(),
+            },
+            DawCommand::DeselectAll,
+            DawCommand::SelectTrack {
+                track_id: fixture.drums.clone(),
+            },
+        ] {
+            manager.execute(command, &mut state).unwrap();
+        }
+        assert_eq!(state.selected_track.as_ref(), Some(&fixture.drums));
+        assert!(!manager.can_undo());
+        assert!(manager.can_redo(), "selecting threw away the redo stack");
+    }
+
+    #[test]
+    fn snapping_a_clip_with_snap_off_leaves_it_in_place() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        manager
+            .execute(
+                DawCommand::MoveClip {
+                    clip_id: fixture.clip.clone(),
+                    track_id: fixture.lead.clone(),
+                    new_start_time: 0.3,
+                },
+                &mut state,
+            )
+            .unwrap();
+        state.snap_mode = SnapMode::None;
+
+        manager
+            .execute(
+                DawCommand::SnapClipToGrid {
+                    clip_id: fixture.clip.clone(),
+                },
+                &mut state,
+            )
+            .unwrap();
+        let clip = &state.project.tracks[0].clips[0];
+        assert_eq!(clip.id(), fixture.clip);
+        assert_eq!(clip.start_time(), 0.3);
+    }
+
+    #[test]
+    fn snap_and_metronome_toggles_stay_out_of_the_history() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        manager
+            .execute(
+                DawCommand::MuteTrack {
+                    track_id: fixture.lead.clone(),
+                },
+                &mut state,
+            )
+            .unwrap();
+        manager.undo(&mut state).unwrap();
+
+        for command in [
+            DawCommand::SetSnapMode {
+                snap_mode: SnapMode::Bar,
+            },
+            DawCommand::EnableMetronome,
+            DawCommand::DisableMetronome,
+        ] {
+            manager.execute(command, &mut state).unwrap();
+        }
+        assert_eq!(state.snap_mode, SnapMode::Bar);
+        assert!(!manager.can_undo());
+        assert!(manager.can_redo(), "a toggle threw away the redo stack");
+    }
+
+    #[test]
+    fn switching_views_leaves_the_transport_alone() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        manager
+            .execute(DawCommand::SeekTime { time: 1.25 }, &mut state)
+            .unwrap();
+        manager
+            .execute(DawCommand::StartPlayback, &mut state)
+            .unwrap();
+        // Hold the clock still so only the view switches could move the playhead
+        state.last_update = None;
+        let time = state.current_time;
+
+        manager
+            .execute(
+                DawCommand::OpenPianoRoll {
+                    clip_id: fixture.clip.clone(),
+                    track_id: fixture.lead.clone(),
+                },
+                &mut state,
+            )
+            .unwrap();
+        assert!(matches!(state.current_view, EditorView::PianoRoll { .. }));
+        manager
+            .execute(
+                DawCommand::OpenEventList {
+                    clip_id: fixture.clip.clone(),
+                    track_id: fixture.lead.clone(),
+                },
+                &mut state,
+            )
+            .unwrap();
+        // Closing an editor sets the view directly
+        state.current_view = EditorView::Arrangement;
+
+        assert_eq!(state.current_time, time);
+        assert!(state.playing);
+        assert_eq!(state.last_update, None);
+    }
+
+    #[test]
+    fn rehearsing_puts_the_metronome_back_afterwards() {
+        let (mut state, _) = test_state();
+        let mut manager = manager();
+        state.metronome = false;
+
+        state.start_rehearsing();
+        assert!(state.metronome);
+        // Turned off mid-rehearsal, playback brings the click back
+        manager
+            .execute(DawCommand::DisableMetronome, &mut state)
+            .unwrap();
+        manager
+            .execute(DawCommand::StartPlayback, &mut state)
+            .unwrap();
+        assert!(state.metronome);
+
+        state.stop_rehearsing();
+        assert!(!state.rehearsing);
+        assert!(!state.metronome);
+
+        // Rehearsing without pressing play still restores it
+        state.metronome = true;
+        state.start_rehearsing();
+        state.stop_rehearsing();
+        assert!(state.metronome);
+    }
+
+    #[test]
+    fn transport_commands_tell_playback_what_happened() {
+        let (mut state, _) = test_state();
+        let mut manager = manager();
+        for command in [
+            DawCommand::StartPlayback,
+            DawCommand::PausePlayback,
+            DawCommand::StartPlayback,
+            DawCommand::StopPlayback,
+        ] {
+            manager.execute(command, &mut state).unwrap();
+        }
+        assert_eq!(
+            state.transport_events,
+            vec![
+                TransportEvent::Started,
+                TransportEvent::Paused,
+                TransportEvent::Started,
+                TransportEvent::Stopped,
+            ]
+        );
+        assert!(!state.playing);
+        assert_eq!(state.current_time, 0.0);
+    }
+
+    #[test]
+    fn seeks_inside_a_batch_are_undone_with_it() {
+        let (mut state, fixture) = test_state();
+        let mut manager = manager();
+        // Step input: a note at the playhead, then the playhead moves on
+        let step = DawCommand::Batch {
+            commands: vec![
+                DawCommand::AddNote {
+                    clip_id: fixture.next_clip.clone(),
+                    start_time: 0.0,
+                    duration: 0.5,
+                    pitch: 60,
+                    velocity: 100,
+                },
+                DawCommand::SeekTime { time: 2.5 },
+            ],
+        };
+        let before = fingerprint(&state);
+        manager.execute(step, &mut state).unwrap();
+        assert_eq!(state.current_time, 2.5);
+
+        manager.undo(&mut state).unwrap();
+        assert_eq!(fingerprint(&state), before);
     }
 }
@@ -1,6 +1,7 @@
 // src/core/commands.rs
 use super::*;
 use crate::core::{AutomationParameter, AutomationLane};
+use std::collections::HashSet;
 use std::path::PathBuf;
 use uuid::Uuid;
 
@@ -17,6 +18,10 @@ pub enum DawCommand {
         clip_id: String,
         track_id: String,
     },
+    OpenEventList {
+        clip_id: String,
+        track_id: String,
+    },
 
     // Notes
     MoveNotes {
@@ -35,6 +40,12 @@ pub enum DawCommand {
         note_id: EventID,
         velocity: u8,
     },
+    /// Move notes to another MIDI channel (0-15)
+    SetNoteChannel {
+        clip_id: String,
+        note_ids: Vec<EventID>,
+        channel: u8,
+    },
 
     ResizeNote {
         clip_id: String,
@@ -87,10 +98,38 @@ pub enum DawCommand {
         track_id: String,
         color: String,
     },
+    /// Shrink a track to a thin strip in the arrangement, or expand it again
+    SetTrackCollapsed {
+        track_id: String,
+        collapsed: bool,
+    },
+    SetTrackMonophonic {
+        track_id: String,
+        monophonic: bool,
+    },
+    /// Curve applied to the velocity of notes recorded on the track
+    SetTrackInputVelocityCurve {
+        track_id: String,
+        curve: VelocityCurve,
+    },
+    /// Play the track on a built-in instrument, or only through external MIDI when `None`
+    SetTrackInstrument {
+        track_id: String,
+        instrument: Option<BuiltinInstrument>,
+    },
     ReorderTracks {
         from_index: usize,
         to_index: usize,
     },
+    /// Insert a track at a position in the track list (undoes DeleteTrack, and adds dropped files)
+    RestoreTrack {
+        index: usize,
+        track: Box<Track>,
+    },
+    /// Set the solo flag of every listed track (used to undo solo changes)
+    SetSoloStates {
+        states: Vec<(String, bool)>,
+    },
 
     // Clips
     SelectClip {
@@ -112,10 +151,103 @@ pub enum DawCommand {
         track_id: String,
         new_start_time: f64,
     },
+    /// Move a clip's start onto the nearest line of the current snap grid
+    SnapClipToGrid {
+        clip_id: String,
+    },
     ResizeClip {
         clip_id: String,
         new_length: f64,
     },
+    /// Silence a single clip without muting its track
+    SetClipMuted {
+        clip_id: String,
+        muted: bool,
+    },
+    /// Override the track color for one clip, or go back to the track color with `None`
+    SetClipColor {
+        clip_id: String,
+        color: Option<String>,
+    },
+    /// Merge a clip with the one directly after it on the same track
+    JoinClips {
+        track_id: String,
+        clip_id: String,
+        next_clip_id: String,
+    },
+    /// Insert a clip at a position in a track's clip list (undoes DeleteClip, and drops copies)
+    RestoreClip {
+        track_id: String,
+        index: usize,
+        clip: Box<Clip>,
+    },
+    /// Remove every note, event and automation point from a MIDI clip, keeping the clip
+    ClearClip {
+        clip_id: String,
+    },
+    /// Shift every note in a MIDI clip by `semitones`, clamped to the MIDI range
+    TransposeClip {
+        clip_id: String,
+        semitones: i8,
+    },
+    /// Set the processors a MIDI clip's notes play through
+    SetClipProcessors {
+        clip_id: String,
+        processors: Vec<ClipProcessor>,
+    },
+    /// Write a MIDI clip's processors into its notes and remove them
+    FreezeClip {
+        clip_id: String,
+    },
+    /// Swap a clip for another copy with the same id (used to undo edits to its contents)
+    ReplaceClip {
+        clip: Box<Clip>,
+    },
+
+    // Markers
+    AddMarker {
+        marker: Marker,
+    },
+    DeleteMarker {
+        marker_id: String,
+    },
+    MoveMarker {
+        marker_id: String,
+        time: f64,
+    },
+    RenameMarker {
+        marker_id: String,
+        name: String,
+    },
+
+    // Arrangement
+    /// Push everything at or after `time` later by `duration` seconds
+    InsertTime {
+        time: f64,
+        duration: f64,
+    },
+    /// Cut `start..end` out of every track and close the gap
+    RemoveTime {
+        start: f64,
+        end: f64,
+    },
+
+    // Sections
+    AddSection {
+        section: Section,
+    },
+    DeleteSection {
+        section_id: String,
+    },
+    SetSectionRange {
+        section_id: String,
+        start: f64,
+        end: f64,
+    },
+    RenameSection {
+        section_id: String,
+        name: String,
+    },
 
     // Automation
     AddAutomationLane {
@@ -155,6 +287,10 @@ pub enum DawCommand {
     SetBpm {
         bpm: f64,
     },
+    /// Beats per bar and beat unit, e.g. (3, 4)
+    SetTimeSignature {
+        time_signature: (u8, u8),
+    },
     SeekTime {
         time: f64,
     },
@@ -164,6 +300,11 @@ pub enum DawCommand {
     StartPlayback,
     PausePlayback,
 
+    /// Several commands applied, and undone, as a single step
+    Batch {
+        commands: Vec<DawCommand>,
+    },
+
     // Does nothing, used for testing and such
     NoOp,
     SetSnapMode {
@@ -190,12 +331,69 @@ impl Command for DawCommand {
                 Ok(())
             }
             DawCommand::OpenPianoRoll { clip_id, track_id } => {
+                let clip = state
+                    .project
+                    .find_clip_mut(clip_id)
+                    .ok_or("Clip not found")?;
+                match clip {
+                    // Audio has nothing to show in the piano roll, send it to its own editor
+                    Clip::Audio { .. } => {
+                        state.selected_clip = Some(clip_id.clone());
+                        state.current_view = EditorView::SampleEditor {
+                            clip_id: clip_id.clone(),
+                            track_id: track_id.clone(),
+                            zoom_level: 1.0,
+                        };
+                        state
+                            .status
+                            .info("Audio clips open in the sample editor".to_string());
+                        return Ok(());
+                    }
+                    Clip::Midi { .. } => clip
+                        .load_midi()
+                        .map_err(|e| format!("Failed to load MIDI data: {}", e))?,
+                }
+                let empty = matches!(clip, Clip::Midi { midi_data: Some(store), .. }
+                    if store.get_notes().next().is_none());
+                if empty {
+                    state
+                        .status
+                        .info("Clip is empty, add notes with the draw tool".to_string());
+                }
+
+                state.selected_clip = Some(clip_id.clone());
+                state.current_view = match state.piano_roll_views.get(clip_id) {
+                    Some(EditorView::PianoRoll {
+                        scroll_position,
+                        vertical_scroll,
+                        horizontal_zoom,
+                        vertical_zoom,
+                        ..
+                    }) => EditorView::PianoRoll {
+                        clip_id: clip_id.clone(),
+                        track_id: track_id.clone(),
+                        scroll_position: *scroll_position,
+                        vertical_scroll: *vertical_scroll,
+                        horizontal_zoom: *horizontal_zoom,
+                        vertical_zoom: *vertical_zoom,
+                    },
+                    _ => EditorView::PianoRoll {
+                        clip_id: clip_id.clone(),
+                        track_id: track_id.clone(),
+                        scroll_position: 0.0,
+                        vertical_scroll: None,
+                        horizontal_zoom: DEFAULT_PIANO_ROLL_ZOOM,
+                        vertical_zoom: 1.0,
+                    },
+                };
+                Ok(())
+            }
+
+            DawCommand::OpenEventList { clip_id, track_id } => {
                 state.selected_clip = Some(clip_id.clone());
-                state.current_view = EditorView::PianoRoll {
+                state.current_view = EditorView::EventList {
                     clip_id: clip_id.clone(),
                     track_id: track_id.clone(),
-                    scroll_position: 0.0,
-                    vertical_zoom: 1.0,
                 };
                 Ok(())
             }
@@ -278,6 +476,50 @@ impl Command for DawCommand {
                 }
                 Ok(())
             }
+
+            DawCommand::SetTrackCollapsed {
+                track_id,
+                collapsed,
+            } => {
+                if let Some(track) = state.project.tracks.iter_mut().find(|t| t.id == *track_id) {
+                    track.is_collapsed = *collapsed;
+                }
+                Ok(())
+            }
+
+            DawCommand::SetTrackMonophonic {
+                track_id,
+                monophonic,
+            } => {
+                if let Some(track) = state.project.tracks.iter_mut().find(|t| t.id == *track_id) {
+                    track.is_monophonic = *monophonic;
+                }
+                Ok(())
+            }
+
+            DawCommand::SetTrackInputVelocityCurve { track_id, curve } => {
+                if let Some(track) = state.project.tracks.iter_mut().find(|t| t.id == *track_id) {
+                    track.input_velocity_curve = *curve;
+                }
+                Ok(())
+            }
+
+            DawCommand::SetTrackInstrument {
+                track_id,
+                instrument,
+            } => {
+                let track = state
+                    .project
+                    .tracks
+                    .iter_mut()
+                    .find(|t| t.id == *track_id)
+                    .ok_or("Track not found")?;
+                if !matches!(track.track_type, TrackType::Midi { .. }) {
+                    return Err("Instruments can only be set on MIDI tracks".into());
+                }
+                track.instrument = *instrument;
+                Ok(())
+            }
             
             DawCommand::ReorderTracks { from_index, to_index } => {
                 let len = state.project.tracks.len();
@@ -288,6 +530,21 @@ impl Command for DawCommand {
                 Ok(())
             }
             
+            DawCommand::RestoreTrack { index, track } => {
+                let index = (*index).min(state.project.tracks.len());
+                state.project.tracks.insert(index, track.as_ref().clone());
+                Ok(())
+            }
+
+            DawCommand::SetSoloStates { states } => {
+                for (track_id, soloed) in states {
+                    if let Some(track) = state.project.tracks.iter_mut().find(|t| t.id == *track_id) {
+                        track.is_soloed = *soloed;
+                    }
+                }
+                Ok(())
+            }
+
             DawCommand::AddTrack { track_type, name } => {
                 let track = Track {
                     id: Uuid::new_v4().to_string(),
@@ -298,6 +555,10 @@ impl Command for DawCommand {
                     is_soloed: false,
                     is_armed: false,
                     color: "#fde047".to_string(), // Default yellow
+                    instrument: None,
+                    is_collapsed: false,
+                    is_monophonic: false,
+                    input_velocity_curve: VelocityCurve::Linear,
                 };
                 state.project.tracks.push(track);
                 Ok(())
@@ -319,8 +580,13 @@ impl Command for DawCommand {
                 length,
                 file_path,
             } => {
+                let ppq = state.project.ppq;
                 if let Some(track) = state.project.tracks.iter_mut().find(|t| t.id == *track_id) {
                     let clip = match track.track_type {
+                        // No source file means a new empty clip at the project's time base
+                        TrackType::Midi { .. } if file_path.as_os_str().is_empty() => {
+                            Clip::empty_midi(*start_time, *length, ppq)
+                        }
                         TrackType::Midi { .. } => Clip::Midi {
                             id: Uuid::new_v4().to_string(),
                             start_time: *start_time,
@@ -329,6 +595,9 @@ impl Command for DawCommand {
                             midi_data: None,
                             loaded: false,
                             automation_lanes: Vec::new(),
+                            processors: Vec::new(),
+                            is_muted: false,
+                            color: None,
                         },
                         TrackType::Audio => Clip::Audio {
                             id: Uuid::new_v4().to_string(),
@@ -337,6 +606,9 @@ impl Command for DawCommand {
                             file_path: file_path.clone(),
                             start_offset: 0.0,
                             end_offset: *length,
+                            peaks: None,
+                            is_muted: false,
+                            color: None,
                         },
                     };
                     track.clips.push(clip);
@@ -376,6 +648,40 @@ impl Command for DawCommand {
                 Ok(())
             }
 
+            DawCommand::SnapClipToGrid { clip_id } => {
+                let (bpm, time_sig) = (state.project.bpm, state.project.time_signature);
+                let snap_mode = state.snap_mode;
+                // No grid to snap to
+                if snap_mode == SnapMode::None {
+                    return Ok(());
+                }
+                let clip = state
+                    .project
+                    .find_clip_mut(clip_id)
+                    .ok_or("Clip not found")?;
+                let snapped = TimeUtils::snap_time(clip.start_time(), bpm, time_sig, snap_mode);
+                clip.set_start_time(snapped.max(0.0));
+                Ok(())
+            }
+
+            DawCommand::SetClipMuted { clip_id, muted } => {
+                state
+                    .project
+                    .find_clip_mut(clip_id)
+                    .ok_or("Clip not found")?
+                    .set_muted(*muted);
+                Ok(())
+            }
+
+            DawCommand::SetClipColor { clip_id, color } => {
+                state
+                    .project
+                    .find_clip_mut(clip_id)
+                    .ok_or("Clip not found")?
+                    .set_color(color.clone());
+                Ok(())
+            }
+
             DawCommand::ResizeClip {
                 clip_id,
                 new_length,
@@ -393,6 +699,157 @@ impl Command for DawCommand {
                 Ok(())
             }
 
+            DawCommand::JoinClips {
+                track_id,
+                clip_id,
+                next_clip_id,
+            } => {
+                let track = state
+                    .project
+                    .tracks
+                    .iter_mut()
+                    .find(|t| t.id == *track_id)
+                    .ok_or("Track not found")?;
+                let next_index = track
+                    .clips
+                    .iter()
+                    .position(|c| c.id() == next_clip_id)
+                    .ok_or("Clip not found")?;
+                let next = track.clips[next_index].clone();
+                track
+                    .clips
+                    .iter_mut()
+                    .find(|c| c.id() == clip_id)
+                    .ok_or("Clip not found")?
+                    .join(&next)?;
+                track.clips.remove(next_index);
+                if state.selected_clip.as_deref() == Some(next_clip_id.as_str()) {
+                    state.selected_clip = Some(clip_id.clone());
+                }
+                Ok(())
+            }
+
+            DawCommand::RestoreClip {
+                track_id,
+                index,
+                clip,
+            } => {
+                let track = state
+                    .project
+                    .tracks
+                    .iter_mut()
+                    .find(|t| t.id == *track_id)
+                    .ok_or("Track not found")?;
+                let index = (*index).min(track.clips.len());
+                track.clips.insert(index, clip.as_ref().clone());
+                Ok(())
+            }
+
+            DawCommand::ClearClip { clip_id } => {
+                let ppq = state.project.ppq;
+                let clip = state
+                    .project
+                    .tracks
+                    .iter_mut()
+                    .flat_map(|t| t.clips.iter_mut())
+                    .find(|c| c.id() == clip_id)
+                    .ok_or("Clip not found")?;
+                let Clip::Midi {
+                    midi_data,
+                    loaded,
+                    automation_lanes,
+                    ..
+                } = clip
+                else {
+                    return Err("Only MIDI clips can be cleared".into());
+                };
+
+                // An unloaded clip would read its notes back from the file, so give it an empty store
+                midi_data.get_or_insert_with(|| MidiEventStore::new(ppq)).clear();
+                *loaded = true;
+                for lane in automation_lanes {
+                    lane.points.clear();
+                }
+                Ok(())
+            }
+
+            DawCommand::TransposeClip { clip_id, semitones } => {
+                let clip = state
+                    .project
+                    .find_clip_mut(clip_id)
+                    .ok_or("Clip not found")?;
+                clip.load_midi()
+                    .map_err(|e| format!("Failed to load MIDI data: {}", e))?;
+                let Clip::Midi {
+                    midi_data: Some(store),
+                    ..
+                } = clip
+                else {
+                    return Err("Only MIDI clips can be transposed".into());
+                };
+                store.transpose_all(*semitones);
+                Ok(())
+            }
+
+            DawCommand::SetClipProcessors {
+                clip_id,
+                processors,
+            } => {
+                let clip = state
+                    .project
+                    .find_clip_mut(clip_id)
+                    .ok_or("Clip not found")?;
+                let Clip::Midi {
+                    processors: clip_processors,
+                    ..
+                } = clip
+                else {
+                    return Err("Only MIDI clips have processors".into());
+                };
+                *clip_processors = processors.clone();
+                Ok(())
+            }
+
+            DawCommand::FreezeClip { clip_id } => {
+                let clip = state
+                    .project
+                    .find_clip_mut(clip_id)
+                    .ok_or("Clip not found")?;
+                clip.load_midi()
+                    .map_err(|e| format!("Failed to load MIDI data: {}", e))?;
+                let Clip::Midi {
+                    midi_data: Some(store),
+                    processors,
+                    ..
+                } = clip
+                else {
+                    return Err("Only MIDI clips can be frozen".into());
+                };
+                for processor in processors.drain(..) {
+                    processor.freeze(store);
+                }
+                Ok(())
+            }
+
+            DawCommand::ReplaceClip { clip } => {
+                let existing = state
+                    .project
+                    .tracks
+                    .iter_mut()
+                    .flat_map(|t| t.clips.iter_mut())
+                    .find(|c| c.id() == clip.id())
+                    .ok_or("Clip not found")?;
+                *existing = clip.as_ref().clone();
+                Ok(())
+            }
+
+            DawCommand::Batch { commands } => {
+                for command in commands {
+                    command.execute(state)?;
+                }
+                Ok(())
+            }
+
             // Do nothing.
             DawCommand::NoOp => Ok(()),
             DawCommand::EnableMetronome {} => {
@@ -410,20 +867,34 @@ impl Command for DawCommand {
                 state.status.info(format!("BPM set to: {}", bpm));
                 Ok(())
             }
+            DawCommand::SetTimeSignature { time_signature } => {
+                state.project.time_signature = *time_signature;
+                Ok(())
+            }
             DawCommand::StopPlayback => {
                 state.playing = false;
                 state.current_time = 0.0;
+                state.transport_events.push(TransportEvent::Stopped);
                 Ok(())
             }
 
             DawCommand::StartPlayback => {
                 state.playing = true;
+                // Measure from now rather than from whenever the playhead last moved
+                state.last_update = Some(std::time::Instant::now());
+                state.transport_events.push(TransportEvent::Started);
+                // Rehearsing is playing along to the click, so make sure there is one
+                if state.rehearsing {
+                    state.metronome_before_rehearsal.get_or_insert(state.metronome);
+                    state.metronome = true;
+                }
 
                 Ok(())
             }
 
             DawCommand::PausePlayback => {
                 state.playing = false;
+                state.transport_events.push(TransportEvent::Paused);
 
                 Ok(())
             }
@@ -537,8 +1008,113 @@ impl Command for DawCommand {
                 }
                 Ok(())
             }
+
+            DawCommand::SetNoteChannel {
+                clip_id,
+                note_ids,
+                channel,
+            } => {
+                if *channel > 15 {
+                    return Err("MIDI channel must be between 1 and 16".into());
+                }
+                let store = state
+                    .project
+                    .find_clip_mut(clip_id)
+                    .and_then(|clip| match clip {
+                        Clip::Midi { midi_data, .. } => midi_data.as_mut(),
+                        Clip::Audio { .. } => None,
+                    })
+                    .ok_or("MIDI clip not found")?;
+                for note_id in note_ids {
+                    store.set_note_channel(note_id, *channel);
+                }
+                Ok(())
+            }
             
             // Automation commands
+            DawCommand::AddMarker { marker } => {
+                state.project.markers.push(marker.clone());
+                Ok(())
+            }
+
+            DawCommand::DeleteMarker { marker_id } => {
+                state.project.markers.retain(|m| m.id != *marker_id);
+                Ok(())
+            }
+
+            DawCommand::MoveMarker { marker_id, time } => {
+                let marker = state
+                    .project
+                    .markers
+                    .iter_mut()
+                    .find(|m| m.id == *marker_id)
+                    .ok_or("Marker not found")?;
+                marker.time = time.max(0.0);
+                Ok(())
+            }
+
+            DawCommand::RenameMarker { marker_id, name } => {
+                let marker = state
+                    .project
+                    .markers
+                    .iter_mut()
+                    .find(|m| m.id == *marker_id)
+                    .ok_or("Marker not found")?;
+                marker.name = name.clone();
+                Ok(())
+            }
+
+            DawCommand::InsertTime { time, duration } => {
+                if *duration <= 0.0 {
+                    return Err("Inserted time must be longer than zero".into());
+                }
+                state.project.insert_time(time.max(0.0), *duration)
+            }
+
+            DawCommand::RemoveTime { start, end } => {
+                if end <= start {
+                    return Err("Removed range must be longer than zero".into());
+                }
+                state.project.remove_time(start.max(0.0), *end)
+            }
+
+            DawCommand::AddSection { section } => {
+                state.project.sections.push(section.clone());
+                Ok(())
+            }
+
+            DawCommand::DeleteSection { section_id } => {
+                state.project.sections.retain(|s| s.id != *section_id);
+                Ok(())
+            }
+
+            DawCommand::SetSectionRange {
+                section_id,
+                start,
+                end,
+            } => {
+                let section = state
+                    .project
+                    .sections
+                    .iter_mut()
+                    .find(|s| s.id == *section_id)
+                    .ok_or("Section not found")?;
+                section.start = start.min(*end).max(0.0);
+                section.end = start.max(*end).max(0.0);
+                Ok(())
+            }
+
+            DawCommand::RenameSection { section_id, name } => {
+                let section = state
+                    .project
+                    .sections
+                    .iter_mut()
+                    .find(|s| s.id == *section_id)
+                    .ok_or("Section not found")?;
+                section.name = name.clone();
+                Ok(())
+            }
+
             DawCommand::AddAutomationLane { clip_id, parameter } => {
                 for track in &mut state.project.tracks {
                     if let Some(Clip::Midi { automation_lanes, .. }) = track
@@ -643,10 +1219,12 @@ impl Command for DawCommand {
             DawCommand::MoveNotes { .. } => "Move Notes",
             DawCommand::DeleteNotes { .. } => "Delete Notes",
             DawCommand::UpdateNoteVelocity { .. } => "Update Note Velocity",
+            DawCommand::SetNoteChannel { .. } => "Set Note Channel",
             DawCommand::AddNote { .. } => "Add Note",
             DawCommand::SetSnapMode { .. } => "Set Snap Mode",
             DawCommand::SeekTime { .. } => "Seek Time",
             DawCommand::OpenPianoRoll { .. } => "Open Piano Roll",
+            DawCommand::OpenEventList { .. } => "Open Event List",
             DawCommand::SelectClip { .. } => "Select Clip",
             DawCommand::SelectTrack { .. } => "Select Track",
             DawCommand::AddTrack { .. } => "Add Track",
@@ -654,11 +1232,23 @@ impl Command for DawCommand {
             DawCommand::AddClip { .. } => "Add Clip",
             DawCommand::DeleteClip { .. } => "Delete Clip",
             DawCommand::MoveClip { .. } => "Move Clip",
+            DawCommand::SnapClipToGrid { .. } => "Snap Clip to Grid",
+            DawCommand::SetClipMuted { .. } => "Set Clip Muted",
+            DawCommand::SetClipColor { .. } => "Set Clip Color",
             DawCommand::ResizeClip { .. } => "Resize Clip",
+            DawCommand::JoinClips { .. } => "Join Clips",
+            DawCommand::RestoreClip { .. } => "Restore Clip",
+            DawCommand::ClearClip { .. } => "Clear Clip",
+            DawCommand::TransposeClip { .. } => "Transpose Clip",
+            DawCommand::SetClipProcessors { .. } => "Set Clip Processors",
+            DawCommand::FreezeClip { .. } => "Freeze Clip",
+            DawCommand::ReplaceClip { .. } => "Replace Clip",
+            DawCommand::Batch { .. } => "Batch",
             DawCommand::NoOp => "NoOp",
             DawCommand::EnableMetronome { .. } => "Enable Metronome",
             DawCommand::DisableMetronome => "Disable Metronome",
             DawCommand::SetBpm { .. } => "Set BPM",
+            DawCommand::SetTimeSignature { .. } => "Set Time Signature",
             DawCommand::StopPlayback => "Stop Playback",
             DawCommand::StartPlayback => "Start Playback",
             DawCommand::PausePlayback => "Pause Playback",
@@ -670,8 +1260,24 @@ impl Command for DawCommand {
             DawCommand::ArmTrack { .. } => "Arm Track",
             DawCommand::UnarmTrack { .. } => "Unarm Track",
             DawCommand::SetTrackColor { .. } => "Set Track Color",
+            DawCommand::SetTrackCollapsed { .. } => "Set Track Collapsed",
+            DawCommand::SetTrackMonophonic { .. } => "Set Track Monophonic",
+            DawCommand::SetTrackInputVelocityCurve { .. } => "Set Track Input Velocity Curve",
+            DawCommand::SetTrackInstrument { .. } => "Set Track Instrument",
             DawCommand::ReorderTracks { .. } => "Reorder Tracks",
+            DawCommand::RestoreTrack { .. } => "Restore Track",
+            DawCommand::SetSoloStates { .. } => "Set Solo States",
             DawCommand::DeselectAll => "Deselect All",
+            DawCommand::AddMarker { .. } => "Add Marker",
+            DawCommand::DeleteMarker { .. } => "Delete Marker",
+            DawCommand::MoveMarker { .. } => "Move Marker",
+            DawCommand::RenameMarker { .. } => "Rename Marker",
+            DawCommand::InsertTime { .. } => "Insert Time",
+            DawCommand::RemoveTime { .. } => "Remove Time",
+            DawCommand::AddSection { .. } => "Add Section",
+            DawCommand::DeleteSection { .. } => "Delete Section",
+            DawCommand::SetSectionRange { .. } => "Set Section Range",
+            DawCommand::RenameSection { .. } => "Rename Section",
             DawCommand::AddAutomationLane { .. } => "Add Automation Lane",
             DawCommand::RemoveAutomationLane { .. } => "Remove Automation Lane",
             DawCommand::SetAutomationLaneVisibility { .. } => "Set Automation Lane Visibility",
@@ -682,6 +1288,556 @@ impl Command for DawCommand {
     }
 }
 
+impl DawCommand {
+    /// Execute the command and return a command that reverses it.
+    /// Returns `None` for commands that can only be undone by restoring a state snapshot.
+    pub fn execute_reversible(
+        &self,
+        state: &mut DawState,
+    ) -> Result<Option<DawCommand>, Box<dyn std::error::Error>> {
+        if let DawCommand::Batch { commands } = self {
+            return Self::execute_batch(commands, state);
+        }
+
+        // New notes and clips get their id during execution, so remember which ones already exist
+        let existing_ids: Option<HashSet<String>> = match self {
+            DawCommand::AddNote { clip_id, .. } => Self::find_midi_store(state, clip_id)
+                .map(|store| store.get_notes().map(|n| n.id.clone()).collect()),
+            DawCommand::AddClip { track_id, .. } => state
+                .project
+                .tracks
+                .iter()
+                .find(|t| t.id == *track_id)
+                .map(|track| track.clips.iter().map(|c| c.id().to_string()).collect()),
+            _ => None,
+        };
+
+        let inverse = self.inverse(state);
+        self.execute(state)?;
+
+        // Commands that create something only know its id after running
+        let inverse = match self {
+            DawCommand::AddNote { clip_id, .. } => existing_ids.and_then(|existing| {
+                Self::find_midi_store(state, clip_id)?
+                    .get_notes()
+                    .find(|n| !existing.contains(&n.id))
+                    .map(|note| DawCommand::DeleteNotes {
+                        clip_id: clip_id.clone(),
+                        note_ids: vec![note.id.clone()],
+                    })
+            }),
+            DawCommand::AddTrack { .. } => {
+                state
+                    .project
+                    .tracks
+                    .last()
+                    .map(|track| DawCommand::DeleteTrack {
+                        track_id: track.id.clone(),
+                    })
+            }
+            DawCommand::AddClip { track_id, .. } => existing_ids.and_then(|existing| {
+                state
+                    .project
+                    .tracks
+                    .iter()
+                    .find(|t| t.id == *track_id)?
+                    .clips
+                    .iter()
+                    .find(|c| !existing.contains(c.id()))
+                    .map(|clip| DawCommand::DeleteClip {
+                        track_id: track_id.clone(),
+                        clip_id: clip.id().to_string(),
+                    })
+            }),
+            _ => inverse,
+        };
+
+        Ok(inverse)
+    }
+
+    /// Whether `execute_reversible` can build an inverse, judged from the state before running.
+    /// The history only copies the whole state for commands where this is false.
+    pub fn has_inverse(&self, state: &DawState) -> bool {
+        match self {
+            DawCommand::AddNote { clip_id, .. } => Self::find_midi_store(state, clip_id).is_some(),
+            DawCommand::AddTrack { .. } => true,
+            DawCommand::AddClip { track_id, .. } => {
+                state.project.tracks.iter().any(|t| t.id == *track_id)
+            }
+            DawCommand::Batch { commands } => commands.iter().all(|c| c.has_inverse(state)),
+            _ => self.inverse(state).is_some(),
+        }
+    }
+
+    /// Run a batch's commands in order; if one fails, the ones already applied are rolled back.
+    /// The batch is only reversible when every command in it is.
+    fn execute_batch(
+        commands: &[DawCommand],
+        state: &mut DawState,
+    ) -> Result<Option<DawCommand>, Box<dyn std::error::Error>> {
+        let mut inverses = Vec::with_capacity(commands.len());
+        let mut reversible = true;
+        // Commands without an inverse can only be rolled back by restoring a copy
+        let before = (!commands.iter().all(|c| c.has_inverse(state))).then(|| state.clone());
+
+        for command in commands {
+            match command.execute_reversible(state) {
+                Ok(Some(inverse)) => inverses.push(inverse),
+                Ok(None) => reversible = false,
+                Err(e) => {
+                    match before {
+                        Some(mut restored) => {
+                            // Keep messages logged while the batch ran
+                            std::mem::swap(&mut restored.status, &mut state.status);
+                            *state = restored;
+                        }
+                        None => {
+                            for inverse in inverses.iter().rev() {
+                                inverse.execute(state)?;
+                            }
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        inverses.reverse();
+        Ok(reversible.then_some(DawCommand::Batch { commands: inverses }))
+    }
+
+    fn find_midi_store<'a>(state: &'a DawState, clip_id: &str) -> Option<&'a MidiEventStore> {
+        state.project.tracks.iter().find_map(|track| {
+            track.clips.iter().find_map(|clip| match clip {
+                Clip::Midi { id, midi_data, .. } if id == clip_id => midi_data.as_ref(),
+                _ => None,
+            })
+        })
+    }
+
+    /// Whether the command is an edit that gets its own undo step. Moving the playhead,
+    /// selecting, switching views, starting or stopping playback and toggling the snap mode
+    /// or metronome are not; recording them would bury real edits and throw away the redo
+    /// stack on every click. Inside a batch (step input advancing the playhead) they are
+    /// still undone with the rest.
+    pub fn records_history(&self) -> bool {
+        !matches!(
+            self,
+            DawCommand::SeekTime { .. }
+                | DawCommand::SelectTrack { .. }
+                | DawCommand::SelectClip { .. }
+                | DawCommand::DeselectAll
+                | DawCommand::OpenPianoRoll { .. }
+                | DawCommand::OpenEventList { .. }
+                | DawCommand::StartPlayback
+                | DawCommand::PausePlayback
+                | DawCommand::StopPlayback
+                | DawCommand::SetSnapMode { .. }
+                | DawCommand::EnableMetronome
+                | DawCommand::DisableMetronome
+        )
+    }
+
+    /// Whether this command continues `previous` (successive frames of the same drag),
+    /// so both can share a single undo step
+    pub fn continues(&self, previous: &DawCommand) -> bool {
+        match (self, previous) {
+            (
+                DawCommand::MoveClip { clip_id, .. },
+                DawCommand::MoveClip {
+                    clip_id: previous_id,
+                    ..
+                },
+            )
+            | (
+                DawCommand::ResizeClip { clip_id, .. },
+                DawCommand::ResizeClip {
+                    clip_id: previous_id,
+                    ..
+                },
+            )
+            | (
+                DawCommand::SetClipProcessors { clip_id, .. },
+                DawCommand::SetClipProcessors {
+                    clip_id: previous_id,
+                    ..
+                },
+            ) => clip_id == previous_id,
+            (
+                DawCommand::ResizeNote { note_id, .. },
+                DawCommand::ResizeNote {
+                    note_id: previous_id,
+                    ..
+                },
+            ) => note_id == previous_id,
+            (
+                DawCommand::MoveNotes {
+                    clip_id, note_ids, ..
+                },
+                DawCommand::MoveNotes {
+                    clip_id: previous_clip,
+                    note_ids: previous_notes,
+                    ..
+                },
+            ) => clip_id == previous_clip && note_ids == previous_notes,
+            (
+                DawCommand::MoveMarker { marker_id, .. },
+                DawCommand::MoveMarker {
+                    marker_id: previous_id,
+                    ..
+                },
+            ) => marker_id == previous_id,
+            (
+                DawCommand::SetSectionRange { section_id, .. },
+                DawCommand::SetSectionRange {
+                    section_id: previous_id,
+                    ..
+                },
+            ) => section_id == previous_id,
+            (
+                DawCommand::Batch { commands },
+                DawCommand::Batch {
+                    commands: previous,
+                },
+            ) => {
+                commands.len() == previous.len()
+                    && commands.iter().zip(previous).all(|(c, p)| c.continues(p))
+            }
+            _ => false,
+        }
+    }
+
+    /// The single command with the effect of `previous` followed by this one, for a command
+    /// that `continues` it. Moves by a delta add up; the rest already carry the final value.
+    pub fn fold(self, previous: DawCommand) -> DawCommand {
+        match (self, previous) {
+            (
+                DawCommand::MoveNotes {
+                    clip_id,
+                    note_ids,
+                    delta_time,
+                    delta_pitch,
+                },
+                DawCommand::MoveNotes {
+                    delta_time: previous_time,
+                    delta_pitch: previous_pitch,
+                    ..
+                },
+            ) => DawCommand::MoveNotes {
+                clip_id,
+                note_ids,
+                delta_time: delta_time + previous_time,
+                delta_pitch: delta_pitch.saturating_add(previous_pitch),
+            },
+            (DawCommand::Batch { commands }, DawCommand::Batch { commands: previous })
+                if commands.len() == previous.len() =>
+            {
+                DawCommand::Batch {
+                    commands: commands
+                        .into_iter()
+                        .zip(previous)
+                        .map(|(command, previous)| command.fold(previous))
+                        .collect(),
+                }
+            }
+            (command, _) => command,
+        }
+    }
+
+    /// Build the command that undoes this one, from the state before it runs
+    fn inverse(&self, state: &DawState) -> Option<DawCommand> {
+        let find_clip = |clip_id: &str| {
+            state.project.tracks.iter().find_map(|track| {
+                track
+                    .clips
+                    .iter()
+                    .position(|c| c.id() == clip_id)
+                    .map(|index| (track, index))
+            })
+        };
+
+        let find_track = |track_id: &str| state.project.tracks.iter().find(|t| t.id == track_id);
+        let find_marker =
+            |marker_id: &str| state.project.markers.iter().find(|m| m.id == marker_id);
+        let find_section =
+            |section_id: &str| state.project.sections.iter().find(|s| s.id == section_id);
+
+        match self {
+            DawCommand::DeleteTrack { track_id } => state
+                .project
+                .tracks
+                .iter()
+                .position(|t| t.id == *track_id)
+                .map(|index| DawCommand::RestoreTrack {
+                    index,
+                    track: Box::new(state.project.tracks[index].clone()),
+                }),
+            DawCommand::RestoreTrack { track, .. } => Some(DawCommand::DeleteTrack {
+                track_id: track.id.clone(),
+            }),
+            DawCommand::ReorderTracks {
+                from_index,
+                to_index,
+            } => {
+                let len = state.project.tracks.len();
+                (*from_index < len && *to_index < len).then_some(DawCommand::ReorderTracks {
+                    from_index: *to_index,
+                    to_index: *from_index,
+                })
+            }
+            DawCommand::SetTrackMidiChannel { track_id, .. } => {
+                find_track(track_id).and_then(|track| match &track.track_type {
+                    TrackType::Midi { channel, .. } => Some(DawCommand::SetTrackMidiChannel {
+                        track_id: track_id.clone(),
+                        channel: *channel,
+                    }),
+                    TrackType::Audio => None,
+                })
+            }
+            DawCommand::MuteTrack { track_id } | DawCommand::UnmuteTrack { track_id } => {
+                find_track(track_id).map(|track| {
+                    let track_id = track_id.clone();
+                    if track.is_muted {
+                        DawCommand::MuteTrack { track_id }
+                    } else {
+                        DawCommand::UnmuteTrack { track_id }
+                    }
+                })
+            }
+            DawCommand::ArmTrack { track_id } | DawCommand::UnarmTrack { track_id } => {
+                find_track(track_id).map(|track| {
+                    let track_id = track_id.clone();
+                    if track.is_armed {
+                        DawCommand::ArmTrack { track_id }
+                    } else {
+                        DawCommand::UnarmTrack { track_id }
+                    }
+                })
+            }
+            // Soloing one track clears every other solo, so capture them all
+            DawCommand::SoloTrack { .. }
+            | DawCommand::UnsoloTrack { .. }
+            | DawCommand::SetSoloStates { .. } => Some(DawCommand::SetSoloStates {
+                states: state
+                    .project
+                    .tracks
+                    .iter()
+                    .map(|t| (t.id.clone(), t.is_soloed))
+                    .collect(),
+            }),
+            DawCommand::UpdateNoteVelocity {
+                clip_id, note_id, ..
+            } => Self::find_midi_store(state, clip_id)
+                .and_then(|store| store.get_note(note_id))
+                .map(|note| DawCommand::UpdateNoteVelocity {
+                    clip_id: clip_id.clone(),
+                    note_id: note_id.clone(),
+                    velocity: note.velocity,
+                }),
+            DawCommand::SetNoteChannel {
+                clip_id, note_ids, ..
+            } => Self::find_midi_store(state, clip_id).map(|store| {
+                // Send each note back to the channel it came from
+                let mut by_channel: Vec<(u8, Vec<EventID>)> = Vec::new();
+                for note in note_ids.iter().filter_map(|id| store.get_note(id)) {
+                    match by_channel.iter_mut().find(|(channel, _)| *channel == note.channel) {
+                        Some((_, ids)) => ids.push(note.id.clone()),
+                        None => by_channel.push((note.channel, vec![note.id.clone()])),
+                    }
+                }
+                DawCommand::Batch {
+                    commands: by_channel
+                        .into_iter()
+                        .map(|(channel, note_ids)| DawCommand::SetNoteChannel {
+                            clip_id: clip_id.clone(),
+                            note_ids,
+                            channel,
+                        })
+                        .collect(),
+                }
+            }),
+            // Moving back by the same amount only works when no note hits the start of the
+            // clip or an end of the key range on the way
+            DawCommand::MoveNotes {
+                clip_id,
+                note_ids,
+                delta_time,
+                delta_pitch,
+            } => Self::find_midi_store(state, clip_id).and_then(|store| {
+                let unclamped = note_ids.iter().filter_map(|id| store.get_note(id)).all(|note| {
+                    note.start_time + delta_time >= 0.0
+                        && (0..=127).contains(&(note.key as i16 + *delta_pitch as i16))
+                });
+                unclamped.then(|| DawCommand::MoveNotes {
+                    clip_id: clip_id.clone(),
+                    note_ids: note_ids.clone(),
+                    delta_time: -delta_time,
+                    delta_pitch: -delta_pitch,
+                })
+            }),
+            DawCommand::ResizeNote {
+                clip_id, note_id, ..
+            } => Self::find_midi_store(state, clip_id)
+                .and_then(|store| store.get_note(note_id))
+                .map(|note| DawCommand::ResizeNote {
+                    clip_id: clip_id.clone(),
+                    note_id: note_id.clone(),
+                    new_start_time: note.start_time,
+                    new_duration: note.duration,
+                }),
+            DawCommand::SeekTime { .. } => Some(DawCommand::SeekTime {
+                time: state.current_time,
+            }),
+            DawCommand::SetTrackColor { track_id, .. } => {
+                find_track(track_id).map(|track| DawCommand::SetTrackColor {
+                    track_id: track_id.clone(),
+                    color: track.color.clone(),
+                })
+            }
+            DawCommand::SetTrackCollapsed { track_id, .. } => {
+                find_track(track_id).map(|track| DawCommand::SetTrackCollapsed {
+                    track_id: track_id.clone(),
+                    collapsed: track.is_collapsed,
+                })
+            }
+            DawCommand::SetTrackMonophonic { track_id, .. } => {
+                find_track(track_id).map(|track| DawCommand::SetTrackMonophonic {
+                    track_id: track_id.clone(),
+                    monophonic: track.is_monophonic,
+                })
+            }
+            DawCommand::SetTrackInputVelocityCurve { track_id, .. } => {
+                find_track(track_id).map(|track| DawCommand::SetTrackInputVelocityCurve {
+                    track_id: track_id.clone(),
+                    curve: track.input_velocity_curve,
+                })
+            }
+            DawCommand::SetTrackInstrument { track_id, .. } => {
+                find_track(track_id).map(|track| DawCommand::SetTrackInstrument {
+                    track_id: track_id.clone(),
+                    instrument: track.instrument,
+                })
+            }
+            DawCommand::SetClipMuted { clip_id, .. } => {
+                find_clip(clip_id).map(|(track, index)| DawCommand::SetClipMuted {
+                    clip_id: clip_id.clone(),
+                    muted: track.clips[index].is_muted(),
+                })
+            }
+            DawCommand::SetBpm { .. } => Some(DawCommand::SetBpm {
+                bpm: state.project.bpm,
+            }),
+            DawCommand::SetTimeSignature { .. } => Some(DawCommand::SetTimeSignature {
+                time_signature: state.project.time_signature,
+            }),
+            DawCommand::AddMarker { marker } => Some(DawCommand::DeleteMarker {
+                marker_id: marker.id.clone(),
+            }),
+            DawCommand::DeleteMarker { marker_id } => find_marker(marker_id).map(|marker| {
+                DawCommand::AddMarker {
+                    marker: marker.clone(),
+                }
+            }),
+            DawCommand::MoveMarker { marker_id, .. } => {
+                find_marker(marker_id).map(|marker| DawCommand::MoveMarker {
+                    marker_id: marker_id.clone(),
+                    time: marker.time,
+                })
+            }
+            DawCommand::RenameMarker { marker_id, .. } => {
+                find_marker(marker_id).map(|marker| DawCommand::RenameMarker {
+                    marker_id: marker_id.clone(),
+                    name: marker.name.clone(),
+                })
+            }
+            DawCommand::AddSection { section } => Some(DawCommand::DeleteSection {
+                section_id: section.id.clone(),
+            }),
+            DawCommand::DeleteSection { section_id } => {
+                find_section(section_id).map(|section| DawCommand::AddSection {
+                    section: section.clone(),
+                })
+            }
+            DawCommand::SetSectionRange { section_id, .. } => {
+                find_section(section_id).map(|section| DawCommand::SetSectionRange {
+                    section_id: section_id.clone(),
+                    start: section.start,
+                    end: section.end,
+                })
+            }
+            DawCommand::RenameSection { section_id, .. } => {
+                find_section(section_id).map(|section| DawCommand::RenameSection {
+                    section_id: section_id.clone(),
+                    name: section.name.clone(),
+                })
+            }
+            DawCommand::SetClipColor { clip_id, .. } => {
+                find_clip(clip_id).map(|(track, index)| DawCommand::SetClipColor {
+                    clip_id: clip_id.clone(),
+                    color: track.clips[index].color().map(str::to_string),
+                })
+            }
+            DawCommand::DeleteClip { clip_id, .. } => {
+                find_clip(clip_id).map(|(track, index)| DawCommand::RestoreClip {
+                    track_id: track.id.clone(),
+                    index,
+                    clip: Box::new(track.clips[index].clone()),
+                })
+            }
+            DawCommand::RestoreClip { track_id, clip, .. } => Some(DawCommand::DeleteClip {
+                track_id: track_id.clone(),
+                clip_id: clip.id().to_string(),
+            }),
+            // Deleted notes come back with their ids, so a redo of AddNote hits the same note.
+            // Transposing can clamp notes together at the ends of the range, so it can't be
+            // undone by transposing back.
+            DawCommand::ClearClip { clip_id }
+            | DawCommand::DeleteNotes { clip_id, .. }
+            | DawCommand::TransposeClip { clip_id, .. }
+            | DawCommand::FreezeClip { clip_id } => {
+                find_clip(clip_id).map(|(track, index)| DawCommand::ReplaceClip {
+                    clip: Box::new(track.clips[index].clone()),
+                })
+            }
+            DawCommand::SetClipProcessors { clip_id, .. } => {
+                find_clip(clip_id).and_then(|(track, index)| match &track.clips[index] {
+                    Clip::Midi { processors, .. } => Some(DawCommand::SetClipProcessors {
+                        clip_id: clip_id.clone(),
+                        processors: processors.clone(),
+                    }),
+                    Clip::Audio { .. } => None,
+                })
+            }
+            DawCommand::ReplaceClip { clip } => {
+                find_clip(clip.id()).map(|(track, index)| DawCommand::ReplaceClip {
+                    clip: Box::new(track.clips[index].clone()),
+                })
+            }
+            DawCommand::MoveClip {
+                clip_id, track_id, ..
+            } => find_clip(clip_id).map(|(track, index)| DawCommand::MoveClip {
+                clip_id: clip_id.clone(),
+                track_id: track_id.clone(),
+                new_start_time: track.clips[index].start_time(),
+            }),
+            DawCommand::SnapClipToGrid { clip_id } => {
+                find_clip(clip_id).map(|(track, index)| DawCommand::MoveClip {
+                    clip_id: clip_id.clone(),
+                    track_id: track.id.clone(),
+                    new_start_time: track.clips[index].start_time(),
+                })
+            }
+            DawCommand::ResizeClip { clip_id, .. } => {
+                find_clip(clip_id).map(|(track, index)| DawCommand::ResizeClip {
+                    clip_id: clip_id.clone(),
+                    new_length: track.clips[index].length(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct CommandCollector {
     commands: Vec<DawCommand>,
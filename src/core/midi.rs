@@ -5,11 +5,22 @@ use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use uuid::Uuid;
 
+/// Source of `MidiEventStore` revisions, shared so no two edits get the same stamp
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+
+fn next_revision() -> u64 {
+    NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
 // Unique identifier for MIDI notes and events
 pub type EventID = String;
 
+/// One track of a parsed MIDI file, no longer borrowing the file's bytes
+type SmfTrack = Vec<midly::TrackEvent<'static>>;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum MidiMessage {
     // Note messages
@@ -52,6 +63,49 @@ pub enum MidiMessage {
     MidiContinue,
 }
 
+/// How `MidiEventStore::save_to_file` lays out a file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MidiExportOptions {
+    /// One track per channel (Format 1) instead of everything merged into one
+    pub split_channels: bool,
+    /// Keep controller and pitch bend data
+    pub include_controllers: bool,
+}
+
+impl Default for MidiExportOptions {
+    fn default() -> Self {
+        Self {
+            split_channels: false,
+            include_controllers: true,
+        }
+    }
+}
+
+impl MidiMessage {
+    /// Continuous controller data, as opposed to notes and program changes
+    pub fn is_controller(&self) -> bool {
+        matches!(
+            self,
+            MidiMessage::ControlChange { .. }
+                | MidiMessage::PitchBend { .. }
+                | MidiMessage::Aftertouch { .. }
+        )
+    }
+
+    /// Channel of a channel message, `None` for system messages
+    pub fn channel(&self) -> Option<u8> {
+        match self {
+            MidiMessage::NoteOn { channel, .. }
+            | MidiMessage::NoteOff { channel, .. }
+            | MidiMessage::ControlChange { channel, .. }
+            | MidiMessage::ProgramChange { channel, .. }
+            | MidiMessage::PitchBend { channel, .. }
+            | MidiMessage::Aftertouch { channel, .. } => Some(*channel),
+            _ => None,
+        }
+    }
+}
+
 // A single MIDI event with timing information
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct MidiEvent {
@@ -89,13 +143,18 @@ pub struct MidiEventStore {
     // Notes for piano roll display/editing
     notes: HashMap<EventID, Note>,
 
-    // Track tempo changes
+    // Track tempo changes. The first entry is always the 120 BPM default from `new()`,
+    // changes read from a file follow it.
     tempo_map: Vec<TempoChange>,
 
-    // Time signature changes
+    // Time signature changes, led by the 4/4 default the same way
     time_signatures: Vec<TimeSignature>,
 
     ppq: u32, // Pulses per quarter note (time resolution)
+
+    // Changes on every edit, so views can cache what they derive from the contents
+    #[serde(skip, default = "next_revision")]
+    revision: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -128,9 +187,15 @@ impl MidiEventStore {
                 denominator: 4,
             }],
             ppq,
+            revision: next_revision(),
         }
     }
 
+    /// Stamp that changes whenever the events or notes are edited
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
     pub fn add_event(&mut self, event: MidiEvent) {
         let id = event.id.clone();
         let time = OrderedFloat(event.time); // Convert f64 to OrderedFloat
@@ -145,6 +210,7 @@ impl MidiEventStore {
             .or_default()
             .push(id.clone());
         self.event_data.insert(id, event);
+        self.revision = next_revision();
     }
 
     pub fn add_note(&mut self, note: Note) {
@@ -176,6 +242,7 @@ impl MidiEventStore {
         self.add_event(note_on);
         self.add_event(note_off);
         self.notes.insert(note.id.clone(), note);
+        self.revision = next_revision();
     }
 
     pub fn get_events_in_range(&self, start_time: f64, end_time: f64) -> Vec<&MidiEvent> {
@@ -211,7 +278,48 @@ impl MidiEventStore {
         self.notes.values()
     }
 
+    /// Time where the last note ends, if there are any notes
+    pub fn last_note_end(&self) -> Option<f64> {
+        self.notes
+            .values()
+            .map(|note| note.start_time + note.duration)
+            .max_by(f64::total_cmp)
+    }
+
     // Time conversion methods
+    pub fn get_note(&self, note_id: &str) -> Option<&Note> {
+        self.notes.get(note_id)
+    }
+
+    /// Tempo in BPM at the start of the file, if the file set one
+    pub fn initial_bpm(&self) -> Option<f64> {
+        // The first entry is the default every store starts with
+        debug_assert!(!self.tempo_map.is_empty(), "tempo map lost its default");
+        let explicit = &self.tempo_map[1..];
+        let first_tick = explicit.iter().map(|tc| tc.tick).min()?;
+        explicit
+            .iter()
+            .rev()
+            .find(|tc| tc.tick == first_tick)
+            .map(|tc| 60_000_000.0 / tc.tempo as f64)
+    }
+
+    /// Time signature the file starts in, if it declares one
+    pub fn initial_time_signature(&self) -> Option<(u8, u8)> {
+        // Same as the tempo map, the first entry is the default
+        debug_assert!(
+            !self.time_signatures.is_empty(),
+            "time signatures lost their default"
+        );
+        let explicit = &self.time_signatures[1..];
+        let first_tick = explicit.iter().map(|ts| ts.tick).min()?;
+        explicit
+            .iter()
+            .rev()
+            .find(|ts| ts.tick == first_tick)
+            .map(|ts| (ts.numerator, ts.denominator))
+    }
+
     pub fn tick_to_time(&self, tick: u32) -> f64 {
         let tempo_change = self
             .tempo_map
@@ -232,6 +340,15 @@ impl MidiEventStore {
         (time * ticks_per_second) as u32
     }
 
+    /// Remove every event and note, keeping the time base, tempo map and time signatures
+    pub fn clear(&mut self) {
+        self.events_by_time.clear();
+        self.events_by_tick.clear();
+        self.event_data.clear();
+        self.notes.clear();
+        self.revision = next_revision();
+    }
+
     pub fn delete_note(&mut self, note_id: &str) {
         // First collect all the IDs we need to remove
         let on_id = format!("{}_on", note_id);
@@ -257,6 +374,7 @@ impl MidiEventStore {
 
         // Remove the note itself
         self.notes.remove(note_id);
+        self.revision = next_revision();
     }
 
     pub fn update_note(&mut self, note_id: &str, new_start: f64, new_duration: f64) {
@@ -287,7 +405,15 @@ impl MidiEventStore {
     pub fn update_note_velocity(&mut self, note_id: &str, new_velocity: u8) {
         if let Some(note) = self.notes.get_mut(note_id) {
             note.velocity = new_velocity;
-            // TODO: Update the corresponding events in the event stores
+            self.revision = next_revision();
+            // Playback reads the velocity from the NoteOn
+            if let Some(MidiMessage::NoteOn { velocity, .. }) = self
+                .event_data
+                .get_mut(&format!("{}_on", note_id))
+                .map(|event| &mut event.message)
+            {
+                *velocity = new_velocity;
+            }
         }
     }
 
@@ -317,8 +443,233 @@ impl MidiEventStore {
         // Add updated note
         self.add_note(updated_note);
     }
+
+    /// Move a note to another MIDI channel, along with its note on/off events
+    pub fn set_note_channel(&mut self, note_id: &str, new_channel: u8) {
+        let Some(note) = self.notes.get_mut(note_id) else {
+            return;
+        };
+        note.channel = new_channel;
+
+        for event_id in [format!("{}_on", note_id), format!("{}_off", note_id)] {
+            if let Some(MidiMessage::NoteOn { channel, .. } | MidiMessage::NoteOff { channel, .. }) =
+                self.event_data.get_mut(&event_id).map(|event| &mut event.message)
+            {
+                *channel = new_channel;
+            }
+        }
+        self.revision = next_revision();
+    }
+
+    /// Shift every note by `semitones`, clamping at the ends of the MIDI range
+    pub fn transpose_all(&mut self, semitones: i8) {
+        for note in self.notes.values_mut() {
+            note.key = (note.key as i16 + semitones as i16).clamp(0, 127) as u8;
+
+            // Keep the note's own events in step so playback hears the new pitch
+            for event_id in [format!("{}_on", note.id), format!("{}_off", note.id)] {
+                if let Some(MidiMessage::NoteOn { key, .. } | MidiMessage::NoteOff { key, .. }) =
+                    self.event_data.get_mut(&event_id).map(|event| &mut event.message)
+                {
+                    *key = note.key;
+                }
+            }
+        }
+        self.revision = next_revision();
+    }
+
+    /// Move every note and non-note event at or after `time` by `delta` seconds
+    pub fn shift_from(&mut self, time: f64, delta: f64) {
+        let note_ids: Vec<EventID> = self
+            .notes
+            .values()
+            .filter(|note| note.start_time >= time)
+            .map(|note| note.id.clone())
+            .collect();
+        for note_id in note_ids {
+            self.move_note(&note_id, delta, 0);
+        }
+
+        for event_id in self.non_note_event_ids(time, f64::INFINITY) {
+            if let Some(mut event) = self.remove_event(&event_id) {
+                event.time = (event.time + delta).max(0.0);
+                event.tick = self.time_to_tick(event.time);
+                self.add_event(event);
+            }
+        }
+    }
+
+    /// Cut `start..end` out of the store: notes starting in it and other events in it are
+    /// dropped, notes running into it end at `start`, and everything later moves left to
+    /// close the gap
+    pub fn remove_span(&mut self, start: f64, end: f64) {
+        let note_ids: Vec<EventID> = self
+            .notes
+            .values()
+            .filter(|note| note.start_time >= start && note.start_time < end)
+            .map(|note| note.id.clone())
+            .collect();
+        for note_id in note_ids {
+            self.delete_note(&note_id);
+        }
+
+        let cut_short: Vec<(EventID, f64)> = self
+            .notes
+            .values()
+            .filter(|note| note.start_time < start && note.start_time + note.duration > start)
+            .map(|note| (note.id.clone(), note.start_time))
+            .collect();
+        for (note_id, note_start) in cut_short {
+            self.update_note(&note_id, note_start, start - note_start);
+        }
+
+        for event_id in self.non_note_event_ids(start, end) {
+            self.remove_event(&event_id);
+        }
+
+        self.shift_from(end, start - end);
+    }
+
+    /// Ids of the events in `start..end` other than note ons and offs
+    fn non_note_event_ids(&self, start: f64, end: f64) -> Vec<EventID> {
+        self.event_data
+            .values()
+            .filter(|event| event.time >= start && event.time < end)
+            .filter(|event| {
+                !matches!(
+                    event.message,
+                    MidiMessage::NoteOn { .. } | MidiMessage::NoteOff { .. }
+                )
+            })
+            .map(|event| event.id.clone())
+            .collect()
+    }
+
+    fn remove_event(&mut self, event_id: &str) -> Option<MidiEvent> {
+        let event = self.event_data.remove(event_id)?;
+        let time = OrderedFloat(event.time);
+        if let Some(ids) = self.events_by_time.get_mut(&time) {
+            ids.retain(|id| id != event_id);
+            if ids.is_empty() {
+                self.events_by_time.remove(&time);
+            }
+        }
+        if let Some(ids) = self.events_by_tick.get_mut(&event.tick) {
+            ids.retain(|id| id != event_id);
+            if ids.is_empty() {
+                self.events_by_tick.remove(&event.tick);
+            }
+        }
+        self.revision = next_revision();
+        Some(event)
+    }
+
+    /// A copy with fresh note and event ids, so it can live in another clip
+    pub fn duplicate(&self) -> Self {
+        let mut copy = Self {
+            tempo_map: self.tempo_map.clone(),
+            time_signatures: self.time_signatures.clone(),
+            ..Self::new(self.ppq)
+        };
+        copy.append_shifted(self, 0.0);
+        copy
+    }
+
+    /// Copy all notes and non-note events from `other`, shifted later by `offset` seconds
+    pub fn append_shifted(&mut self, other: &MidiEventStore, offset: f64) {
+        for note in other.notes.values() {
+            let start_time = note.start_time + offset;
+            self.add_note(Note {
+                id: Uuid::new_v4().to_string(),
+                start_time,
+                start_tick: self.time_to_tick(start_time),
+                duration_ticks: self.time_to_tick(note.duration),
+                ..note.clone()
+            });
+        }
+
+        for event in other.event_data.values() {
+            if matches!(
+                event.message,
+                MidiMessage::NoteOn { .. } | MidiMessage::NoteOff { .. }
+            ) {
+                continue;
+            }
+            let time = event.time + offset;
+            self.add_event(MidiEvent {
+                id: Uuid::new_v4().to_string(),
+                time,
+                tick: self.time_to_tick(time),
+                message: event.message.clone(),
+            });
+        }
+    }
+
     // Load from MIDI file
     pub fn load_from_file(path: &PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        let (ppq, tracks) = Self::read_smf(path)?;
+        let mut store = Self::read_timing(ppq, &tracks);
+        for track in &tracks {
+            Self::read_track(&mut store, track);
+        }
+        Ok(store)
+    }
+
+    /// Load each track of a MIDI file into a store of its own, leaving out tracks without
+    /// notes. Tempo and time signature changes from any track are shared by all of them.
+    pub fn load_tracks_from_file(path: &PathBuf) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
+        let (ppq, tracks) = Self::read_smf(path)?;
+        // Format 1 files keep the tempo in the first track and the notes in the others,
+        // so the whole map has to be known before any note is timed
+        let timing = Self::read_timing(ppq, &tracks);
+        let stores = tracks
+            .iter()
+            .map(|track| {
+                let mut store = MidiEventStore::new(ppq);
+                store.tempo_map = timing.tempo_map.clone();
+                store.time_signatures = timing.time_signatures.clone();
+                Self::read_track(&mut store, track);
+                store
+            })
+            .filter(|store| !store.notes.is_empty())
+            .collect();
+        Ok(stores)
+    }
+
+    /// Split the store into one store per MIDI channel that has notes, in channel order.
+    /// Events without a channel (SysEx) go with the first channel.
+    pub fn split_by_channel(&self) -> Vec<(u8, Self)> {
+        let mut channels: Vec<u8> = self.notes.values().map(|note| note.channel).collect();
+        channels.sort_unstable();
+        channels.dedup();
+
+        channels
+            .iter()
+            .enumerate()
+            .map(|(index, &channel)| {
+                let mut store = MidiEventStore::new(self.ppq);
+                store.tempo_map = self.tempo_map.clone();
+                store.time_signatures = self.time_signatures.clone();
+                for note in self.notes.values().filter(|note| note.channel == channel) {
+                    store.add_note(note.clone());
+                }
+                for event in self.event_data.values() {
+                    let belongs = match &event.message {
+                        // Recreated along with their notes
+                        MidiMessage::NoteOn { .. } | MidiMessage::NoteOff { .. } => false,
+                        message => message.channel().map_or(index == 0, |c| c == channel),
+                    };
+                    if belongs {
+                        store.add_event(event.clone());
+                    }
+                }
+                (channel, store)
+            })
+            .collect()
+    }
+
+    /// The PPQ and the tracks of a MIDI file
+    fn read_smf(path: &PathBuf) -> Result<(u32, Vec<SmfTrack>), Box<dyn std::error::Error>> {
         let mut file = File::open(path)?;
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)?;
@@ -328,162 +679,239 @@ impl MidiEventStore {
             midly::Timing::Metrical(ticks) => ticks.as_int() as u32,
             _ => return Err("Unsupported timing format".into()),
         };
+        let tracks = smf
+            .tracks
+            .into_iter()
+            .map(|track| track.into_iter().map(|event| event.to_static()).collect())
+            .collect();
+        Ok((ppq, tracks))
+    }
 
+    /// An empty store holding the tempo and time signature changes of every track, in
+    /// tick order after the defaults
+    fn read_timing(ppq: u32, tracks: &[SmfTrack]) -> Self {
         let mut store = MidiEventStore::new(ppq);
-        let mut running_tick = 0;
-
-        // Process each track
-        for track in smf.tracks {
-            running_tick = 0;
-            let mut active_notes: HashMap<(u8, u8), (EventID, u32, u8)> = HashMap::new(); // (channel, key) -> (id, start_tick, velocity)
-
+        for track in tracks {
+            let mut running_tick = 0;
             for event in track {
                 running_tick += event.delta.as_int();
-
                 match event.kind {
-                    TrackEventKind::Midi { message, channel } => {
-                        match message {
-                            MidlyMessage::NoteOn { key, vel } => {
-                                if vel.as_int() > 0 {
-                                    // Note ON
-                                    let id = Uuid::new_v4().to_string();
-                                    active_notes.insert(
-                                        (channel.as_int(), key.as_int()),
-                                        (id, running_tick, vel.as_int()),
-                                    );
-                                } else {
-                                    // Note OFF (velocity 0)
-                                    Self::handle_note_off(
-                                        &mut store,
-                                        channel.as_int(),
-                                        key.as_int(),
-                                        running_tick,
-                                        &mut active_notes,
-                                    );
-                                }
-                            }
-                            MidlyMessage::NoteOff { key, vel } => {
+                    TrackEventKind::Meta(MetaMessage::Tempo(tempo)) => {
+                        store.tempo_map.push(TempoChange {
+                            tick: running_tick,
+                            tempo: tempo.as_int(),
+                        });
+                    }
+                    TrackEventKind::Meta(MetaMessage::TimeSignature(num, denom, _, _)) => {
+                        store.time_signatures.push(TimeSignature {
+                            tick: running_tick,
+                            numerator: num,
+                            denominator: 2u8.pow(denom as u32),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+        store.tempo_map[1..].sort_by_key(|change| change.tick);
+        store.time_signatures[1..].sort_by_key(|signature| signature.tick);
+        store
+    }
+
+    /// Add the notes and events of one file track, timed by the store's tempo map
+    fn read_track(store: &mut Self, track: &[midly::TrackEvent]) {
+        let mut running_tick = 0;
+        let mut active_notes: HashMap<(u8, u8), (EventID, u32, u8)> = HashMap::new(); // (channel, key) -> (id, start_tick, velocity)
+
+        for event in track {
+            running_tick += event.delta.as_int();
+
+            match event.kind {
+                TrackEventKind::Midi { message, channel } => {
+                    match message {
+                        MidlyMessage::NoteOn { key, vel } => {
+                            if vel.as_int() > 0 {
+                                // Note ON
+                                let id = Uuid::new_v4().to_string();
+                                active_notes.insert(
+                                    (channel.as_int(), key.as_int()),
+                                    (id, running_tick, vel.as_int()),
+                                );
+                            } else {
+                                // Note OFF (velocity 0)
                                 Self::handle_note_off(
-                                    &mut store,
+                                    store,
                                     channel.as_int(),
                                     key.as_int(),
                                     running_tick,
                                     &mut active_notes,
                                 );
                             }
-                            // Handle other MIDI messages
-                            msg => {
-                                if let Some(midi_msg) =
-                                    Self::convert_midly_message(msg, channel.as_int())
-                                {
-                                    store.add_event(MidiEvent {
-                                        id: Uuid::new_v4().to_string(),
-                                        time: store.tick_to_time(running_tick),
-                                        tick: running_tick,
-                                        message: midi_msg,
-                                    });
-                                }
-                            }
                         }
-                    }
-                    TrackEventKind::Meta(meta_msg) => match meta_msg {
-                        MetaMessage::Tempo(tempo) => {
-                            store.tempo_map.push(TempoChange {
-                                tick: running_tick,
-                                tempo: tempo.as_int(),
-                            });
+                        MidlyMessage::NoteOff { key, vel } => {
+                            Self::handle_note_off(
+                                store,
+                                channel.as_int(),
+                                key.as_int(),
+                                running_tick,
+                                &mut active_notes,
+                            );
                         }
-                        MetaMessage::TimeSignature(num, denom, _, _) => {
-                            store.time_signatures.push(TimeSignature {
-                                tick: running_tick,
-                                numerator: num,
-                                denominator: 2u8.pow(denom as u32),
-                            });
+                        // Handle other MIDI messages
+                        msg => {
+                            if let Some(midi_msg) =
+                                Self::convert_midly_message(msg, channel.as_int())
+                            {
+                                store.add_event(MidiEvent {
+                                    id: Uuid::new_v4().to_string(),
+                                    time: store.tick_to_time(running_tick),
+                                    tick: running_tick,
+                                    message: midi_msg,
+                                });
+                            }
                         }
-                        _ => {}
-                    },
-                    TrackEventKind::SysEx(data) => {
-                        store.add_event(MidiEvent {
-                            id: Uuid::new_v4().to_string(),
-                            time: store.tick_to_time(running_tick),
-                            tick: running_tick,
-                            message: MidiMessage::SysEx(data.to_vec()),
-                        });
                     }
-                    _ => {}
                 }
-            }
-
-            // Handle any still-active notes at track end
-            for ((channel, key), (id, start_tick, velocity)) in active_notes {
-                store.add_note(Note {
-                    id,
-                    channel,
-                    key,
-                    velocity,
-                    start_time: store.tick_to_time(start_tick),
-                    duration: store.tick_to_time(running_tick) - store.tick_to_time(start_tick),
-                    start_tick,
-                    duration_ticks: running_tick - start_tick,
-                });
+                TrackEventKind::SysEx(data) => {
+                    store.add_event(MidiEvent {
+                        id: Uuid::new_v4().to_string(),
+                        time: store.tick_to_time(running_tick),
+                        tick: running_tick,
+                        message: MidiMessage::SysEx(data.to_vec()),
+                    });
+                }
+                _ => {}
             }
         }
 
-        Ok(store)
+        // Handle any still-active notes at track end
+        for ((channel, key), (id, start_tick, velocity)) in active_notes {
+            store.add_note(Note {
+                id,
+                channel,
+                key,
+                velocity,
+                start_time: store.tick_to_time(start_tick),
+                duration: store.tick_to_time(running_tick) - store.tick_to_time(start_tick),
+                start_tick,
+                duration_ticks: running_tick - start_tick,
+            });
+        }
     }
 
     // Save to MIDI file
-    pub fn save_to_file(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let mut tracks = Vec::new();
-        let mut events: Vec<(u32, MidiMessage)> = Vec::new();
+    pub fn save_to_file(
+        &self,
+        path: &PathBuf,
+        options: &MidiExportOptions,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let smf = self.to_smf(options);
+        let mut file = File::create(path)?;
+        smf.write_std(&mut file)?;
+
+        Ok(())
+    }
+
+    /// Build a Standard MIDI File from the store. Tempo and time signature changes go in
+    /// the first track; with `split_channels` every channel then gets a track of its own.
+    pub fn to_smf(&self, options: &MidiExportOptions) -> midly::Smf<'static> {
+        let mut meta: Vec<(u32, MetaMessage<'static>)> = Vec::new();
+        for change in &self.tempo_map {
+            meta.push((change.tick, MetaMessage::Tempo(change.tempo.into())));
+        }
+        for signature in &self.time_signatures {
+            // Files store the denominator as a power of two
+            let denominator = signature.denominator.max(1).trailing_zeros() as u8;
+            meta.push((
+                signature.tick,
+                MetaMessage::TimeSignature(signature.numerator, denominator, 24, 8),
+            ));
+        }
 
         // Collect all events sorted by tick
+        let mut events: Vec<(u32, u8, MidlyMessage)> = Vec::new();
         for (tick, event_ids) in &self.events_by_tick {
             for id in event_ids {
-                if let Some(event) = self.event_data.get(id) {
-                    events.push((*tick, event.message.clone()));
+                let Some(event) = self.event_data.get(id) else {
+                    continue;
+                };
+                if !options.include_controllers && event.message.is_controller() {
+                    continue;
+                }
+                // Convert our message type to midly's message type
+                if let Some((channel, message)) = Self::convert_to_midly_message(&event.message) {
+                    events.push((*tick, channel, message));
                 }
             }
         }
 
-        // Sort by tick
-        events.sort_by_key(|(tick, _)| *tick);
-
-        // Convert to MIDI track
-        let mut track = Vec::new();
-        let mut last_tick = 0;
-
-        for (tick, msg) in events {
-            let delta = tick - last_tick;
-            last_tick = tick;
-
-            // Convert our message type to midly's message type
-            if let Some((channel, midi_msg)) = Self::convert_to_midly_message(&msg) {
-                track.push(midly::TrackEvent {
-                    delta: delta.into(),
-                    kind: TrackEventKind::Midi {
-                        channel: channel.into(),
-                        message: midi_msg,
-                    },
-                });
+        let tracks = if options.split_channels {
+            let mut channels: Vec<u8> = events.iter().map(|(_, channel, _)| *channel).collect();
+            channels.sort_unstable();
+            channels.dedup();
+
+            let mut tracks = vec![Self::encode_track(meta, Vec::new())];
+            for channel in channels {
+                let channel_events = events
+                    .iter()
+                    .filter(|(_, event_channel, _)| *event_channel == channel)
+                    .copied()
+                    .collect();
+                tracks.push(Self::encode_track(Vec::new(), channel_events));
             }
-        }
-
-        tracks.push(track);
+            tracks
+        } else {
+            vec![Self::encode_track(meta, events)]
+        };
 
-        // Create and write SMF
-        let smf = midly::Smf {
+        midly::Smf {
             header: midly::Header {
-                format: midly::Format::SingleTrack,
+                format: if tracks.len() > 1 {
+                    midly::Format::Parallel
+                } else {
+                    midly::Format::SingleTrack
+                },
                 timing: midly::Timing::Metrical((self.ppq as u16).into()), // Convert to u16 first
             },
             tracks,
-        };
+        }
+    }
 
-        let mut file = File::create(path)?;
-        smf.write_std(&mut file)?;
+    /// Turn absolute-tick meta and channel events into one delta-timed track
+    fn encode_track(
+        meta: Vec<(u32, MetaMessage<'static>)>,
+        events: Vec<(u32, u8, MidlyMessage)>,
+    ) -> Vec<midly::TrackEvent<'static>> {
+        let mut timed: Vec<(u32, TrackEventKind<'static>)> = meta
+            .into_iter()
+            .map(|(tick, message)| (tick, TrackEventKind::Meta(message)))
+            .chain(events.into_iter().map(|(tick, channel, message)| {
+                (
+                    tick,
+                    TrackEventKind::Midi {
+                        channel: channel.into(),
+                        message,
+                    },
+                )
+            }))
+            .collect();
+        // Stable, so meta events stay ahead of notes on the same tick
+        timed.sort_by_key(|(tick, _)| *tick);
 
-        Ok(())
+        let mut track = Vec::with_capacity(timed.len() + 1);
+        let mut last_tick = 0;
+        for (tick, kind) in timed {
+            track.push(midly::TrackEvent {
+                delta: (tick - last_tick).into(),
+                kind,
+            });
+            last_tick = tick;
+        }
+        track.push(midly::TrackEvent {
+            delta: 0.into(),
+            kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+        });
+        track
     }
 
     fn handle_note_off(
@@ -582,3 +1010,273 @@ impl MidiEventStore {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Project, SnapMode, TimeUtils};
+
+    const PPQ: u16 = 480;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("supersaw-{}-{}", Uuid::new_v4(), name))
+    }
+
+    /// A file track with the given meta events at tick 0 and a one-beat note on C4
+    /// starting `note_start` ticks in
+    fn file_track(meta: Vec<MetaMessage<'static>>, note_start: u32) -> SmfTrack {
+        let mut track: SmfTrack = meta
+            .into_iter()
+            .map(|message| midly::TrackEvent {
+                delta: 0.into(),
+                kind: TrackEventKind::Meta(message),
+            })
+            .collect();
+        for (delta, message) in [
+            (
+                note_start,
+                MidlyMessage::NoteOn {
+                    key: 60.into(),
+                    vel: 100.into(),
+                },
+            ),
+            (
+                PPQ as u32,
+                MidlyMessage::NoteOff {
+                    key: 60.into(),
+                    vel: 0.into(),
+                },
+            ),
+        ] {
+            track.push(midly::TrackEvent {
+                delta: delta.into(),
+                kind: TrackEventKind::Midi {
+                    channel: 0.into(),
+                    message,
+                },
+            });
+        }
+        track.push(midly::TrackEvent {
+            delta: 0.into(),
+            kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+        });
+        track
+    }
+
+    fn save_tracks(format: midly::Format, tracks: Vec<SmfTrack>) -> PathBuf {
+        let smf = midly::Smf {
+            header: midly::Header {
+                format,
+                timing: midly::Timing::Metrical(PPQ.into()),
+            },
+            tracks,
+        };
+        let path = temp_path("import.mid");
+        smf.save(&path).unwrap();
+        path
+    }
+
+    /// Write a one-track file with the given meta events at tick 0 and a single note
+    fn write_file(meta: Vec<MetaMessage<'static>>) -> PathBuf {
+        save_tracks(midly::Format::SingleTrack, vec![file_track(meta, 0)])
+    }
+
+    fn import(meta: Vec<MetaMessage<'static>>) -> MidiEventStore {
+        let path = write_file(meta);
+        let stores = MidiEventStore::load_tracks_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(stores.len(), 1);
+        stores.into_iter().next().unwrap()
+    }
+
+    /// Keys of the notes and of their NoteOn events, both sorted
+    fn keys(store: &MidiEventStore) -> (Vec<u8>, Vec<u8>) {
+        let mut notes: Vec<u8> = store.get_notes().map(|note| note.key).collect();
+        let mut events: Vec<u8> = store
+            .get_events()
+            .filter_map(|event| match event.message {
+                MidiMessage::NoteOn { key, .. } => Some(key),
+                _ => None,
+            })
+            .collect();
+        notes.sort_unstable();
+        events.sort_unstable();
+        (notes, events)
+    }
+
+    fn store_with_keys(keys: &[u8]) -> MidiEventStore {
+        let mut store = MidiEventStore::new(PPQ as u32);
+        for (index, &key) in keys.iter().enumerate() {
+            store.add_note(Note {
+                id: Uuid::new_v4().to_string(),
+                channel: 0,
+                key,
+                velocity: 100,
+                start_time: index as f64,
+                duration: 0.5,
+                start_tick: store.time_to_tick(index as f64),
+                duration_ticks: store.time_to_tick(0.5),
+            });
+        }
+        store
+    }
+
+    /// Channels of the channel events in a file track
+    fn track_channels(track: &[midly::TrackEvent]) -> Vec<u8> {
+        let mut channels: Vec<u8> = track
+            .iter()
+            .filter_map(|event| match event.kind {
+                TrackEventKind::Midi { channel, .. } => Some(channel.as_int()),
+                _ => None,
+            })
+            .collect();
+        channels.dedup();
+        channels
+    }
+
+    #[test]
+    fn split_export_gives_each_channel_a_track() {
+        let mut store = store_with_keys(&[60, 64]);
+        store.add_note(Note {
+            id: Uuid::new_v4().to_string(),
+            channel: 9,
+            key: 36,
+            velocity: 100,
+            start_time: 0.5,
+            duration: 0.25,
+            start_tick: store.time_to_tick(0.5),
+            duration_ticks: store.time_to_tick(0.25),
+        });
+
+        let smf = store.to_smf(&MidiExportOptions {
+            split_channels: true,
+            include_controllers: true,
+        });
+        assert_eq!(smf.header.format, midly::Format::Parallel);
+        assert_eq!(smf.tracks.len(), 3);
+
+        // Tempo and meter lead, with no notes of their own
+        assert!(track_channels(&smf.tracks[0]).is_empty());
+        assert!(smf.tracks[0]
+            .iter()
+            .any(|event| matches!(event.kind, TrackEventKind::Meta(MetaMessage::Tempo(_)))));
+        assert_eq!(track_channels(&smf.tracks[1]), vec![0]);
+        assert_eq!(track_channels(&smf.tracks[2]), vec![9]);
+
+        for track in &smf.tracks {
+            let last = track.last().unwrap();
+            assert_eq!(last.kind, TrackEventKind::Meta(MetaMessage::EndOfTrack));
+            let ends = track
+                .iter()
+                .filter(|event| event.kind == TrackEventKind::Meta(MetaMessage::EndOfTrack))
+                .count();
+            assert_eq!(ends, 1);
+        }
+    }
+
+    #[test]
+    fn transposing_moves_notes_and_their_events() {
+        let mut store = store_with_keys(&[60, 64]);
+        store.transpose_all(-12);
+        assert_eq!(keys(&store), (vec![48, 52], vec![48, 52]));
+    }
+
+    #[test]
+    fn transposing_clamps_at_the_ends_of_the_midi_range() {
+        let mut store = store_with_keys(&[3, 60, 125]);
+        store.transpose_all(5);
+        assert_eq!(keys(&store), (vec![8, 65, 127], vec![8, 65, 127]));
+
+        let mut store = store_with_keys(&[3, 60, 125]);
+        store.transpose_all(-5);
+        assert_eq!(keys(&store), (vec![0, 55, 120], vec![0, 55, 120]));
+    }
+
+    #[test]
+    fn imported_tempo_is_read_from_the_file() {
+        // 140 BPM is 428_571 microseconds per quarter note
+        let store = import(vec![MetaMessage::Tempo(428_571.into())]);
+        let bpm = store.initial_bpm().unwrap();
+        assert!((bpm - 140.0).abs() < 0.01, "got {} BPM", bpm);
+    }
+
+    /// Import a file with the given meta events into `project` as a new track
+    fn import_into(project: &mut Project, meta: Vec<MetaMessage<'static>>) {
+        let path = write_file(meta);
+        project.create_midi_track_from_file_path(&path, 0.0).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn the_first_imported_file_sets_the_project_tempo() {
+        let mut project = Project::new("Import".to_string());
+        import_into(&mut project, vec![MetaMessage::Tempo(428_571.into())]);
+        assert!((project.bpm - 140.0).abs() < 0.01, "got {} BPM", project.bpm);
+
+        // 90 BPM, ignored now the project has a clip
+        import_into(&mut project, vec![MetaMessage::Tempo(666_667.into())]);
+        assert_eq!(project.tracks.len(), 2);
+        assert!((project.bpm - 140.0).abs() < 0.01, "got {} BPM", project.bpm);
+    }
+
+    #[test]
+    fn imported_time_signature_is_read_from_the_file() {
+        // Files store the denominator as a power of two, 2^2 = 4
+        let store = import(vec![MetaMessage::TimeSignature(3, 2, 24, 8)]);
+        assert_eq!(store.initial_time_signature(), Some((3, 4)));
+    }
+
+    #[test]
+    fn an_imported_three_four_file_gives_three_beat_bars() {
+        let mut project = Project::new("Import".to_string());
+        import_into(&mut project, vec![MetaMessage::TimeSignature(3, 2, 24, 8)]);
+        assert_eq!(project.time_signature, (3, 4));
+
+        let beat = 60.0 / project.bpm;
+        let bar = TimeUtils::seconds_per_bar(project.bpm, project.time_signature);
+        assert!((bar - 3.0 * beat).abs() < 1e-9, "bar lasts {} seconds", bar);
+
+        // Bar snap steps from one drawn bar line to the next
+        let bar_snap = SnapMode::Bar.get_division(project.bpm, project.time_signature);
+        assert!((bar_snap - bar).abs() < 1e-9, "bar snap is {} seconds", bar_snap);
+        let beat_snap = SnapMode::Beat.get_division(project.bpm, project.time_signature);
+        assert!((beat_snap - beat).abs() < 1e-9, "beat snap is {} seconds", beat_snap);
+    }
+
+    #[test]
+    fn files_without_a_tempo_leave_the_bpm_alone() {
+        let store = import(Vec::new());
+        assert_eq!(store.initial_bpm(), None);
+        assert_eq!(store.initial_time_signature(), None);
+        // The default is still there for time conversions
+        assert_eq!(store.tick_to_time(PPQ as u32), 0.5);
+    }
+
+    #[test]
+    fn notes_in_later_tracks_follow_the_tempo_of_the_first() {
+        // Format 1 layout: the tempo track has no notes, the note sits one beat into track 1
+        let tempo_track = vec![
+            midly::TrackEvent {
+                delta: 0.into(),
+                kind: TrackEventKind::Meta(MetaMessage::Tempo(428_571.into())),
+            },
+            midly::TrackEvent {
+                delta: 0.into(),
+                kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+            },
+        ];
+        let path = save_tracks(
+            midly::Format::Parallel,
+            vec![tempo_track, file_track(Vec::new(), PPQ as u32)],
+        );
+        let stores = MidiEventStore::load_tracks_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(stores.len(), 1);
+        let note = stores[0].get_notes().next().unwrap();
+        // One beat at 140 BPM
+        let beat = 60.0 / 140.0;
+        assert!((note.start_time - beat).abs() < 1e-4, "started at {}", note.start_time);
+        assert!((note.duration - beat).abs() < 1e-4, "lasted {}", note.duration);
+    }
+}
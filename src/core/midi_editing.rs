@@ -1,7 +1,6 @@
-use crate::core::{MidiEvent, MidiMessage};
+use crate::core::{DawCommand, EventID, MidiEvent, MidiEventStore, MidiMessage};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuantizeSettings {
@@ -12,6 +11,14 @@ pub struct QuantizeSettings {
     pub preserve_flams: bool, // Don't quantize notes very close together
 }
 
+/// A named feel: per-step timing offsets repeated across the grid
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GrooveTemplate {
+    pub name: String,
+    pub grid: QuantizeGrid,
+    pub offsets: Vec<f64>, // Fraction of a grid step per position, positive = late
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum QuantizeGrid {
     Quarter,      // 1/4 note
@@ -43,8 +50,9 @@ pub enum VelocityEditMode {
     Curve,      // Apply curve shape
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
 pub enum VelocityCurve {
+    #[default]
     Linear,
     Exponential,
     Logarithmic,
@@ -52,33 +60,73 @@ pub enum VelocityCurve {
     Cosine,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ControllerLane {
-    pub controller: u8,     // CC number (0-127)
-    pub name: String,       // Display name
-    pub events: Vec<ControllerEvent>,
-    pub visible: bool,
-    pub height: f32,        // Lane height in pixels
-    pub color: [f32; 3],    // RGB color
+/// Pitch class names, starting from C
+pub const PITCH_CLASS_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ScaleMode {
+    Major,
+    NaturalMinor,
+    HarmonicMinor,
+    Dorian,
+    Mixolydian,
+    MajorPentatonic,
+    MinorPentatonic,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ControllerEvent {
-    pub id: String,
-    pub time: f64,          // Time in seconds
-    pub value: u8,          // Controller value (0-127)
-    pub selected: bool,
+/// A key: the notes of `mode` built on the pitch class `root` (0 = C)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Scale {
+    pub root: u8,
+    pub mode: ScaleMode,
 }
 
 #[derive(Debug, Clone)]
 pub struct MidiEditor {
     pub velocity_settings: VelocityEditSettings,
     pub quantize_settings: QuantizeSettings,
-    pub controller_lanes: HashMap<u8, ControllerLane>,
     pub show_velocity_lane: bool,
     pub velocity_lane_height: f32,
     pub selected_notes: Vec<String>, // Note IDs
-    pub selected_controllers: Vec<String>, // Controller event IDs
+}
+
+impl GrooveTemplate {
+    pub fn new(name: impl Into<String>, grid: QuantizeGrid, offsets: Vec<f64>) -> Self {
+        Self {
+            name: name.into(),
+            grid,
+            offsets,
+        }
+    }
+
+    pub fn presets() -> Vec<GrooveTemplate> {
+        vec![
+            // MPC swing percentages delay every second 16th: 58% = 0.16 of a step
+            GrooveTemplate::new("MPC Swing 54%", QuantizeGrid::Sixteenth, vec![0.0, 0.08]),
+            GrooveTemplate::new("MPC Swing 58%", QuantizeGrid::Sixteenth, vec![0.0, 0.16]),
+            GrooveTemplate::new("MPC Swing 62%", QuantizeGrid::Sixteenth, vec![0.0, 0.24]),
+            GrooveTemplate::new("Shuffle 8ths", QuantizeGrid::Eighth, vec![0.0, 0.33]),
+            GrooveTemplate::new("Laid Back", QuantizeGrid::Sixteenth, vec![0.04, 0.12, 0.06, 0.14]),
+            GrooveTemplate::new("Pushed", QuantizeGrid::Sixteenth, vec![0.0, -0.06, -0.03, -0.08]),
+        ]
+    }
+
+    /// Move a time toward the template's position for its nearest grid step.
+    /// `strength` blends between the original time (0.0) and the full groove (1.0).
+    pub fn apply(&self, time: f64, bpm: f64, strength: f64) -> f64 {
+        if self.offsets.is_empty() {
+            return time;
+        }
+
+        let step = self.grid.duration(bpm);
+        let index = (time / step).round().max(0.0);
+        let offset = self.offsets[index as usize % self.offsets.len()];
+        let target = (index + offset) * step;
+
+        (time + (target - time) * strength.clamp(0.0, 1.0)).max(0.0)
+    }
 }
 
 impl Default for QuantizeSettings {
@@ -104,55 +152,126 @@ impl Default for VelocityEditSettings {
     }
 }
 
+impl VelocityEditSettings {
+    /// Largest velocity change `randomize` = 1.0 can produce, in either direction
+    const MAX_RANDOM_OFFSET: f32 = 20.0;
+
+    /// Vary a velocity by up to `randomize * 20` units. Takes the RNG so callers can seed it.
+    pub fn randomize_velocity(&self, velocity: u8, rng: &mut impl Rng) -> u8 {
+        if self.randomize <= 0.0 {
+            return velocity;
+        }
+
+        let random_offset =
+            rng.gen_range(-1.0..=1.0) * self.randomize * Self::MAX_RANDOM_OFFSET;
+        (velocity as f32 + random_offset).round().clamp(1.0, 127.0) as u8
+    }
+}
+
+/// Linearly remap velocities so their lowest and highest values span `min..=max`.
+/// If all velocities are equal they are set to the middle of the range.
+pub fn fit_velocities_to_range(velocities: &[u8], min: u8, max: u8) -> Vec<u8> {
+    let (min, max) = (min.min(max) as f32, min.max(max) as f32);
+    let lowest = velocities.iter().copied().min().unwrap_or(0) as f32;
+    let highest = velocities.iter().copied().max().unwrap_or(0) as f32;
+
+    velocities
+        .iter()
+        .map(|&velocity| {
+            let fitted = if highest > lowest {
+                min + (velocity as f32 - lowest) / (highest - lowest) * (max - min)
+            } else {
+                (min + max) / 2.0
+            };
+            fitted.round().clamp(1.0, 127.0) as u8
+        })
+        .collect()
+}
+
+/// A drag on one note's bar in the velocity lane. The frames only move the pending
+/// velocity; releasing turns the whole gesture into one command, so it undoes in one step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VelocityDrag {
+    pub note_id: EventID,
+    start_velocity: u8,
+    /// Bar height as a fraction of the lane, kept unrounded so slow drags add up
+    level: f32,
+}
+
+impl VelocityDrag {
+    pub fn new(note_id: EventID, velocity: u8) -> Self {
+        Self {
+            note_id,
+            start_velocity: velocity,
+            level: velocity as f32 / 127.0,
+        }
+    }
+
+    /// Raise the bar by `delta_y` pixels (negative lowers it) in a lane `lane_height` tall
+    pub fn drag_by(&mut self, delta_y: f32, lane_height: f32) {
+        if lane_height > 0.0 {
+            self.level = (self.level + delta_y / lane_height).clamp(0.0, 1.0);
+        }
+    }
+
+    /// Velocity the note gets on release, never 0 (which would be a NoteOff)
+    pub fn velocity(&self) -> u8 {
+        (self.level * 127.0).round().max(1.0) as u8
+    }
+
+    /// The command applying the drag, or `None` if the velocity ended where it started
+    pub fn finish(self, clip_id: &str) -> Option<DawCommand> {
+        let velocity = self.velocity();
+        (velocity != self.start_velocity).then(|| DawCommand::UpdateNoteVelocity {
+            clip_id: clip_id.to_string(),
+            note_id: self.note_id,
+            velocity,
+        })
+    }
+}
+
+/// Notes starting within this many seconds of each other are treated as one chord
+const CHORD_TOLERANCE: f64 = 0.001;
+
+/// Shift each time by a random amount of up to `max_offset` seconds either way, never before 0.
+/// With `preserve_chords`, times that coincide share one offset so chords move together.
+pub fn humanize_times(
+    times: &[f64],
+    max_offset: f64,
+    preserve_chords: bool,
+    rng: &mut impl Rng,
+) -> Vec<f64> {
+    // Group by visiting the times in order, each chord starting where the last one ended
+    let mut order: Vec<usize> = (0..times.len()).collect();
+    if preserve_chords {
+        order.sort_by(|&a, &b| times[a].total_cmp(&times[b]));
+    }
+
+    let mut humanized = vec![0.0; times.len()];
+    let mut chord: Option<(f64, f64)> = None; // (first time in the chord, its offset)
+    for index in order {
+        let time = times[index];
+        let offset = match chord {
+            Some((start, offset)) if preserve_chords && time - start <= CHORD_TOLERANCE => offset,
+            _ => {
+                let offset = rng.gen_range(-1.0..=1.0) * max_offset;
+                chord = Some((time, offset));
+                offset
+            }
+        };
+        humanized[index] = (time + offset).max(0.0);
+    }
+    humanized
+}
+
 impl Default for MidiEditor {
     fn default() -> Self {
-        let mut controller_lanes = HashMap::new();
-        
-        // Add common controller lanes
-        controller_lanes.insert(1, ControllerLane {
-            controller: 1,
-            name: "Mod Wheel".to_string(),
-            events: Vec::new(),
-            visible: false,
-            height: 60.0,
-            color: [0.2, 0.8, 0.2],
-        });
-        
-        controller_lanes.insert(7, ControllerLane {
-            controller: 7,
-            name: "Volume".to_string(),
-            events: Vec::new(),
-            visible: false,
-            height: 60.0,
-            color: [0.8, 0.2, 0.2],
-        });
-        
-        controller_lanes.insert(10, ControllerLane {
-            controller: 10,
-            name: "Pan".to_string(),
-            events: Vec::new(),
-            visible: false,
-            height: 60.0,
-            color: [0.2, 0.2, 0.8],
-        });
-        
-        controller_lanes.insert(11, ControllerLane {
-            controller: 11,
-            name: "Expression".to_string(),
-            events: Vec::new(),
-            visible: false,
-            height: 60.0,
-            color: [0.8, 0.8, 0.2],
-        });
-
         Self {
             velocity_settings: VelocityEditSettings::default(),
             quantize_settings: QuantizeSettings::default(),
-            controller_lanes,
             show_velocity_lane: true,
             velocity_lane_height: 80.0,
             selected_notes: Vec::new(),
-            selected_controllers: Vec::new(),
         }
     }
 }
@@ -163,38 +282,57 @@ impl MidiEditor {
     }
 
     pub fn quantize_events(&self, events: &mut [MidiEvent], bpm: f64) {
-        let grid_size = self.get_grid_size(bpm);
-        let swing_offset = grid_size * self.quantize_settings.swing as f64 * 0.1;
-        
         for event in events.iter_mut() {
             if let MidiMessage::NoteOn { .. } = event.message {
-                // Calculate quantized time
-                let beat_position = event.time / grid_size;
-                let quantized_beat = beat_position.round();
-                let quantized_time = quantized_beat * grid_size;
-                
-                // Apply swing on off-beats
-                let swing_time = if (quantized_beat as i32) % 2 == 1 {
-                    quantized_time + swing_offset
-                } else {
-                    quantized_time
-                };
-                
-                // Apply humanization (random timing variation)
-                let humanize_offset = if self.quantize_settings.humanize > 0.0 {
-                    let max_offset = grid_size * 0.1 * self.quantize_settings.humanize as f64;
-                    (rand::random::<f64>() - 0.5) * 2.0 * max_offset
-                } else {
-                    0.0
-                };
-                
-                // Blend between original and quantized time based on strength
-                let target_time = swing_time + humanize_offset;
-                event.time = event.time + (target_time - event.time) * self.quantize_settings.strength as f64;
+                event.time = self.quantize_time(event.time, bpm);
             }
         }
     }
 
+    /// Move one time toward the grid according to the quantize settings
+    pub fn quantize_time(&self, time: f64, bpm: f64) -> f64 {
+        let grid_size = self.get_grid_size(bpm);
+        let swing_offset = grid_size * self.quantize_settings.swing as f64 * 0.1;
+
+        // Calculate quantized time
+        let beat_position = time / grid_size;
+        let quantized_beat = beat_position.round();
+        let quantized_time = quantized_beat * grid_size;
+
+        // Apply swing on off-beats
+        let swing_time = if (quantized_beat as i32) % 2 == 1 {
+            quantized_time + swing_offset
+        } else {
+            quantized_time
+        };
+
+        // Apply humanization (random timing variation)
+        let humanize_offset = if self.quantize_settings.humanize > 0.0 {
+            let max_offset = grid_size * 0.1 * self.quantize_settings.humanize as f64;
+            (rand::random::<f64>() - 0.5) * 2.0 * max_offset
+        } else {
+            0.0
+        };
+
+        // Blend between original and quantized time based on strength
+        let target_time = swing_time + humanize_offset;
+        time + (target_time - time) * self.quantize_settings.strength as f64
+    }
+
+    /// Quantize the start of every note in a store. Notes move whole, keeping their length.
+    pub fn quantize_notes(&self, store: &mut MidiEventStore, bpm: f64) {
+        let moves: Vec<(EventID, f64, f64)> = store
+            .get_notes()
+            .map(|note| {
+                let start = self.quantize_time(note.start_time, bpm).max(0.0);
+                (note.id.clone(), start, note.duration)
+            })
+            .collect();
+        for (note_id, start, duration) in moves {
+            store.update_note(&note_id, start, duration);
+        }
+    }
+
     pub fn edit_velocities(&self, events: &mut [MidiEvent], selection_start: f64, selection_end: f64) {
         let selected_events: Vec<_> = events.iter_mut()
             .filter(|e| {
@@ -255,95 +393,38 @@ impl MidiEditor {
                 };
 
                 // Apply randomization
-                let final_velocity = if self.velocity_settings.randomize > 0.0 {
-                    let random_offset = (rand::random::<f32>() - 0.5) * 2.0 * 
-                        self.velocity_settings.randomize * 20.0; // Max ±20 velocity units
-                    (new_velocity as f32 + random_offset).clamp(1.0, 127.0) as u8
-                } else {
-                    new_velocity
-                };
-
-                *velocity = final_velocity;
+                *velocity = self
+                    .velocity_settings
+                    .randomize_velocity(new_velocity, &mut rand::thread_rng());
             }
         }
     }
 
-    pub fn add_controller_event(&mut self, controller: u8, time: f64, value: u8) {
-        if let Some(lane) = self.controller_lanes.get_mut(&controller) {
-            let event = ControllerEvent {
-                id: Uuid::new_v4().to_string(),
-                time,
-                value,
-                selected: false,
-            };
-            lane.events.push(event);
-            lane.events.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
-        }
-    }
-
-    pub fn remove_controller_event(&mut self, controller: u8, event_id: &str) {
-        if let Some(lane) = self.controller_lanes.get_mut(&controller) {
-            lane.events.retain(|e| e.id != event_id);
-        }
-    }
-
-    pub fn get_controller_value_at_time(&self, controller: u8, time: f64) -> Option<u8> {
-        if let Some(lane) = self.controller_lanes.get(&controller) {
-            // Find the most recent event before or at the given time
-            lane.events.iter()
-                .filter(|e| e.time <= time)
-                .last()
-                .map(|e| e.value)
-        } else {
-            None
-        }
-    }
-
-    pub fn interpolate_controller_values(&mut self, controller: u8, start_time: f64, end_time: f64, start_value: u8, end_value: u8, steps: usize) {
-        if let Some(lane) = self.controller_lanes.get_mut(&controller) {
-            // Remove existing events in the range
-            lane.events.retain(|e| e.time < start_time || e.time > end_time);
-            
-            // Add interpolated events
-            for i in 0..=steps {
-                let progress = if steps > 0 { i as f64 / steps as f64 } else { 0.0 };
-                let time = start_time + (end_time - start_time) * progress;
-                let value = (start_value as f64 + (end_value as f64 - start_value as f64) * progress) as u8;
-                
-                let event = ControllerEvent {
-                    id: Uuid::new_v4().to_string(),
-                    time,
-                    value,
-                    selected: false,
-                };
-                lane.events.push(event);
-            }
-            
-            lane.events.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
-        }
+    fn get_grid_size(&self, bpm: f64) -> f64 {
+        self.quantize_settings.grid.duration(bpm)
     }
 
-    pub fn toggle_controller_lane(&mut self, controller: u8) {
-        if let Some(lane) = self.controller_lanes.get_mut(&controller) {
-            lane.visible = !lane.visible;
-        }
+    fn apply_curve(&self, progress: f32) -> f32 {
+        self.velocity_settings.curve.shape(progress)
     }
+}
 
-    pub fn add_custom_controller_lane(&mut self, controller: u8, name: String, color: [f32; 3]) {
-        let lane = ControllerLane {
-            controller,
-            name,
-            events: Vec::new(),
-            visible: true,
-            height: 60.0,
-            color,
-        };
-        self.controller_lanes.insert(controller, lane);
-    }
+impl QuantizeGrid {
+    pub const ALL: [QuantizeGrid; 8] = [
+        QuantizeGrid::Quarter,
+        QuantizeGrid::Eighth,
+        QuantizeGrid::Sixteenth,
+        QuantizeGrid::ThirtySecond,
+        QuantizeGrid::EighthTriplet,
+        QuantizeGrid::SixteenthTriplet,
+        QuantizeGrid::Dotted8th,
+        QuantizeGrid::Dotted16th,
+    ];
 
-    fn get_grid_size(&self, bpm: f64) -> f64 {
+    /// Length of one grid step in seconds
+    pub fn duration(&self, bpm: f64) -> f64 {
         let beat_duration = 60.0 / bpm;
-        match self.quantize_settings.grid {
+        match self {
             QuantizeGrid::Quarter => beat_duration,
             QuantizeGrid::Eighth => beat_duration * 0.5,
             QuantizeGrid::Sixteenth => beat_duration * 0.25,
@@ -355,18 +436,6 @@ impl MidiEditor {
         }
     }
 
-    fn apply_curve(&self, progress: f32) -> f32 {
-        match self.velocity_settings.curve {
-            VelocityCurve::Linear => progress,
-            VelocityCurve::Exponential => progress * progress,
-            VelocityCurve::Logarithmic => progress.sqrt(),
-            VelocityCurve::Sine => (progress * std::f32::consts::PI * 0.5).sin(),
-            VelocityCurve::Cosine => 1.0 - (progress * std::f32::consts::PI * 0.5).cos(),
-        }
-    }
-}
-
-impl QuantizeGrid {
     pub fn display_name(&self) -> &'static str {
         match self {
             QuantizeGrid::Quarter => "1/4",
@@ -381,6 +450,73 @@ impl QuantizeGrid {
     }
 }
 
+impl ScaleMode {
+    pub const ALL: [ScaleMode; 7] = [
+        ScaleMode::Major,
+        ScaleMode::NaturalMinor,
+        ScaleMode::HarmonicMinor,
+        ScaleMode::Dorian,
+        ScaleMode::Mixolydian,
+        ScaleMode::MajorPentatonic,
+        ScaleMode::MinorPentatonic,
+    ];
+
+    /// Semitones above the root of each degree, ascending
+    pub fn intervals(&self) -> &'static [u8] {
+        match self {
+            ScaleMode::Major => &[0, 2, 4, 5, 7, 9, 11],
+            ScaleMode::NaturalMinor => &[0, 2, 3, 5, 7, 8, 10],
+            ScaleMode::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            ScaleMode::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            ScaleMode::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            ScaleMode::MajorPentatonic => &[0, 2, 4, 7, 9],
+            ScaleMode::MinorPentatonic => &[0, 3, 5, 7, 10],
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ScaleMode::Major => "Major",
+            ScaleMode::NaturalMinor => "Minor",
+            ScaleMode::HarmonicMinor => "Harmonic Minor",
+            ScaleMode::Dorian => "Dorian",
+            ScaleMode::Mixolydian => "Mixolydian",
+            ScaleMode::MajorPentatonic => "Major Pentatonic",
+            ScaleMode::MinorPentatonic => "Minor Pentatonic",
+        }
+    }
+}
+
+impl Default for Scale {
+    fn default() -> Self {
+        Self {
+            root: 0,
+            mode: ScaleMode::Major,
+        }
+    }
+}
+
+impl Scale {
+    /// Move a key by `steps` degrees of the scale, clamped to the MIDI range.
+    /// A key outside the scale keeps its offset from the degree below it.
+    pub fn transpose(&self, key: u8, steps: i32) -> u8 {
+        let intervals = self.mode.intervals();
+        let from_root = key as i32 - (self.root % 12) as i32;
+        let pitch_class = from_root.rem_euclid(12);
+        let degree = intervals
+            .iter()
+            .rposition(|&interval| interval as i32 <= pitch_class)
+            .unwrap_or(0);
+        let accidental = pitch_class - intervals[degree] as i32;
+
+        let target = degree as i32 + steps;
+        let octave = from_root.div_euclid(12) + target.div_euclid(intervals.len() as i32);
+        let interval = intervals[target.rem_euclid(intervals.len() as i32) as usize] as i32;
+        let new_key = (self.root % 12) as i32 + octave * 12 + interval + accidental;
+        new_key.clamp(0, 127) as u8
+    }
+}
+
 impl VelocityEditMode {
     pub fn display_name(&self) -> &'static str {
         match self {
@@ -396,6 +532,32 @@ impl VelocityEditMode {
 }
 
 impl VelocityCurve {
+    pub const ALL: [VelocityCurve; 5] = [
+        VelocityCurve::Linear,
+        VelocityCurve::Exponential,
+        VelocityCurve::Logarithmic,
+        VelocityCurve::Sine,
+        VelocityCurve::Cosine,
+    ];
+
+    /// Map 0.0..=1.0 through the curve
+    pub fn shape(&self, progress: f32) -> f32 {
+        match self {
+            VelocityCurve::Linear => progress,
+            VelocityCurve::Exponential => progress * progress,
+            VelocityCurve::Logarithmic => progress.sqrt(),
+            VelocityCurve::Sine => (progress * std::f32::consts::PI * 0.5).sin(),
+            VelocityCurve::Cosine => 1.0 - (progress * std::f32::consts::PI * 0.5).cos(),
+        }
+    }
+
+    /// Map a played velocity through the curve. Linear leaves it unchanged; the
+    /// result stays at 1 or above so a note never turns into a note-off.
+    pub fn apply_to_velocity(&self, velocity: u8) -> u8 {
+        let progress = velocity.min(127) as f32 / 127.0;
+        (self.shape(progress) * 127.0).round().clamp(1.0, 127.0) as u8
+    }
+
     pub fn display_name(&self) -> &'static str {
         match self {
             VelocityCurve::Linear => "Linear",
@@ -405,4 +567,71 @@ impl VelocityCurve {
             VelocityCurve::Cosine => "Cosine",
         }
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn velocity_settings(randomize: f32) -> VelocityEditSettings {
+        VelocityEditSettings {
+            mode: VelocityEditMode::Set,
+            amount: 0.0,
+            curve: VelocityCurve::Linear,
+            randomize,
+        }
+    }
+
+    #[test]
+    fn randomized_velocities_stay_within_the_amount() {
+        let settings = velocity_settings(0.5);
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..1000 {
+            let velocity = settings.randomize_velocity(100, &mut rng);
+            assert!((90..=110).contains(&velocity), "{}", velocity);
+        }
+    }
+
+    #[test]
+    fn randomized_velocities_stay_valid_at_the_extremes() {
+        let settings = velocity_settings(1.0);
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..1000 {
+            assert!(settings.randomize_velocity(1, &mut rng) >= 1);
+            assert!(settings.randomize_velocity(127, &mut rng) <= 127);
+        }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_velocities() {
+        let settings = velocity_settings(1.0);
+        let run = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..32)
+                .map(|_| settings.randomize_velocity(64, &mut rng))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42), run(43));
+    }
+
+    #[test]
+    fn input_curves_keep_velocities_playable() {
+        for curve in VelocityCurve::ALL {
+            assert_eq!(curve.apply_to_velocity(127), 127, "{}", curve.display_name());
+            assert_eq!(curve.apply_to_velocity(0), 1, "{}", curve.display_name());
+        }
+        assert_eq!(VelocityCurve::Linear.apply_to_velocity(100), 100);
+        // Exponential takes a harder touch for the same velocity, logarithmic a lighter one
+        assert!(VelocityCurve::Exponential.apply_to_velocity(64) < 64);
+        assert!(VelocityCurve::Logarithmic.apply_to_velocity(64) > 64);
+    }
+
+    #[test]
+    fn zero_randomize_leaves_velocities_alone() {
+        let settings = velocity_settings(0.0);
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(settings.randomize_velocity(100, &mut rng), 100);
+    }
+}
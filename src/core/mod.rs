@@ -1,19 +1,29 @@
+pub mod audio;
+pub mod audio_output;
 pub mod automation;
 pub mod command_manager;
 pub mod commands;
 pub mod midi;
 pub mod midi_editing;
 pub mod project;
+pub mod render;
+pub mod settings;
 pub mod state;
 pub mod status;
+pub mod synth;
 pub mod utils;
 
+pub use audio::*;
+pub use audio_output::*;
 pub use automation::*;
 pub use command_manager::*;
 pub use commands::*;
 pub use midi::*;
 pub use midi_editing::*;
 pub use project::*;
+pub use render::*;
+pub use settings::*;
 pub use state::*;
 pub use status::*;
+pub use synth::*;
 pub use utils::*;
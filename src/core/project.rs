@@ -1,16 +1,23 @@
 #![allow(unused_variables)]
 #![allow(unused_imports)]
 
-use crate::core::{MidiEvent, MidiEventStore, AutomationLane};
+use crate::core::{
+    app_config_dir, AudioPeaks, AutomationLane, BuiltinInstrument, MidiEvent, MidiEventStore,
+    MidiMessage, TimeUtils, VelocityCurve,
+};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use uuid::Uuid;
 
 type TrackId = String; // Uuid
 type ClipId = String; // Uuid
 
+/// Tick resolution (pulses per quarter note) for clips created inside the app
+pub const DEFAULT_PPQ: u32 = 480;
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum SnapMode {
     None,
@@ -26,12 +33,30 @@ pub enum SnapMode {
 }
 
 impl SnapMode {
-    pub fn get_division(&self, bpm: f64) -> f64 {
-        let beat_duration = 60.0 / bpm; // Duration of one beat in seconds
+    /// Every mode, coarsest first within each family
+    pub const ALL: [SnapMode; 10] = [
+        SnapMode::None,
+        SnapMode::Bar,
+        SnapMode::Beat,
+        SnapMode::Halfbeat,
+        SnapMode::Quarter,
+        SnapMode::Eighth,
+        SnapMode::Sixteenth,
+        SnapMode::ThirtySecond,
+        SnapMode::Triplet,
+        SnapMode::SixteenthTriplet,
+    ];
+
+    /// Length of one grid step in seconds. Bars and beats follow the time signature, so
+    /// they match the grid lines; the finer divisions are note values counted from the
+    /// quarter note the BPM is given in. With snapping off the grid still shows beats, so
+    /// this is a beat; anything that moves or sizes notes should use `step` instead.
+    pub fn get_division(&self, bpm: f64, time_sig: (u8, u8)) -> f64 {
+        let beat_duration = 60.0 / bpm; // Duration of one quarter note in seconds
         match self {
             SnapMode::None => beat_duration,
-            SnapMode::Bar => beat_duration * 4.0, // Full measure
-            SnapMode::Beat => beat_duration,      // Quarter note
+            SnapMode::Bar => TimeUtils::seconds_per_bar(bpm, time_sig),
+            SnapMode::Beat => TimeUtils::seconds_per_beat(bpm, time_sig),
             SnapMode::Halfbeat => beat_duration / 2.0, // Eighth note
             SnapMode::Quarter => beat_duration / 4.0, // Sixteenth note
             SnapMode::Eighth => beat_duration / 8.0, // 32nd note
@@ -42,11 +67,16 @@ impl SnapMode {
         }
     }
 
+    /// Grid step in seconds, or `None` when snapping is off
+    pub fn step(&self, bpm: f64, time_sig: (u8, u8)) -> Option<f64> {
+        (*self != SnapMode::None).then(|| self.get_division(bpm, time_sig))
+    }
+
     pub fn display_name(&self) -> &str {
         match self {
             SnapMode::None => "None",
             SnapMode::Bar => "Bar",
-            SnapMode::Beat => "Beat (1/4)",
+            SnapMode::Beat => "Beat",
             SnapMode::Halfbeat => "1/8",
             SnapMode::Quarter => "1/16",
             SnapMode::Eighth => "1/32",
@@ -58,6 +88,9 @@ impl SnapMode {
     }
 }
 
+/// Horizontal piano roll zoom, in pixels per second
+pub const DEFAULT_PIANO_ROLL_ZOOM: f32 = 100.0;
+
 #[derive(Debug, Clone)]
 pub enum EditorView {
     Arrangement,
@@ -65,8 +98,15 @@ pub enum EditorView {
         clip_id: ClipId,
         track_id: TrackId,
         scroll_position: f32,
+        /// `None` until the clip has been shown, which centres it on middle C
+        vertical_scroll: Option<f32>,
+        horizontal_zoom: f32,
         vertical_zoom: f32,
     },
+    EventList {
+        clip_id: ClipId,
+        track_id: TrackId,
+    },
     SampleEditor {
         clip_id: ClipId,
         track_id: TrackId,
@@ -84,12 +124,79 @@ impl Default for EditorView {
 pub struct Project {
     pub name: String,
     pub bpm: f64,
+    /// Time base for new empty clips and recordings; imported files keep their own
+    #[serde(default = "default_ppq")]
     pub ppq: u32,
+    #[serde(default = "default_time_signature")]
+    pub time_signature: (u8, u8), // (numerator, denominator)
     pub tracks: Vec<Track>,
+    /// Named cue points on the ruler, e.g. "Verse" or "Chorus"
+    #[serde(default)]
+    pub markers: Vec<Marker>,
+    /// Named spans of the arrangement, e.g. "Intro" or "Chorus 2"
+    #[serde(default)]
+    pub sections: Vec<Section>,
+    /// Saved as a starting point for new projects rather than as a session
+    #[serde(default)]
+    pub is_template: bool,
     #[serde(skip)]
     pub project_path: Option<PathBuf>,
 }
 
+/// Directory in the app's config directory where project templates are stored
+const TEMPLATES_DIR: &str = "templates";
+
+/// Markers this close to the playhead count as "at" it when jumping between them
+const MARKER_EPSILON: f64 = 0.001;
+
+/// Gap in seconds below which two clips count as touching
+const CLIP_ADJACENCY_EPSILON: f64 = 0.001;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Marker {
+    pub id: String,
+    pub name: String,
+    pub time: f64,
+}
+
+impl Marker {
+    pub fn new(name: impl Into<String>, time: f64) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name: name.into(),
+            time,
+        }
+    }
+}
+
+/// Colors new sections cycle through
+const SECTION_COLORS: [&str; 6] = ["#93c5fd", "#86efac", "#fcd34d", "#fca5a5", "#c4b5fd", "#5eead4"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Section {
+    pub id: String,
+    pub name: String,
+    pub start: f64,
+    pub end: f64,
+    pub color: String, // Hex color like "#93c5fd"
+}
+
+impl Section {
+    pub fn new(name: impl Into<String>, start: f64, end: f64, color: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name: name.into(),
+            start: start.min(end),
+            end: start.max(end),
+            color: color.into(),
+        }
+    }
+
+    pub fn length(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Track {
     pub id: String,
@@ -100,6 +207,18 @@ pub struct Track {
     pub is_soloed: bool,
     pub is_armed: bool,
     pub color: String, // Hex color like "#fde047"
+    /// Built-in instrument that plays this track's notes, if any
+    #[serde(default)]
+    pub instrument: Option<BuiltinInstrument>,
+    /// Shown as a thin strip in the arrangement, with clip editing disabled
+    #[serde(default)]
+    pub is_collapsed: bool,
+    /// Edits trim overlapping notes so only one note sounds at a time
+    #[serde(default)]
+    pub is_monophonic: bool,
+    /// Shapes the velocity of notes entered in the piano roll, by drawing, rolling or step input
+    #[serde(default)]
+    pub input_velocity_curve: VelocityCurve,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -115,7 +234,6 @@ pub enum TrackType {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "type")]
 pub enum Clip {
-    // TODO: color
     Midi {
         id: ClipId,
         start_time: f64,
@@ -125,6 +243,14 @@ pub enum Clip {
         loaded: bool,
         #[serde(default)]
         automation_lanes: Vec<AutomationLane>,
+        /// Applied in order to the notes as they play, until the clip is frozen
+        #[serde(default)]
+        processors: Vec<ClipProcessor>,
+        #[serde(default)]
+        is_muted: bool,
+        /// Hex color overriding the track color
+        #[serde(default)]
+        color: Option<String>,
     },
     Audio {
         id: ClipId,
@@ -133,10 +259,393 @@ pub enum Clip {
         file_path: PathBuf, // Relative to project directory
         start_offset: f64,  // Start point within audio file
         end_offset: f64,    // End point within audio file
+        /// Waveform overview, computed once from the file
+        #[serde(skip)]
+        peaks: Option<Arc<AudioPeaks>>,
+        #[serde(default)]
+        is_muted: bool,
+        /// Hex color overriding the track color
+        #[serde(default)]
+        color: Option<String>,
     },
 }
 
+/// A non-destructive change to a MIDI clip's notes, heard on playback while the stored
+/// notes stay as they are. Freezing the clip writes the change into the notes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ClipProcessor {
+    /// Shift every note by this many semitones, clamped to the MIDI range
+    Transpose { semitones: i8 },
+    /// Multiply note velocities, keeping them within 1..=127
+    VelocityScale { factor: f32 },
+}
+
+impl ClipProcessor {
+    /// Change a message on its way out of the clip
+    pub fn process(&self, message: &mut MidiMessage) {
+        match (self, message) {
+            (
+                ClipProcessor::Transpose { semitones },
+                MidiMessage::NoteOn { key, .. } | MidiMessage::NoteOff { key, .. },
+            ) => *key = (*key as i16 + *semitones as i16).clamp(0, 127) as u8,
+            (ClipProcessor::VelocityScale { factor }, MidiMessage::NoteOn { velocity, .. }) => {
+                *velocity = Self::scale_velocity(*velocity, *factor)
+            }
+            _ => {}
+        }
+    }
+
+    /// Make the change to the stored notes and their events
+    pub fn freeze(&self, store: &mut MidiEventStore) {
+        match *self {
+            ClipProcessor::Transpose { semitones } => store.transpose_all(semitones),
+            ClipProcessor::VelocityScale { factor } => {
+                let velocities: Vec<(String, u8)> = store
+                    .get_notes()
+                    .map(|note| (note.id.clone(), Self::scale_velocity(note.velocity, factor)))
+                    .collect();
+                for (note_id, velocity) in velocities {
+                    store.update_note_velocity(&note_id, velocity);
+                }
+            }
+        }
+    }
+
+    // Never 0, which would turn a NoteOn into a NoteOff
+    fn scale_velocity(velocity: u8, factor: f32) -> u8 {
+        (velocity as f32 * factor).round().clamp(1.0, 127.0) as u8
+    }
+}
+
 impl Clip {
+    pub fn id(&self) -> &str {
+        match self {
+            Clip::Midi { id, .. } | Clip::Audio { id, .. } => id,
+        }
+    }
+
+    /// A copy of this clip at `start_time`, with new ids for the clip and everything in it
+    pub fn duplicate(&self, start_time: f64) -> Clip {
+        let mut copy = self.clone();
+        match &mut copy {
+            Clip::Midi {
+                id,
+                start_time: copy_start,
+                midi_data,
+                automation_lanes,
+                ..
+            } => {
+                *id = Uuid::new_v4().to_string();
+                *copy_start = start_time;
+                if let Some(store) = midi_data {
+                    *store = store.duplicate();
+                }
+                for lane in automation_lanes {
+                    lane.id = Uuid::new_v4().to_string();
+                    for point in &mut lane.points {
+                        point.id = Uuid::new_v4().to_string();
+                    }
+                }
+            }
+            Clip::Audio {
+                id,
+                start_time: copy_start,
+                ..
+            } => {
+                *id = Uuid::new_v4().to_string();
+                *copy_start = start_time;
+            }
+        }
+        copy
+    }
+
+    pub fn start_time(&self) -> f64 {
+        match self {
+            Clip::Midi { start_time, .. } | Clip::Audio { start_time, .. } => *start_time,
+        }
+    }
+
+    pub fn length(&self) -> f64 {
+        match self {
+            Clip::Midi { length, .. } | Clip::Audio { length, .. } => *length,
+        }
+    }
+
+    /// Initial tempo stored in a loaded MIDI clip's file, if it has one
+    pub fn midi_file_bpm(&self) -> Option<f64> {
+        match self {
+            Clip::Midi {
+                midi_data: Some(store),
+                ..
+            } => store.initial_bpm(),
+            _ => None,
+        }
+    }
+
+    /// Initial time signature stored in a loaded MIDI clip's file, if it has one
+    pub fn midi_file_time_signature(&self) -> Option<(u8, u8)> {
+        match self {
+            Clip::Midi {
+                midi_data: Some(store),
+                ..
+            } => store.initial_time_signature(),
+            _ => None,
+        }
+    }
+
+    /// Where a loaded MIDI clip's last note ends, relative to the clip start
+    pub fn content_end(&self) -> Option<f64> {
+        match self {
+            Clip::Midi {
+                midi_data: Some(store),
+                ..
+            } => store.last_note_end(),
+            _ => None,
+        }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        match self {
+            Clip::Midi { is_muted, .. } | Clip::Audio { is_muted, .. } => *is_muted,
+        }
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        match self {
+            Clip::Midi { is_muted, .. } | Clip::Audio { is_muted, .. } => *is_muted = muted,
+        }
+    }
+
+    pub fn color(&self) -> Option<&str> {
+        match self {
+            Clip::Midi { color, .. } | Clip::Audio { color, .. } => color.as_deref(),
+        }
+    }
+
+    pub fn set_color(&mut self, new_color: Option<String>) {
+        match self {
+            Clip::Midi { color, .. } | Clip::Audio { color, .. } => *color = new_color,
+        }
+    }
+
+    /// Whether `next` starts where this clip ends
+    pub fn is_followed_by(&self, next: &Clip) -> bool {
+        (next.start_time() - (self.start_time() + self.length())).abs() < CLIP_ADJACENCY_EPSILON
+    }
+
+    /// Whether `next` continues the same stretch of the same audio file, as left
+    /// behind when a clip is cut in two
+    pub fn is_split_from(&self, next: &Clip) -> bool {
+        match (self, next) {
+            (
+                Clip::Audio {
+                    file_path,
+                    length,
+                    start_offset,
+                    ..
+                },
+                Clip::Audio {
+                    file_path: next_path,
+                    start_offset: next_offset,
+                    ..
+                },
+            ) => {
+                self.is_followed_by(next)
+                    && file_path == next_path
+                    && (next_offset - (start_offset + length)).abs() < CLIP_ADJACENCY_EPSILON
+            }
+            _ => false,
+        }
+    }
+
+    /// Absorb the clip that follows this one. Split audio clips are healed back into
+    /// one; MIDI clips are merged by copying the second clip's notes and automation.
+    pub fn join(&mut self, next: &Clip) -> Result<(), Box<dyn Error>> {
+        if !self.is_followed_by(next) {
+            return Err("Clips must be next to each other to be joined".into());
+        }
+        let offset = next.start_time() - self.start_time();
+        let is_split = self.is_split_from(next);
+
+        match (self, next) {
+            (
+                Clip::Audio {
+                    length, end_offset, ..
+                },
+                Clip::Audio {
+                    length: next_length,
+                    end_offset: next_end_offset,
+                    ..
+                },
+            ) => {
+                if !is_split {
+                    return Err("Only audio clips split from the same recording can be joined".into());
+                }
+                *length += next_length;
+                *end_offset = *next_end_offset;
+                Ok(())
+            }
+            (
+                Clip::Midi {
+                    length,
+                    midi_data,
+                    automation_lanes,
+                    processors,
+                    ..
+                },
+                Clip::Midi {
+                    length: next_length,
+                    midi_data: next_data,
+                    automation_lanes: next_lanes,
+                    processors: next_processors,
+                    ..
+                },
+            ) => {
+                if processors != next_processors {
+                    return Err("Freeze the clips before joining clips that are processed differently".into());
+                }
+                let (Some(store), Some(next_store)) = (midi_data.as_mut(), next_data.as_ref())
+                else {
+                    return Err("Both MIDI clips must be loaded to be joined".into());
+                };
+                store.append_shifted(next_store, offset);
+
+                for next_lane in next_lanes {
+                    match automation_lanes
+                        .iter_mut()
+                        .find(|lane| lane.parameter == next_lane.parameter)
+                    {
+                        Some(lane) => lane.append_shifted(next_lane, offset),
+                        None => {
+                            let mut lane = next_lane.clone();
+                            lane.points.clear();
+                            lane.append_shifted(next_lane, offset);
+                            automation_lanes.push(lane);
+                        }
+                    }
+                }
+
+                *length = offset + next_length;
+                Ok(())
+            }
+            _ => Err("Cannot join a MIDI clip with an audio clip".into()),
+        }
+    }
+
+    pub fn set_start_time(&mut self, time: f64) {
+        match self {
+            Clip::Midi { start_time, .. } | Clip::Audio { start_time, .. } => *start_time = time,
+        }
+    }
+
+    /// Open a gap of `duration` seconds at `offset` into the clip: later notes, events and
+    /// automation move right and the clip grows to fit. MIDI clips must be loaded first.
+    /// Audio can't be opened up, so `Project::insert_time` cuts it in two with
+    /// `split_audio` instead.
+    fn insert_gap(&mut self, offset: f64, duration: f64) {
+        if let Clip::Midi {
+            length,
+            midi_data,
+            automation_lanes,
+            ..
+        } = self
+        {
+            if let Some(store) = midi_data {
+                store.shift_from(offset, duration);
+            }
+            for lane in automation_lanes {
+                lane.insert_gap(offset, duration);
+            }
+            *length += duration;
+        }
+    }
+
+    /// Cut `from..to` (relative to the clip start) out of the clip. MIDI notes starting
+    /// in the cut are dropped, notes running into it are shortened to end where it starts,
+    /// and later notes, events and automation close the gap; MIDI clips must be loaded
+    /// first. Audio is only trimmed when the cut reaches one of its ends, so split it
+    /// first to take out its middle.
+    fn remove_span(&mut self, from: f64, to: f64) {
+        let cut = to - from;
+        match self {
+            Clip::Midi {
+                length,
+                midi_data,
+                automation_lanes,
+                ..
+            } => {
+                if let Some(store) = midi_data {
+                    store.remove_span(from, to);
+                }
+                for lane in automation_lanes {
+                    lane.remove_span(from, to);
+                }
+                *length -= cut;
+            }
+            Clip::Audio {
+                length,
+                start_offset,
+                end_offset,
+                ..
+            } => {
+                if from <= 0.0 {
+                    *start_offset += cut;
+                    *length -= cut;
+                } else if to >= *length {
+                    *end_offset -= cut;
+                    *length -= cut;
+                }
+            }
+        }
+    }
+
+    /// Cut an audio clip in two at `offset` into it, keeping the first half and returning
+    /// the second with a new id. MIDI clips and offsets outside the clip are left alone.
+    fn split_audio(&mut self, offset: f64) -> Option<Clip> {
+        if !matches!(self, Clip::Audio { .. }) || offset <= 0.0 || offset >= self.length() {
+            return None;
+        }
+
+        let mut tail = self.clone();
+        if let Clip::Audio {
+            id,
+            start_time,
+            length,
+            start_offset,
+            ..
+        } = &mut tail
+        {
+            *id = Uuid::new_v4().to_string();
+            *start_time += offset;
+            *length -= offset;
+            *start_offset += offset;
+        }
+        if let Clip::Audio {
+            length, end_offset, ..
+        } = self
+        {
+            *end_offset -= *length - offset;
+            *length = offset;
+        }
+        Some(tail)
+    }
+
+    /// A MIDI clip with no backing file, ready for drawing or recording notes
+    pub fn empty_midi(start_time: f64, length: f64, ppq: u32) -> Self {
+        Clip::Midi {
+            id: Uuid::new_v4().to_string(),
+            start_time,
+            length,
+            file_path: PathBuf::new(),
+            midi_data: Some(MidiEventStore::new(ppq)),
+            loaded: true,
+            automation_lanes: Vec::new(),
+            processors: Vec::new(),
+            is_muted: false,
+            color: None,
+        }
+    }
+
     pub fn load_midi(&mut self) -> Result<(), Box<dyn Error>> {
         if let Clip::Midi {
             file_path,
@@ -161,26 +670,80 @@ impl Clip {
         Ok(())
     }
 
-    pub fn get_events_in_time_range(&self, start: f64, end: f64) -> Vec<MidiEvent> {
+    /// Events the clip plays between `start` and `end`, in project time. CC lanes play on
+    /// `cc_channel`, the channel of the track the clip is on. Nothing plays from the clip
+    /// end on, and notes still sounding there get a NoteOff at the end.
+    pub fn get_events_in_time_range(&self, start: f64, end: f64, cc_channel: u8) -> Vec<MidiEvent> {
         match self {
             Clip::Midi {
                 midi_data,
                 start_time,
+                length,
+                automation_lanes,
+                processors,
                 ..
             } => {
                 if let Some(store) = midi_data {
                     // Adjust time range for clip position
                     let clip_start = start - start_time;
-                    let clip_end = end - start_time;
+                    let clip_end = (end - start_time).min(*length).max(clip_start);
 
-                    store
+                    let mut events: Vec<MidiEvent> = store
                         .get_events_in_range(clip_start, clip_end)
                         .into_iter()
-                        .map(|event| MidiEvent {
-                            time: event.time + start_time,
-                            ..event.clone()
-                        })
-                        .collect()
+                        .cloned()
+                        .collect();
+
+                    // Notes running past a shortened clip are cut off where it ends
+                    if (clip_start..end - start_time).contains(length) {
+                        let tick = store.time_to_tick(*length);
+                        events.extend(
+                            store
+                                .get_notes()
+                                .filter(|note| {
+                                    note.start_time < *length
+                                        && note.start_time + note.duration >= *length
+                                })
+                                .map(|note| MidiEvent {
+                                    id: format!("{}_cut", note.id),
+                                    time: *length,
+                                    tick,
+                                    message: MidiMessage::NoteOff {
+                                        channel: note.channel,
+                                        key: note.key,
+                                        velocity: 0,
+                                    },
+                                }),
+                        );
+                    }
+
+                    for event in &mut events {
+                        event.time += start_time;
+                        for processor in processors {
+                            processor.process(&mut event.message);
+                        }
+                    }
+
+                    // CC lanes play as a ControlChange each time their value steps
+                    for lane in automation_lanes {
+                        let Some(controller) = lane.cc_number() else {
+                            continue;
+                        };
+                        for (time, value) in lane.cc_steps_in_range(clip_start, clip_end) {
+                            events.push(MidiEvent {
+                                id: format!("{}_{}", lane.id, store.time_to_tick(time)),
+                                time: time + start_time,
+                                tick: store.time_to_tick(time),
+                                message: MidiMessage::ControlChange {
+                                    channel: cc_channel,
+                                    controller,
+                                    value,
+                                },
+                            });
+                        }
+                    }
+
+                    events
                 } else {
                     Vec::new()
                 }
@@ -188,18 +751,148 @@ impl Clip {
             _ => Vec::new(),
         }
     }
+
+    /// A copy of the clip's notes and events as it plays them, for writing to a file: CC
+    /// lanes become ControlChange events on `cc_channel` and processors are applied
+    pub fn export_store(&self, cc_channel: u8) -> Option<MidiEventStore> {
+        let Clip::Midi {
+            midi_data: Some(store),
+            length,
+            automation_lanes,
+            processors,
+            ..
+        } = self
+        else {
+            return None;
+        };
+
+        let mut export = store.clone();
+        for processor in processors {
+            processor.freeze(&mut export);
+        }
+        for lane in automation_lanes {
+            let Some(controller) = lane.cc_number() else {
+                continue;
+            };
+            for (time, value) in lane.cc_steps_in_range(0.0, *length) {
+                let tick = export.time_to_tick(time);
+                export.add_event(MidiEvent {
+                    id: format!("{}_{}", lane.id, tick),
+                    time,
+                    tick,
+                    message: MidiMessage::ControlChange {
+                        channel: cc_channel,
+                        controller,
+                        value,
+                    },
+                });
+            }
+        }
+        Some(export)
+    }
+
+    /// A ControlChange for each CC lane with the lane's value at `time`, when playback
+    /// starts part-way into the clip and would otherwise keep a stale controller value
+    /// until the lane next changes
+    pub fn cc_values_at(&self, time: f64, cc_channel: u8) -> Vec<MidiEvent> {
+        let Clip::Midi {
+            midi_data: Some(store),
+            start_time,
+            length,
+            automation_lanes,
+            ..
+        } = self
+        else {
+            return Vec::new();
+        };
+        // Lanes send their first value themselves when playback starts at the clip start
+        let clip_time = time - start_time;
+        if clip_time <= 0.0 || clip_time >= *length {
+            return Vec::new();
+        }
+
+        automation_lanes
+            .iter()
+            .filter(|lane| !lane.points.is_empty())
+            .filter_map(|lane| {
+                let controller = lane.cc_number()?;
+                Some(MidiEvent {
+                    id: format!("{}_{}", lane.id, store.time_to_tick(clip_time)),
+                    time,
+                    tick: store.time_to_tick(clip_time),
+                    message: MidiMessage::ControlChange {
+                        channel: cc_channel,
+                        controller,
+                        value: lane.cc_value_at(clip_time),
+                    },
+                })
+            })
+            .collect()
+    }
 }
 
 // Track-level MIDI handling
 impl Track {
+    /// Whether the track is heard, given whether any track in the project is soloed
+    pub fn is_audible(&self, any_soloed: bool) -> bool {
+        !self.is_muted && (!any_soloed || self.is_soloed)
+    }
+
+    /// Cut the audio clips that start before `starting_before` and run across `time` in
+    /// two there, with the second half placed right after the first in the clip list
+    fn split_audio_clips(&mut self, time: f64, starting_before: f64) {
+        let mut index = 0;
+        while index < self.clips.len() {
+            let clip = &mut self.clips[index];
+            if clip.start_time() < starting_before {
+                if let Some(tail) = clip.split_audio(time - clip.start_time()) {
+                    self.clips.insert(index + 1, tail);
+                    index += 1;
+                }
+            }
+            index += 1;
+        }
+    }
+
+    /// The clip of the same kind starting right where the given clip ends
+    pub fn next_adjacent_clip(&self, clip_id: &str) -> Option<&Clip> {
+        let clip = self.clips.iter().find(|c| c.id() == clip_id)?;
+        self.clips.iter().find(|next| {
+            next.id() != clip_id
+                && std::mem::discriminant(*next) == std::mem::discriminant(clip)
+                && clip.is_followed_by(next)
+        })
+    }
+
     pub fn get_events_in_time_range(&self, start: f64, end: f64) -> Vec<MidiEvent> {
+        let Some(cc_channel) = self.message_channel() else {
+            return Vec::new();
+        };
+        self.clips
+            .iter()
+            .filter(|clip| !clip.is_muted())
+            .flat_map(|clip| clip.get_events_in_time_range(start, end, cc_channel))
+            .collect()
+    }
+
+    /// Current CC lane values of the clips under `time`, see `Clip::cc_values_at`
+    pub fn cc_values_at(&self, time: f64) -> Vec<MidiEvent> {
+        let Some(cc_channel) = self.message_channel() else {
+            return Vec::new();
+        };
+        self.clips
+            .iter()
+            .filter(|clip| !clip.is_muted())
+            .flat_map(|clip| clip.cc_values_at(time, cc_channel))
+            .collect()
+    }
+
+    /// Channel of messages the track generates itself, counted from 0 like those in files.
+    /// Track channels count from 1.
+    pub fn message_channel(&self) -> Option<u8> {
         match &self.track_type {
-            TrackType::Midi { .. } => self
-                .clips
-                .iter()
-                .flat_map(|clip| clip.get_events_in_time_range(start, end))
-                .collect(),
-            _ => Vec::new(),
+            TrackType::Midi { channel, .. } => Some(channel.saturating_sub(1)),
+            _ => None,
         }
     }
 }
@@ -218,6 +911,39 @@ impl Project {
             .collect()
     }
 
+    /// Current CC lane values on every track, sent when playback starts or jumps
+    pub fn cc_values_at(&self, time: f64) -> Vec<(String, MidiEvent)> {
+        self.tracks
+            .iter()
+            .flat_map(|track| {
+                track
+                    .cc_values_at(time)
+                    .into_iter()
+                    .map(move |event| (track.id.clone(), event))
+            })
+            .collect()
+    }
+
+    pub fn find_clip_mut(&mut self, clip_id: &str) -> Option<&mut Clip> {
+        self.tracks
+            .iter_mut()
+            .flat_map(|track| track.clips.iter_mut())
+            .find(|clip| clip.id() == clip_id)
+    }
+
+    pub fn any_track_soloed(&self) -> bool {
+        self.tracks.iter().any(|t| t.is_soloed)
+    }
+
+    /// Time where the last clip ends
+    pub fn end_time(&self) -> f64 {
+        self.tracks
+            .iter()
+            .flat_map(|track| &track.clips)
+            .map(|clip| clip.start_time() + clip.length())
+            .fold(0.0, f64::max)
+    }
+
     pub fn ticks_per_second(&self) -> f64 {
         (self.bpm / 60.0) * self.ppq as f64
     }
@@ -238,14 +964,225 @@ impl Project {
         Self {
             name,
             bpm: 120.0,
-            ppq: 480,
+            ppq: DEFAULT_PPQ,
+            time_signature: default_time_signature(),
             tracks: Vec::new(),
+            markers: Vec::new(),
+            sections: Vec::new(),
+            is_template: false,
             project_path: None,
         }
     }
 
-    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
-        // Create project directory if it doesn't exist
+    /// Where templates are stored, falling back to the working directory when the
+    /// platform has no config directory
+    pub fn templates_dir() -> PathBuf {
+        match app_config_dir() {
+            Some(dir) => dir.join(TEMPLATES_DIR),
+            None => PathBuf::from(TEMPLATES_DIR),
+        }
+    }
+
+    /// Save the track layout, routing, tempo and markers (but no clips) as a template
+    /// named `name` in the templates directory
+    pub fn save_as_template(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        self.save_template_in(&Self::templates_dir(), name)
+    }
+
+    fn save_template_in(&self, dir: &Path, name: &str) -> Result<(), Box<dyn Error>> {
+        // The name becomes the template's directory and file name
+        let name = template_file_name(name)?;
+        let mut template = self.clone();
+        template.name = name.clone();
+        template.is_template = true;
+        for track in &mut template.tracks {
+            track.clips.clear();
+            track.is_armed = false;
+        }
+        template.save(&dir.join(name))
+    }
+
+    /// Template files in the templates directory, sorted by name
+    pub fn list_templates() -> Vec<PathBuf> {
+        Self::list_templates_in(&Self::templates_dir())
+    }
+
+    fn list_templates_in(dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+
+        let mut templates: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let dir = entry.path();
+                let name = dir.file_name()?.to_string_lossy().to_string();
+                let file = dir.join(format!("{}.supersaw", name));
+                file.is_file().then_some(file)
+            })
+            .collect();
+        templates.sort();
+        templates
+    }
+
+    /// Start a new untitled project from a saved template
+    pub fn from_template(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut project = Self::load(path)?;
+        if !project.is_template {
+            return Err(format!("{} is not a template", path.display()).into());
+        }
+
+        project.name = "Untitled".to_string();
+        project.is_template = false;
+        project.project_path = None;
+        // Fresh ids so projects started from the same template don't share them
+        for track in &mut project.tracks {
+            track.id = Uuid::new_v4().to_string();
+        }
+        for marker in &mut project.markers {
+            marker.id = Uuid::new_v4().to_string();
+        }
+        for section in &mut project.sections {
+            section.id = Uuid::new_v4().to_string();
+        }
+        Ok(project)
+    }
+
+    /// Latest marker strictly before `time`
+    pub fn previous_marker_time(&self, time: f64) -> Option<f64> {
+        self.markers
+            .iter()
+            .map(|m| m.time)
+            .filter(|t| *t < time - MARKER_EPSILON)
+            .reduce(f64::max)
+    }
+
+    /// Earliest marker strictly after `time`
+    pub fn next_marker_time(&self, time: f64) -> Option<f64> {
+        self.markers
+            .iter()
+            .map(|m| m.time)
+            .filter(|t| *t > time + MARKER_EPSILON)
+            .reduce(f64::min)
+    }
+
+    /// Marker named after its position in the list, ready to be added at `time`
+    pub fn new_marker(&self, time: f64) -> Marker {
+        Marker::new(format!("Marker {}", self.markers.len() + 1), time)
+    }
+
+    /// Section named and colored after its position in the list, covering `start..end`
+    pub fn new_section(&self, start: f64, end: f64) -> Section {
+        let count = self.sections.len();
+        Section::new(
+            format!("Section {}", count + 1),
+            start,
+            end,
+            SECTION_COLORS[count % SECTION_COLORS.len()],
+        )
+    }
+
+    /// Insert `duration` seconds of silence at `time`: clips, markers and sections after it
+    /// move right, MIDI clips and sections spanning it grow, and audio clips spanning it
+    /// are split
+    pub fn insert_time(&mut self, time: f64, duration: f64) -> Result<(), Box<dyn Error>> {
+        self.load_midi_clips()?;
+        for track in &mut self.tracks {
+            // Audio can't grow a gap, so it is cut in two and the second half moves
+            track.split_audio_clips(time, time);
+            for clip in &mut track.clips {
+                let start = clip.start_time();
+                if start >= time {
+                    clip.set_start_time(start + duration);
+                } else if start + clip.length() > time {
+                    clip.insert_gap(time - start, duration);
+                }
+            }
+        }
+        for marker in &mut self.markers {
+            if marker.time >= time {
+                marker.time += duration;
+            }
+        }
+        for section in &mut self.sections {
+            if section.start >= time {
+                section.start += duration;
+            }
+            if section.end > time {
+                section.end += duration;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete `start..end` from the arrangement: content inside it is removed and
+    /// everything after it moves left to close the gap
+    pub fn remove_time(&mut self, start: f64, end: f64) -> Result<(), Box<dyn Error>> {
+        self.load_midi_clips()?;
+        let cut = end - start;
+        let ripple = |time: f64| {
+            if time >= end {
+                time - cut
+            } else {
+                time.min(start)
+            }
+        };
+
+        for track in &mut self.tracks {
+            // Audio can't lose its middle, so clips running across the whole range are cut
+            // in two at its end: the first half is trimmed and the second moves to meet it
+            track.split_audio_clips(end, start);
+            track.clips.retain(|clip| {
+                clip.start_time() < start || clip.start_time() + clip.length() > end
+            });
+            for clip in &mut track.clips {
+                let clip_start = clip.start_time();
+                let clip_end = clip_start + clip.length();
+                if clip_start < end && clip_end > start {
+                    let from = (start - clip_start).max(0.0);
+                    let to = (end - clip_start).min(clip.length());
+                    clip.remove_span(from, to);
+                }
+                clip.set_start_time(ripple(clip_start));
+            }
+        }
+
+        self.markers
+            .retain(|marker| marker.time < start || marker.time >= end);
+        for marker in &mut self.markers {
+            marker.time = ripple(marker.time);
+        }
+
+        self.sections
+            .retain(|section| section.start < start || section.end > end);
+        for section in &mut self.sections {
+            section.start = ripple(section.start);
+            section.end = ripple(section.end);
+        }
+        Ok(())
+    }
+
+    /// Load every MIDI clip not loaded yet, before an edit that needs their real lengths
+    /// and notes. Nothing is changed until all of them have loaded.
+    fn load_midi_clips(&mut self) -> Result<(), Box<dyn Error>> {
+        for clip in self.tracks.iter_mut().flat_map(|track| track.clips.iter_mut()) {
+            clip.load_midi()
+                .map_err(|e| format!("Failed to load MIDI data: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Clips (with their track ids) that start inside `start..end`
+    pub fn clips_starting_in(&self, start: f64, end: f64) -> Vec<(&str, &Clip)> {
+        self.tracks
+            .iter()
+            .flat_map(|t| t.clips.iter().map(move |c| (t.id.as_str(), c)))
+            .filter(|(_, c)| c.start_time() >= start && c.start_time() < end)
+            .collect()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        // Create project directory if it doesn't exist
         fs::create_dir_all(path)?;
 
         // Create subdirectories for different asset types
@@ -280,6 +1217,8 @@ impl Project {
                         let new_path = copy_to_project_dir(file_path, &samples_dir)?;
                         *file_path = new_path;
                     }
+                    // Clips created in the app have no source file, their notes are stored in the project file
+                    Clip::Midi { file_path, .. } if file_path.as_os_str().is_empty() => {}
                     Clip::Midi { file_path, .. } => {
                         println!("MIDI clip file path: {:?}", file_path);
                         let new_path = copy_to_project_dir(file_path, &midi_dir)?;
@@ -316,15 +1255,31 @@ impl Project {
     pub fn create_midi_track_from_file_path(
         &mut self,
         file_path: &Path,
+        start_time: f64,
     ) -> Result<TrackId, Box<dyn Error>> {
+        let track = self.midi_track_from_file_path(file_path, start_time, self.tracks.len() + 1)?;
+        Ok(self.push_track(track))
+    }
+
+    /// Build the track `create_midi_track_from_file_path` would add, without adding it.
+    /// `number` is the position shown in front of its name.
+    pub fn midi_track_from_file_path(
+        &self,
+        file_path: &Path,
+        start_time: f64,
+        number: usize,
+    ) -> Result<Track, Box<dyn Error>> {
         let mut clip = Clip::Midi {
             id: Uuid::new_v4().to_string(),
-            start_time: 0.0,
+            start_time,
             length: 0.0,
             file_path: file_path.to_path_buf(),
             midi_data: None,
             loaded: false,
             automation_lanes: Vec::new(),
+            processors: Vec::new(),
+            is_muted: false,
+            color: None,
         };
 
         // Load the MIDI data
@@ -332,40 +1287,175 @@ impl Project {
             return Err(format!("Failed to load MIDI file {}: {}", file_path.display(), e).into());
         }
 
-        // Extract file name for track name
-        let mid_name = file_path
-            .file_name()
-            .map(|n| n.to_string_lossy().to_string())
-            .unwrap_or_else(|| "Unnamed MIDI".to_string());
-
-        // Create track with the loaded clip
-        let track_id = Uuid::new_v4().to_string();
-        let track = Track {
-            id: track_id.clone(),
-            name: format!(
-                "{} - {}",
-                self.tracks.len() + 1,
-                mid_name.trim_end_matches(".mid")
-            ),
-            track_type: TrackType::Midi {
-                channel: 1,
-                device_name: None,
-            },
-            clips: vec![clip],
-            is_muted: false,
-            is_soloed: false,
-            is_armed: false,
-            color: "#fde047".to_string(), // Default yellow
+        let name = midi_file_stem(file_path);
+        Ok(new_midi_track(number, name, 1, clip))
+    }
+
+    /// Import a MIDI file as one track, or as one track per file track or channel
+    pub fn create_midi_tracks_from_file_path(
+        &mut self,
+        file_path: &Path,
+        start_time: f64,
+        split: MidiImportSplit,
+    ) -> Result<Vec<TrackId>, Box<dyn Error>> {
+        let path = file_path.to_path_buf();
+        let parts: Vec<(String, u8, MidiEventStore)> = match split {
+            MidiImportSplit::Single => {
+                return Ok(vec![self.create_midi_track_from_file_path(file_path, start_time)?]);
+            }
+            MidiImportSplit::PerTrack => MidiEventStore::load_tracks_from_file(&path)
+                .map_err(|e| format!("Failed to load MIDI file {}: {}", file_path.display(), e))?
+                .into_iter()
+                .enumerate()
+                .map(|(index, store)| {
+                    let channel = store.get_notes().map(|note| note.channel).min().unwrap_or(0);
+                    (format!("Track {}", index + 1), channel, store)
+                })
+                .collect(),
+            MidiImportSplit::PerChannel => MidiEventStore::load_from_file(&path)
+                .map_err(|e| format!("Failed to load MIDI file {}: {}", file_path.display(), e))?
+                .split_by_channel()
+                .into_iter()
+                .map(|(channel, store)| (format!("Ch {}", channel + 1), channel, store))
+                .collect(),
         };
+        if parts.is_empty() {
+            return Err(format!("{} has no notes", file_path.display()).into());
+        }
 
-        // Add the track to the project
+        let stem = midi_file_stem(file_path);
+        Ok(parts
+            .into_iter()
+            .map(|(part, channel, store)| {
+                let clip = Clip::Midi {
+                    id: Uuid::new_v4().to_string(),
+                    start_time,
+                    length: store.get_last_event_time().unwrap_or(0.0),
+                    file_path: path.clone(),
+                    midi_data: Some(store),
+                    loaded: true,
+                    automation_lanes: Vec::new(),
+                    processors: Vec::new(),
+                    is_muted: false,
+                    color: None,
+                };
+                // Track channels count from 1, file channels from 0
+                self.push_midi_track(format!("{} {}", stem, part), channel + 1, clip)
+            })
+            .collect())
+    }
+
+    fn push_midi_track(&mut self, name: String, channel: u8, clip: Clip) -> TrackId {
+        let track = new_midi_track(self.tracks.len() + 1, name, channel, clip);
+        self.push_track(track)
+    }
+
+    fn push_track(&mut self, track: Track) -> TrackId {
+        let (bpm, time_signature) = self.imported_timing(&track);
+        if let Some(bpm) = bpm {
+            self.bpm = bpm;
+        }
+        if let Some(time_signature) = time_signature {
+            self.time_signature = time_signature;
+        }
+
+        let track_id = track.id.clone();
         self.tracks.push(track);
+        track_id
+    }
 
-        // Return the new track ID
-        Ok(track_id)
+    /// Tempo and meter a track imported from a MIDI file brings to the project. Only the
+    /// first file sets them, so the grid lines up with its notes.
+    pub fn imported_timing(&self, track: &Track) -> (Option<f64>, Option<(u8, u8)>) {
+        if !self.tracks.iter().all(|t| t.clips.is_empty()) {
+            return (None, None);
+        }
+        match track.clips.first() {
+            Some(clip) => (clip.midi_file_bpm(), clip.midi_file_time_signature()),
+            None => (None, None),
+        }
+    }
+}
+
+/// A MIDI track holding `clip`, named after its position in the project
+fn new_midi_track(number: usize, name: String, channel: u8, clip: Clip) -> Track {
+    Track {
+        id: Uuid::new_v4().to_string(),
+        name: format!("{} - {}", number, name),
+        track_type: TrackType::Midi {
+            channel,
+            device_name: None,
+        },
+        clips: vec![clip],
+        is_muted: false,
+        is_soloed: false,
+        is_armed: false,
+        color: "#fde047".to_string(), // Default yellow
+        instrument: None,
+        is_collapsed: false,
+        is_monophonic: false,
+        input_velocity_curve: VelocityCurve::Linear,
     }
 }
 
+/// How an imported MIDI file is spread over tracks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MidiImportSplit {
+    /// Everything on one track
+    Single,
+    /// One track per track in the file
+    PerTrack,
+    /// One track per MIDI channel used in the file
+    PerChannel,
+}
+
+impl MidiImportSplit {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            MidiImportSplit::Single => "One track",
+            MidiImportSplit::PerTrack => "One track per file track",
+            MidiImportSplit::PerChannel => "One track per channel",
+        }
+    }
+}
+
+/// File name without a .mid extension, for naming imported tracks
+fn midi_file_stem(file_path: &Path) -> String {
+    file_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Unnamed MIDI".to_string())
+        .trim_end_matches(".mid")
+        .to_string()
+}
+
+fn default_time_signature() -> (u8, u8) {
+    (4, 4)
+}
+
+fn default_ppq() -> u32 {
+    DEFAULT_PPQ
+}
+
+/// A template name typed by the user, made safe to use as a single file name: path
+/// separators and other characters file systems reject become `_`
+fn template_file_name(name: &str) -> Result<String, Box<dyn Error>> {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // Trailing dots are dropped by Windows, and "." and ".." name directories
+    let name = name.trim().trim_end_matches('.').trim_end();
+    if name.is_empty() {
+        return Err("Template name can't be empty".into());
+    }
+    Ok(name.to_string())
+}
+
 // Helper function to copy a file to the project directory and return the relative path
 fn copy_to_project_dir(source_path: &Path, target_dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
     if !source_path.exists() {
@@ -403,3 +1493,574 @@ fn copy_to_project_dir(source_path: &Path, target_dir: &Path) -> Result<PathBuf,
 
     Ok(target_path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{AutomationParameter, MidiExportOptions, Note};
+
+    fn midi_track(channel: u8, clips: Vec<Clip>) -> Track {
+        Track {
+            id: Uuid::new_v4().to_string(),
+            name: "Synth".to_string(),
+            track_type: TrackType::Midi {
+                channel,
+                device_name: None,
+            },
+            clips,
+            is_muted: false,
+            is_soloed: false,
+            is_armed: false,
+            color: "#fde047".to_string(),
+            instrument: None,
+            is_collapsed: false,
+            is_monophonic: false,
+            input_velocity_curve: VelocityCurve::Linear,
+        }
+    }
+
+    /// A MIDI clip with a CC 74 lane rising from 0 to 100 over its first second
+    fn clip_with_cutoff_lane(start_time: f64) -> Clip {
+        let mut lane = AutomationLane::new(AutomationParameter::MidiCC {
+            cc_number: 74,
+            name: "Cutoff".to_string(),
+        });
+        lane.add_point(0.0, 0.0);
+        lane.add_point(1.0, 100.0);
+
+        let mut clip = Clip::empty_midi(start_time, 2.0, DEFAULT_PPQ);
+        if let Clip::Midi {
+            automation_lanes, ..
+        } = &mut clip
+        {
+            automation_lanes.push(lane);
+        }
+        clip
+    }
+
+    fn midi_clip(start_time: f64, length: f64, note_starts: &[f64]) -> Clip {
+        let mut clip = Clip::empty_midi(start_time, length, DEFAULT_PPQ);
+        if let Clip::Midi {
+            midi_data: Some(store),
+            ..
+        } = &mut clip
+        {
+            for &start in note_starts {
+                store.add_note(Note {
+                    id: Uuid::new_v4().to_string(),
+                    channel: 0,
+                    key: 60,
+                    velocity: 100,
+                    start_time: start,
+                    duration: 0.25,
+                    start_tick: store.time_to_tick(start),
+                    duration_ticks: store.time_to_tick(0.25),
+                });
+            }
+        }
+        clip
+    }
+
+    /// An audio clip playing `start_offset..start_offset + length` of its file
+    fn audio_clip(start_time: f64, length: f64, start_offset: f64) -> Clip {
+        Clip::Audio {
+            id: Uuid::new_v4().to_string(),
+            start_time,
+            length,
+            file_path: PathBuf::from("take.wav"),
+            start_offset,
+            end_offset: start_offset + length,
+            peaks: None,
+            is_muted: false,
+            color: None,
+        }
+    }
+
+    fn note_starts(clip: &Clip) -> Vec<f64> {
+        let Clip::Midi {
+            midi_data: Some(store),
+            ..
+        } = clip
+        else {
+            return Vec::new();
+        };
+        let mut starts: Vec<f64> = store.get_notes().map(|note| note.start_time).collect();
+        starts.sort_by(f64::total_cmp);
+        starts
+    }
+
+    fn audio_offsets(clip: &Clip) -> (f64, f64) {
+        match clip {
+            Clip::Audio {
+                start_offset,
+                end_offset,
+                ..
+            } => (*start_offset, *end_offset),
+            Clip::Midi { .. } => panic!("not an audio clip"),
+        }
+    }
+
+    /// (start, length) of each clip on the track
+    fn spans(track: &Track) -> Vec<(f64, f64)> {
+        track
+            .clips
+            .iter()
+            .map(|clip| (clip.start_time(), clip.length()))
+            .collect()
+    }
+
+    fn project_with(clips: Vec<Clip>) -> Project {
+        let mut project = Project::new("Test".to_string());
+        project.tracks.push(midi_track(1, clips));
+        project
+    }
+
+    #[test]
+    fn removing_time_cuts_and_ripples_midi_clips() {
+        let mut project = project_with(vec![
+            midi_clip(0.0, 1.0, &[0.5]),       // before
+            midi_clip(1.0, 2.0, &[0.2, 1.5]),  // straddles the start
+            midi_clip(2.5, 1.0, &[0.1]),       // inside
+            midi_clip(3.0, 3.0, &[0.5, 1.5]),  // straddles the end
+            midi_clip(0.5, 5.0, &[0.5, 3.5]),  // covers the whole range
+            midi_clip(5.0, 1.0, &[0.0]),       // after
+        ]);
+        project.remove_time(2.0, 4.0).unwrap();
+
+        let track = &project.tracks[0];
+        assert_eq!(
+            spans(track),
+            vec![(0.0, 1.0), (1.0, 1.0), (2.0, 2.0), (0.5, 3.0), (3.0, 1.0)]
+        );
+        assert_eq!(note_starts(&track.clips[0]), vec![0.5]);
+        assert_eq!(note_starts(&track.clips[1]), vec![0.2]);
+        assert_eq!(note_starts(&track.clips[2]), vec![0.5]);
+        assert_eq!(note_starts(&track.clips[3]), vec![0.5, 1.5]);
+        assert_eq!(note_starts(&track.clips[4]), vec![0.0]);
+    }
+
+    #[test]
+    fn removing_the_middle_of_an_audio_clip_splits_it() {
+        let mut project = project_with(vec![audio_clip(1.0, 4.0, 0.5)]);
+        let id = project.tracks[0].clips[0].id().to_string();
+        project.remove_time(2.0, 4.0).unwrap();
+
+        let track = &project.tracks[0];
+        assert_eq!(spans(track), vec![(1.0, 1.0), (2.0, 1.0)]);
+        assert_eq!(track.clips[0].id(), id);
+        assert_ne!(track.clips[1].id(), id);
+        assert_eq!(audio_offsets(&track.clips[0]), (0.5, 1.5));
+        assert_eq!(audio_offsets(&track.clips[1]), (3.5, 4.5));
+        // The halves meet, but the removed audio isn't there to heal them back together
+        assert!(track.clips[0].is_followed_by(&track.clips[1]));
+        assert!(!track.clips[0].is_split_from(&track.clips[1]));
+    }
+
+    #[test]
+    fn removing_time_trims_audio_clips_at_their_ends() {
+        let mut project = project_with(vec![
+            audio_clip(1.0, 2.0, 0.0), // straddles the start
+            audio_clip(3.0, 2.0, 0.0), // straddles the end
+            audio_clip(2.5, 1.0, 0.0), // inside
+        ]);
+        project.remove_time(2.0, 4.0).unwrap();
+
+        let track = &project.tracks[0];
+        assert_eq!(spans(track), vec![(1.0, 1.0), (2.0, 1.0)]);
+        assert_eq!(audio_offsets(&track.clips[0]), (0.0, 1.0));
+        assert_eq!(audio_offsets(&track.clips[1]), (1.0, 2.0));
+    }
+
+    #[test]
+    fn removing_time_drops_and_ripples_markers_and_sections() {
+        let mut project = Project::new("Test".to_string());
+        for time in [1.0, 2.0, 3.0, 4.0, 6.0] {
+            project.markers.push(Marker::new("M", time));
+        }
+        for (start, end) in [(0.0, 1.0), (1.0, 3.0), (2.5, 3.5), (1.0, 5.0), (4.0, 8.0)] {
+            project.sections.push(Section::new("S", start, end, "#93c5fd"));
+        }
+        project.remove_time(2.0, 4.0).unwrap();
+
+        let markers: Vec<f64> = project.markers.iter().map(|m| m.time).collect();
+        assert_eq!(markers, vec![1.0, 2.0, 4.0]);
+        let sections: Vec<(f64, f64)> = project.sections.iter().map(|s| (s.start, s.end)).collect();
+        assert_eq!(sections, vec![(0.0, 1.0), (1.0, 2.0), (1.0, 3.0), (2.0, 6.0)]);
+    }
+
+    #[test]
+    fn inserting_time_grows_midi_clips_and_splits_audio() {
+        let mut project = project_with(vec![
+            midi_clip(0.0, 1.0, &[0.5]),      // before
+            midi_clip(1.0, 2.0, &[0.5, 1.5]), // spans the insert point
+            midi_clip(2.0, 1.0, &[0.0]),      // starts on it
+            audio_clip(1.0, 2.0, 0.0),        // spans it
+        ]);
+        project.insert_time(2.0, 1.0).unwrap();
+
+        let track = &project.tracks[0];
+        assert_eq!(
+            spans(track),
+            vec![(0.0, 1.0), (1.0, 3.0), (3.0, 1.0), (1.0, 1.0), (3.0, 1.0)]
+        );
+        assert_eq!(note_starts(&track.clips[1]), vec![0.5, 2.5]);
+        assert_eq!(note_starts(&track.clips[2]), vec![0.0]);
+        assert_eq!(audio_offsets(&track.clips[3]), (0.0, 1.0));
+        assert_eq!(audio_offsets(&track.clips[4]), (1.0, 2.0));
+    }
+
+    #[test]
+    fn inserting_time_moves_markers_and_stretches_sections() {
+        let mut project = Project::new("Test".to_string());
+        for time in [1.0, 2.0, 3.0] {
+            project.markers.push(Marker::new("M", time));
+        }
+        for (start, end) in [(0.0, 2.0), (1.0, 3.0), (2.0, 3.0)] {
+            project.sections.push(Section::new("S", start, end, "#93c5fd"));
+        }
+        project.insert_time(2.0, 1.0).unwrap();
+
+        let markers: Vec<f64> = project.markers.iter().map(|m| m.time).collect();
+        assert_eq!(markers, vec![1.0, 3.0, 4.0]);
+        let sections: Vec<(f64, f64)> = project.sections.iter().map(|s| (s.start, s.end)).collect();
+        assert_eq!(sections, vec![(0.0, 2.0), (1.0, 4.0), (3.0, 4.0)]);
+    }
+
+    #[test]
+    fn template_names_become_a_single_file_name() {
+        assert_eq!(template_file_name("Band: Live").unwrap(), "Band_ Live");
+        assert_eq!(template_file_name("../../etc/x").unwrap(), ".._.._etc_x");
+        assert_eq!(template_file_name(" Drums... ").unwrap(), "Drums");
+        assert!(template_file_name("..").is_err());
+        assert!(template_file_name("  ").is_err());
+    }
+
+    #[test]
+    fn templates_stay_in_their_directory_and_start_projects_with_fresh_ids() {
+        let dir = std::env::temp_dir().join(format!("supersaw-templates-{}", Uuid::new_v4()));
+        let mut project = Project::new("Song".to_string());
+        project.tracks.push(midi_track(1, vec![midi_clip(0.0, 1.0, &[0.0])]));
+        project.markers.push(Marker::new("Drop", 4.0));
+        project.sections.push(Section::new("Intro", 0.0, 4.0, "#93c5fd"));
+
+        project.save_template_in(&dir, "../Band/Live").unwrap();
+        let templates = Project::list_templates_in(&dir);
+        let loaded = templates.first().map(|path| Project::from_template(path));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(templates, vec![dir.join(".._Band_Live").join(".._Band_Live.supersaw")]);
+        let loaded = loaded.unwrap().unwrap();
+        assert!(loaded.tracks[0].clips.is_empty());
+        assert_ne!(loaded.tracks[0].id, project.tracks[0].id);
+        assert_ne!(loaded.markers[0].id, project.markers[0].id);
+        assert_ne!(loaded.sections[0].id, project.sections[0].id);
+        assert_eq!(loaded.sections[0].name, "Intro");
+    }
+
+    #[test]
+    fn removing_a_span_from_a_clip() {
+        // MIDI closes the gap
+        let mut clip = midi_clip(0.0, 4.0, &[0.5, 1.5, 3.0]);
+        clip.remove_span(1.0, 2.0);
+        assert_eq!(clip.length(), 3.0);
+        assert_eq!(note_starts(&clip), vec![0.5, 2.0]);
+
+        // Audio is trimmed from whichever end the span reaches
+        let mut clip = audio_clip(0.0, 4.0, 1.0);
+        clip.remove_span(0.0, 1.0);
+        assert_eq!((clip.length(), audio_offsets(&clip)), (3.0, (2.0, 5.0)));
+        clip.remove_span(2.0, 3.0);
+        assert_eq!((clip.length(), audio_offsets(&clip)), (2.0, (2.0, 4.0)));
+    }
+
+    /// Times of a clip's program changes and of its first automation lane's points
+    fn event_and_point_times(clip: &Clip) -> (Vec<f64>, Vec<f64>) {
+        let Clip::Midi {
+            midi_data: Some(store),
+            automation_lanes,
+            ..
+        } = clip
+        else {
+            return (Vec::new(), Vec::new());
+        };
+        let mut events: Vec<f64> = store
+            .get_events_in_range(0.0, f64::MAX)
+            .into_iter()
+            .filter(|event| matches!(event.message, MidiMessage::ProgramChange { .. }))
+            .map(|event| event.time)
+            .collect();
+        events.sort_by(f64::total_cmp);
+        let points = automation_lanes[0].points.iter().map(|p| p.time).collect();
+        (events, points)
+    }
+
+    #[test]
+    fn events_and_automation_follow_the_notes_when_time_changes() {
+        let mut clip = midi_clip(0.0, 4.0, &[0.5, 1.5, 3.0]);
+        let mut lane = AutomationLane::new(AutomationParameter::MidiCC {
+            cc_number: 74,
+            name: "Cutoff".to_string(),
+        });
+        for time in [0.5, 1.5, 3.0] {
+            lane.add_point(time, 64.0);
+        }
+        if let Clip::Midi {
+            midi_data: Some(store),
+            automation_lanes,
+            ..
+        } = &mut clip
+        {
+            // A long note running into the span
+            store.add_note(Note {
+                id: "long".to_string(),
+                channel: 0,
+                key: 64,
+                velocity: 100,
+                start_time: 0.8,
+                duration: 0.5,
+                start_tick: store.time_to_tick(0.8),
+                duration_ticks: store.time_to_tick(0.5),
+            });
+            for (index, time) in [1.5, 3.0].into_iter().enumerate() {
+                store.add_event(MidiEvent {
+                    id: format!("program_{}", index),
+                    time,
+                    tick: store.time_to_tick(time),
+                    message: MidiMessage::ProgramChange {
+                        channel: 0,
+                        program: 5,
+                    },
+                });
+            }
+            automation_lanes.push(lane);
+        }
+
+        clip.remove_span(1.0, 2.0);
+        assert_eq!(note_starts(&clip), vec![0.5, 0.8, 2.0]);
+        assert_eq!(event_and_point_times(&clip), (vec![2.0], vec![0.5, 2.0]));
+        if let Clip::Midi {
+            midi_data: Some(store),
+            ..
+        } = &clip
+        {
+            let long = store.get_note("long").unwrap();
+            assert!((long.duration - 0.2).abs() < 1e-9);
+            // Its NoteOff moved with it, so it doesn't play past the cut
+            let off = store
+                .get_events_in_range(0.0, f64::MAX)
+                .into_iter()
+                .find(|event| event.id == "long_off")
+                .unwrap();
+            assert!((off.time - 1.0).abs() < 1e-9);
+        }
+
+        clip.insert_gap(1.0, 1.0);
+        assert_eq!(note_starts(&clip), vec![0.5, 0.8, 3.0]);
+        assert_eq!(event_and_point_times(&clip), (vec![3.0], vec![0.5, 3.0]));
+    }
+
+    #[test]
+    fn removing_time_loads_clips_first() {
+        let path = std::env::temp_dir().join(format!("supersaw-{}-gap.mid", Uuid::new_v4()));
+        let Clip::Midi {
+            midi_data: Some(store),
+            ..
+        } = midi_clip(0.0, 4.0, &[0.5, 3.0])
+        else {
+            unreachable!()
+        };
+        store.save_to_file(&path, &MidiExportOptions::default()).unwrap();
+
+        let mut clip = Clip::empty_midi(0.0, 0.0, DEFAULT_PPQ);
+        if let Clip::Midi {
+            file_path,
+            midi_data,
+            loaded,
+            ..
+        } = &mut clip
+        {
+            *file_path = path.clone();
+            *midi_data = None;
+            *loaded = false;
+        }
+        let mut project = Project::new("Test".to_string());
+        project.tracks.push(midi_track(1, vec![clip]));
+        let removed = project.remove_time(1.0, 2.0);
+        fs::remove_file(&path).unwrap();
+
+        removed.unwrap();
+        let clip = &project.tracks[0].clips[0];
+        assert_eq!(note_starts(clip), vec![0.5, 2.0]);
+        assert_eq!(clip.length(), 2.25);
+    }
+
+    #[test]
+    fn exported_clips_include_their_cc_lanes() {
+        let clip = clip_with_cutoff_lane(0.0);
+        let store = clip.export_store(2).unwrap();
+        let values: Vec<u8> = control_changes(&store.get_events().cloned().collect::<Vec<_>>())
+            .into_iter()
+            .map(|(channel, controller, value)| {
+                assert_eq!((channel, controller), (2, 74));
+                value
+            })
+            .collect();
+        assert_eq!(values.iter().min(), Some(&0));
+        assert_eq!(values.iter().max(), Some(&100));
+
+        let cc_count = |options: MidiExportOptions| {
+            store.to_smf(&options).tracks[0]
+                .iter()
+                .filter(|event| {
+                    matches!(
+                        event.kind,
+                        midly::TrackEventKind::Midi {
+                            message: midly::MidiMessage::Controller { .. },
+                            ..
+                        }
+                    )
+                })
+                .count()
+        };
+        assert_eq!(cc_count(MidiExportOptions::default()), values.len());
+        assert_eq!(
+            cc_count(MidiExportOptions {
+                include_controllers: false,
+                ..MidiExportOptions::default()
+            }),
+            0
+        );
+        // The clip's own store doesn't gain the lane's events
+        if let Clip::Midi {
+            midi_data: Some(own),
+            ..
+        } = &clip
+        {
+            assert_eq!(own.get_events().count(), 0);
+        }
+    }
+
+    #[test]
+    fn notes_stop_at_the_end_of_a_shortened_clip() {
+        let track = midi_track(1, vec![midi_clip(2.0, 1.0, &[0.5, 0.9, 1.2])]);
+        let played = |start: f64, end: f64| -> Vec<(f64, bool)> {
+            let mut played: Vec<(f64, bool)> = track
+                .get_events_in_time_range(start, end)
+                .into_iter()
+                .map(|event| {
+                    let on = matches!(event.message, MidiMessage::NoteOn { .. });
+                    ((event.time * 1000.0).round() / 1000.0, on)
+                })
+                .collect();
+            played.sort_by(|a, b| a.0.total_cmp(&b.0));
+            played
+        };
+
+        // The note past the end never starts, and the one across it is cut off there
+        assert_eq!(
+            played(0.0, 10.0),
+            vec![(2.5, true), (2.75, false), (2.9, true), (3.0, false)]
+        );
+        // The cut is sent once, by the block that starts at the clip end
+        assert_eq!(played(2.0, 3.0).len(), 3);
+        assert_eq!(played(3.0, 4.0), vec![(3.0, false)]);
+    }
+
+    #[test]
+    fn processors_change_notes_on_their_way_out() {
+        let mut clip = midi_clip(0.0, 1.0, &[0.0]);
+        if let Clip::Midi { processors, .. } = &mut clip {
+            *processors = vec![
+                ClipProcessor::Transpose { semitones: 12 },
+                ClipProcessor::VelocityScale { factor: 0.5 },
+            ];
+        }
+        let track = midi_track(1, vec![clip]);
+
+        let played: Vec<MidiMessage> = track
+            .get_events_in_time_range(0.0, 1.0)
+            .into_iter()
+            .map(|event| event.message)
+            .collect();
+        assert!(played.contains(&MidiMessage::NoteOn {
+            channel: 0,
+            key: 72,
+            velocity: 50
+        }));
+        assert!(played
+            .iter()
+            .all(|message| !matches!(message, MidiMessage::NoteOff { key, .. } if *key != 72)));
+        // The stored note is untouched
+        assert_eq!(note_starts(&track.clips[0]), vec![0.0]);
+        if let Clip::Midi {
+            midi_data: Some(store),
+            ..
+        } = &track.clips[0]
+        {
+            assert_eq!(store.get_notes().next().unwrap().key, 60);
+        }
+    }
+
+    #[test]
+    fn scaled_velocities_stay_audible() {
+        let mut message = MidiMessage::NoteOn {
+            channel: 0,
+            key: 60,
+            velocity: 1,
+        };
+        ClipProcessor::VelocityScale { factor: 0.1 }.process(&mut message);
+        assert!(matches!(message, MidiMessage::NoteOn { velocity: 1, .. }));
+        ClipProcessor::VelocityScale { factor: 1000.0 }.process(&mut message);
+        assert!(matches!(message, MidiMessage::NoteOn { velocity: 127, .. }));
+    }
+
+    fn control_changes(events: &[MidiEvent]) -> Vec<(u8, u8, u8)> {
+        events
+            .iter()
+            .filter_map(|event| match event.message {
+                MidiMessage::ControlChange {
+                    channel,
+                    controller,
+                    value,
+                } => Some((channel, controller, value)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cc_lanes_play_on_the_track_channel() {
+        let track = midi_track(10, vec![clip_with_cutoff_lane(1.0)]);
+        let events = track.get_events_in_time_range(1.0, 1.025);
+        assert_eq!(
+            control_changes(&events),
+            vec![(9, 74, 0), (9, 74, 1), (9, 74, 2)]
+        );
+    }
+
+    #[test]
+    fn starting_inside_a_clip_sends_the_current_lane_value() {
+        let mut project = Project::new("Test".to_string());
+        project.tracks.push(midi_track(3, vec![clip_with_cutoff_lane(1.0)]));
+
+        let chased = project.cc_values_at(1.5);
+        assert_eq!(chased.len(), 1);
+        assert_eq!(chased[0].0, project.tracks[0].id);
+        assert_eq!(chased[0].1.time, 1.5);
+        assert_eq!(control_changes(&[chased[0].1.clone()]), vec![(2, 74, 50)]);
+
+        // At the clip start the lane sends its first value as part of playback
+        assert!(project.cc_values_at(1.0).is_empty());
+        // Outside the clip there is nothing to catch up on
+        assert!(project.cc_values_at(0.5).is_empty());
+        assert!(project.cc_values_at(3.5).is_empty());
+    }
+
+    #[test]
+    fn muted_clips_send_no_lane_values() {
+        let mut clip = clip_with_cutoff_lane(0.0);
+        clip.set_muted(true);
+        let track = midi_track(1, vec![clip]);
+        assert!(track.cc_values_at(0.5).is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+// src/core/render.rs
+use crate::core::{AudioBuffer, Clip, MidiMessage, Project, Synth, TrackType};
+use std::error::Error;
+use std::path::Path;
+
+/// Frames rendered between MIDI dispatches; events are quantized to block starts
+const RENDER_BLOCK_FRAMES: usize = 64;
+
+/// Exports are always stereo
+const RENDER_CHANNELS: usize = 2;
+
+pub const EXPORT_SAMPLE_RATES: [u32; 3] = [44_100, 48_000, 96_000];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSettings {
+    pub start_time: f64,
+    pub end_time: f64,
+    pub sample_rate: u32,
+}
+
+struct AudioSource {
+    start_time: f64,
+    length: f64,
+    start_offset: f64,
+    buffer: AudioBuffer,
+}
+
+/// Render the project offline into interleaved stereo samples, playing MIDI tracks
+/// through their built-in instruments and mixing in audio clips
+pub fn render_project(
+    project: &Project,
+    settings: &RenderSettings,
+) -> Result<Vec<f32>, Box<dyn Error>> {
+    if settings.end_time <= settings.start_time {
+        return Err("Render range is empty".into());
+    }
+
+    let any_soloed = project.any_track_soloed();
+    let sample_rate = settings.sample_rate as f64;
+    let total_frames = ((settings.end_time - settings.start_time) * sample_rate).ceil() as usize;
+
+    let mut audio_sources = Vec::new();
+    for track in project.tracks.iter().filter(|t| t.is_audible(any_soloed)) {
+        if !matches!(track.track_type, TrackType::Audio) {
+            continue;
+        }
+        for clip in track.clips.iter().filter(|c| !c.is_muted()) {
+            if let Clip::Audio {
+                start_time,
+                length,
+                file_path,
+                start_offset,
+                ..
+            } = clip
+            {
+                let buffer = AudioBuffer::from_wav(file_path).map_err(|e| {
+                    format!("Failed to read audio clip {}: {}", file_path.display(), e)
+                })?;
+                audio_sources.push(AudioSource {
+                    start_time: *start_time,
+                    length: *length,
+                    start_offset: *start_offset,
+                    buffer,
+                });
+            }
+        }
+    }
+
+    let mut synth = Synth::new(settings.sample_rate as f32);
+    let mut output = vec![0.0; total_frames * RENDER_CHANNELS];
+
+    for (block_index, block) in output
+        .chunks_mut(RENDER_BLOCK_FRAMES * RENDER_CHANNELS)
+        .enumerate()
+    {
+        let block_start =
+            settings.start_time + (block_index * RENDER_BLOCK_FRAMES) as f64 / sample_rate;
+        let block_end = block_start + (block.len() / RENDER_CHANNELS) as f64 / sample_rate;
+
+        let mut events = project.get_all_events_in_time_range(block_start, block_end);
+        // NoteOffs first, so a note ending where the next one on the same key starts
+        // doesn't cut off the new note
+        events.sort_by(|(_, a), (_, b)| {
+            a.time
+                .total_cmp(&b.time)
+                .then_with(|| is_note_on(&a.message).cmp(&is_note_on(&b.message)))
+        });
+
+        for (track_id, event) in events {
+            let Some(track) = project.tracks.iter().find(|t| t.id == track_id) else {
+                continue;
+            };
+            if !track.is_audible(any_soloed) {
+                continue;
+            }
+            if let Some(instrument) = track.instrument {
+                synth.handle_message(&track_id, instrument, &event.message);
+            }
+        }
+
+        synth.render(block, RENDER_CHANNELS);
+
+        for source in &audio_sources {
+            for (i, frame) in block.chunks_mut(RENDER_CHANNELS).enumerate() {
+                let time = block_start + i as f64 / sample_rate - source.start_time;
+                if time < 0.0 || time >= source.length {
+                    continue;
+                }
+                for (channel, sample) in frame.iter_mut().enumerate() {
+                    *sample += source
+                        .buffer
+                        .sample_at(time + source.start_offset, channel);
+                }
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Render the project and write it to a 16-bit stereo WAV file
+pub fn export_wav(
+    project: &Project,
+    settings: &RenderSettings,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let samples = render_project(project, settings)?;
+
+    let spec = hound::WavSpec {
+        channels: RENDER_CHANNELS as u16,
+        sample_rate: settings.sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for sample in samples {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+
+    Ok(())
+}
+
+fn is_note_on(message: &MidiMessage) -> bool {
+    matches!(message, MidiMessage::NoteOn { velocity, .. } if *velocity > 0)
+}
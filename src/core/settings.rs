@@ -0,0 +1,229 @@
+// src/core/settings.rs
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SETTINGS_FILE: &str = "settings.json";
+const APP_DIR: &str = "supersaw";
+/// Where settings lived before they moved to the config directory, relative to the working directory
+const LEGACY_SETTINGS_FILE: &str = "supersaw_settings.json";
+
+/// Pixels a drag has to travel before grid snapping kicks in
+pub const DEFAULT_SNAP_THRESHOLD: f32 = 10.0;
+
+/// Width of the piano roll's key column until the user drags it
+pub const DEFAULT_PIANO_ROLL_KEY_WIDTH: f32 = 80.0;
+
+/// What drawing a note does to notes of the same pitch it would overlap
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NoteOverlap {
+    /// Stack the new note on top of the existing ones
+    Allow,
+    /// Delete the overlapped notes
+    Replace,
+    /// End an earlier note where the new one starts, and the new one where a later one starts
+    Shorten,
+}
+
+impl NoteOverlap {
+    pub const ALL: [NoteOverlap; 3] = [
+        NoteOverlap::Allow,
+        NoteOverlap::Replace,
+        NoteOverlap::Shorten,
+    ];
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            NoteOverlap::Allow => "Allow overlaps",
+            NoteOverlap::Replace => "Replace overlapped notes",
+            NoteOverlap::Shorten => "Shorten to make room",
+        }
+    }
+}
+
+/// User preferences that outlive a single project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub snap_threshold: f32,
+    /// Scroll the timeline and piano roll to keep the playhead in view during playback
+    pub follow_playhead: bool,
+    /// Let MIDI clips be resized shorter than their last note, cutting those notes off
+    pub trim_clip_content: bool,
+    /// Move audio clip edges onto the nearest zero-crossing after grid snapping, to avoid clicks
+    pub snap_to_zero_crossing: bool,
+    /// How a drawn note treats same-pitch notes it lands on
+    pub note_overlap: NoteOverlap,
+    /// Width of the piano roll's key column, in pixels
+    pub piano_roll_key_width: f32,
+    /// Jump the playhead back to the start when playback stops, instead of stopping in place
+    pub return_to_start_on_stop: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            snap_threshold: DEFAULT_SNAP_THRESHOLD,
+            follow_playhead: true,
+            trim_clip_content: false,
+            snap_to_zero_crossing: true,
+            note_overlap: NoteOverlap::Shorten,
+            piano_roll_key_width: DEFAULT_PIANO_ROLL_KEY_WIDTH,
+            return_to_start_on_stop: false,
+        }
+    }
+}
+
+/// Per-user config directory for the platform: `%APPDATA%` on Windows,
+/// `~/Library/Application Support` on macOS and `$XDG_CONFIG_HOME` (or `~/.config`) elsewhere
+fn config_dir() -> Option<PathBuf> {
+    let non_empty = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+    if cfg!(target_os = "windows") {
+        non_empty("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        non_empty("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        non_empty("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".config")))
+    }
+}
+
+/// The app's own directory inside the config directory, holding settings and templates
+pub fn app_config_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(APP_DIR))
+}
+
+impl Settings {
+    /// Location of the settings file, falling back to the working directory when the
+    /// platform has no config directory
+    fn path() -> PathBuf {
+        match app_config_dir() {
+            Some(dir) => dir.join(SETTINGS_FILE),
+            None => PathBuf::from(LEGACY_SETTINGS_FILE),
+        }
+    }
+
+    /// Load settings from disk, falling back to defaults if the file is missing or invalid.
+    /// Settings saved by older versions next to the executable are picked up until the
+    /// next save writes them to the config directory.
+    pub fn load() -> Self {
+        let path = Self::path();
+        if path.exists() {
+            Self::load_from(&path)
+        } else {
+            Self::load_from(Path::new(LEGACY_SETTINGS_FILE))
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        self.save_to(&Self::path())
+    }
+
+    fn load_from(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+
+        match fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+        {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("Failed to load settings, using defaults: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+        }
+        fs::write(path, json).map_err(|e| format!("Failed to write settings file: {}", e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Settings file inside a fresh directory under the system temp dir
+    fn temp_settings_path() -> PathBuf {
+        env::temp_dir()
+            .join(format!("supersaw-settings-{}", uuid::Uuid::new_v4()))
+            .join(SETTINGS_FILE)
+    }
+
+    #[test]
+    fn saved_settings_load_back() {
+        let path = temp_settings_path();
+        let settings = Settings {
+            snap_threshold: 4.0,
+            follow_playhead: false,
+            note_overlap: NoteOverlap::Replace,
+            piano_roll_key_width: 120.0,
+            return_to_start_on_stop: true,
+            ..Settings::default()
+        };
+
+        settings.save_to(&path).unwrap();
+        let loaded = Settings::load_from(&path);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(loaded.snap_threshold, 4.0);
+        assert!(!loaded.follow_playhead);
+        assert_eq!(loaded.note_overlap, NoteOverlap::Replace);
+        assert_eq!(loaded.piano_roll_key_width, 120.0);
+        assert!(loaded.return_to_start_on_stop);
+    }
+
+    #[test]
+    fn missing_file_gives_defaults() {
+        let loaded = Settings::load_from(&temp_settings_path());
+        let defaults = Settings::default();
+        assert_eq!(loaded.snap_threshold, defaults.snap_threshold);
+        assert_eq!(loaded.note_overlap, defaults.note_overlap);
+        assert_eq!(loaded.follow_playhead, defaults.follow_playhead);
+    }
+
+    #[test]
+    fn invalid_file_gives_defaults() {
+        let path = temp_settings_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "not json").unwrap();
+        let loaded = Settings::load_from(&path);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(loaded.snap_threshold, DEFAULT_SNAP_THRESHOLD);
+    }
+
+    #[test]
+    fn fields_missing_from_older_files_use_defaults() {
+        let path = temp_settings_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, r#"{ "snap_threshold": 6.0 }"#).unwrap();
+        let loaded = Settings::load_from(&path);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(loaded.snap_threshold, 6.0);
+        assert_eq!(loaded.piano_roll_key_width, DEFAULT_PIANO_ROLL_KEY_WIDTH);
+        assert_eq!(loaded.note_overlap, NoteOverlap::Shorten);
+    }
+
+    #[test]
+    fn settings_live_in_an_app_directory() {
+        let path = Settings::path();
+        assert_eq!(path.file_name().unwrap(), SETTINGS_FILE);
+        if config_dir().is_some() {
+            assert_eq!(path.parent().unwrap().file_name().unwrap(), APP_DIR);
+        }
+    }
+}
@@ -1,4 +1,14 @@
-use crate::core::{EditorView, Project, SnapMode, StatusManager};
+use crate::core::{EditorView, Project, Settings, SnapMode, StatusManager};
+use std::collections::HashMap;
+
+/// A change of transport state, for whatever plays the project to react to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransportEvent {
+    Started,
+    Paused,
+    /// Stopped and returned to the start
+    Stopped,
+}
 
 #[derive(Clone, Debug)]
 pub struct DawState {
@@ -7,20 +17,31 @@ pub struct DawState {
     pub metronome: bool,
     pub playing: bool,
     pub recording: bool,
+    /// Play with the metronome only: the playhead runs but tracks stay silent and nothing records
+    pub rehearsing: bool,
+    /// Metronome setting from before rehearsing turned it on, put back when rehearsing ends
+    pub metronome_before_rehearsal: Option<bool>,
     pub current_time: f64,
     pub loop_enabled: bool,
     pub loop_start: f64,
     pub loop_end: f64,
 
     pub last_update: Option<std::time::Instant>,
+    /// Transport changes since playback last looked, drained by it every frame
+    pub transport_events: Vec<TransportEvent>,
     pub selected_track: Option<String>,
     pub selected_clip: Option<String>,
     pub current_view: EditorView,
+    /// Last piano roll view of each clip, keyed by clip id, restored when it reopens
+    pub piano_roll_views: HashMap<String, EditorView>,
     pub status: StatusManager,
+    pub settings: Settings,
     // pub plugin_manager: PluginManager,
     
     // Shared UI state
     pub track_scroll_y: f32,
+    /// Clip the timeline should scroll into view on its next frame
+    pub reveal_clip: Option<String>,
 }
 
 impl DawState {
@@ -31,16 +52,37 @@ impl DawState {
             metronome: false,
             playing: false,
             recording: false,
+            rehearsing: false,
+            metronome_before_rehearsal: None,
             current_time: 0.0,
             last_update: None,
+            transport_events: Vec::new(),
             selected_track: None,
             selected_clip: None,
             loop_enabled: false,
             loop_start: 3.0,
             loop_end: 4.0,
             current_view: EditorView::default(),
+            piano_roll_views: HashMap::new(),
             status: StatusManager::new(),
+            settings: Settings::load(),
             track_scroll_y: 0.0,
+            reveal_clip: None,
+        }
+    }
+
+    /// Rehearse along to the click, turning the metronome on until rehearsing stops
+    pub fn start_rehearsing(&mut self) {
+        self.rehearsing = true;
+        self.recording = false;
+        self.metronome_before_rehearsal.get_or_insert(self.metronome);
+        self.metronome = true;
+    }
+
+    pub fn stop_rehearsing(&mut self) {
+        self.rehearsing = false;
+        if let Some(metronome) = self.metronome_before_rehearsal.take() {
+            self.metronome = metronome;
         }
     }
 
@@ -1,7 +1,12 @@
 // src/core/status.rs
+use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone)]
+/// Most messages kept in the status history
+const HISTORY_LENGTH: usize = 200;
+
+/// Ordered by severity, so `>=` can be used to filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum MessageType {
     Info,
     Success,
@@ -9,48 +14,81 @@ pub enum MessageType {
     Error,
 }
 
+impl MessageType {
+    /// How long messages of this type stay up, `None` meaning until dismissed
+    pub fn default_duration(&self) -> Option<Duration> {
+        match self {
+            MessageType::Info | MessageType::Success => Some(Duration::from_secs(3)),
+            MessageType::Warning => Some(Duration::from_secs(8)),
+            MessageType::Error => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct StatusMessage {
     pub text: String,
     pub message_type: MessageType,
     pub created_at: Instant,
-    pub duration: Duration,
+    /// `None` keeps the message up until it is dismissed
+    pub duration: Option<Duration>,
 }
 
 impl StatusMessage {
     pub fn new(text: impl Into<String>, message_type: MessageType) -> Self {
         Self {
             text: text.into(),
-            message_type,
             created_at: Instant::now(),
-            duration: Duration::from_secs(3), // Default 3 second display
+            duration: message_type.default_duration(),
+            message_type,
         }
     }
 
     pub fn with_duration(mut self, duration: Duration) -> Self {
-        self.duration = duration;
+        self.duration = Some(duration);
         self
     }
 
     pub fn is_expired(&self) -> bool {
-        self.created_at.elapsed() >= self.duration
+        self.duration
+            .is_some_and(|duration| self.created_at.elapsed() >= duration)
+    }
+
+    /// Time left before the message expires, `None` if it waits to be dismissed
+    pub fn remaining(&self) -> Option<Duration> {
+        self.duration
+            .map(|duration| duration.saturating_sub(self.created_at.elapsed()))
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct StatusManager {
     current_message: Option<StatusMessage>,
+    /// Every message shown, oldest first, so ones that flashed by can be reviewed
+    history: VecDeque<StatusMessage>,
 }
 
 impl StatusManager {
     pub fn new() -> Self {
         Self {
             current_message: None,
+            history: VecDeque::new(),
         }
     }
 
     pub fn set_message(&mut self, message: StatusMessage) {
-        self.current_message = Some(message);
+        self.history.push_back(message.clone());
+        if self.history.len() > HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        // A lesser message doesn't push an undismissed error off the bar; it
+        // still lands in the history
+        let keep_current = self.current_message.as_ref().is_some_and(|current| {
+            current.duration.is_none() && current.message_type > message.message_type
+        });
+        if !keep_current {
+            self.current_message = Some(message);
+        }
     }
 
     pub fn info<S: Into<String>>(&mut self, text: S) {
@@ -69,6 +107,7 @@ impl StatusManager {
         self.set_message(StatusMessage::new(text, MessageType::Error));
     }
 
+    /// Remove the current message, e.g. from its ✕ button
     pub fn clear(&mut self) {
         self.current_message = None;
     }
@@ -84,4 +123,13 @@ impl StatusManager {
     pub fn get_message(&self) -> Option<&StatusMessage> {
         self.current_message.as_ref()
     }
+
+    /// Past messages, oldest first
+    pub fn history(&self) -> impl DoubleEndedIterator<Item = &StatusMessage> + ExactSizeIterator {
+        self.history.iter()
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
 }
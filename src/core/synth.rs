@@ -0,0 +1,331 @@
+// src/core/synth.rs
+use crate::core::MidiMessage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
+/// Shortest attack/release in seconds, just long enough to avoid clicks
+const MIN_ENVELOPE_TIME: f32 = 0.005;
+
+/// Output gain applied to the voice mix so a few stacked notes don't clip
+const MASTER_GAIN: f32 = 0.2;
+
+/// Voices sounding at once before the oldest ones get stolen
+pub const MAX_VOICES: usize = 32;
+
+/// MIDI controller that sets the lowpass cutoff (Brightness)
+pub const CUTOFF_CC: u8 = 74;
+
+/// MIDI controller that releases every note on a track
+pub const ALL_NOTES_OFF_CC: u8 = 123;
+
+/// Resonance of the lowpass filter (1 / Q)
+const FILTER_DAMPING: f32 = 1.0;
+
+/// Instruments that ship with Supersaw and are played by the internal synth
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuiltinInstrument {
+    Sine,
+    Saw,
+}
+
+impl BuiltinInstrument {
+    pub const ALL: [BuiltinInstrument; 2] = [BuiltinInstrument::Sine, BuiltinInstrument::Saw];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuiltinInstrument::Sine => "Sine Synth",
+            BuiltinInstrument::Saw => "Saw Synth",
+        }
+    }
+
+    pub fn envelope(&self) -> Envelope {
+        match self {
+            BuiltinInstrument::Sine => Envelope {
+                attack: 0.01,
+                decay: 0.1,
+                sustain: 0.8,
+                release: 0.2,
+            },
+            BuiltinInstrument::Saw => Envelope {
+                attack: 0.005,
+                decay: 0.3,
+                sustain: 0.5,
+                release: 0.15,
+            },
+        }
+    }
+
+    /// Cutoff as a CC value, used until the track sends CC74
+    pub fn default_cutoff(&self) -> u8 {
+        match self {
+            BuiltinInstrument::Sine => 127,
+            BuiltinInstrument::Saw => 90,
+        }
+    }
+
+    fn oscillator(&self, phase: f32) -> f32 {
+        match self {
+            BuiltinInstrument::Sine => (phase * TAU).sin(),
+            BuiltinInstrument::Saw => 2.0 * phase - 1.0,
+        }
+    }
+}
+
+/// ADSR envelope; times are in seconds, sustain is a level from 0.0 to 1.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Envelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+pub fn midi_key_to_frequency(key: u8) -> f32 {
+    440.0 * 2f32.powf((key as f32 - 69.0) / 12.0)
+}
+
+/// Map a CC value onto an exponential cutoff range of roughly 40 Hz to 20 kHz
+pub fn cc_to_cutoff_hz(value: u8) -> f32 {
+    40.0 * 2f32.powf(value.min(127) as f32 / 127.0 * 9.0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    Done,
+}
+
+/// Two-pole state variable lowpass
+#[derive(Default)]
+struct Lowpass {
+    a1: f32,
+    a2: f32,
+    a3: f32,
+    ic1eq: f32,
+    ic2eq: f32,
+}
+
+impl Lowpass {
+    fn set_cutoff(&mut self, cutoff_hz: f32, sample_rate: f32) {
+        let cutoff_hz = cutoff_hz.min(sample_rate * 0.45);
+        let g = (PI * cutoff_hz / sample_rate).tan();
+        self.a1 = 1.0 / (1.0 + g * (g + FILTER_DAMPING));
+        self.a2 = g * self.a1;
+        self.a3 = g * self.a2;
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let v3 = input - self.ic2eq;
+        let v1 = self.a1 * self.ic1eq + self.a2 * v3;
+        let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3;
+        self.ic1eq = 2.0 * v1 - self.ic1eq;
+        self.ic2eq = 2.0 * v2 - self.ic2eq;
+        v2
+    }
+}
+
+struct Voice {
+    /// Track the note came from, so NoteOffs and CCs only reach that track's voices
+    source: String,
+    key: u8,
+    instrument: BuiltinInstrument,
+    envelope: Envelope,
+    stage: Stage,
+    phase: f32,
+    phase_step: f32,
+    amplitude: f32,
+    level: f32,
+    release_step: f32,
+    filter: Lowpass,
+}
+
+impl Voice {
+    fn release(&mut self, sample_rate: f32) {
+        if matches!(self.stage, Stage::Release | Stage::Done) {
+            return;
+        }
+        self.stage = Stage::Release;
+        self.release_step =
+            self.level / (self.envelope.release.max(MIN_ENVELOPE_TIME) * sample_rate);
+    }
+
+    fn advance_envelope(&mut self, sample_rate: f32) {
+        let env = self.envelope;
+        match self.stage {
+            Stage::Attack => {
+                self.level += 1.0 / (env.attack.max(MIN_ENVELOPE_TIME) * sample_rate);
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                self.level -=
+                    (1.0 - env.sustain) / (env.decay.max(MIN_ENVELOPE_TIME) * sample_rate);
+                if self.level <= env.sustain {
+                    self.level = env.sustain;
+                    self.stage = Stage::Sustain;
+                }
+            }
+            Stage::Sustain => {}
+            Stage::Release => {
+                self.level -= self.release_step;
+                if self.level <= 0.0 {
+                    self.level = 0.0;
+                    self.stage = Stage::Done;
+                }
+            }
+            Stage::Done => {}
+        }
+    }
+
+    fn next_sample(&mut self, sample_rate: f32) -> f32 {
+        self.advance_envelope(sample_rate);
+
+        let raw = self.instrument.oscillator(self.phase);
+        self.phase = (self.phase + self.phase_step).fract();
+        self.filter.process(raw) * self.level * self.amplitude
+    }
+}
+
+/// A small polyphonic subtractive synth (oscillator, ADSR, lowpass) rendering the
+/// built-in instruments
+pub struct Synth {
+    sample_rate: f32,
+    voices: Vec<Voice>,
+    /// Last CC74 value received per source track
+    cutoffs: HashMap<String, u8>,
+}
+
+impl Synth {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            voices: Vec::new(),
+            cutoffs: HashMap::new(),
+        }
+    }
+
+    pub fn active_voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    pub fn note_on(&mut self, source: &str, instrument: BuiltinInstrument, key: u8, velocity: u8) {
+        if velocity == 0 {
+            self.note_off(source, key);
+            return;
+        }
+
+        if self.voices.len() >= MAX_VOICES {
+            self.steal_voice();
+        }
+
+        let cutoff = self
+            .cutoffs
+            .get(source)
+            .copied()
+            .unwrap_or_else(|| instrument.default_cutoff());
+        let mut filter = Lowpass::default();
+        filter.set_cutoff(cc_to_cutoff_hz(cutoff), self.sample_rate);
+
+        self.voices.push(Voice {
+            source: source.to_string(),
+            key,
+            instrument,
+            envelope: instrument.envelope(),
+            stage: Stage::Attack,
+            phase: 0.0,
+            phase_step: midi_key_to_frequency(key) / self.sample_rate,
+            amplitude: velocity as f32 / 127.0,
+            level: 0.0,
+            release_step: 0.0,
+            filter,
+        });
+    }
+
+    pub fn note_off(&mut self, source: &str, key: u8) {
+        let sample_rate = self.sample_rate;
+        for voice in self
+            .voices
+            .iter_mut()
+            .filter(|v| v.key == key && v.source == source)
+        {
+            voice.release(sample_rate);
+        }
+    }
+
+    /// Release every sounding voice, e.g. when playback stops
+    pub fn all_notes_off(&mut self) {
+        let sample_rate = self.sample_rate;
+        for voice in &mut self.voices {
+            voice.release(sample_rate);
+        }
+    }
+
+    /// Set the lowpass cutoff (as a CC value) for a source's current and future notes
+    pub fn set_cutoff(&mut self, source: &str, value: u8) {
+        self.cutoffs.insert(source.to_string(), value);
+        let cutoff_hz = cc_to_cutoff_hz(value);
+        for voice in self.voices.iter_mut().filter(|v| v.source == source) {
+            voice.filter.set_cutoff(cutoff_hz, self.sample_rate);
+        }
+    }
+
+    /// Play a MIDI message from `source` on the given instrument
+    pub fn handle_message(
+        &mut self,
+        source: &str,
+        instrument: BuiltinInstrument,
+        message: &MidiMessage,
+    ) {
+        match message {
+            MidiMessage::NoteOn { key, velocity, .. } => {
+                self.note_on(source, instrument, *key, *velocity)
+            }
+            MidiMessage::NoteOff { key, .. } => self.note_off(source, *key),
+            MidiMessage::ControlChange {
+                controller, value, ..
+            } => match *controller {
+                CUTOFF_CC => self.set_cutoff(source, *value),
+                ALL_NOTES_OFF_CC => {
+                    let sample_rate = self.sample_rate;
+                    for voice in self.voices.iter_mut().filter(|v| v.source == source) {
+                        voice.release(sample_rate);
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// Drop the oldest voice, preferring ones that are already releasing
+    fn steal_voice(&mut self) {
+        let index = self
+            .voices
+            .iter()
+            .position(|v| v.stage == Stage::Release)
+            .unwrap_or(0);
+        self.voices.remove(index);
+    }
+
+    /// Mix all voices into an interleaved buffer, overwriting its contents
+    pub fn render(&mut self, buffer: &mut [f32], channels: usize) {
+        let sample_rate = self.sample_rate;
+
+        for frame in buffer.chunks_mut(channels.max(1)) {
+            let sample: f32 = self
+                .voices
+                .iter_mut()
+                .map(|voice| voice.next_sample(sample_rate))
+                .sum();
+            frame.fill(sample * MASTER_GAIN);
+        }
+
+        self.voices.retain(|voice| voice.stage != Stage::Done);
+    }
+}
@@ -18,13 +18,53 @@ pub fn hex_to_color32(hex: &str) -> Option<egui::Color32> {
     Some(egui::Color32::from_rgb(r, g, b))
 }
 
+/// Held during a drag to place things off the grid, like Shift
+pub const SNAP_BYPASS_KEY: egui::Key = egui::Key::Q;
+
+/// Whether the user is holding Shift or the snap bypass key, so drags ignore the grid
+/// without changing the snap setting
+pub fn snap_bypassed(ctx: &egui::Context) -> bool {
+    let typing = ctx.wants_keyboard_input();
+    ctx.input(|i| i.modifiers.shift || (!typing && i.key_down(SNAP_BYPASS_KEY)))
+}
+
+/// Which grid line a time snaps to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SnapBias {
+    /// Closest grid line (used for note insertion)
+    #[default]
+    Nearest,
+    /// Grid line at or before the time (used for clip starts and left resize edges)
+    Floor,
+    /// Grid line at or after the time (used for right resize edges)
+    Ceil,
+}
+
 impl TimeUtils {
-    pub fn snap_time(time: f64, bpm: f64, snap_mode: SnapMode) -> f64 {
-        let division = snap_mode.get_division(bpm);
-        if division == 0.0 {
+    pub fn snap_time(time: f64, bpm: f64, time_sig: (u8, u8), snap_mode: SnapMode) -> f64 {
+        Self::snap_time_with_bias(time, bpm, time_sig, snap_mode, SnapBias::Nearest)
+    }
+
+    pub fn snap_time_with_bias(
+        time: f64,
+        bpm: f64,
+        time_sig: (u8, u8),
+        snap_mode: SnapMode,
+        bias: SnapBias,
+    ) -> f64 {
+        let Some(division) = snap_mode.step(bpm, time_sig) else {
             return time;
-        }
-        (time / division).round() * division
+        };
+
+        // Tolerance keeps times that sit on a grid line (give or take float error) in place
+        const EPSILON: f64 = 1e-9;
+        let divisions = time / division;
+        let snapped = match bias {
+            SnapBias::Nearest => divisions.round(),
+            SnapBias::Floor => (divisions + EPSILON).floor(),
+            SnapBias::Ceil => (divisions - EPSILON).ceil(),
+        };
+        snapped * division
     }
 
     pub fn beats_to_seconds(beats: f64, bpm: f64) -> f64 {
@@ -34,6 +74,79 @@ impl TimeUtils {
     pub fn seconds_to_beats(seconds: f64, bpm: f64) -> f64 {
         seconds * bpm / 60.0
     }
+
+    /// Duration of one beat in seconds. The BPM counts quarter notes, so the
+    /// beat length follows the time signature's denominator (e.g. 6/8 beats are eighths).
+    pub fn seconds_per_beat(bpm: f64, time_sig: (u8, u8)) -> f64 {
+        let denominator = time_sig.1.max(1) as f64;
+        (60.0 / bpm) * (4.0 / denominator)
+    }
+
+    /// Duration of one bar in seconds
+    pub fn seconds_per_bar(bpm: f64, time_sig: (u8, u8)) -> f64 {
+        Self::seconds_per_beat(bpm, time_sig) * time_sig.0.max(1) as f64
+    }
+
+    /// Convert a time in seconds to a 1-based bar:beat:tick position.
+    /// Negative times are clamped to the start of the first bar.
+    pub fn time_to_bars_beats(time: f64, bpm: f64, time_sig: (u8, u8)) -> BarBeatTick {
+        let beat_duration = Self::seconds_per_beat(bpm, time_sig);
+        let beats_per_bar = time_sig.0.max(1) as u64;
+
+        // Round to whole ticks first so values like 1.9999999 land on the next beat
+        let total_ticks =
+            (time.max(0.0) / beat_duration * BarBeatTick::TICKS_PER_BEAT as f64).round() as u64;
+        let total_beats = total_ticks / BarBeatTick::TICKS_PER_BEAT as u64;
+
+        BarBeatTick {
+            bar: (total_beats / beats_per_bar) as u32 + 1,
+            beat: (total_beats % beats_per_bar) as u32 + 1,
+            tick: (total_ticks % BarBeatTick::TICKS_PER_BEAT as u64) as u32,
+        }
+    }
+
+    /// Convert a 1-based bar:beat:tick position back to a time in seconds
+    pub fn bars_beats_to_time(position: BarBeatTick, bpm: f64, time_sig: (u8, u8)) -> f64 {
+        let beat_duration = Self::seconds_per_beat(bpm, time_sig);
+        let beats_per_bar = time_sig.0.max(1) as f64;
+
+        let beats = position.bar.saturating_sub(1) as f64 * beats_per_bar
+            + position.beat.saturating_sub(1) as f64
+            + position.tick as f64 / BarBeatTick::TICKS_PER_BEAT as f64;
+
+        beats * beat_duration
+    }
+
+    /// Beats starting in `[start, end)`, each paired with whether it is the first beat of a
+    /// bar. Used to sound the metronome.
+    pub fn beats_in_range(start: f64, end: f64, bpm: f64, time_sig: (u8, u8)) -> Vec<(f64, bool)> {
+        let beat_duration = Self::seconds_per_beat(bpm, time_sig);
+        let beats_per_bar = time_sig.0.max(1) as u64;
+
+        let first = (start.max(0.0) / beat_duration).ceil() as u64;
+        (first..)
+            .map(|beat| (beat as f64 * beat_duration, beat % beats_per_bar == 0))
+            .take_while(|(time, _)| *time < end)
+            .collect()
+    }
+}
+
+/// A musical position, as shown on the transport and rulers (bars and beats are 1-based)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarBeatTick {
+    pub bar: u32,
+    pub beat: u32,
+    pub tick: u32,
+}
+
+impl BarBeatTick {
+    pub const TICKS_PER_BEAT: u32 = 480;
+}
+
+impl std::fmt::Display for BarBeatTick {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{:03}", self.bar, self.beat, self.tick)
+    }
 }
 
 /// Handles smooth snapping with accumulator to prevent jumpiness
@@ -50,6 +163,11 @@ impl SnapHandler {
         }
     }
     
+    /// Change how far (in pixels) a drag must travel before snapping applies
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
     /// Reset the accumulator (call on drag start)
     pub fn reset(&mut self) {
         self.accumulator = 0.0;
@@ -76,15 +194,101 @@ impl SnapHandler {
         initial_time: f64,
         delta_time: f64,
         bpm: f64,
+        time_sig: (u8, u8),
+        snap_mode: SnapMode,
+        snap_enabled: bool,
+    ) -> f64 {
+        self.snap_time(initial_time + delta_time, bpm, time_sig, snap_mode, snap_enabled)
+    }
+
+    /// Snap an already computed time once the accumulated drag passes the threshold
+    pub fn snap_time(
+        &self,
+        time: f64,
+        bpm: f64,
+        time_sig: (u8, u8),
+        snap_mode: SnapMode,
+        snap_enabled: bool,
+    ) -> f64 {
+        self.snap_time_with_bias(time, bpm, time_sig, snap_mode, snap_enabled, SnapBias::Nearest)
+    }
+
+    /// Snap the start edge of a resize to the grid line at or before the time
+    pub fn snap_start(
+        &self,
+        time: f64,
+        bpm: f64,
+        time_sig: (u8, u8),
+        snap_mode: SnapMode,
+        snap_enabled: bool,
+    ) -> f64 {
+        self.snap_time_with_bias(time, bpm, time_sig, snap_mode, snap_enabled, SnapBias::Floor)
+    }
+
+    /// Snap the end edge of a resize to the grid line at or after the time, so the
+    /// edge never jumps back past where it was dragged
+    pub fn snap_end(
+        &self,
+        time: f64,
+        bpm: f64,
+        time_sig: (u8, u8),
+        snap_mode: SnapMode,
+        snap_enabled: bool,
+    ) -> f64 {
+        self.snap_time_with_bias(time, bpm, time_sig, snap_mode, snap_enabled, SnapBias::Ceil)
+    }
+
+    /// New start for a drag on the left edge of something ending at `end`: snapped down
+    /// onto the grid, and never closer than `min_length` to the end
+    #[allow(clippy::too_many_arguments)]
+    pub fn resize_start(
+        &self,
+        proposed_start: f64,
+        end: f64,
+        min_length: f64,
+        bpm: f64,
+        time_sig: (u8, u8),
+        snap_mode: SnapMode,
+        snap_enabled: bool,
+    ) -> f64 {
+        let latest = (end - min_length).max(0.0);
+        let start = proposed_start.clamp(0.0, latest);
+        self.snap_start(start, bpm, time_sig, snap_mode, snap_enabled)
+            .min(latest)
+    }
+
+    /// New end for a drag on the right edge of something starting at `start`: snapped up
+    /// onto the grid, and never closer than `min_length` to the start
+    #[allow(clippy::too_many_arguments)]
+    pub fn resize_end(
+        &self,
+        start: f64,
+        proposed_end: f64,
+        min_length: f64,
+        bpm: f64,
+        time_sig: (u8, u8),
+        snap_mode: SnapMode,
+        snap_enabled: bool,
+    ) -> f64 {
+        let earliest = start + min_length;
+        let end = proposed_end.max(earliest);
+        self.snap_end(end, bpm, time_sig, snap_mode, snap_enabled)
+            .max(earliest)
+    }
+
+    fn snap_time_with_bias(
+        &self,
+        time: f64,
+        bpm: f64,
+        time_sig: (u8, u8),
         snap_mode: SnapMode,
         snap_enabled: bool,
+        bias: SnapBias,
     ) -> f64 {
-        let proposed_time = initial_time + delta_time;
-        
         if snap_enabled && self.should_snap() {
-            TimeUtils::snap_time(proposed_time, bpm, snap_mode)
+            TimeUtils::snap_time_with_bias(time, bpm, time_sig, snap_mode, bias)
         } else {
-            proposed_time
+            time
         }
     }
 }
@@ -211,4 +415,267 @@ impl ViewportPosition {
     pub fn get_scroll_offset(&self) -> f32 {
         self.scroll_offset
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BPM: f64 = 120.0;
+    const FOUR_FOUR: (u8, u8) = (4, 4);
+
+    fn position(bar: u32, beat: u32, tick: u32) -> BarBeatTick {
+        BarBeatTick { bar, beat, tick }
+    }
+
+    #[test]
+    fn beats_in_range_marks_the_downbeats() {
+        // 3/4 at 120 BPM: half a second per beat, 1.5s per bar
+        let beats = TimeUtils::beats_in_range(1.0, 3.1, BPM, (3, 4));
+        assert_eq!(
+            beats,
+            vec![(1.0, false), (1.5, true), (2.0, false), (2.5, false), (3.0, true)]
+        );
+        // The end is exclusive, so consecutive ranges never click twice
+        assert_eq!(TimeUtils::beats_in_range(0.6, 1.0, BPM, (3, 4)), vec![]);
+        assert_eq!(TimeUtils::beats_in_range(0.0, 0.1, BPM, FOUR_FOUR), vec![(0.0, true)]);
+    }
+
+    #[test]
+    fn zero_and_negative_times_are_the_first_beat() {
+        assert_eq!(TimeUtils::time_to_bars_beats(0.0, BPM, (4, 4)), position(1, 1, 0));
+        assert_eq!(TimeUtils::time_to_bars_beats(-3.0, BPM, (4, 4)), position(1, 1, 0));
+    }
+
+    #[test]
+    fn bar_starts_land_on_beat_one() {
+        // 4/4 at 120 BPM: two seconds per bar
+        assert_eq!(TimeUtils::time_to_bars_beats(2.0, BPM, (4, 4)), position(2, 1, 0));
+        assert_eq!(TimeUtils::time_to_bars_beats(8.0, BPM, (4, 4)), position(5, 1, 0));
+        // Float error just below a bar line still rounds onto it
+        assert_eq!(
+            TimeUtils::time_to_bars_beats(2.0 - 1e-12, BPM, (4, 4)),
+            position(2, 1, 0)
+        );
+    }
+
+    #[test]
+    fn eighth_note_meters_count_eighths() {
+        // 6/8 at 120 BPM: an eighth is 0.25s, a bar is 1.5s
+        assert_eq!(TimeUtils::seconds_per_beat(BPM, (6, 8)), 0.25);
+        assert_eq!(TimeUtils::seconds_per_bar(BPM, (6, 8)), 1.5);
+        assert_eq!(TimeUtils::time_to_bars_beats(1.5, BPM, (6, 8)), position(2, 1, 0));
+        assert_eq!(TimeUtils::time_to_bars_beats(1.25, BPM, (6, 8)), position(1, 6, 0));
+    }
+
+    #[test]
+    fn positions_round_trip_through_time() {
+        for time_sig in [(4, 4), (3, 4), (6, 8), (7, 8)] {
+            for bar in 1..=4 {
+                for beat in 1..=time_sig.0 as u32 {
+                    for tick in [0, 1, 240, 479] {
+                        let original = position(bar, beat, tick);
+                        let time = TimeUtils::bars_beats_to_time(original, BPM, time_sig);
+                        assert_eq!(
+                            TimeUtils::time_to_bars_beats(time, BPM, time_sig),
+                            original,
+                            "{} in {:?}",
+                            original,
+                            time_sig
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn times_round_trip_through_positions() {
+        for time_sig in [(4, 4), (6, 8)] {
+            for step in 0..100 {
+                let time = step as f64 * 0.125;
+                let position = TimeUtils::time_to_bars_beats(time, BPM, time_sig);
+                let back = TimeUtils::bars_beats_to_time(position, BPM, time_sig);
+                assert!((back - time).abs() < 1e-9, "{} in {:?}", time, time_sig);
+            }
+        }
+    }
+
+    // Beats at 120 BPM are half a second apart
+    const GRID: f64 = 0.5;
+
+    fn snap(time: f64, bias: SnapBias) -> f64 {
+        TimeUtils::snap_time_with_bias(time, BPM, FOUR_FOUR, SnapMode::Beat, bias)
+    }
+
+    #[test]
+    fn bar_and_beat_snap_follow_the_time_signature() {
+        // 6/8 at 120 BPM: eighth-note beats of a quarter second, bars of six of them
+        let six_eight = (6, 8);
+        assert_eq!(SnapMode::Beat.get_division(BPM, six_eight), 0.25);
+        assert_eq!(SnapMode::Bar.get_division(BPM, six_eight), 1.5);
+        assert_eq!(TimeUtils::snap_time(1.0, BPM, six_eight, SnapMode::Bar), 1.5);
+        // Note values don't change with the meter
+        assert_eq!(SnapMode::Halfbeat.get_division(BPM, six_eight), 0.25);
+    }
+
+    #[test]
+    fn snapping_off_leaves_times_alone() {
+        assert_eq!(SnapMode::None.step(BPM, FOUR_FOUR), None);
+        for bias in [SnapBias::Nearest, SnapBias::Floor, SnapBias::Ceil] {
+            let time = TimeUtils::snap_time_with_bias(0.3, BPM, FOUR_FOUR, SnapMode::None, bias);
+            assert_eq!(time, 0.3, "{:?}", bias);
+        }
+    }
+
+    #[test]
+    fn times_on_a_grid_line_stay_put_for_every_bias() {
+        for bias in [SnapBias::Nearest, SnapBias::Floor, SnapBias::Ceil] {
+            assert_eq!(snap(GRID, bias), GRID, "{:?}", bias);
+            // Float error either side of the line is still on it
+            assert_eq!(snap(GRID + 1e-12, bias), GRID, "{:?}", bias);
+            assert_eq!(snap(GRID - 1e-12, bias), GRID, "{:?}", bias);
+        }
+    }
+
+    #[test]
+    fn times_just_off_a_grid_line_follow_the_bias() {
+        let after = GRID + 1e-6;
+        assert_eq!(snap(after, SnapBias::Nearest), GRID);
+        assert_eq!(snap(after, SnapBias::Floor), GRID);
+        assert_eq!(snap(after, SnapBias::Ceil), 2.0 * GRID);
+
+        let before = GRID - 1e-6;
+        assert_eq!(snap(before, SnapBias::Nearest), GRID);
+        assert_eq!(snap(before, SnapBias::Floor), 0.0);
+        assert_eq!(snap(before, SnapBias::Ceil), GRID);
+    }
+
+    #[test]
+    fn nearest_rounds_halfway_up() {
+        assert_eq!(snap(0.74, SnapBias::Nearest), GRID);
+        assert_eq!(snap(0.75, SnapBias::Nearest), 2.0 * GRID);
+    }
+
+    #[test]
+    fn resize_edges_snap_outward_only_past_the_threshold() {
+        let mut handler = SnapHandler::new(10.0);
+        handler.add_delta(5.0);
+        assert_eq!(handler.snap_start(0.7, BPM, FOUR_FOUR, SnapMode::Beat, true), 0.7);
+        assert_eq!(handler.snap_end(0.7, BPM, FOUR_FOUR, SnapMode::Beat, true), 0.7);
+
+        handler.add_delta(10.0);
+        assert_eq!(handler.snap_start(0.7, BPM, FOUR_FOUR, SnapMode::Beat, true), GRID);
+        assert_eq!(handler.snap_end(0.7, BPM, FOUR_FOUR, SnapMode::Beat, true), 2.0 * GRID);
+        assert_eq!(handler.snap_end(0.7, BPM, FOUR_FOUR, SnapMode::Beat, false), 0.7);
+    }
+
+    #[test]
+    fn resize_edges_snap_only_beyond_the_threshold() {
+        // Exactly at the threshold is not past it
+        let handler = dragged_handler(10.0);
+        assert_eq!(handler.snap_start(0.7, BPM, FOUR_FOUR, SnapMode::Beat, true), 0.7);
+        assert_eq!(handler.snap_end(0.7, BPM, FOUR_FOUR, SnapMode::Beat, true), 0.7);
+
+        let handler = dragged_handler(10.01);
+        assert_eq!(handler.snap_start(0.7, BPM, FOUR_FOUR, SnapMode::Beat, true), GRID);
+        assert_eq!(handler.snap_end(0.7, BPM, FOUR_FOUR, SnapMode::Beat, true), 2.0 * GRID);
+    }
+
+    #[test]
+    fn a_new_threshold_applies_to_the_drag_in_progress() {
+        let mut handler = dragged_handler(15.0);
+        handler.set_threshold(20.0);
+        assert_eq!(handler.snap_start(0.7, BPM, FOUR_FOUR, SnapMode::Beat, true), 0.7);
+        assert_eq!(handler.snap_end(0.7, BPM, FOUR_FOUR, SnapMode::Beat, true), 0.7);
+
+        handler.set_threshold(5.0);
+        assert_eq!(handler.snap_start(0.7, BPM, FOUR_FOUR, SnapMode::Beat, true), GRID);
+        assert_eq!(handler.snap_end(0.7, BPM, FOUR_FOUR, SnapMode::Beat, true), 2.0 * GRID);
+    }
+
+    const MIN_LENGTH: f64 = 0.1;
+
+    fn dragged_handler(pixels: f32) -> SnapHandler {
+        let mut handler = SnapHandler::new(10.0);
+        handler.add_delta(pixels);
+        handler
+    }
+
+    #[test]
+    fn resized_ends_stay_after_the_start() {
+        let handler = dragged_handler(-200.0);
+        // Dragged far past the start: the end stops one minimum length after it
+        let end = handler.resize_end(1.2, 0.3, MIN_LENGTH, BPM, FOUR_FOUR, SnapMode::Beat, true);
+        assert!(end >= 1.2 + MIN_LENGTH);
+        // Within one grid step of the start the end snaps up to the next line
+        let end = handler.resize_end(1.2, 1.25, MIN_LENGTH, BPM, FOUR_FOUR, SnapMode::Beat, true);
+        assert_eq!(end, 1.5);
+        let end = handler.resize_end(1.2, 1.25, MIN_LENGTH, BPM, FOUR_FOUR, SnapMode::Beat, false);
+        assert!((end - (1.2 + MIN_LENGTH)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn resized_starts_stay_before_the_end() {
+        let handler = dragged_handler(200.0);
+        let start =
+            handler.resize_start(3.0, 1.3, MIN_LENGTH, BPM, FOUR_FOUR, SnapMode::Beat, true);
+        assert!(start <= 1.3 - MIN_LENGTH);
+        assert_eq!(start, 1.0);
+        let start =
+            handler.resize_start(-1.0, 1.3, MIN_LENGTH, BPM, FOUR_FOUR, SnapMode::Beat, true);
+        assert_eq!(start, 0.0);
+    }
+
+    /// The piano roll resizes notes in f64 seconds at its zoom, the timeline resizes clips
+    /// in f32 seconds at its pixels per second; the same drag has to land on the same line
+    #[test]
+    fn notes_and_clips_resize_to_the_same_grid_lines() {
+        let pixels_per_second = 100.0_f32;
+        let (start, length) = (0.5_f32, 1.0_f32);
+
+        for pixels in (-150..=150).step_by(7).map(|p| p as f32) {
+            let handler = dragged_handler(pixels);
+            let delta = pixels / pixels_per_second;
+
+            let note_end = handler.resize_end(
+                start as f64,
+                (start + length) as f64 + delta as f64,
+                MIN_LENGTH,
+                BPM,
+                FOUR_FOUR,
+                SnapMode::Halfbeat,
+                true,
+            );
+            let clip_end = handler.resize_end(
+                start as f64,
+                (start + length + delta) as f64,
+                MIN_LENGTH,
+                BPM,
+                FOUR_FOUR,
+                SnapMode::Halfbeat,
+                true,
+            ) as f32;
+            assert!((note_end as f32 - clip_end).abs() < 1e-5, "end after {}px", pixels);
+
+            let note_start = handler.resize_start(
+                start as f64 + delta as f64,
+                (start + length) as f64,
+                MIN_LENGTH,
+                BPM,
+                FOUR_FOUR,
+                SnapMode::Halfbeat,
+                true,
+            );
+            let clip_start = handler.resize_start(
+                (start + delta) as f64,
+                (start + length) as f64,
+                MIN_LENGTH,
+                BPM,
+                FOUR_FOUR,
+                SnapMode::Halfbeat,
+                true,
+            ) as f32;
+            assert!((note_start as f32 - clip_start).abs() < 1e-5, "start after {}px", pixels);
+        }
+    }
+}
@@ -1,28 +1,116 @@
 use crate::core::{
-    CommandManager, DawCommand, DawState, EditorView, MessageType, MidiMessage, Project, SnapMode,
-    StatusMessage, Track, TrackType,
+    export_wav, AudioOutput, BarBeatTick, BuiltinInstrument, ALL_NOTES_OFF_CC, Clip, Command, CommandManager, DawCommand, DawState, EditorView, MessageType, MidiEditor, MidiEvent, MidiImportSplit, MidiMessage, NoteOverlap, Project, SnapMode,
+    QuantizeGrid, StatusMessage, TimeUtils, Track, TrackType, TransportEvent,
 };
+use crate::ui::command_palette::CommandPalette;
+use crate::ui::event_list::EventList;
+use crate::ui::export_dialog::ExportDialog;
 use crate::ui::piano_roll::PianoRoll;
 use crate::ui::plugin_browser::PluginBrowser;
 use crate::ui::Timeline;
 use eframe::egui;
 use eframe::emath::Align;
 use egui::Key;
-use std::path::PathBuf;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+/// Largest playhead advance between frames (in seconds) that is still treated as continuous playback
+const MAX_DISPATCH_GAP: f64 = 0.5;
+
+/// Synth source the metronome plays its clicks as, apart from every track
+const METRONOME_SOURCE: &str = "metronome";
+/// Keys of the metronome click on the first beat of a bar and on the other beats
+const METRONOME_DOWNBEAT_KEY: u8 = 96;
+const METRONOME_BEAT_KEY: u8 = 84;
+/// How long each metronome click is held before it is released
+const METRONOME_CLICK_LENGTH: Duration = Duration::from_millis(30);
+
 pub struct SupersawApp {
     state: DawState,
     command_manager: CommandManager,
     midi_output: Option<midir::MidiOutputConnection>,
     midi_ports: Vec<String>,
+    audio_output: Option<AudioOutput>,
+    /// End of the time range already dispatched during the current playback
+    dispatched_until: Option<f64>,
+    /// Key each sounding note was sent on, keyed by (track id, note id). A clip processor
+    /// changed while the note plays must not send its NoteOff to a different key.
+    sounding_keys: HashMap<(String, String), u8>,
+    /// Sounding metronome click and when to release it
+    metronome_click: Option<(u8, Instant)>,
+    /// MIDI events dispatched since `dispatch_rate_since`, for the status bar meter
+    dispatched_events: u32,
+    dispatch_rate_since: Instant,
+    events_per_second: f32,
     file_dialog: Option<FileDialog>,
 
     // Views
     timeline: Timeline,
     piano_roll: PianoRoll,
+    event_list: EventList,
     plugin_browser: PluginBrowser,
+    export_dialog: ExportDialog,
+    command_palette: CommandPalette,
+    /// Name typed into File > Save as Template
+    template_name: String,
+    /// Tempo of an imported MIDI file the user can choose to adopt
+    tempo_prompt: Option<f64>,
+    /// MIDI file picked for import, waiting for the user to choose how to split it
+    midi_import: Option<(PathBuf, MidiImportSplit)>,
+    /// Whether imported MIDI notes are quantized with `import_quantizer`
+    quantize_import: bool,
+    import_quantizer: MidiEditor,
+    /// Bars added by Time > Insert Bars at Playhead
+    insert_bars: u32,
+    /// Whether the status message history is expanded above the status bar
+    show_status_history: bool,
+    /// Least severe message type listed in the status history
+    status_history_level: MessageType,
+}
+
+/// Quietest level shown on the output meter
+const METER_FLOOR_DB: f32 = -60.0;
+
+fn linear_to_db(level: f32) -> f32 {
+    20.0 * level.max(1e-6).log10()
+}
+
+/// Position of a level along the meter, from 0.0 at the floor to 1.0 at full scale
+fn meter_position(level: f32) -> f32 {
+    (1.0 - linear_to_db(level) / METER_FLOOR_DB).clamp(0.0, 1.0)
+}
+
+fn meter_color(position: f32) -> egui::Color32 {
+    if position >= 0.95 {
+        egui::Color32::RED
+    } else if position >= 0.8 {
+        egui::Color32::YELLOW
+    } else {
+        egui::Color32::from_rgb(80, 200, 120)
+    }
+}
+
+fn message_color(message_type: &MessageType, ui: &egui::Ui) -> egui::Color32 {
+    match message_type {
+        MessageType::Info => ui.visuals().text_color(),
+        MessageType::Success => egui::Color32::GREEN,
+        MessageType::Warning => egui::Color32::YELLOW,
+        MessageType::Error => egui::Color32::RED,
+    }
+}
+
+/// Short "how long ago" label for the status history
+fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    if seconds < 60 {
+        format!("{}s ago", seconds)
+    } else if seconds < 3600 {
+        format!("{}m ago", seconds / 60)
+    } else {
+        format!("{}h ago", seconds / 3600)
+    }
 }
 
 enum FileDialog {
@@ -35,21 +123,7 @@ enum FileDialog {
 impl SupersawApp {
     fn handle_key_action(&mut self, action: KeyAction) {
         match action {
-            KeyAction::TogglePlay => {
-                if let Err(e) = self.command_manager.execute(
-                    if self.state.playing {
-                        DawCommand::PausePlayback
-                    } else {
-                        DawCommand::StartPlayback
-                    },
-                    &mut self.state,
-                ) {
-                    eprintln!("Failed to toggle playback: {}", e);
-                    self.state
-                        .status
-                        .error(format!("Failed to toggle playback: {}", e));
-                }
-            }
+            KeyAction::TogglePlay => self.toggle_playback(),
             KeyAction::LoadProject => {
                 self.file_dialog = Some(FileDialog::LoadProject);
             }
@@ -68,8 +142,189 @@ impl SupersawApp {
                     self.state.status.error(format!("Redo failed: {}", e));
                 }
             }
+            KeyAction::PreviousMarker | KeyAction::NextMarker => {
+                let target = if matches!(action, KeyAction::PreviousMarker) {
+                    self.state.project.previous_marker_time(self.state.current_time)
+                } else {
+                    self.state.project.next_marker_time(self.state.current_time)
+                };
+                if let Some(time) = target {
+                    if let Err(e) = self
+                        .command_manager
+                        .execute(DawCommand::SeekTime { time }, &mut self.state)
+                    {
+                        self.state.status.error(format!("Failed to seek: {}", e));
+                    }
+                }
+            }
+        }
+    }
+    /// Start playback, or stop it in place or at the start depending on the settings
+    fn toggle_playback(&mut self) {
+        let command = if !self.state.playing {
+            DawCommand::StartPlayback
+        } else if self.state.settings.return_to_start_on_stop {
+            DawCommand::StopPlayback
+        } else {
+            DawCommand::PausePlayback
+        };
+        if let Err(e) = self.command_manager.execute(command, &mut self.state) {
+            eprintln!("Failed to toggle playback: {}", e);
+            self.state
+                .status
+                .error(format!("Failed to toggle playback: {}", e));
+        }
+    }
+
+    /// Voice count, audio callback load and MIDI event rate, for diagnosing glitches
+    fn draw_performance_meter(&self, ui: &mut egui::Ui) {
+        ui.label(format!("MIDI: {:.0} ev/s", self.events_per_second))
+            .on_hover_text("MIDI events dispatched per second");
+
+        if let Some(output) = &self.audio_output {
+            let load = output.callback_load();
+            let color = if load >= 0.8 {
+                egui::Color32::RED
+            } else if load >= 0.5 {
+                egui::Color32::YELLOW
+            } else {
+                ui.visuals().text_color()
+            };
+            ui.separator();
+            ui.colored_label(color, format!("DSP: {:.0}%", load * 100.0))
+                .on_hover_text("Time spent rendering each audio buffer");
+            ui.separator();
+            ui.label(format!("Voices: {}", output.active_voices()));
         }
     }
+
+    /// Release all notes held by the built-in instruments
+    fn silence_synth(&self) {
+        if let Some(output) = &self.audio_output {
+            if let Ok(mut synth) = output.synth().lock() {
+                synth.all_notes_off();
+            }
+        }
+    }
+
+    /// Silence the built-in instruments and send All Notes Off to every MIDI track's device,
+    /// so nothing hangs when playback stops or jumps
+    fn release_held_notes(&mut self) {
+        self.silence_synth();
+        self.sounding_keys.clear();
+
+        let channels: Vec<u8> = self
+            .state
+            .project
+            .tracks
+            .iter()
+            .filter_map(|track| match &track.track_type {
+                TrackType::Midi {
+                    channel,
+                    device_name: Some(device),
+                } if !device.is_empty() => Some(*channel),
+                _ => None,
+            })
+            .collect();
+        for channel in channels {
+            let all_notes_off = MidiMessage::ControlChange {
+                channel,
+                controller: ALL_NOTES_OFF_CC,
+                value: 0,
+            };
+            if let Err(e) = self.send_midi_message(channel, &all_notes_off) {
+                eprintln!("Failed to send All Notes Off: {}", e);
+            }
+        }
+    }
+
+    /// Remember the key a note starts on, and end it on that same key
+    fn track_sounding_key(&mut self, track_id: &str, event: &mut MidiEvent) {
+        match &mut event.message {
+            MidiMessage::NoteOn { key, velocity, .. } if *velocity > 0 => {
+                if let Some(note_id) = event.id.strip_suffix("_on") {
+                    self.sounding_keys
+                        .insert((track_id.to_string(), note_id.to_string()), *key);
+                }
+            }
+            MidiMessage::NoteOff { key, .. } => {
+                if let Some(sent) = event.id.strip_suffix("_off").and_then(|note_id| {
+                    self.sounding_keys
+                        .remove(&(track_id.to_string(), note_id.to_string()))
+                }) {
+                    *key = sent;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Play a message on a track's built-in instrument and send it to the track's MIDI device
+    /// Release the metronome click once it has sounded long enough, then start a new one
+    /// if a beat falls in `[start, end)`
+    fn play_metronome(&mut self, start: f64, end: f64) {
+        let Some(output) = &self.audio_output else {
+            return;
+        };
+        let Ok(mut synth) = output.synth().lock() else {
+            return;
+        };
+
+        if let Some((key, until)) = self.metronome_click {
+            if Instant::now() >= until {
+                synth.note_off(METRONOME_SOURCE, key);
+                self.metronome_click = None;
+            }
+        }
+
+        if !self.state.playing || !self.state.metronome {
+            return;
+        }
+        let project = &self.state.project;
+        let Some(&(_, downbeat)) =
+            TimeUtils::beats_in_range(start, end, project.bpm, project.time_signature).last()
+        else {
+            return;
+        };
+
+        if let Some((key, _)) = self.metronome_click.take() {
+            synth.note_off(METRONOME_SOURCE, key);
+        }
+        let key = if downbeat {
+            METRONOME_DOWNBEAT_KEY
+        } else {
+            METRONOME_BEAT_KEY
+        };
+        synth.note_on(METRONOME_SOURCE, BuiltinInstrument::Sine, key, 100);
+        self.metronome_click = Some((key, Instant::now() + METRONOME_CLICK_LENGTH));
+    }
+
+    fn play_on_track(&mut self, track_id: &str, message: &MidiMessage) {
+        let Some(track) = self.state.project.tracks.iter().find(|t| t.id == track_id) else {
+            return;
+        };
+
+        if let (Some(instrument), Some(output)) = (track.instrument, &self.audio_output) {
+            if let Ok(mut synth) = output.synth().lock() {
+                synth.handle_message(track_id, instrument, message);
+            }
+        }
+
+        let device_channel = match &track.track_type {
+            TrackType::Midi {
+                channel,
+                device_name: Some(device),
+            } if !device.is_empty() => Some(*channel),
+            _ => None,
+        };
+        if let Some(channel) = device_channel {
+            if let Err(e) = self.send_midi_message(channel, message) {
+                // Log the error, but don't show in UI to avoid spam
+                eprintln!("Failed to send MIDI message: {}", e);
+            }
+        }
+    }
+
     fn scan_midi_ports() -> Vec<String> {
         match midir::MidiOutput::new("Supersaw") {
             Ok(midi_out) => midi_out
@@ -146,11 +401,35 @@ impl SupersawApp {
             state: DawState::new(),
             midi_output: None,
             midi_ports,
+            audio_output: match AudioOutput::new() {
+                Ok(output) => Some(output),
+                Err(e) => {
+                    eprintln!("Audio output unavailable: {}", e);
+                    None
+                }
+            },
+            dispatched_until: None,
+            sounding_keys: HashMap::new(),
+            metronome_click: None,
+            dispatched_events: 0,
+            dispatch_rate_since: Instant::now(),
+            events_per_second: 0.0,
             file_dialog: None,
             timeline,
             piano_roll: PianoRoll::default(),
+            event_list: EventList::default(),
             command_manager: CommandManager::default(),
             plugin_browser: PluginBrowser::default(),
+            export_dialog: ExportDialog::default(),
+            command_palette: CommandPalette::default(),
+            template_name: String::new(),
+            tempo_prompt: None,
+            insert_bars: 1,
+            show_status_history: false,
+            status_history_level: MessageType::Info,
+            midi_import: None,
+            quantize_import: false,
+            import_quantizer: MidiEditor::default(),
         };
 
         app.state.status.set_message(
@@ -170,7 +449,7 @@ impl SupersawApp {
             if let Err(e) = app
                 .state
                 .project
-                .create_midi_track_from_file_path(&file_path)
+                .create_midi_track_from_file_path(&file_path, 0.0)
             {
                 app.state
                     .status
@@ -188,21 +467,31 @@ impl SupersawApp {
             // Play/Stop button
             if ui
                 .button(if self.state.playing { "⏹" } else { "▶" })
+                .on_hover_text(if self.state.playing {
+                    "Stop playback (Space)"
+                } else {
+                    "Start playback (Space)"
+                })
                 .clicked()
             {
-                self.state.playing = !self.state.playing;
-                if self.state.playing {
-                    self.state.last_update = Some(std::time::Instant::now());
-                }
+                self.toggle_playback();
             }
 
-            if ui.button("⏮").clicked() {
-                // Return to start
-                self.state.current_time = 0.0;
+            if ui.button("⏮").on_hover_text("Return to start").clicked() {
+                if let Err(e) = self
+                    .command_manager
+                    .execute(DawCommand::SeekTime { time: 0.0 }, &mut self.state)
+                {
+                    self.state.status.error(format!("Failed to seek: {}", e));
+                }
             }
 
             // Toggle metronome
-            if ui.button("M").clicked() {
+            if ui
+                .selectable_label(self.state.metronome, "M")
+                .on_hover_text("Metronome")
+                .clicked()
+            {
                 if let Err(e) = self.command_manager.execute(
                     if self.state.metronome {
                         DawCommand::DisableMetronome
@@ -217,16 +506,50 @@ impl SupersawApp {
                 }
             }
 
-            if ui.button("Rec").clicked() {
+            if ui
+                .add_enabled(
+                    !self.state.rehearsing,
+                    egui::Button::new("Rec").selected(self.state.recording),
+                )
+                .on_hover_text("Record")
+                .clicked()
+            {
                 self.state.recording = !self.state.recording
             }
+            if self.state.recording {
+                // Blink once a second
+                let lit = ui.input(|i| i.time).fract() < 0.5;
+                let color = if lit {
+                    egui::Color32::from_rgb(255, 40, 40)
+                } else {
+                    egui::Color32::from_rgb(110, 30, 30)
+                };
+                ui.label(egui::RichText::new("● REC").color(color).strong());
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_millis(100));
+            }
+            if ui
+                .selectable_label(self.state.rehearsing, "Rehearse")
+                .on_hover_text("Play over the metronome only: tracks stay silent and nothing records")
+                .clicked()
+            {
+                if self.state.rehearsing {
+                    self.state.stop_rehearsing();
+                } else {
+                    self.state.start_rehearsing();
+                }
+                self.silence_synth();
+            }
 
             ui.separator();
 
             ui.label(format!("BPM: {:.1}", self.state.project.bpm));
 
-            for (label, delta) in [("−", -1.0), ("+", 1.0)] {
-                if ui.button(label).clicked() {
+            for (label, delta, hint) in [
+                ("−", -1.0, "Lower the tempo by 1 BPM"),
+                ("+", 1.0, "Raise the tempo by 1 BPM"),
+            ] {
+                if ui.button(label).on_hover_text(hint).clicked() {
                     if let Err(e) = self.command_manager.execute(
                         DawCommand::SetBpm {
                             bpm: self.state.project.bpm + delta,
@@ -269,7 +592,34 @@ impl SupersawApp {
                             }
                         }
                     }
-                });
+                })
+                .response
+                .on_hover_text("Hold Shift or Q while dragging to ignore the grid");
+
+            // One-click access to the common snap values
+            for (snap_mode, label) in [
+                (SnapMode::Bar, "Bar"),
+                (SnapMode::Beat, "1/4"),
+                (SnapMode::Halfbeat, "1/8"),
+                (SnapMode::Quarter, "1/16"),
+                (SnapMode::Triplet, "3"),
+                (SnapMode::None, "Off"),
+            ] {
+                if ui
+                    .selectable_label(self.state.snap_mode == snap_mode, label)
+                    .on_hover_text(format!("Snap: {}", snap_mode.display_name()))
+                    .clicked()
+                {
+                    if let Err(e) = self
+                        .command_manager
+                        .execute(DawCommand::SetSnapMode { snap_mode }, &mut self.state)
+                    {
+                        self.state
+                            .status
+                            .error(format!("Failed to set snap mode: {}", e));
+                    }
+                }
+            }
 
             ui.separator();
 
@@ -279,16 +629,77 @@ impl SupersawApp {
             let frames = ((self.state.current_time % 1.0) * 30.0).floor(); // Assuming 30fps
             ui.label(format!("{:02}:{:02}:{:02}", minutes, seconds, frames));
 
+            // Musical position; dragging the bar or beat moves the playhead there
+            let time_signature = self.state.project.time_signature;
+            let position = TimeUtils::time_to_bars_beats(
+                self.state.current_time,
+                self.state.project.bpm,
+                time_signature,
+            );
+            let mut target = BarBeatTick { tick: 0, ..position };
+            let bar_changed = ui
+                .add(egui::DragValue::new(&mut target.bar).speed(0.1).range(1..=9999))
+                .on_hover_text("Bar")
+                .changed();
+            let beat_changed = ui
+                .add(
+                    egui::DragValue::new(&mut target.beat)
+                        .speed(0.1)
+                        .range(1..=time_signature.0.max(1) as u32),
+                )
+                .on_hover_text("Beat")
+                .changed();
+            ui.monospace(format!("{:03}", position.tick));
+            if bar_changed || beat_changed {
+                let time = TimeUtils::bars_beats_to_time(
+                    target,
+                    self.state.project.bpm,
+                    time_signature,
+                );
+                if let Err(e) = self
+                    .command_manager
+                    .execute(DawCommand::SeekTime { time }, &mut self.state)
+                {
+                    self.state.status.error(format!("Failed to seek: {}", e));
+                }
+            }
+
+            ui.separator();
+
+            if ui
+                .toggle_value(&mut self.state.settings.follow_playhead, "Follow")
+                .on_hover_text("Scroll the view to follow the playhead during playback")
+                .clicked()
+            {
+                if let Err(e) = self.state.settings.save() {
+                    self.state
+                        .status
+                        .error(format!("Failed to save settings: {}", e));
+                }
+            }
+
             ui.separator();
 
             let mut loop_enabled = self.state.loop_enabled;
-            if ui.toggle_value(&mut loop_enabled, "⟲").clicked() {
+            if ui
+                .toggle_value(&mut loop_enabled, "⟲")
+                .on_hover_text("Loop between the loop start and end")
+                .clicked()
+            {
                 self.state.loop_enabled = loop_enabled;
             }
-            if ui.button("Set Start").clicked() {
+            if ui
+                .button("Set Start")
+                .on_hover_text("Start the loop at the playhead")
+                .clicked()
+            {
                 self.state.loop_start = self.state.current_time;
             }
-            if ui.button("Set End").clicked() {
+            if ui
+                .button("Set End")
+                .on_hover_text("End the loop at the playhead")
+                .clicked()
+            {
                 self.state.loop_end = self.state.current_time;
             }
 
@@ -300,9 +711,53 @@ impl SupersawApp {
 
             ui.separator();
 
-            if ui.button("Arrangement").clicked() {
+            if ui
+                .button("Arrangement")
+                .on_hover_text("Back to the arrangement (Esc in the piano roll)")
+                .clicked()
+            {
                 self.state.current_view = EditorView::Arrangement;
             }
+
+            if let EditorView::PianoRoll {
+                clip_id, track_id, ..
+            } = self.state.current_view.clone()
+            {
+                if ui
+                    .button("Go to Clip")
+                    .on_hover_text("Back to the arrangement with this clip selected and in view")
+                    .clicked()
+                {
+                    let command = DawCommand::Batch {
+                        commands: vec![
+                            DawCommand::SelectTrack {
+                                track_id: track_id.clone(),
+                            },
+                            DawCommand::SelectClip {
+                                clip_id: clip_id.clone(),
+                            },
+                        ],
+                    };
+                    if let Err(e) = self.command_manager.execute(command, &mut self.state) {
+                        self.state
+                            .status
+                            .error(format!("Failed to select clip: {}", e));
+                    }
+                    self.state.reveal_clip = Some(clip_id.clone());
+                    self.state.current_view = EditorView::Arrangement;
+                }
+                if ui.button("Event List").clicked() {
+                    let command = DawCommand::OpenEventList {
+                        clip_id: clip_id.clone(),
+                        track_id: track_id.clone(),
+                    };
+                    if let Err(e) = self.command_manager.execute(command, &mut self.state) {
+                        self.state
+                            .status
+                            .error(format!("Failed to open event list: {}", e));
+                    }
+                }
+            }
             
             ui.separator();
             
@@ -314,9 +769,325 @@ impl SupersawApp {
                     ui.close_menu();
                 }
             });
+
+            self.draw_level_meter(ui);
         });
     }
 
+    /// Stereo output meter: RMS bars with a peak tick, plus a clip light that resets on click.
+    /// Hidden when there is no audio output.
+    fn draw_level_meter(&self, ui: &mut egui::Ui) {
+        let Some(output) = &self.audio_output else {
+            return;
+        };
+        let levels = output.levels();
+
+        ui.separator();
+
+        let (rect, response) =
+            ui.allocate_exact_size(egui::vec2(120.0, 16.0), egui::Sense::click());
+        let clip_rect =
+            egui::Rect::from_min_max(egui::pos2(rect.right() - 8.0, rect.top()), rect.max);
+        let bars_rect = rect.with_max_x(clip_rect.left() - 2.0);
+        let bar_height = (bars_rect.height() - 1.0) / 2.0;
+
+        let painter = ui.painter();
+        painter.rect_filled(bars_rect, 2.0, egui::Color32::from_gray(30));
+
+        for channel in 0..2 {
+            let top = bars_rect.top() + channel as f32 * (bar_height + 1.0);
+            let rms = meter_position(levels.rms(channel));
+            let peak = meter_position(levels.peak(channel));
+
+            let bar = egui::Rect::from_min_size(
+                egui::pos2(bars_rect.left(), top),
+                egui::vec2(bars_rect.width() * rms, bar_height),
+            );
+            painter.rect_filled(bar, 0.0, meter_color(rms));
+
+            let peak_x = bars_rect.left() + bars_rect.width() * peak;
+            painter.line_segment(
+                [egui::pos2(peak_x, top), egui::pos2(peak_x, top + bar_height)],
+                egui::Stroke::new(1.0, meter_color(peak)),
+            );
+        }
+
+        let clip_color = if levels.clipped() {
+            egui::Color32::RED
+        } else {
+            egui::Color32::from_gray(50)
+        };
+        painter.rect_filled(clip_rect, 2.0, clip_color);
+
+        if response.clicked() {
+            levels.reset_clip();
+        }
+        response.on_hover_text(format!(
+            "Peak L {:.1} dB, R {:.1} dB (click to reset clip indicator)",
+            linear_to_db(levels.peak(0)),
+            linear_to_db(levels.peak(1))
+        ));
+    }
+
+
+    /// Ask whether to switch the project to an imported file's tempo
+    fn draw_tempo_prompt(&mut self, ctx: &egui::Context) {
+        let Some(file_bpm) = self.tempo_prompt else {
+            return;
+        };
+
+        egui::Window::new("Tempo Mismatch")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The imported file is at {:.1} BPM, the project is at {:.1} BPM.",
+                    file_bpm, self.state.project.bpm
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button(format!("Use {:.1} BPM", file_bpm)).clicked() {
+                        if let Err(e) = self
+                            .command_manager
+                            .execute(DawCommand::SetBpm { bpm: file_bpm }, &mut self.state)
+                        {
+                            self.state.status.error(format!("Failed to set tempo: {}", e));
+                        }
+                        self.tempo_prompt = None;
+                    }
+                    if ui
+                        .button(format!("Keep {:.1} BPM", self.state.project.bpm))
+                        .clicked()
+                    {
+                        self.tempo_prompt = None;
+                    }
+                });
+            });
+    }
+
+    /// Run one command per track as a single undo step
+    fn execute_track_batch(&mut self, commands: Vec<DawCommand>) {
+        if commands.is_empty() {
+            return;
+        }
+        if let Err(e) = self
+            .command_manager
+            .execute(DawCommand::Batch { commands }, &mut self.state)
+        {
+            self.state.status.error(format!("Failed to update tracks: {}", e));
+        }
+    }
+
+    fn set_all_tracks_muted(&mut self, muted: bool) {
+        let commands = self
+            .state
+            .project
+            .tracks
+            .iter()
+            .filter(|track| track.is_muted != muted)
+            .map(|track| {
+                let track_id = track.id.clone();
+                if muted {
+                    DawCommand::MuteTrack { track_id }
+                } else {
+                    DawCommand::UnmuteTrack { track_id }
+                }
+            })
+            .collect();
+        self.execute_track_batch(commands);
+    }
+
+    fn clear_solos(&mut self) {
+        let commands = self
+            .state
+            .project
+            .tracks
+            .iter()
+            .filter(|track| track.is_soloed)
+            .map(|track| DawCommand::UnsoloTrack {
+                track_id: track.id.clone(),
+            })
+            .collect();
+        self.execute_track_batch(commands);
+    }
+
+    /// Everything the command palette offers right now. Project commands are labelled
+    /// with `Command::name()` and, where they act on something, what that is.
+    fn palette_actions(&self) -> Vec<(String, PaletteAction)> {
+        let state = &self.state;
+        let mut commands = vec![
+            if state.playing {
+                DawCommand::StopPlayback
+            } else {
+                DawCommand::StartPlayback
+            },
+            if state.metronome {
+                DawCommand::DisableMetronome
+            } else {
+                DawCommand::EnableMetronome
+            },
+            DawCommand::AddTrack {
+                track_type: TrackType::Midi {
+                    channel: 1,
+                    device_name: None,
+                },
+                name: format!("Track {}", state.project.tracks.len() + 1),
+            },
+            DawCommand::AddMarker {
+                marker: state.project.new_marker(state.current_time),
+            },
+            DawCommand::InsertTime {
+                time: state.current_time,
+                duration: self.insert_bars as f64
+                    * TimeUtils::seconds_per_bar(state.project.bpm, state.project.time_signature),
+            },
+            DawCommand::DeselectAll,
+        ];
+        if state.playing {
+            commands.push(DawCommand::PausePlayback);
+        }
+        if state.loop_end > state.loop_start {
+            commands.push(DawCommand::AddSection {
+                section: state.project.new_section(state.loop_start, state.loop_end),
+            });
+            commands.push(DawCommand::RemoveTime {
+                start: state.loop_start,
+                end: state.loop_end,
+            });
+        }
+        let mut actions: Vec<(String, PaletteAction)> = commands
+            .into_iter()
+            .map(|command| (command.name().to_string(), PaletteAction::Command(command)))
+            .collect();
+
+        // Commands on the selected track and clip
+        let mut targeted = Vec::new();
+        if let Some(track) = state
+            .selected_track
+            .as_ref()
+            .and_then(|id| state.project.tracks.iter().find(|t| t.id == *id))
+        {
+            let track_id = track.id.clone();
+            targeted.push((
+                track.name.clone(),
+                if track.is_muted {
+                    DawCommand::UnmuteTrack { track_id: track_id.clone() }
+                } else {
+                    DawCommand::MuteTrack { track_id: track_id.clone() }
+                },
+            ));
+            targeted.push((
+                track.name.clone(),
+                if track.is_soloed {
+                    DawCommand::UnsoloTrack { track_id: track_id.clone() }
+                } else {
+                    DawCommand::SoloTrack { track_id: track_id.clone() }
+                },
+            ));
+            targeted.push((
+                track.name.clone(),
+                if track.is_armed {
+                    DawCommand::UnarmTrack { track_id: track_id.clone() }
+                } else {
+                    DawCommand::ArmTrack { track_id: track_id.clone() }
+                },
+            ));
+            targeted.push((track.name.clone(), DawCommand::DeleteTrack { track_id }));
+        }
+        if let Some((track, clip)) = state.selected_clip.as_ref().and_then(|clip_id| {
+            state.project.tracks.iter().find_map(|track| {
+                let clip = track.clips.iter().find(|c| c.id() == clip_id)?;
+                Some((track, clip))
+            })
+        }) {
+            let name = format!("clip on {}", track.name);
+            let clip_id = clip.id().to_string();
+            let track_id = track.id.clone();
+            if matches!(clip, Clip::Midi { .. }) {
+                targeted.push((
+                    name.clone(),
+                    DawCommand::OpenPianoRoll {
+                        clip_id: clip_id.clone(),
+                        track_id: track_id.clone(),
+                    },
+                ));
+                targeted.push((
+                    name.clone(),
+                    DawCommand::OpenEventList {
+                        clip_id: clip_id.clone(),
+                        track_id: track_id.clone(),
+                    },
+                ));
+            }
+            targeted.push((
+                name.clone(),
+                DawCommand::SnapClipToGrid {
+                    clip_id: clip_id.clone(),
+                },
+            ));
+            targeted.push((
+                name.clone(),
+                DawCommand::SetClipMuted {
+                    clip_id: clip_id.clone(),
+                    muted: !clip.is_muted(),
+                },
+            ));
+            targeted.push((name, DawCommand::DeleteClip { track_id, clip_id }));
+        }
+        for marker in &state.project.markers {
+            targeted.push((
+                marker.name.clone(),
+                DawCommand::SeekTime { time: marker.time },
+            ));
+        }
+        actions.extend(targeted.into_iter().map(|(target, command)| {
+            (
+                format!("{}: {}", command.name(), target),
+                PaletteAction::Command(command),
+            )
+        }));
+
+        actions.extend([
+            ("Undo".to_string(), PaletteAction::Key(KeyAction::Undo)),
+            ("Redo".to_string(), PaletteAction::Key(KeyAction::Redo)),
+            ("Previous Marker".to_string(), PaletteAction::Key(KeyAction::PreviousMarker)),
+            ("Next Marker".to_string(), PaletteAction::Key(KeyAction::NextMarker)),
+            ("Save Project".to_string(), PaletteAction::Key(KeyAction::SaveProject)),
+            ("Load Project".to_string(), PaletteAction::Key(KeyAction::LoadProject)),
+            ("Import MIDI...".to_string(), PaletteAction::OpenFile(FileDialog::ImportMidi)),
+            ("Import Audio...".to_string(), PaletteAction::OpenFile(FileDialog::ImportAudio)),
+            ("Export Audio...".to_string(), PaletteAction::ExportAudio),
+            ("Browse Plugins...".to_string(), PaletteAction::BrowsePlugins),
+            ("Show Arrangement".to_string(), PaletteAction::ShowArrangement),
+            ("Message History".to_string(), PaletteAction::ToggleStatusHistory),
+            ("Mute All".to_string(), PaletteAction::SetAllMuted(true)),
+            ("Unmute All".to_string(), PaletteAction::SetAllMuted(false)),
+            ("Clear Solos".to_string(), PaletteAction::ClearSolos),
+        ]);
+        actions
+    }
+
+    fn run_palette_action(&mut self, action: PaletteAction) {
+        match action {
+            PaletteAction::Command(command) => {
+                if let Err(e) = self.command_manager.execute(command, &mut self.state) {
+                    self.state.status.error(format!("Command failed: {}", e));
+                }
+            }
+            PaletteAction::Key(action) => self.handle_key_action(action),
+            PaletteAction::OpenFile(dialog) => self.file_dialog = Some(dialog),
+            PaletteAction::ExportAudio => self.export_dialog.open(&self.state),
+            PaletteAction::BrowsePlugins => self.plugin_browser.show_browser(),
+            PaletteAction::ShowArrangement => {
+                self.state.current_view = EditorView::Arrangement;
+            }
+            PaletteAction::ToggleStatusHistory => {
+                self.show_status_history = !self.show_status_history;
+            }
+            PaletteAction::SetAllMuted(muted) => self.set_all_tracks_muted(muted),
+            PaletteAction::ClearSolos => self.clear_solos(),
+        }
+    }
 
     fn import_midi_file(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(file_path) = rfd::FileDialog::new()
@@ -325,20 +1096,132 @@ impl SupersawApp {
             .set_directory(std::env::current_dir().unwrap())
             .pick_file()
         {
-            let track_id = self
+            self.midi_import = Some((file_path, MidiImportSplit::Single));
+        }
+
+        Ok(())
+    }
+
+    fn draw_midi_import_dialog(&mut self, ctx: &egui::Context) {
+        let Some((file_path, split)) = &mut self.midi_import else {
+            return;
+        };
+
+        let mut import = false;
+        let mut open = true;
+        egui::Window::new("Import MIDI")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(file_path.file_name().unwrap_or_default().to_string_lossy());
+                for option in [
+                    MidiImportSplit::Single,
+                    MidiImportSplit::PerTrack,
+                    MidiImportSplit::PerChannel,
+                ] {
+                    ui.radio_value(split, option, option.display_name());
+                }
+
+                ui.add_space(8.0);
+                let settings = &mut self.import_quantizer.quantize_settings;
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.quantize_import, "Quantize to");
+                    ui.add_enabled_ui(self.quantize_import, |ui| {
+                        egui::ComboBox::from_id_salt("import_quantize_grid")
+                            .selected_text(settings.grid.display_name())
+                            .show_ui(ui, |ui| {
+                                for grid in QuantizeGrid::ALL {
+                                    ui.selectable_value(&mut settings.grid, grid, grid.display_name());
+                                }
+                            });
+                    });
+                });
+                ui.add_enabled(
+                    self.quantize_import,
+                    egui::Slider::new(&mut settings.strength, 0.0..=1.0).text("Strength"),
+                )
+                .on_hover_text("How far notes move toward the grid");
+                ui.add_space(8.0);
+                import = ui.button("Import").clicked();
+            });
+
+        if !open {
+            self.midi_import = None;
+        } else if import {
+            if let Some((file_path, split)) = self.midi_import.take() {
+                if let Err(e) = self.import_midi_tracks(&file_path, split) {
+                    self.state
+                        .status
+                        .error(format!("Failed to import MIDI file: {}", e));
+                }
+            }
+        }
+    }
+
+    fn import_midi_tracks(
+        &mut self,
+        file_path: &Path,
+        split: MidiImportSplit,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let track_ids = self
+            .state
+            .project
+            .create_midi_tracks_from_file_path(file_path, 0.0, split)?;
+
+        // Tighten the notes on the project grid before anything else sees the clips
+        if self.quantize_import {
+            let bpm = self.state.project.bpm;
+            for track in self
                 .state
                 .project
-                .create_midi_track_from_file_path(&file_path)?;
-
-            // Select the newly created track
-            self.state.selected_track = Some(track_id);
+                .tracks
+                .iter_mut()
+                .filter(|t| track_ids.contains(&t.id))
+            {
+                for clip in &mut track.clips {
+                    if let Clip::Midi {
+                        midi_data: Some(store),
+                        length,
+                        ..
+                    } = clip
+                    {
+                        self.import_quantizer.quantize_notes(store, bpm);
+                        *length = length.max(store.last_note_end().unwrap_or(0.0));
+                    }
+                }
+            }
+        }
 
-            self.state.status.success(format!(
-                "Imported MIDI file: {}",
-                file_path.file_name().unwrap_or_default().to_string_lossy()
-            ));
+        // Offer the file's tempo when it differs from the project's
+        let file_bpm = self
+            .state
+            .project
+            .tracks
+            .iter()
+            .find(|t| track_ids.first() == Some(&t.id))
+            .and_then(|t| t.clips.first())
+            .and_then(Clip::midi_file_bpm);
+        if let Some(bpm) = file_bpm {
+            if (bpm - self.state.project.bpm).abs() > 0.01 {
+                self.tempo_prompt = Some(bpm);
+            }
         }
 
+        // Select the first newly created track
+        self.state.selected_track = track_ids.first().cloned();
+
+        self.state.status.success(format!(
+            "Imported MIDI file: {}{}",
+            file_path.file_name().unwrap_or_default().to_string_lossy(),
+            if track_ids.len() > 1 {
+                format!(" as {} tracks", track_ids.len())
+            } else {
+                String::new()
+            }
+        ));
+
         Ok(())
     }
 }
@@ -349,56 +1232,115 @@ enum KeyAction {
     SaveProject,
     Undo,
     Redo,
+    PreviousMarker,
+    NextMarker,
+}
+
+/// What a command palette entry does when picked
+enum PaletteAction {
+    /// Runs through the command manager, so it can be undone
+    Command(DawCommand),
+    Key(KeyAction),
+    OpenFile(FileDialog),
+    ExportAudio,
+    BrowsePlugins,
+    ShowArrangement,
+    ToggleStatusHistory,
+    SetAllMuted(bool),
+    ClearSolos,
 }
 
 impl eframe::App for SupersawApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.state.update_playhead();
 
+        // Release held notes and start dispatching afresh whenever the transport stops
+        let transport_events = std::mem::take(&mut self.state.transport_events);
+        if transport_events
+            .iter()
+            .any(|event| matches!(event, TransportEvent::Paused | TransportEvent::Stopped))
+        {
+            self.release_held_notes();
+            self.dispatched_until = None;
+        }
+
         // Send MIDI events during playback
         if self.state.playing {
             // Get all MIDI events for the current time step
             let lookahead = 0.01;
-            let start_time = self.state.current_time;
             let end_time = self.state.current_time + lookahead;
 
-            let events = self
-                .state
-                .project
-                .get_all_events_in_time_range(start_time, end_time);
+            // Pick up where the previous frame stopped so no events fall between frames,
+            // unless the playhead jumped (seek or loop)
+            let (start_time, jumped) = match self.dispatched_until {
+                Some(until) if until <= end_time && end_time - until < MAX_DISPATCH_GAP => {
+                    (until, false)
+                }
+                Some(_) => {
+                    self.release_held_notes();
+                    (self.state.current_time, true)
+                }
+                None => (self.state.current_time, true),
+            };
+            self.dispatched_until = Some(end_time);
+            self.play_metronome(start_time, end_time);
 
-            for (track_id, event) in events {
+            // Rehearsal keeps the playhead moving without any track output
+            let events = if self.state.rehearsing {
+                Vec::new()
+            } else {
+                // After a start or jump, bring controllers up to the lanes' current values
+                let mut events = if jumped {
+                    self.state.project.cc_values_at(start_time)
+                } else {
+                    Vec::new()
+                };
+                events.extend(
+                    self.state
+                        .project
+                        .get_all_events_in_time_range(start_time, end_time),
+                );
+                events
+            };
+            let any_soloed = self.state.project.any_track_soloed();
+
+            for (track_id, mut event) in events {
                 // Find the track for this event
-                if let Some(track) = self.state.project.tracks.iter().find(|t| t.id == track_id) {
-                    // If it's a MIDI track, send the event
-                    if let TrackType::Midi {
-                        channel,
-                        device_name,
-                    } = &track.track_type
-                    {
-                        if let Some(device) = device_name {
-                            if !device.is_empty() && !track.is_muted {
-                                // Check if track is soloed, or if no tracks are soloed
-                                let any_soloed =
-                                    self.state.project.tracks.iter().any(|t| t.is_soloed);
-                                if !any_soloed || track.is_soloed {
-                                    if let Err(e) = self.send_midi_message(*channel, &event.message)
-                                    {
-                                        // Log the error, but don't show in UI to avoid spam
-                                        eprintln!("Failed to send MIDI message: {}", e);
-                                    }
-                                }
-                            }
-                        }
-                    }
+                let Some(track) = self.state.project.tracks.iter().find(|t| t.id == track_id)
+                else {
+                    continue;
+                };
+
+                if !track.is_audible(any_soloed) {
+                    continue;
                 }
+                self.dispatched_events += 1;
+                self.track_sounding_key(&track_id, &mut event);
+                self.play_on_track(&track_id, &event.message);
             }
+        } else {
+            // Let the last click ring out and no further
+            self.play_metronome(0.0, 0.0);
+            if self.dispatched_until.take().is_some() {
+                // Playback ended without a transport event, e.g. a new project replaced it
+                self.release_held_notes();
+            }
+        }
+
+        let rate_window = self.dispatch_rate_since.elapsed().as_secs_f32();
+        if rate_window >= 1.0 {
+            self.events_per_second = self.dispatched_events as f32 / rate_window;
+            self.dispatched_events = 0;
+            self.dispatch_rate_since = Instant::now();
         }
 
         // Keyboard shortcuts
         // SAVE -  Ctrl + S
         // REDO -  Shift + Ctrl + Z
         // UNDO -  Ctrl + Z
+        // PREVIOUS/NEXT MARKER - Comma / Period
+        // COMMAND PALETTE - Ctrl + P
+        let typing = ctx.wants_keyboard_input();
         ctx.input(|i| {
             if i.key_pressed(Key::Z) && (i.modifiers.ctrl || i.modifiers.command) {
                 if i.modifiers.shift {
@@ -412,18 +1354,71 @@ impl eframe::App for SupersawApp {
                 self.handle_key_action(KeyAction::SaveProject);
             }
 
-            if i.key_pressed(Key::Space) {
+            if i.key_pressed(Key::P) && (i.modifiers.ctrl || i.modifiers.command) {
+                self.command_palette.toggle();
+            }
+
+            if i.key_pressed(Key::Space) && !typing {
                 self.handle_key_action(KeyAction::TogglePlay);
             }
+
+            if !typing {
+                if i.key_pressed(Key::Comma) {
+                    self.handle_key_action(KeyAction::PreviousMarker);
+                }
+                if i.key_pressed(Key::Period) {
+                    self.handle_key_action(KeyAction::NextMarker);
+                }
+            }
         });
 
+        if self.command_palette.is_open() {
+            let mut actions = self.palette_actions();
+            let labels: Vec<String> = actions.iter().map(|(label, _)| label.clone()).collect();
+            if let Some(index) = self.command_palette.show(ctx, &labels) {
+                let (_, action) = actions.swap_remove(index);
+                self.run_palette_action(action);
+            }
+        }
+
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
                     if ui.button("New Project").clicked() {
                         self.state = DawState::new();
+                        // Undo steps recorded against the old project don't apply to the new one
+                        self.command_manager = CommandManager::default();
                         ui.close_menu();
                     }
+                    ui.menu_button("New from Template", |ui| {
+                        let templates = Project::list_templates();
+                        if templates.is_empty() {
+                            ui.label("No templates saved yet");
+                        }
+                        for path in templates {
+                            let name = path
+                                .file_stem()
+                                .map(|n| n.to_string_lossy().to_string())
+                                .unwrap_or_default();
+                            if ui.button(&name).clicked() {
+                                match Project::from_template(&path) {
+                                    Ok(project) => {
+                                        self.state = DawState::new();
+                                        self.state.project = project;
+                                        self.command_manager = CommandManager::default();
+                                        self.state
+                                            .status
+                                            .success(format!("New project from template {}", name));
+                                    }
+                                    Err(e) => self
+                                        .state
+                                        .status
+                                        .error(format!("Failed to load template: {}", e)),
+                                }
+                                ui.close_menu();
+                            }
+                        }
+                    });
                     if ui.button("Save Project").clicked() {
                         self.file_dialog = Some(FileDialog::SaveProject);
                         ui.close_menu();
@@ -432,6 +1427,29 @@ impl eframe::App for SupersawApp {
                         self.file_dialog = Some(FileDialog::LoadProject);
                         ui.close_menu();
                     }
+                    ui.menu_button("Save as Template", |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Name:");
+                            ui.text_edit_singleline(&mut self.template_name);
+                        });
+                        let name = self.template_name.trim().to_string();
+                        if ui
+                            .add_enabled(!name.is_empty(), egui::Button::new("Save Template"))
+                            .clicked()
+                        {
+                            match self.state.project.save_as_template(&name) {
+                                Ok(()) => self
+                                    .state
+                                    .status
+                                    .success(format!("Saved template {}", name)),
+                                Err(e) => self
+                                    .state
+                                    .status
+                                    .error(format!("Failed to save template: {}", e)),
+                            }
+                            ui.close_menu();
+                        }
+                    });
                     ui.separator();
                     if ui.button("Import MIDI...").clicked() {
                         self.file_dialog = Some(FileDialog::ImportMidi);
@@ -441,6 +1459,156 @@ impl eframe::App for SupersawApp {
                         self.file_dialog = Some(FileDialog::ImportAudio);
                         ui.close_menu();
                     }
+                    ui.separator();
+                    if ui.button("Export Audio...").clicked() {
+                        self.export_dialog.open(&self.state);
+                        ui.close_menu();
+                    }
+                });
+
+                ui.menu_button("Markers", |ui| {
+                    if ui.button("Add Marker at Playhead").clicked() {
+                        let marker = self.state.project.new_marker(self.state.current_time);
+                        if let Err(e) = self
+                            .command_manager
+                            .execute(DawCommand::AddMarker { marker }, &mut self.state)
+                        {
+                            self.state.status.error(format!("Failed to add marker: {}", e));
+                        }
+                        ui.close_menu();
+                    }
+
+                    if !self.state.project.markers.is_empty() {
+                        ui.separator();
+                    }
+
+                    let mut markers = self.state.project.markers.clone();
+                    markers.sort_by(|a, b| a.time.total_cmp(&b.time));
+                    for marker in markers {
+                        if ui
+                            .button(format!("{}  ({:.2}s)", marker.name, marker.time))
+                            .clicked()
+                        {
+                            if let Err(e) = self.command_manager.execute(
+                                DawCommand::SeekTime { time: marker.time },
+                                &mut self.state,
+                            ) {
+                                self.state.status.error(format!("Failed to seek: {}", e));
+                            }
+                            ui.close_menu();
+                        }
+                    }
+                });
+
+                ui.menu_button("Sections", |ui| {
+                    if ui.button("Add Section from Loop").clicked() {
+                        let section = self
+                            .state
+                            .project
+                            .new_section(self.state.loop_start, self.state.loop_end);
+                        if let Err(e) = self
+                            .command_manager
+                            .execute(DawCommand::AddSection { section }, &mut self.state)
+                        {
+                            self.state.status.error(format!("Failed to add section: {}", e));
+                        }
+                        ui.close_menu();
+                    }
+
+                    if !self.state.project.sections.is_empty() {
+                        ui.separator();
+                    }
+
+                    // Jump to a section and loop it
+                    let mut sections = self.state.project.sections.clone();
+                    sections.sort_by(|a, b| a.start.total_cmp(&b.start));
+                    for section in sections {
+                        if ui
+                            .button(format!(
+                                "{}  ({:.2}s - {:.2}s)",
+                                section.name, section.start, section.end
+                            ))
+                            .clicked()
+                        {
+                            self.state.loop_start = section.start;
+                            self.state.loop_end = section.end;
+                            self.state.loop_enabled = true;
+                            if let Err(e) = self.command_manager.execute(
+                                DawCommand::SeekTime {
+                                    time: section.start,
+                                },
+                                &mut self.state,
+                            ) {
+                                self.state.status.error(format!("Failed to seek: {}", e));
+                            }
+                            ui.close_menu();
+                        }
+                    }
+                });
+
+                ui.menu_button("Time", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut self.insert_bars).range(1..=64));
+                        if ui.button("Insert Bars at Playhead").clicked() {
+                            let duration = self.insert_bars as f64
+                                * TimeUtils::seconds_per_bar(
+                                    self.state.project.bpm,
+                                    self.state.project.time_signature,
+                                );
+                            let command = DawCommand::InsertTime {
+                                time: self.state.current_time,
+                                duration,
+                            };
+                            if let Err(e) = self.command_manager.execute(command, &mut self.state) {
+                                self.state.status.error(format!("Failed to insert time: {}", e));
+                            }
+                            ui.close_menu();
+                        }
+                    });
+
+                    let has_range = self.state.loop_end > self.state.loop_start;
+                    if ui
+                        .add_enabled(has_range, egui::Button::new("Remove Loop Range"))
+                        .clicked()
+                    {
+                        let command = DawCommand::RemoveTime {
+                            start: self.state.loop_start,
+                            end: self.state.loop_end,
+                        };
+                        if let Err(e) = self.command_manager.execute(command, &mut self.state) {
+                            self.state.status.error(format!("Failed to remove time: {}", e));
+                        }
+                        ui.close_menu();
+                    }
+                });
+
+                ui.menu_button("Tracks", |ui| {
+                    let tracks = &self.state.project.tracks;
+                    let any_unmuted = tracks.iter().any(|t| !t.is_muted);
+                    let any_muted = tracks.iter().any(|t| t.is_muted);
+                    let any_soloed = self.state.project.any_track_soloed();
+
+                    if ui
+                        .add_enabled(any_unmuted, egui::Button::new("Mute All"))
+                        .clicked()
+                    {
+                        self.set_all_tracks_muted(true);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(any_muted, egui::Button::new("Unmute All"))
+                        .clicked()
+                    {
+                        self.set_all_tracks_muted(false);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(any_soloed, egui::Button::new("Clear Solos"))
+                        .clicked()
+                    {
+                        self.clear_solos();
+                        ui.close_menu();
+                    }
                 });
 
                 ui.menu_button("Plugins", |ui| {
@@ -449,23 +1617,190 @@ impl eframe::App for SupersawApp {
                         ui.close_menu();
                     }
                 });
+
+                ui.menu_button("Settings", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Snap threshold:");
+                        let response = ui.add(
+                            egui::DragValue::new(&mut self.state.settings.snap_threshold)
+                                .speed(0.5)
+                                .range(0.0..=50.0)
+                                .suffix(" px"),
+                        )
+                        .on_hover_text("How far a drag must travel before it snaps to the grid");
+
+                        // Avoid writing the file on every frame of a drag
+                        if (response.changed() && !response.dragged()) || response.drag_stopped() {
+                            if let Err(e) = self.state.settings.save() {
+                                self.state.status.error(format!("Failed to save settings: {}", e));
+                            }
+                        }
+                    });
+
+                    if ui
+                        .checkbox(
+                            &mut self.state.settings.trim_clip_content,
+                            "Resize MIDI clips past their notes",
+                        )
+                        .on_hover_text(
+                            "Allow shortening a MIDI clip so its last notes are cut off",
+                        )
+                        .changed()
+                    {
+                        if let Err(e) = self.state.settings.save() {
+                            self.state.status.error(format!("Failed to save settings: {}", e));
+                        }
+                    }
+
+                    if ui
+                        .checkbox(
+                            &mut self.state.settings.snap_to_zero_crossing,
+                            "Snap audio edges to zero-crossings",
+                        )
+                        .on_hover_text(
+                            "Nudge resized audio clip ends onto the nearest zero-crossing to avoid clicks",
+                        )
+                        .changed()
+                    {
+                        if let Err(e) = self.state.settings.save() {
+                            self.state.status.error(format!("Failed to save settings: {}", e));
+                        }
+                    }
+
+                    if ui
+                        .checkbox(
+                            &mut self.state.settings.return_to_start_on_stop,
+                            "Return to start on stop",
+                        )
+                        .on_hover_text(
+                            "Move the playhead back to the start when playback stops, instead of leaving it where it stopped",
+                        )
+                        .changed()
+                    {
+                        if let Err(e) = self.state.settings.save() {
+                            self.state.status.error(format!("Failed to save settings: {}", e));
+                        }
+                    }
+
+                    ui.separator();
+                    ui.label("Drawing over a note of the same pitch:");
+                    for overlap in NoteOverlap::ALL {
+                        if ui
+                            .radio_value(
+                                &mut self.state.settings.note_overlap,
+                                overlap,
+                                overlap.display_name(),
+                            )
+                            .changed()
+                        {
+                            if let Err(e) = self.state.settings.save() {
+                                self.state.status.error(format!("Failed to save settings: {}", e));
+                            }
+                        }
+                    }
+                });
             });
         });
 
         egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
             self.state.status.update(); // Clear expired messages
 
-            if let Some(message) = self.state.status.get_message() {
-                let color = match message.message_type {
-                    MessageType::Info => ui.visuals().text_color(),
-                    MessageType::Success => egui::Color32::GREEN,
-                    MessageType::Warning => egui::Color32::YELLOW,
-                    MessageType::Error => egui::Color32::RED,
-                };
-                ui.colored_label(color, &message.text);
-            }
+            ui.horizontal(|ui| {
+                let arrow = if self.show_status_history { "⏷" } else { "⏶" };
+                let mut toggled = ui
+                    .selectable_label(
+                        self.show_status_history,
+                        format!("{} {}", arrow, self.state.status.history().len()),
+                    )
+                    .on_hover_text("Show message history")
+                    .clicked();
+
+                if let Some(message) = self.state.status.get_message() {
+                    let text = egui::RichText::new(&message.text)
+                        .color(message_color(&message.message_type, ui));
+                    toggled |= ui
+                        .add(egui::Label::new(text).sense(egui::Sense::click()))
+                        .on_hover_text("Click to show message history")
+                        .clicked();
+                    if ui
+                        .small_button("✕")
+                        .on_hover_text("Dismiss message")
+                        .clicked()
+                    {
+                        self.state.status.clear();
+                    }
+                }
+
+                if toggled {
+                    self.show_status_history = !self.show_status_history;
+                }
+
+                ui.with_layout(egui::Layout::right_to_left(Align::Center), |ui| {
+                    self.draw_performance_meter(ui);
+                });
+            });
         });
 
+        if self.show_status_history {
+            egui::TopBottomPanel::bottom("status_history")
+                .resizable(true)
+                .default_height(140.0)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.strong("Messages");
+                        egui::ComboBox::from_id_salt("status_history_level")
+                            .selected_text(match self.status_history_level {
+                                MessageType::Info | MessageType::Success => "All",
+                                MessageType::Warning => "Warnings and errors",
+                                MessageType::Error => "Errors only",
+                            })
+                            .show_ui(ui, |ui| {
+                                let level = &mut self.status_history_level;
+                                ui.selectable_value(level, MessageType::Info, "All");
+                                ui.selectable_value(level, MessageType::Warning, "Warnings and errors");
+                                ui.selectable_value(level, MessageType::Error, "Errors only");
+                            });
+                        ui.with_layout(egui::Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button("Close").clicked() {
+                                self.show_status_history = false;
+                            }
+                            if ui.button("Clear").clicked() {
+                                self.state.status.clear_history();
+                            }
+                        });
+                    });
+                    ui.separator();
+
+                    egui::ScrollArea::vertical()
+                        .auto_shrink([false, false])
+                        .show(ui, |ui| {
+                            let level = self.status_history_level;
+                            let mut shown = self
+                                .state
+                                .status
+                                .history()
+                                .filter(|message| message.message_type >= level)
+                                .peekable();
+                            if shown.peek().is_none() {
+                                ui.weak("No messages");
+                            }
+                            // Newest first, so the latest error is at the top
+                            for message in shown.rev() {
+                                ui.horizontal(|ui| {
+                                    ui.weak(format_age(message.created_at.elapsed()));
+                                    ui.colored_label(
+                                        message_color(&message.message_type, ui),
+                                        format!("{:?}", message.message_type),
+                                    );
+                                    ui.label(&message.text);
+                                });
+                            }
+                        });
+                });
+            // Keep the ages ticking while the history is open
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+
         egui::TopBottomPanel::top("transport").show(ctx, |ui| {
             self.draw_transport(ui);
         });
@@ -532,11 +1867,25 @@ impl eframe::App for SupersawApp {
                     }
                 }
             }
+            EditorView::EventList { .. } => {
+                let commands = self.event_list.show(ui, &mut self.state);
+                for command in commands {
+                    if let Err(e) = self.command_manager.execute(command, &mut self.state) {
+                        eprintln!("event_list: Command failed: {}", e);
+                        self.state.status.error(format!("Command failed: {}", e));
+                    }
+                }
+            }
             EditorView::SampleEditor { .. } => {
                 ui.label("Sample Editor (Not Implemented)");
             }
         });
 
+        // Auditioned notes, drained every frame so a preview still ends after leaving the editor
+        for (track_id, message) in self.piano_roll.take_preview_events(ctx) {
+            self.play_on_track(&track_id, &message);
+        }
+
         // Handle plugin browser
         let commands = self.plugin_browser.show(ctx, &mut self.state);
         for command in commands {
@@ -547,6 +1896,19 @@ impl eframe::App for SupersawApp {
             }
         }
 
+        self.draw_midi_import_dialog(ctx);
+        self.draw_tempo_prompt(ctx);
+
+        if let Some((path, settings)) = self.export_dialog.show(ctx, &self.state) {
+            match export_wav(&self.state.project, &settings, &path) {
+                Ok(()) => self
+                    .state
+                    .status
+                    .success(format!("Exported audio to {}", path.display())),
+                Err(e) => self.state.status.error(format!("Failed to export audio: {}", e)),
+            }
+        }
+
         // MIDI editor functionality is now integrated into the piano roll
 
         // Handle file dialogs
@@ -587,6 +1949,7 @@ impl eframe::App for SupersawApp {
                         match Project::load(&file_path) {
                             Ok(project) => {
                                 self.state.project = project;
+                                self.command_manager = CommandManager::default();
                                 self.state.status.success("Project loaded successfully");
                             }
                             Err(e) => {
@@ -617,9 +1980,22 @@ impl eframe::App for SupersawApp {
             }
         }
 
-        // Request continuous repaints while playing
-        if self.state.playing {
+        // Request continuous repaints while playing, and while released notes ring out
+        // so the meters fall back to silence
+        let audio_active = self
+            .audio_output
+            .as_ref()
+            .is_some_and(|output| output.active_voices() > 0);
+        if self.state.playing || audio_active {
             ctx.request_repaint();
+        } else if let Some(remaining) = self
+            .state
+            .status
+            .get_message()
+            .and_then(|message| message.remaining())
+        {
+            // Otherwise frames only follow input; wake once more to clear the status message
+            ctx.request_repaint_after(remaining);
         }
     }
 }
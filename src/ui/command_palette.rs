@@ -0,0 +1,120 @@
+// src/ui/command_palette.rs
+use eframe::egui;
+
+/// Rows visible before the list scrolls
+const VISIBLE_ROWS: f32 = 12.0;
+
+/// Searchable list of actions, opened with Ctrl+P.
+/// The palette only knows labels; the app decides what each one does.
+#[derive(Default)]
+pub struct CommandPalette {
+    open: bool,
+    query: String,
+    /// Highlighted row among the entries matching the query
+    selected: usize,
+}
+
+/// Whether every word of the query appears in the label, ignoring case
+fn matches(label: &str, query: &str) -> bool {
+    let label = label.to_lowercase();
+    query
+        .to_lowercase()
+        .split_whitespace()
+        .all(|word| label.contains(word))
+}
+
+impl CommandPalette {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// Draw the palette over `labels` and return the index of the one picked, if any.
+    /// Arrow keys move the highlight, Enter runs it and Escape closes the palette.
+    pub fn show(&mut self, ctx: &egui::Context, labels: &[String]) -> Option<usize> {
+        if !self.open {
+            return None;
+        }
+
+        let filtered: Vec<usize> = labels
+            .iter()
+            .enumerate()
+            .filter(|(_, label)| matches(label, &self.query))
+            .map(|(index, _)| index)
+            .collect();
+
+        // Take the navigation keys before the text field or the editors see them
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        if escape {
+            self.toggle();
+            return None;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if down {
+            self.selected += 1;
+        }
+        self.selected = self.selected.min(filtered.len().saturating_sub(1));
+
+        let mut picked = enter.then(|| filtered.get(self.selected).copied()).flatten();
+
+        egui::Window::new("Command Palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .fixed_size([420.0, 0.0])
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Type a command…")
+                        .desired_width(f32::INFINITY),
+                );
+                response.request_focus();
+                if response.changed() {
+                    self.selected = 0;
+                }
+                ui.separator();
+
+                if filtered.is_empty() {
+                    ui.weak("No matching commands");
+                }
+                let row_height = ui.spacing().interact_size.y;
+                egui::ScrollArea::vertical()
+                    .max_height(row_height * VISIBLE_ROWS)
+                    .show(ui, |ui| {
+                        for (row, &index) in filtered.iter().enumerate() {
+                            let highlighted = row == self.selected;
+                            let response = ui.add_sized(
+                                [ui.available_width(), row_height],
+                                egui::SelectableLabel::new(highlighted, &labels[index]),
+                            );
+                            if highlighted && (up || down) {
+                                response.scroll_to_me(None);
+                            }
+                            if response.clicked() {
+                                picked = Some(index);
+                            }
+                        }
+                    });
+            });
+
+        if picked.is_some() {
+            self.toggle();
+        }
+        picked
+    }
+}
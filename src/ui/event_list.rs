@@ -0,0 +1,266 @@
+// src/ui/event_list.rs
+use crate::core::*;
+use eframe::egui;
+
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+pub struct EventList {
+    sort_column: SortColumn,
+    sort_ascending: bool,
+    command_collector: CommandCollector,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortColumn {
+    Time,
+    Pitch,
+    Velocity,
+    Duration,
+    Channel,
+}
+
+impl SortColumn {
+    fn label(&self) -> &'static str {
+        match self {
+            SortColumn::Time => "Time",
+            SortColumn::Pitch => "Pitch",
+            SortColumn::Velocity => "Velocity",
+            SortColumn::Duration => "Duration",
+            SortColumn::Channel => "Channel",
+        }
+    }
+}
+
+impl Default for EventList {
+    fn default() -> Self {
+        Self {
+            sort_column: SortColumn::Time,
+            sort_ascending: true,
+            command_collector: CommandCollector::new(),
+        }
+    }
+}
+
+impl EventList {
+    pub fn show(&mut self, ui: &mut egui::Ui, state: &mut DawState) -> Vec<DawCommand> {
+        let (clip_id, track_id) = if let EditorView::EventList { clip_id, track_id } =
+            &state.current_view
+        {
+            (clip_id.clone(), track_id.clone())
+        } else {
+            return Vec::new();
+        };
+
+        // Load MIDI data if needed
+        if let Some(track) = state.project.tracks.iter_mut().find(|t| t.id == track_id) {
+            if let Some(clip @ Clip::Midi { loaded: false, .. }) = track
+                .clips
+                .iter_mut()
+                .find(|c| matches!(c, Clip::Midi { id, .. } if id == &clip_id))
+            {
+                if let Err(e) = clip.load_midi() {
+                    state
+                        .status
+                        .error(format!("Failed to load MIDI data: {}", e));
+                }
+            }
+        }
+
+        // Snapshot the notes so edits can be collected while iterating
+        let mut notes: Vec<Note> = state
+            .project
+            .tracks
+            .iter()
+            .find(|t| t.id == track_id)
+            .and_then(|track| {
+                track
+                    .clips
+                    .iter()
+                    .find(|c| matches!(c, Clip::Midi { id, .. } if id == &clip_id))
+            })
+            .and_then(|clip| match clip {
+                Clip::Midi { midi_data, .. } => midi_data.as_ref(),
+                _ => None,
+            })
+            .map(|store| store.get_notes().cloned().collect())
+            .unwrap_or_default();
+
+        self.sort_notes(&mut notes);
+
+        ui.horizontal(|ui| {
+            ui.heading("Event List");
+            ui.label(format!("{} notes", notes.len()));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("Piano Roll").clicked() {
+                    self.command_collector.add_command(DawCommand::OpenPianoRoll {
+                        clip_id: clip_id.clone(),
+                        track_id: track_id.clone(),
+                    });
+                }
+            });
+        });
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                egui::Grid::new("event_list_grid")
+                    .striped(true)
+                    .num_columns(5)
+                    .spacing(egui::vec2(24.0, 4.0))
+                    .show(ui, |ui| {
+                        for column in [
+                            SortColumn::Time,
+                            SortColumn::Pitch,
+                            SortColumn::Velocity,
+                            SortColumn::Duration,
+                            SortColumn::Channel,
+                        ] {
+                            self.draw_column_header(ui, column);
+                        }
+                        ui.end_row();
+
+                        for note in &notes {
+                            self.draw_note_row(ui, note, &clip_id);
+                            ui.end_row();
+                        }
+                    });
+
+                if notes.is_empty() {
+                    ui.add_space(8.0);
+                    ui.label("This clip has no notes.");
+                }
+            });
+
+        self.command_collector.take_commands()
+    }
+
+    fn draw_column_header(&mut self, ui: &mut egui::Ui, column: SortColumn) {
+        let label = if self.sort_column == column {
+            format!(
+                "{} {}",
+                column.label(),
+                if self.sort_ascending { "⏶" } else { "⏷" }
+            )
+        } else {
+            column.label().to_string()
+        };
+
+        if ui
+            .add(egui::Button::new(egui::RichText::new(label).strong()).frame(false))
+            .clicked()
+        {
+            if self.sort_column == column {
+                self.sort_ascending = !self.sort_ascending;
+            } else {
+                self.sort_column = column;
+                self.sort_ascending = true;
+            }
+        }
+    }
+
+    fn draw_note_row(&mut self, ui: &mut egui::Ui, note: &Note, clip_id: &str) {
+        // Time
+        let mut start_time = note.start_time;
+        let response = ui.add(
+            egui::DragValue::new(&mut start_time)
+                .speed(0.01)
+                .range(0.0..=f64::MAX)
+                .suffix(" s")
+                .max_decimals(3),
+        );
+        if response.changed() {
+            self.command_collector.add_command(DawCommand::ResizeNote {
+                clip_id: clip_id.to_string(),
+                note_id: note.id.clone(),
+                new_start_time: start_time,
+                new_duration: note.duration,
+            });
+        }
+
+        // Pitch
+        let mut key = note.key;
+        let response = ui.add(
+            egui::DragValue::new(&mut key)
+                .speed(0.2)
+                .range(0..=127)
+                .custom_formatter(|value, _| {
+                    let key = value as usize;
+                    format!("{}{} ({})", NOTE_NAMES[key % 12], key as i32 / 12 - 1, key)
+                }),
+        );
+        if response.changed() {
+            self.command_collector.add_command(DawCommand::MoveNotes {
+                clip_id: clip_id.to_string(),
+                note_ids: vec![note.id.clone()],
+                delta_time: 0.0,
+                delta_pitch: (key as i16 - note.key as i16) as i8,
+            });
+        }
+
+        // Velocity
+        let mut velocity = note.velocity;
+        let response = ui.add(egui::DragValue::new(&mut velocity).speed(0.5).range(1..=127));
+        if response.changed() {
+            self.command_collector.add_command(DawCommand::UpdateNoteVelocity {
+                clip_id: clip_id.to_string(),
+                note_id: note.id.clone(),
+                velocity,
+            });
+        }
+
+        // Duration
+        let mut duration = note.duration;
+        let response = ui.add(
+            egui::DragValue::new(&mut duration)
+                .speed(0.01)
+                .range(0.01..=f64::MAX)
+                .suffix(" s")
+                .max_decimals(3),
+        );
+        if response.changed() {
+            self.command_collector.add_command(DawCommand::ResizeNote {
+                clip_id: clip_id.to_string(),
+                note_id: note.id.clone(),
+                new_start_time: note.start_time,
+                new_duration: duration,
+            });
+        }
+
+        // Channel (MIDI channels are displayed 1-based)
+        let mut channel = note.channel + 1;
+        let response = ui.add(egui::DragValue::new(&mut channel).speed(0.1).range(1..=16));
+        if response.changed() {
+            self.command_collector.add_command(DawCommand::SetNoteChannel {
+                clip_id: clip_id.to_string(),
+                note_ids: vec![note.id.clone()],
+                channel: channel - 1,
+            });
+        }
+    }
+
+    fn sort_notes(&self, notes: &mut [Note]) {
+        notes.sort_by(|a, b| {
+            let ordering = match self.sort_column {
+                SortColumn::Time => a.start_time.total_cmp(&b.start_time),
+                SortColumn::Pitch => a.key.cmp(&b.key),
+                SortColumn::Velocity => a.velocity.cmp(&b.velocity),
+                SortColumn::Duration => a.duration.total_cmp(&b.duration),
+                SortColumn::Channel => a.channel.cmp(&b.channel),
+            }
+            // Keep rows stable between frames when the sort keys are equal
+            .then_with(|| a.start_time.total_cmp(&b.start_time))
+            .then_with(|| a.key.cmp(&b.key))
+            .then_with(|| a.id.cmp(&b.id));
+
+            if self.sort_ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        });
+    }
+}
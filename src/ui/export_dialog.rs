@@ -0,0 +1,140 @@
+// src/ui/export_dialog.rs
+use crate::core::*;
+use eframe::egui;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExportRange {
+    WholeProject,
+    LoopRegion,
+    Custom,
+}
+
+pub struct ExportDialog {
+    open: bool,
+    range: ExportRange,
+    custom_start: f64,
+    custom_end: f64,
+    sample_rate: u32,
+}
+
+impl Default for ExportDialog {
+    fn default() -> Self {
+        Self {
+            open: false,
+            range: ExportRange::WholeProject,
+            custom_start: 0.0,
+            custom_end: 0.0,
+            sample_rate: EXPORT_SAMPLE_RATES[0],
+        }
+    }
+}
+
+impl ExportDialog {
+    pub fn open(&mut self, state: &DawState) {
+        self.open = true;
+        if self.custom_end <= self.custom_start {
+            self.custom_end = state.project.end_time();
+        }
+    }
+
+    /// Draw the dialog; returns the chosen file and settings once the user confirms
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &DawState,
+    ) -> Option<(PathBuf, RenderSettings)> {
+        if !self.open {
+            return None;
+        }
+
+        let mut open = self.open;
+        let mut export = false;
+
+        egui::Window::new("Export Audio")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Range:");
+                ui.radio_value(&mut self.range, ExportRange::WholeProject, "Whole project");
+                ui.radio_value(&mut self.range, ExportRange::LoopRegion, "Loop region");
+                ui.radio_value(&mut self.range, ExportRange::Custom, "Custom");
+
+                if self.range == ExportRange::Custom {
+                    ui.horizontal(|ui| {
+                        ui.label("Start:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.custom_start)
+                                .speed(0.1)
+                                .range(0.0..=f64::MAX)
+                                .suffix(" s"),
+                        );
+                        ui.label("End:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.custom_end)
+                                .speed(0.1)
+                                .range(0.0..=f64::MAX)
+                                .suffix(" s"),
+                        );
+                    });
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Sample rate:");
+                    egui::ComboBox::from_id_salt("export_sample_rate")
+                        .selected_text(format!("{} Hz", self.sample_rate))
+                        .show_ui(ui, |ui| {
+                            for rate in EXPORT_SAMPLE_RATES {
+                                ui.selectable_value(
+                                    &mut self.sample_rate,
+                                    rate,
+                                    format!("{} Hz", rate),
+                                );
+                            }
+                        });
+                });
+
+                let (start_time, end_time) = self.time_range(state);
+                ui.label(format!("{:.2}s to {:.2}s", start_time, end_time));
+
+                ui.add_space(8.0);
+                if ui
+                    .add_enabled(end_time > start_time, egui::Button::new("Export..."))
+                    .clicked()
+                {
+                    export = true;
+                }
+            });
+
+        self.open = open;
+        if !export {
+            return None;
+        }
+
+        let path = rfd::FileDialog::new()
+            .set_title("Export Audio")
+            .add_filter("WAV Audio", &["wav"])
+            .set_file_name(format!("{}.wav", state.project.name))
+            .save_file()?;
+
+        self.open = false;
+        let (start_time, end_time) = self.time_range(state);
+        Some((
+            path,
+            RenderSettings {
+                start_time,
+                end_time,
+                sample_rate: self.sample_rate,
+            },
+        ))
+    }
+
+    fn time_range(&self, state: &DawState) -> (f64, f64) {
+        match self.range {
+            ExportRange::WholeProject => (0.0, state.project.end_time()),
+            ExportRange::LoopRegion => (state.loop_start, state.loop_end),
+            ExportRange::Custom => (self.custom_start, self.custom_end),
+        }
+    }
+}
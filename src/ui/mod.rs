@@ -1,4 +1,7 @@
 mod app;
+mod command_palette;
+mod event_list;
+mod export_dialog;
 mod piano_roll;
 mod plugin_browser;
 mod timeline;
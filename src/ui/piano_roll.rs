@@ -1,20 +1,63 @@
 use crate::core::*;
 use eframe::egui;
 use egui::{FontId, StrokeKind};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::time::{Duration, Instant};
 
 const MIDDLE_C: i32 = 60; // MIDI note number for middle C
 const DEFAULT_OCTAVES: i32 = 8; // Number of octaves to show
 const NOTES_PER_OCTAVE: i32 = 12;
+const DEFAULT_KEY_HEIGHT: f32 = 20.0;
+const MIN_NOTE_DURATION: f64 = 0.1; // Shortest note a resize can produce, in seconds
+const MIN_TIME_DELTA: f64 = 0.001; // Smaller note moves than this are not worth a command
+const DEFAULT_NOTE_VELOCITY: u8 = 100;
+const MIN_NOTE_WIDTH: f32 = 2.0; // Short notes are drawn at least this wide when zoomed out
+const MIN_NOTE_HIT_WIDTH: f32 = 12.0; // and can be clicked anywhere this wide
+const WHEEL_PIXELS_PER_VELOCITY: f32 = 10.0; // Alt+wheel travel for one velocity step
+const KEY_WIDTH_RANGE: std::ops::RangeInclusive<f32> = 40.0..=240.0;
+const PREVIEW_NOTE_LENGTH: Duration = Duration::from_millis(300); // How long an auditioned note sounds
+const RULER_HEIGHT: f32 = 30.0;
+const FRAME_MARGIN: f32 = 40.0; // Room left of the first note when a clip is first framed
+const SEEK_ROW_HEIGHT: f32 = 16.0; // Top part of the ruler, with the bar labels
+
+// Computer keyboard layout for step input, one octave starting at `step_base_note`
+const STEP_INPUT_KEYS: [(egui::Key, u8); 13] = [
+    (egui::Key::A, 0),
+    (egui::Key::W, 1),
+    (egui::Key::S, 2),
+    (egui::Key::E, 3),
+    (egui::Key::D, 4),
+    (egui::Key::F, 5),
+    (egui::Key::T, 6),
+    (egui::Key::G, 7),
+    (egui::Key::Y, 8),
+    (egui::Key::H, 9),
+    (egui::Key::U, 10),
+    (egui::Key::J, 11),
+    (egui::Key::K, 12),
+];
+const STEP_REST_KEY: egui::Key = egui::Key::ArrowRight;
+/// Pressed with Alt, shows or hides the automation panel
+const AUTOMATION_TOGGLE_KEY: egui::Key = egui::Key::A;
+/// Pressed with Ctrl, chops the selected notes into pieces at the snap division
+const CHOP_KEY: egui::Key = egui::Key::E;
 
 pub struct PianoRoll {
     key_width: f32,
     key_height: f32,
     grid_snap: bool,
+    snap_override: Option<SnapMode>, // Used instead of the global snap mode when set
     zoom: f32,
     scroll_x: f32,
     scroll_y: f32,
     viewport_height: f32,
+    center_pending: bool, // Centre on `center_key` once the viewport has been measured
+    center_key: f32,
+    viewed_clip: Option<String>,
     selected_notes: Vec<EventID>,
+    time_selection: Option<(f64, f64)>, // (start, end) in clip time, set by dragging in the ruler
+    time_selection_anchor: f64,
     dragging: Option<DragOperation>,
     command_collector: CommandCollector,
     // Automation panel
@@ -22,30 +65,94 @@ pub struct PianoRoll {
     selected_automation_points: Vec<(String, String)>, // (lane_id, point_id)
     automation_scroll_y: f32,
     resizing_divider: bool,
+    resizing_key_column: bool,
+    automation_drag_origin: Option<(f64, f64, egui::Vec2)>, // (point time, point value, pointer travel)
+    automation_line_tool: bool,
+    automation_point_edit: Option<AutomationPointEdit>,
+    thin_tolerance: f64, // Share of a lane's range a thinned curve may deviate by
     // UI state
     show_automation: bool,
+    color_by_velocity: bool,
+    color_by_channel: bool,
+    channel_filter: Option<u8>, // Only notes on this channel can be edited; others are dimmed
+    target_channel: u8,         // Channel "Set channel" moves the selected notes to
+    show_crosshair: bool,
+    absolute_time: bool, // Label positions in arrangement time rather than from the clip start
+    audition_velocity: bool, // Play notes when their velocity bar is hovered
+    // Note preview
+    preview_events: Vec<(String, MidiMessage)>, // (track_id, message) waiting for the app to play
+    sounding_preview: Option<(String, u8, u8, Instant)>, // (track_id, channel, key, started)
+    auditioned: Option<(EventID, u8)>, // (note_id, velocity) last played from the velocity lane
     // Drag state
-    drag_accumulator: f32,
+    velocity_drag: Option<VelocityDrag>, // The dragged bar, applied when released
+    resize_snap_handler: SnapHandler,
     resize_initial_values: Option<(f64, f64)>, // (start_time, duration)
     drag_initial_positions: Option<Vec<(String, f64, u8)>>, // Vec<(note_id, start_time, pitch)>
-    drag_accumulator_x: f32,
+    copying_notes: bool, // Alt was held when the note drag started, so it copies instead of moving
+    move_snap_handler: SnapHandler,
     drag_accumulator_y: f32,
+    velocity_wheel_accumulator: f32,
     last_applied_delta_time: f64,
     last_applied_delta_pitch: i8,
     // CC search
     cc_search_query: String,
+    // Note input
+    randomize_input_velocity: bool,
+    input_velocity_settings: VelocityEditSettings,
+    rng: StdRng,
+    step_input: bool,
+    step_base_note: u8,
+    // Tools
+    tool: PianoRollTool,
+    roll_velocity_ramp: bool,
+    roll_ramp_start_velocity: u8,
+    fit_velocity_range: (u8, u8),
+    groove_templates: Vec<GrooveTemplate>,
+    selected_groove: usize,
+    groove_strength: f64,
+    humanize_ms: f64,
+    /// Vertical note drags move by degrees of `scale` instead of semitones
+    scale_lock: bool,
+    scale: Scale,
+    humanize_chords: bool, // Notes starting together get the same humanize offset
+    humanize_preview: Option<HumanizePreview>,
+}
+
+/// Humanized note starts shown as ghosts until the user applies or cancels them
+struct HumanizePreview {
+    clip_id: String,
+    revision: u64, // Store revision the targets were computed from
+    targets: Vec<(EventID, f64)>, // (note_id, new start_time)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PianoRollTool {
+    /// Click to add notes, drag to move and resize them
+    Draw,
+    /// Drag to fill a pitch with repeated notes at the snap division
+    Roll,
 }
 
 #[derive(Debug)]
 enum DragOperation {
     MovingNotes { start_x: f32, start_y: f32 },
-    ResizingNotes { edge: ResizeEdge, start_x: f32 },
+    ResizingNotes { edge: ResizeEdge },
     Drawing { start_x: f32, start_y: f32 },
+    Rolling { start_time: f64, pitch: u8 },
     SelectionBox { start_x: f32, start_y: f32 },
     MovingAutomationPoint { lane_id: String, point_id: String, start_x: f32, start_y: f32 },
     DrawingAutomation { lane_id: String, start_x: f32, start_y: f32 },
 }
 
+/// Inline editor for typing an automation point's exact value and time
+struct AutomationPointEdit {
+    lane_id: String,
+    point_id: String,
+    value: String,
+    time: String,
+    focus_requested: bool,
+}
+
 #[derive(Debug, Clone, Copy)]
 enum ResizeEdge {
     Left,
@@ -86,29 +193,73 @@ impl NotePositioning {
 impl PianoRoll {
     pub fn default() -> Self {
         Self {
-            key_width: 80.0,
-            key_height: 20.0,
+            key_width: DEFAULT_PIANO_ROLL_KEY_WIDTH,
+            key_height: DEFAULT_KEY_HEIGHT,
             grid_snap: true,
-            zoom: 100.0,
+            snap_override: None,
+            zoom: DEFAULT_PIANO_ROLL_ZOOM,
             scroll_x: 0.0,
             scroll_y: 0.0,
             viewport_height: 0.0,
+            center_pending: true,
+            center_key: MIDDLE_C as f32,
+            viewed_clip: None,
             selected_notes: Vec::new(),
+            time_selection: None,
+            time_selection_anchor: 0.0,
             dragging: None,
             command_collector: CommandCollector::new(),
             automation_panel_height: 200.0,
             selected_automation_points: Vec::new(),
             automation_scroll_y: 0.0,
             resizing_divider: false,
+            resizing_key_column: false,
+            automation_drag_origin: None,
+            automation_line_tool: false,
+            automation_point_edit: None,
+            thin_tolerance: 0.02,
             show_automation: true,
-            drag_accumulator: 0.0,
+            color_by_velocity: false,
+            color_by_channel: false,
+            channel_filter: None,
+            target_channel: 0,
+            show_crosshair: true,
+            absolute_time: false,
+            audition_velocity: false,
+            preview_events: Vec::new(),
+            sounding_preview: None,
+            auditioned: None,
+            velocity_drag: None,
+            resize_snap_handler: SnapHandler::new(DEFAULT_SNAP_THRESHOLD),
             resize_initial_values: None,
             drag_initial_positions: None,
-            drag_accumulator_x: 0.0,
+            copying_notes: false,
+            move_snap_handler: SnapHandler::new(DEFAULT_SNAP_THRESHOLD),
             drag_accumulator_y: 0.0,
+            velocity_wheel_accumulator: 0.0,
             last_applied_delta_time: 0.0,
             last_applied_delta_pitch: 0,
             cc_search_query: String::new(),
+            randomize_input_velocity: false,
+            input_velocity_settings: VelocityEditSettings {
+                randomize: 0.25,
+                ..VelocityEditSettings::default()
+            },
+            rng: StdRng::from_entropy(),
+            step_input: false,
+            step_base_note: MIDDLE_C as u8,
+            tool: PianoRollTool::Draw,
+            roll_velocity_ramp: false,
+            roll_ramp_start_velocity: 40,
+            fit_velocity_range: (40, 120),
+            groove_templates: GrooveTemplate::presets(),
+            selected_groove: 0,
+            groove_strength: 1.0,
+            humanize_ms: 20.0,
+            scale_lock: false,
+            scale: Scale::default(),
+            humanize_chords: true,
+            humanize_preview: None,
         }
     }
     fn ensure_default_automation_lanes(&mut self, clip_id: &str) {
@@ -166,7 +317,6 @@ impl PianoRoll {
         } else {
             return Vec::new();
         };
-        
         // TODO: move into the project.rs - track struct
         // Load MIDI data if needed
         if let Some(track) = state.project.tracks.iter_mut().find(|t| &t.id == &track_id) {
@@ -183,6 +333,18 @@ impl PianoRoll {
                 }
             }
 
+        // A different clip gets its own view: the one it was last shown with, or one
+        // framed around its notes when it is opened for the first time
+        if self.viewed_clip.as_ref() != Some(&clip_id) {
+            self.restore_view(&state.current_view);
+            if self.center_pending {
+                self.frame_content(state, &clip_id);
+            }
+            self.viewed_clip = Some(clip_id.clone());
+            self.time_selection = None;
+            self.channel_filter = None;
+        }
+
         // Get clip start time
         let clip_start =
             if let Some(track) = state.project.tracks.iter().find(|t| &t.id == &track_id) {
@@ -199,6 +361,25 @@ impl PianoRoll {
                 0.0
             };
 
+        self.key_width = state
+            .settings
+            .piano_roll_key_width
+            .clamp(*KEY_WIDTH_RANGE.start(), *KEY_WIDTH_RANGE.end());
+        // A humanize preview is only valid for the notes it was computed from
+        if let Some(preview) = &self.humanize_preview {
+            let revision = clip_track(state, &clip_id)
+                .and_then(|track| midi_store(track, &clip_id))
+                .map(MidiEventStore::revision);
+            if preview.clip_id != clip_id || revision != Some(preview.revision) {
+                self.humanize_preview = None;
+            }
+        }
+
+        self.draw_header(ui, &clip_id, state);
+        if self.step_input {
+            self.handle_step_keys(ui, &clip_id, clip_start, state);
+        }
+
         let full_rect = ui.available_rect_before_wrap();
             
             // Calculate rects for piano roll and automation
@@ -234,23 +415,55 @@ impl PianoRoll {
                 egui::Rect::NOTHING
             };
 
+            // Escape belongs to the point value popup while it is open
+            let point_edit_open = self.automation_point_edit.is_some();
+
             // Draw piano roll in its rect
             ui.allocate_new_ui(egui::UiBuilder::new().max_rect(piano_roll_rect), |ui| {
-                let (rect, response) =
-                    ui.allocate_exact_size(ui.available_size(), egui::Sense::click_and_drag());
+                let area = ui.available_rect_before_wrap();
+                let ruler_rect =
+                    egui::Rect::from_min_size(area.min, egui::vec2(area.width(), RULER_HEIGHT));
+                let rect = egui::Rect::from_min_max(
+                    egui::pos2(area.left(), ruler_rect.bottom()),
+                    area.max,
+                );
+                let response = ui.allocate_rect(rect, egui::Sense::click_and_drag());
 
-                self.center_on_middle_c(rect.height());
+                self.center_view(rect.height());
                 self.draw_grid(ui, rect, state);
+                self.draw_time_selection(ui, rect);
+                if self.show_crosshair {
+                    self.draw_crosshair(ui, rect, clip_start, state);
+                }
                 
                 // Handle note area interactions before drawing notes
                 self.handle_note_area_interaction(ui, rect, &clip_id, &track_id, state, &response);
                 
                 self.draw_notes(ui, rect, &clip_id, &track_id, state);
-                self.draw_piano_keys(ui, rect, state, &clip_id, &track_id);
+                let clicked_key = self.draw_piano_keys(ui, rect, state, &clip_id, &track_id);
+                self.draw_key_column_divider(ui, rect, state);
+                if let Some(pitch) = clicked_key {
+                    if self.step_input {
+                        self.step_input_notes(&[pitch], &clip_id, clip_start, state);
+                    }
+                }
+
+                self.draw_ruler(ui, ruler_rect, clip_start, state);
 
                 // Draw playhead after everything else
-                self.draw_playhead(ui, rect, clip_start, state.current_time);
+                self.draw_playhead(ui, rect, clip_start, state.current_time, state.recording);
+                if state.playing && state.settings.follow_playhead {
+                    self.handle_playhead_autoscroll(rect, clip_start, state.current_time);
+                }
                 
+                // A resize handle can lose its drag if the notes are redrawn in another order
+                if matches!(self.dragging, Some(DragOperation::ResizingNotes { .. }))
+                    && !ui.input(|i| i.pointer.any_down())
+                {
+                    self.dragging = None;
+                }
+                self.draw_drag_feedback(ui, rect, &clip_id, clip_start, state);
+
                 // Draw selection box on top of everything
                 if let Some(DragOperation::SelectionBox { start_x, start_y }) = self.dragging {
                     if let Some(current_pos) = response.interact_pointer_pos() {
@@ -307,7 +520,15 @@ impl PianoRoll {
             }
 
             // Handle keyboard shortcuts
+            let typing = ui.ctx().wants_keyboard_input();
+            let mut back_to_arrangement = false;
             ui.input(|i| {
+                // Alt+A - Show/hide the automation panel
+                if !typing && i.modifiers.alt && i.key_pressed(AUTOMATION_TOGGLE_KEY) {
+                    self.show_automation = !self.show_automation;
+                }
+
+
                 // Delete key - delete selected notes and automation points
                 if i.key_pressed(egui::Key::Delete) || i.key_pressed(egui::Key::Backspace) {
                     if !self.selected_notes.is_empty() {
@@ -328,6 +549,11 @@ impl PianoRoll {
                     }
                 }
                 
+                // Ctrl+E - Chop the selected notes at the grid
+                if !typing && i.key_pressed(CHOP_KEY) && (i.modifiers.ctrl || i.modifiers.command) {
+                    self.chop_selected_notes(&clip_id, state);
+                }
+
                 // Ctrl+A - Select all notes
                 if i.key_pressed(egui::Key::A) && (i.modifiers.ctrl || i.modifiers.command) {
                     self.selected_notes.clear();
@@ -338,26 +564,581 @@ impl PianoRoll {
                         {
                             if let Some(store) = midi_data {
                                 for note in store.get_notes() {
-                                    self.selected_notes.push(note.id.clone());
+                                    if self.shows_channel(note) {
+                                        self.selected_notes.push(note.id.clone());
+                                    }
                                 }
                             }
                         }
                     }
                 }
                 
-                // Escape - Clear selection
-                if i.key_pressed(egui::Key::Escape) {
+                // Escape - Clear selection, or go back to the arrangement when nothing
+                // is selected
+                if i.key_pressed(egui::Key::Escape) && !typing && !point_edit_open {
+                    if self.selected_notes.is_empty() && self.selected_automation_points.is_empty() {
+                        back_to_arrangement = true;
+                    }
                     self.selected_notes.clear();
                     self.selected_automation_points.clear();
                 }
             });
 
-            // Auto-scroll to follow playhead if it's outside view
-            // self.handle_playhead_autoscroll(rect, clip_start, state.current_time);
-
+        self.store_view(state);
+        if back_to_arrangement {
+            state.current_view = EditorView::Arrangement;
+        }
         self.command_collector.take_commands()
     }
 
+    /// Snap mode for edits in the piano roll: the override if one is set, else the global one
+    /// Whether edits snap to the grid right now (Shift or the bypass key turn it off)
+    fn snaps(&self, ctx: &egui::Context) -> bool {
+        self.grid_snap && !snap_bypassed(ctx)
+    }
+
+    fn snap_mode(&self, state: &DawState) -> SnapMode {
+        self.snap_override.unwrap_or(state.snap_mode)
+    }
+
+    fn draw_header(&mut self, ui: &mut egui::Ui, clip_id: &str, state: &DawState) {
+        ui.horizontal_wrapped(|ui| {
+            let follow_label = format!("Global ({})", state.snap_mode.display_name());
+            egui::ComboBox::from_id_salt("piano_roll_snap")
+                .selected_text(match self.snap_override {
+                    Some(snap_mode) => snap_mode.display_name().to_string(),
+                    None => follow_label.clone(),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.snap_override, None, follow_label);
+                    for snap_mode in SnapMode::ALL {
+                        ui.selectable_value(
+                            &mut self.snap_override,
+                            Some(snap_mode),
+                            snap_mode.display_name(),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text("Snap for note edits; follows the arrangement's snap unless set here. Hold Shift or Q while dragging to ignore the grid.");
+
+            ui.separator();
+
+
+            ui.selectable_value(&mut self.tool, PianoRollTool::Draw, "✏ Draw")
+                .on_hover_text("Click to add notes, drag notes to move or resize them");
+            ui.selectable_value(&mut self.tool, PianoRollTool::Roll, "⋯ Roll")
+                .on_hover_text("Drag across a key to fill it with notes at the snap division");
+            if self.tool == PianoRollTool::Roll {
+                ui.checkbox(&mut self.roll_velocity_ramp, "Ramp")
+                    .on_hover_text("Ramp the velocity up across the roll");
+                if self.roll_velocity_ramp {
+                    ui.add(
+                        egui::DragValue::new(&mut self.roll_ramp_start_velocity)
+                            .range(1..=127)
+                            .prefix("From: "),
+                    );
+                }
+            }
+
+            ui.separator();
+
+            ui.toggle_value(&mut self.scale_lock, "Scale lock")
+                .on_hover_text("Dragging notes up or down moves them along the scale, keeping melodies in key");
+            egui::ComboBox::from_id_salt("piano_roll_scale_root")
+                .width(48.0)
+                .selected_text(PITCH_CLASS_NAMES[self.scale.root as usize])
+                .show_ui(ui, |ui| {
+                    for (root, name) in PITCH_CLASS_NAMES.iter().enumerate() {
+                        ui.selectable_value(&mut self.scale.root, root as u8, *name);
+                    }
+                });
+            egui::ComboBox::from_id_salt("piano_roll_scale_mode")
+                .selected_text(self.scale.mode.display_name())
+                .show_ui(ui, |ui| {
+                    for mode in ScaleMode::ALL {
+                        ui.selectable_value(&mut self.scale.mode, mode, mode.display_name());
+                    }
+                });
+
+            ui.separator();
+
+            ui.checkbox(&mut self.randomize_input_velocity, "Randomize velocity")
+                .on_hover_text("Vary the velocity of newly drawn notes so parts sound less mechanical");
+            ui.add_enabled(
+                self.randomize_input_velocity,
+                egui::Slider::new(&mut self.input_velocity_settings.randomize, 0.0..=1.0)
+                    .text("Amount")
+                    .fixed_decimals(2),
+            );
+
+            ui.separator();
+
+            ui.add_enabled(
+                self.snap_mode(state) != SnapMode::None,
+                egui::Checkbox::new(&mut self.step_input, "Step input"),
+            )
+            .on_hover_text(
+                "Enter notes with the piano keys or A-K on the keyboard; the playhead advances one snap step per note. Right arrow inserts a rest.",
+            )
+            .on_disabled_hover_text("Step input needs a snap value to set the note length");
+            if self.step_input {
+                ui.add(
+                    egui::DragValue::new(&mut self.step_base_note)
+                        .range(0..=115)
+                        .speed(0.2)
+                        .prefix("Base note: "),
+                );
+            }
+
+            ui.separator();
+
+            let (mut min, mut max) = self.fit_velocity_range;
+            ui.add(egui::DragValue::new(&mut min).range(1..=127).prefix("Min: "));
+            ui.add(egui::DragValue::new(&mut max).range(1..=127).prefix("Max: "));
+            self.fit_velocity_range = (min, max);
+            if ui
+                .add_enabled(
+                    !self.selected_notes.is_empty() || self.time_selection.is_some(),
+                    egui::Button::new("Fit velocities"),
+                )
+                .on_hover_text("Scale the selected notes' (or the time selection's) velocities to span the range")
+                .clicked()
+            {
+                self.fit_selected_velocities(clip_id, state);
+            }
+
+            ui.separator();
+
+            let selected_name = self
+                .groove_templates
+                .get(self.selected_groove)
+                .map(|groove| groove.name.clone())
+                .unwrap_or_default();
+            egui::ComboBox::from_id_salt("piano_roll_groove")
+                .selected_text(selected_name)
+                .show_ui(ui, |ui| {
+                    for (index, groove) in self.groove_templates.iter().enumerate() {
+                        ui.selectable_value(&mut self.selected_groove, index, &groove.name);
+                    }
+                });
+            ui.add(
+                egui::Slider::new(&mut self.groove_strength, 0.0..=1.0)
+                    .text("Strength")
+                    .fixed_decimals(2),
+            );
+            if ui
+                .button("Apply groove")
+                .on_hover_text("Shift the selected notes (or the time selection, or the whole clip) to the groove's feel")
+                .clicked()
+            {
+                self.apply_groove(clip_id, state);
+            }
+
+            ui.separator();
+
+            ui.add(
+                egui::DragValue::new(&mut self.humanize_ms)
+                    .range(1.0..=100.0)
+                    .prefix("±")
+                    .suffix(" ms"),
+            );
+            if ui
+                .checkbox(&mut self.humanize_chords, "Keep chords")
+                .on_hover_text("Notes that start together move by the same amount")
+                .changed()
+                && self.humanize_preview.is_some()
+            {
+                self.preview_humanize(clip_id, state);
+            }
+            if self.humanize_preview.is_some() {
+                if ui
+                    .button("Apply")
+                    .on_hover_text("Move the notes to the previewed positions")
+                    .clicked()
+                {
+                    self.apply_humanize(clip_id, state);
+                }
+                if ui.button("Cancel").clicked() {
+                    self.humanize_preview = None;
+                }
+            } else if ui
+                .button("Humanize…")
+                .on_hover_text("Preview random timing shifts for the selected notes (or the time selection, or the whole clip)")
+                .clicked()
+            {
+                self.preview_humanize(clip_id, state);
+            }
+
+            ui.separator();
+
+            ui.toggle_value(&mut self.color_by_velocity, "Velocity colors")
+                .on_hover_text("Tint notes from blue (soft) to red (loud) by velocity");
+            ui.toggle_value(&mut self.color_by_channel, "Channel colors")
+                .on_hover_text("Give each MIDI channel its own note color");
+
+            let mut channels: Vec<u8> = clip_track(state, clip_id)
+                .and_then(|track| midi_store(track, clip_id))
+                .map(|store| store.get_notes().map(|note| note.channel).collect())
+                .unwrap_or_default();
+            channels.sort_unstable();
+            channels.dedup();
+            egui::ComboBox::from_id_salt("piano_roll_channel")
+                .selected_text(match self.channel_filter {
+                    Some(channel) => format!("Ch {}", channel + 1),
+                    None => "All channels".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.channel_filter, None, "All channels");
+                    for channel in channels {
+                        ui.selectable_value(
+                            &mut self.channel_filter,
+                            Some(channel),
+                            format!("Ch {}", channel + 1),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text("Edit one channel at a time; notes on other channels are dimmed");
+
+            let mut target_channel = self.target_channel + 1;
+            ui.add(
+                egui::DragValue::new(&mut target_channel)
+                    .range(1..=16)
+                    .speed(0.1)
+                    .prefix("Ch "),
+            );
+            self.target_channel = target_channel - 1;
+            if ui
+                .add_enabled(!self.selected_notes.is_empty(), egui::Button::new("Set channel"))
+                .on_hover_text("Move the selected notes to this MIDI channel")
+                .clicked()
+            {
+                self.command_collector.add_command(DawCommand::SetNoteChannel {
+                    clip_id: clip_id.to_string(),
+                    note_ids: self.selected_notes.clone(),
+                    channel: self.target_channel,
+                });
+            }
+
+            ui.toggle_value(&mut self.show_crosshair, "Crosshair")
+                .on_hover_text("Highlight the key and time under the cursor");
+            ui.toggle_value(&mut self.absolute_time, "Song time")
+                .on_hover_text("Label the ruler and positions in arrangement time instead of from the clip start");
+            ui.toggle_value(&mut self.show_automation, "Automation")
+                .on_hover_text("Show or hide the automation panel (Alt+A)");
+            ui.toggle_value(&mut self.audition_velocity, "Audition")
+                .on_hover_text("Play a note when hovering or dragging its velocity bar");
+        });
+        ui.separator();
+    }
+
+    /// Whether a note is on the channel being edited (always true with no channel filter)
+    fn shows_channel(&self, note: &Note) -> bool {
+        self.channel_filter.is_none_or(|channel| note.channel == channel)
+    }
+
+    /// Whether the header's edit tools act on a note: the selected notes if there are any,
+    /// else the notes starting in the time selection, else every note
+    fn is_tool_target(&self, note: &Note) -> bool {
+        if !self.shows_channel(note) {
+            return false;
+        }
+        if !self.selected_notes.is_empty() {
+            return self.selected_notes.contains(&note.id);
+        }
+        match self.time_selection {
+            Some((start, end)) => note.start_time >= start && note.start_time < end,
+            None => true,
+        }
+    }
+
+    fn fit_selected_velocities(&mut self, clip_id: &str, state: &DawState) {
+        let Some(store) = state.project.tracks.iter().find_map(|track| {
+            track.clips.iter().find_map(|clip| match clip {
+                Clip::Midi { id, midi_data, .. } if id == clip_id => midi_data.as_ref(),
+                _ => None,
+            })
+        }) else {
+            return;
+        };
+
+        let notes: Vec<&Note> = store
+            .get_notes()
+            .filter(|note| self.is_tool_target(note))
+            .collect();
+        let velocities: Vec<u8> = notes.iter().map(|note| note.velocity).collect();
+        let (min, max) = self.fit_velocity_range;
+
+        let commands = notes
+            .iter()
+            .zip(fit_velocities_to_range(&velocities, min, max))
+            .filter(|(note, velocity)| note.velocity != *velocity)
+            .map(|(note, velocity)| DawCommand::UpdateNoteVelocity {
+                clip_id: clip_id.to_string(),
+                note_id: note.id.clone(),
+                velocity,
+            })
+            .collect();
+
+        self.command_collector
+            .add_command(DawCommand::Batch { commands });
+    }
+
+    fn apply_groove(&mut self, clip_id: &str, state: &DawState) {
+        let Some(groove) = self.groove_templates.get(self.selected_groove) else {
+            return;
+        };
+        let Some(store) = state.project.tracks.iter().find_map(|track| {
+            track.clips.iter().find_map(|clip| match clip {
+                Clip::Midi { id, midi_data, .. } if id == clip_id => midi_data.as_ref(),
+                _ => None,
+            })
+        }) else {
+            return;
+        };
+
+        let commands = store
+            .get_notes()
+            .filter(|note| self.is_tool_target(note))
+            .filter_map(|note| {
+                let new_start_time =
+                    groove.apply(note.start_time, state.project.bpm, self.groove_strength);
+                ((new_start_time - note.start_time).abs() > MIN_TIME_DELTA).then(|| {
+                    DawCommand::ResizeNote {
+                        clip_id: clip_id.to_string(),
+                        note_id: note.id.clone(),
+                        new_start_time,
+                        new_duration: note.duration,
+                    }
+                })
+            })
+            .collect();
+
+        self.command_collector
+            .add_command(DawCommand::Batch { commands });
+    }
+
+    /// Cut each selected note at the snap grid lines inside it. Notes shorter than one
+    /// division are left alone.
+    fn chop_selected_notes(&mut self, clip_id: &str, state: &DawState) {
+        let Some(store) = clip_track(state, clip_id).and_then(|track| midi_store(track, clip_id)) else {
+            return;
+        };
+        // Snapping off leaves no grid lines to cut at
+        let Some(division) = self
+            .snap_mode(state)
+            .step(state.project.bpm, state.project.time_signature)
+        else {
+            return;
+        };
+
+        let mut commands = Vec::new();
+        for note in store.get_notes().filter(|note| self.selected_notes.contains(&note.id)) {
+            if note.duration < division - MIN_TIME_DELTA {
+                continue;
+            }
+
+            let end = note.start_time + note.duration;
+            let first_line = (note.start_time / division).floor() as i64 + 1;
+            let mut cuts = vec![note.start_time];
+            cuts.extend(
+                (first_line..)
+                    .map(|line| line as f64 * division)
+                    .take_while(|time| *time < end - MIN_TIME_DELTA)
+                    .filter(|time| *time > note.start_time + MIN_TIME_DELTA),
+            );
+            cuts.push(end);
+            if cuts.len() < 3 {
+                continue;
+            }
+
+            commands.push(DawCommand::DeleteNotes {
+                clip_id: clip_id.to_string(),
+                note_ids: vec![note.id.clone()],
+            });
+            commands.extend(cuts.windows(2).map(|piece| DawCommand::AddNote {
+                clip_id: clip_id.to_string(),
+                start_time: piece[0],
+                duration: piece[1] - piece[0],
+                pitch: note.key,
+                velocity: note.velocity,
+            }));
+        }
+
+        if !commands.is_empty() {
+            // The pieces get new ids, so the old selection no longer points anywhere
+            self.selected_notes.clear();
+            self.command_collector
+                .add_command(DawCommand::Batch { commands });
+        }
+    }
+
+    fn preview_humanize(&mut self, clip_id: &str, state: &DawState) {
+        let Some(store) = clip_track(state, clip_id).and_then(|track| midi_store(track, clip_id)) else {
+            return;
+        };
+
+        let notes: Vec<&Note> = store
+            .get_notes()
+            .filter(|note| self.is_tool_target(note))
+            .collect();
+        let times: Vec<f64> = notes.iter().map(|note| note.start_time).collect();
+        let targets = notes
+            .iter()
+            .zip(humanize_times(
+                &times,
+                self.humanize_ms / 1000.0,
+                self.humanize_chords,
+                &mut self.rng,
+            ))
+            .map(|(note, time)| (note.id.clone(), time))
+            .collect();
+
+        self.humanize_preview = Some(HumanizePreview {
+            clip_id: clip_id.to_string(),
+            revision: store.revision(),
+            targets,
+        });
+    }
+
+    fn apply_humanize(&mut self, clip_id: &str, state: &DawState) {
+        let Some(preview) = self.humanize_preview.take() else {
+            return;
+        };
+        let Some(store) = clip_track(state, clip_id).and_then(|track| midi_store(track, clip_id)) else {
+            return;
+        };
+
+        let commands = preview
+            .targets
+            .into_iter()
+            .filter_map(|(note_id, new_start_time)| {
+                let note = store.get_note(&note_id)?;
+                ((new_start_time - note.start_time).abs() > MIN_TIME_DELTA).then(|| {
+                    DawCommand::ResizeNote {
+                        clip_id: clip_id.to_string(),
+                        note_id,
+                        new_start_time,
+                        new_duration: note.duration,
+                    }
+                })
+            })
+            .collect();
+
+        self.command_collector
+            .add_command(DawCommand::Batch { commands });
+    }
+
+    fn handle_step_keys(&mut self, ui: &egui::Ui, clip_id: &str, clip_start: f64, state: &DawState) {
+        // Leave the keyboard alone while a text field has focus
+        if ui.ctx().wants_keyboard_input() {
+            return;
+        }
+
+        let (pitches, rest) = ui.input(|i| {
+            if i.modifiers.ctrl || i.modifiers.command || i.modifiers.alt {
+                return (Vec::new(), false);
+            }
+            let pitches: Vec<u8> = STEP_INPUT_KEYS
+                .iter()
+                .filter(|(key, _)| i.key_pressed(*key))
+                .map(|(_, offset)| self.step_base_note.saturating_add(*offset).min(127))
+                .collect();
+            (pitches, i.key_pressed(STEP_REST_KEY))
+        });
+
+        if !pitches.is_empty() || rest {
+            self.step_input_notes(&pitches, clip_id, clip_start, state);
+        }
+    }
+
+    /// Add the given notes (a chord, or nothing for a rest) at the playhead and advance it by one snap step
+    fn step_input_notes(&mut self, pitches: &[u8], clip_id: &str, clip_start: f64, state: &DawState) {
+        // Without a snap step there is no note length or distance to advance by
+        let Some(step) = self
+            .snap_mode(state)
+            .step(state.project.bpm, state.project.time_signature)
+        else {
+            return;
+        };
+        let start_time = TimeUtils::snap_time_with_bias(
+            (state.current_time - clip_start).max(0.0),
+            state.project.bpm,
+            state.project.time_signature,
+            self.snap_mode(state),
+            SnapBias::Floor,
+        );
+
+        let mut commands = Vec::with_capacity(pitches.len() + 1);
+        for &pitch in pitches {
+            commands.push(DawCommand::AddNote {
+                clip_id: clip_id.to_string(),
+                start_time,
+                duration: step,
+                pitch,
+                velocity: self.input_velocity(DEFAULT_NOTE_VELOCITY, state, clip_id),
+            });
+        }
+        commands.push(DawCommand::SeekTime {
+            time: clip_start + start_time + step,
+        });
+
+        // One undo step removes the chord and moves the playhead back
+        self.command_collector
+            .add_command(DawCommand::Batch { commands });
+    }
+
+    /// Velocity for a newly entered note, randomized around `base` when enabled and then
+    /// shaped by the track's input velocity curve. The result is baked into the command
+    /// so undo/redo replays the same note.
+    fn input_velocity(&mut self, base: u8, state: &DawState, clip_id: &str) -> u8 {
+        let velocity = if self.randomize_input_velocity {
+            self.input_velocity_settings
+                .randomize_velocity(base, &mut self.rng)
+        } else {
+            base
+        };
+        clip_track(state, clip_id).map_or(velocity, |track| {
+            track.input_velocity_curve.apply_to_velocity(velocity)
+        })
+    }
+
+    /// Start times and velocities of the notes a roll between two times would insert
+    fn roll_notes(&self, from: f64, to: f64, state: &DawState) -> Vec<(f64, u8)> {
+        // A roll repeats notes one snap step apart, so there is nothing to insert with snapping off
+        let Some(step) = self
+            .snap_mode(state)
+            .step(state.project.bpm, state.project.time_signature)
+        else {
+            return Vec::new();
+        };
+        let bpm = state.project.bpm;
+        let start = TimeUtils::snap_time_with_bias(
+            from.min(to).max(0.0),
+            bpm,
+            state.project.time_signature,
+            self.snap_mode(state),
+            SnapBias::Floor,
+        );
+        let end = from.max(to);
+        let count = (((end - start) / step).ceil() as usize).max(1);
+
+        (0..count)
+            .map(|i| {
+                let velocity = if self.roll_velocity_ramp && count > 1 {
+                    let progress = i as f32 / (count - 1) as f32;
+                    let start_velocity = self.roll_ramp_start_velocity as f32;
+                    (start_velocity + (DEFAULT_NOTE_VELOCITY as f32 - start_velocity) * progress)
+                        .round() as u8
+                } else {
+                    DEFAULT_NOTE_VELOCITY
+                };
+                (start + i as f64 * step, velocity)
+            })
+            .collect()
+    }
+
     fn handle_scrolling(&mut self, ui: &egui::Ui, rect: egui::Rect) {
         ui.input(|i| {
             if i.modifiers.shift {
@@ -441,7 +1222,10 @@ impl PianoRoll {
             }
             
             self.drag_initial_positions = Some(initial_positions);
-            self.drag_accumulator_x = 0.0;
+            self.copying_notes = response.ctx.input(|i| i.modifiers.alt);
+            self.move_snap_handler.reset();
+            self.move_snap_handler
+                .set_threshold(state.settings.snap_threshold);
             self.drag_accumulator_y = 0.0;
             self.last_applied_delta_time = 0.0;
             self.last_applied_delta_pitch = 0;
@@ -455,46 +1239,58 @@ impl PianoRoll {
         if response.dragged() {
             if let Some(ref initial_positions) = self.drag_initial_positions {
                 // Accumulate drag deltas
-                self.drag_accumulator_x += response.drag_delta().x;
+                self.move_snap_handler.add_delta(response.drag_delta().x);
                 self.drag_accumulator_y += response.drag_delta().y;
 
                 // Only process if we've exceeded the threshold
-                if self.drag_accumulator_x.abs() >= DRAG_THRESHOLD || 
+                if self.move_snap_handler.get_accumulated().abs() >= DRAG_THRESHOLD || 
                    (self.drag_accumulator_y.abs() / self.key_height) >= PITCH_DRAG_THRESHOLD {
                     
                     // Convert accumulated pixel delta to time and pitch deltas from initial position
-                    let accumulated_time_delta = self.drag_accumulator_x / self.zoom;
+                    let accumulated_time_delta =
+                        (self.move_snap_handler.get_accumulated() / self.zoom) as f64;
                     let accumulated_pitch_delta = -(self.drag_accumulator_y / self.key_height).round() as i8;
 
-                    // Apply snapping less aggressively (only when accumulated drag is significant)
-                    let total_delta_time = if self.grid_snap && self.drag_accumulator_x.abs() > 10.0 {
-                        // Find the first note's initial position to use as reference
-                        if let Some((_, initial_time, _)) = initial_positions.first() {
-                            let new_time = TimeUtils::snap_time(
-                                initial_time + accumulated_time_delta as f64,
+                    // Snap the first note's position and move the rest by the same amount
+                    let total_delta_time = match initial_positions.first() {
+                        Some((_, initial_time, _)) => {
+                            self.move_snap_handler.snap_time_accumulated(
+                                *initial_time,
+                                accumulated_time_delta,
                                 state.project.bpm,
-                                state.snap_mode,
-                            );
-                            new_time - initial_time
-                        } else {
-                            accumulated_time_delta as f64
+                                state.project.time_signature,
+                                self.snap_mode(state),
+                                self.snaps(&response.ctx),
+                            ) - initial_time
                         }
-                    } else {
-                        accumulated_time_delta as f64
+                        None => accumulated_time_delta,
                     };
 
                     // Calculate incremental delta from last applied position
                     let incremental_delta_time = total_delta_time - self.last_applied_delta_time;
                     let incremental_delta_pitch = accumulated_pitch_delta - self.last_applied_delta_pitch;
 
-                    // Only send command if there's an actual change
-                    if incremental_delta_time.abs() > 0.001 || incremental_delta_pitch != 0 {
-                        self.command_collector.add_command(DawCommand::MoveNotes {
-                            clip_id: clip_id.to_string(),
-                            note_ids: self.selected_notes.clone(),
-                            delta_time: incremental_delta_time,
-                            delta_pitch: incremental_delta_pitch,
-                        });
+                    // A copy leaves the originals in place until the drag ends
+                    if self.copying_notes {
+                        self.last_applied_delta_time = total_delta_time;
+                        self.last_applied_delta_pitch = accumulated_pitch_delta;
+                    } else if incremental_delta_time.abs() > MIN_TIME_DELTA || incremental_delta_pitch != 0 {
+                        let command = if self.scale_lock && incremental_delta_pitch != 0 {
+                            self.scale_move_command(
+                                clip_id,
+                                initial_positions,
+                                incremental_delta_time,
+                                accumulated_pitch_delta,
+                            )
+                        } else {
+                            DawCommand::MoveNotes {
+                                clip_id: clip_id.to_string(),
+                                note_ids: self.selected_notes.clone(),
+                                delta_time: incremental_delta_time,
+                                delta_pitch: incremental_delta_pitch,
+                            }
+                        };
+                        self.command_collector.add_command(command);
 
                         // Update last applied deltas
                         self.last_applied_delta_time = total_delta_time;
@@ -506,8 +1302,22 @@ impl PianoRoll {
 
         // Clean up when drag ends
         if response.drag_stopped() {
+            if self.copying_notes {
+                let commands = self.copy_commands(clip_id, state);
+                if !commands.is_empty() {
+                    self.command_collector
+                        .add_command(DawCommand::Batch { commands });
+                }
+            } else if self.drag_initial_positions.is_some() {
+                let trims = monophonic_trims(state, clip_id, &self.selected_notes);
+                if !trims.is_empty() {
+                    self.command_collector
+                        .add_command(DawCommand::Batch { commands: trims });
+                }
+            }
             self.drag_initial_positions = None;
-            self.drag_accumulator_x = 0.0;
+            self.copying_notes = false;
+            self.move_snap_handler.reset();
             self.drag_accumulator_y = 0.0;
             self.last_applied_delta_time = 0.0;
             self.last_applied_delta_pitch = 0;
@@ -515,6 +1325,76 @@ impl PianoRoll {
         }
     }
 
+    /// Key a note at `key` lands on after a vertical drag of `rows` key rows:
+    /// that many semitones, or that many scale degrees with scale lock on
+    fn dragged_key(&self, key: u8, rows: i8) -> u8 {
+        if self.scale_lock {
+            self.scale.transpose(key, rows as i32)
+        } else {
+            (key as i16 + rows as i16).clamp(0, 127) as u8
+        }
+    }
+
+    /// Move the dragged notes on to a scale-locked drag of `rows` degrees. Notes on
+    /// different degrees move by different intervals, so each interval gets a MoveNotes.
+    fn scale_move_command(
+        &self,
+        clip_id: &str,
+        initial_positions: &[(String, f64, u8)],
+        delta_time: f64,
+        rows: i8,
+    ) -> DawCommand {
+        let mut moves: Vec<(i8, Vec<EventID>)> = Vec::new();
+        for (note_id, _, key) in initial_positions {
+            let delta_pitch = self.dragged_key(*key, rows) as i8
+                - self.dragged_key(*key, self.last_applied_delta_pitch) as i8;
+            match moves.iter_mut().find(|(delta, _)| *delta == delta_pitch) {
+                Some((_, note_ids)) => note_ids.push(note_id.clone()),
+                None => moves.push((delta_pitch, vec![note_id.clone()])),
+            }
+        }
+
+        let mut commands: Vec<DawCommand> = moves
+            .into_iter()
+            .map(|(delta_pitch, note_ids)| DawCommand::MoveNotes {
+                clip_id: clip_id.to_string(),
+                note_ids,
+                delta_time,
+                delta_pitch,
+            })
+            .collect();
+        if commands.len() == 1 {
+            commands.remove(0)
+        } else {
+            DawCommand::Batch { commands }
+        }
+    }
+
+    /// Notes to add for an Alt-drag copy: the dragged notes at the current drag offset
+    fn copy_commands(&self, clip_id: &str, state: &DawState) -> Vec<DawCommand> {
+        let delta_time = self.last_applied_delta_time;
+        let delta_pitch = self.last_applied_delta_pitch;
+        if delta_time.abs() <= MIN_TIME_DELTA && delta_pitch == 0 {
+            return Vec::new();
+        }
+        let Some(store) = clip_track(state, clip_id).and_then(|track| midi_store(track, clip_id)) else {
+            return Vec::new();
+        };
+
+        self.selected_notes
+            .iter()
+            .filter_map(|note_id| store.get_note(note_id))
+            .map(|note| DawCommand::AddNote {
+                clip_id: clip_id.to_string(),
+                start_time: (note.start_time + delta_time).max(0.0),
+                duration: note.duration,
+                pitch: self.dragged_key(note.key, delta_pitch),
+                velocity: note.velocity,
+            })
+            .collect()
+    }
+
+    /// Draws the keyboard and returns the key that was clicked, if any
     fn draw_piano_keys(
         &self,
         ui: &mut egui::Ui,
@@ -522,7 +1402,7 @@ impl PianoRoll {
         state: &DawState,
         clip_id: &str,
         track_id: &str,
-    ) {
+    ) -> Option<u8> {
         let keys_rect = rect.intersect(egui::Rect::from_min_size(
             rect.min,
             egui::vec2(self.key_width, rect.height()),
@@ -540,21 +1420,29 @@ impl PianoRoll {
         ui.painter()
             .rect_filled(keys_rect, 0.0, piano_bg_color);
 
+        let mut clicked_key = None;
+
         // Draw white keys first
         for note_number in visible_notes.clone() {
             let note = note_number % 12;
-            if [0, 2, 4, 5, 7, 9, 11].contains(&note) {
-                self.draw_key(ui, note_number as u8, false, keys_rect, &active_notes);
+            if [0, 2, 4, 5, 7, 9, 11].contains(&note)
+                && self.draw_key(ui, note_number as u8, false, keys_rect, &active_notes)
+            {
+                clicked_key = Some(note_number as u8);
             }
         }
 
         // Draw black keys on top
         for note_number in visible_notes {
             let note = note_number % 12;
-            if [1, 3, 6, 8, 10].contains(&note) {
-                self.draw_key(ui, note_number as u8, true, keys_rect, &active_notes);
+            if [1, 3, 6, 8, 10].contains(&note)
+                && self.draw_key(ui, note_number as u8, true, keys_rect, &active_notes)
+            {
+                clicked_key = Some(note_number as u8);
             }
         }
+
+        clicked_key
     }
 
     fn draw_key(
@@ -564,7 +1452,7 @@ impl PianoRoll {
         is_black: bool,
         rect: egui::Rect,
         active_notes: &[u8],
-    ) {
+    ) -> bool {
         let y = rect.bottom() - (note_number as f32 + 1.0) * self.key_height + self.scroll_y;
 
         // Simply adjust width for black keys, always start from left
@@ -674,6 +1562,8 @@ impl PianoRoll {
                 midi_color,
             );
         }
+
+        response.clicked()
     }
 
     fn draw_grid(&self, ui: &mut egui::Ui, rect: egui::Rect, state: &DawState) {
@@ -683,17 +1573,19 @@ impl PianoRoll {
         );
 
         let bpm = state.project.bpm;
-        let beat_duration = 60.0 / bpm;
-        let bar_duration = beat_duration * 4.0;
+        let time_sig = state.project.time_signature;
+        let beats_per_bar = time_sig.0 as i32;
+        let beat_duration = TimeUtils::seconds_per_beat(bpm, time_sig);
 
-        let pixels_per_beat = self.zoom;
-        let pixels_per_bar = pixels_per_beat * 4.0;
+        let pixels_per_beat = self.zoom * beat_duration as f32;
+        let pixels_per_bar = self.zoom * TimeUtils::seconds_per_bar(bpm, time_sig) as f32;
 
         let start_bar = (self.scroll_x / pixels_per_bar).floor() as i32;
         let end_bar = ((self.scroll_x + grid_rect.width()) / pixels_per_bar).ceil() as i32;
 
-        let division = state.snap_mode.get_division(bpm);
-        let subdivisions_per_beat = (beat_duration / division).round() as i32;
+        let division = self.snap_mode(state).get_division(bpm, time_sig);
+        // Bar snap draws no lines between the beats
+        let subdivisions_per_beat = (beat_duration / division).round().max(1.0) as i32;
         let pixels_per_division = pixels_per_beat / subdivisions_per_beat as f32;
 
         for bar in start_bar..=end_bar {
@@ -721,7 +1613,7 @@ impl PianoRoll {
             );
 
             // **Draw beat and subdivision lines**
-            for beat in 0..4 {
+            for beat in 0..beats_per_bar {
                 let beat_x = x + (beat as f32 * pixels_per_beat);
                 let beat_line_color = ui.visuals().window_stroke.color.linear_multiply(0.8);
                 ui.painter().line_segment(
@@ -771,6 +1663,333 @@ impl PianoRoll {
         }
     }
 
+    /// Musical position of a clip-relative time, counted from the clip start or from the
+    /// start of the song depending on the "Song time" toggle
+    fn display_position(&self, time: f64, clip_start: f64, state: &DawState) -> BarBeatTick {
+        let time = if self.absolute_time { clip_start + time } else { time };
+        TimeUtils::time_to_bars_beats(time, state.project.bpm, state.project.time_signature)
+    }
+
+    /// Strip above the notes: the top row shows bars and moves the playhead, the bottom
+    /// row holds the time selection
+    fn draw_ruler(&mut self, ui: &mut egui::Ui, rect: egui::Rect, clip_start: f64, state: &DawState) {
+        ui.painter()
+            .rect_filled(rect, 0.0, ui.visuals().faint_bg_color);
+        let seek_rect =
+            egui::Rect::from_min_size(rect.min, egui::vec2(rect.width(), SEEK_ROW_HEIGHT));
+        let selection_rect =
+            egui::Rect::from_min_max(egui::pos2(rect.left(), seek_rect.bottom()), rect.max);
+
+        self.draw_seek_row(ui, seek_rect, clip_start, state);
+        self.draw_selection_row(ui, selection_rect, state);
+    }
+
+    /// Bar and beat marks with the loop range; clicking or dragging seeks, in arrangement
+    /// time, and dragging near either end scrolls the view
+    fn draw_seek_row(&mut self, ui: &mut egui::Ui, rect: egui::Rect, clip_start: f64, state: &DawState) {
+        const EDGE_SCROLL_MARGIN: f32 = 50.0; // Pixels from edge where scrolling starts
+        const EDGE_SCROLL_SPEED: f32 = 10.0; // Pixels per frame when scrolling
+
+        let response = ui.allocate_rect(rect, egui::Sense::click_and_drag());
+        let left = rect.left() + self.key_width;
+
+        if response.dragged() && !state.playing {
+            if let Some(pos) = response.interact_pointer_pos() {
+                if pos.x < left + EDGE_SCROLL_MARGIN {
+                    self.scroll_x = (self.scroll_x - EDGE_SCROLL_SPEED).max(0.0);
+                } else if pos.x > rect.right() - EDGE_SCROLL_MARGIN {
+                    self.scroll_x += EDGE_SCROLL_SPEED;
+                }
+            }
+        }
+
+        // Seeking outside the loop turns looping off, as in the arrangement
+        if response.clicked() || response.dragged() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let time = ((pos.x - left + self.scroll_x) / self.zoom).max(0.0) as f64;
+                self.command_collector.add_command(DawCommand::SeekTime {
+                    time: clip_start + time,
+                });
+            }
+        }
+
+        let bpm = state.project.bpm;
+        let time_sig = state.project.time_signature;
+        let beat_duration = TimeUtils::seconds_per_beat(bpm, time_sig);
+        let bar_duration = TimeUtils::seconds_per_bar(bpm, time_sig);
+        let pixels_per_beat = self.zoom * beat_duration as f32;
+        let pixels_per_bar = self.zoom * bar_duration as f32;
+        let first_bar = (self.scroll_x / pixels_per_bar).floor().max(0.0) as i32;
+        let last_bar = ((self.scroll_x + rect.width()) / pixels_per_bar).ceil() as i32;
+
+        let painter = ui.painter_at(egui::Rect::from_x_y_ranges(left..=rect.right(), rect.y_range()));
+        if state.loop_enabled {
+            let loop_x = |time: f64| left + (time - clip_start) as f32 * self.zoom - self.scroll_x;
+            let loop_rect = egui::Rect::from_x_y_ranges(
+                loop_x(state.loop_start)..=loop_x(state.loop_end),
+                rect.y_range(),
+            );
+            painter.rect_filled(
+                loop_rect,
+                0.0,
+                ui.visuals().selection.bg_fill.linear_multiply(0.4),
+            );
+        }
+        let tick_color = ui.visuals().weak_text_color();
+        for bar in first_bar..=last_bar {
+            let x = left + bar as f32 * pixels_per_bar - self.scroll_x;
+            painter.line_segment(
+                [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                (1.0, tick_color),
+            );
+            for beat in 1..time_sig.0 {
+                let beat_x = x + beat as f32 * pixels_per_beat;
+                painter.line_segment(
+                    [
+                        egui::pos2(beat_x, rect.bottom() - 4.0),
+                        egui::pos2(beat_x, rect.bottom()),
+                    ],
+                    (1.0, tick_color),
+                );
+            }
+
+            let position = self.display_position(bar as f64 * bar_duration, clip_start, state);
+            let label = if position.beat == 1 && position.tick == 0 {
+                position.bar.to_string()
+            } else {
+                position.to_string()
+            };
+            painter.text(
+                egui::pos2(x + 3.0, rect.center().y),
+                egui::Align2::LEFT_CENTER,
+                label,
+                FontId::monospace(10.0),
+                ui.visuals().text_color(),
+            );
+        }
+
+        response.on_hover_text("Click or drag to move the playhead");
+    }
+
+    /// Dragging sets the time selection for the edit tools, clicking clears it
+    fn draw_selection_row(&mut self, ui: &mut egui::Ui, rect: egui::Rect, state: &DawState) {
+        let response = ui.allocate_rect(rect, egui::Sense::click_and_drag());
+        ui.painter().line_segment(
+            [rect.left_top(), rect.right_top()],
+            (1.0, ui.visuals().widgets.noninteractive.bg_stroke.color),
+        );
+
+        let left = rect.left() + self.key_width;
+        let snap_mode = self.snap_mode(state);
+        let snap = self.snaps(ui.ctx());
+        let pointer_time = |pos: egui::Pos2| {
+            let time = ((pos.x - left + self.scroll_x) / self.zoom).max(0.0) as f64;
+            if snap {
+                TimeUtils::snap_time(
+                    time,
+                    state.project.bpm,
+                    state.project.time_signature,
+                    snap_mode,
+                )
+            } else {
+                time
+            }
+        };
+
+        if response.drag_started() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                self.time_selection_anchor = pointer_time(pos);
+                self.time_selection = None;
+            }
+        } else if response.dragged() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let time = pointer_time(pos);
+                let (start, end) = if time < self.time_selection_anchor {
+                    (time, self.time_selection_anchor)
+                } else {
+                    (self.time_selection_anchor, time)
+                };
+                self.time_selection = (end > start).then_some((start, end));
+            }
+        } else if response.clicked() {
+            self.time_selection = None;
+        }
+
+        if let Some((start, end)) = self.time_selection {
+            let x_start = (left + start as f32 * self.zoom - self.scroll_x).max(left);
+            let x_end = (left + end as f32 * self.zoom - self.scroll_x).min(rect.right());
+            if x_end > x_start {
+                let selection = egui::Rect::from_x_y_ranges(x_start..=x_end, rect.y_range());
+                ui.painter()
+                    .rect_filled(selection, 0.0, ui.visuals().selection.bg_fill);
+            }
+        }
+
+        response.on_hover_text("Drag to select a time range for the edit tools, click to clear it");
+    }
+
+    /// Shades the time selection across the note area
+    fn draw_time_selection(&self, ui: &mut egui::Ui, rect: egui::Rect) {
+        let Some((start, end)) = self.time_selection else {
+            return;
+        };
+        let left = rect.left() + self.key_width;
+        let x_start = (left + start as f32 * self.zoom - self.scroll_x).max(left);
+        let x_end = (left + end as f32 * self.zoom - self.scroll_x).min(rect.right());
+        if x_end > x_start {
+            let selection = egui::Rect::from_x_y_ranges(x_start..=x_end, rect.y_range());
+            ui.painter().rect_filled(
+                selection,
+                0.0,
+                ui.visuals().selection.bg_fill.gamma_multiply(0.15),
+            );
+        }
+    }
+
+    /// Tints the key row and marks the time under the pointer, with a pitch and position readout
+    fn draw_crosshair(&self, ui: &mut egui::Ui, rect: egui::Rect, clip_start: f64, state: &DawState) {
+        let note_area = egui::Rect::from_min_max(
+            egui::pos2(rect.left() + self.key_width, rect.top()),
+            rect.max,
+        );
+        let Some(pos) = ui.ctx().pointer_hover_pos().filter(|pos| note_area.contains(*pos)) else {
+            return;
+        };
+
+        let pitch = ((rect.bottom() - pos.y + self.scroll_y) / self.key_height).floor();
+        if !(0.0..=127.0).contains(&pitch) {
+            return;
+        }
+        let row_top = rect.bottom() - (pitch + 1.0) * self.key_height + self.scroll_y;
+        let row = egui::Rect::from_min_size(
+            egui::pos2(note_area.left(), row_top),
+            egui::vec2(note_area.width(), self.key_height),
+        );
+        let highlight = ui.visuals().selection.bg_fill;
+        ui.painter().rect_filled(row, 0.0, highlight.gamma_multiply(0.15));
+        ui.painter().line_segment(
+            [egui::pos2(pos.x, note_area.top()), egui::pos2(pos.x, note_area.bottom())],
+            (1.0, highlight.gamma_multiply(0.5)),
+        );
+
+        let time = ((pos.x - note_area.left() + self.scroll_x) / self.zoom) as f64;
+        let position = self.display_position(time, clip_start, state);
+        ui.painter().text(
+            pos + egui::vec2(12.0, -12.0),
+            egui::Align2::LEFT_BOTTOM,
+            format!("{}  {}", Self::get_note_name(pitch as i32), position),
+            FontId::proportional(11.0),
+            ui.visuals().weak_text_color(),
+        );
+    }
+
+    /// Cursor and overlay for the drag in progress: where moved notes came from and
+    /// their new position, or the length of a note being resized
+    fn draw_drag_feedback(
+        &self,
+        ui: &mut egui::Ui,
+        rect: egui::Rect,
+        clip_id: &str,
+        clip_start: f64,
+        state: &DawState,
+    ) {
+        let Some(dragging) = &self.dragging else {
+            return;
+        };
+        let cursor = match dragging {
+            DragOperation::MovingNotes { .. } if self.copying_notes => egui::CursorIcon::Copy,
+            DragOperation::MovingNotes { .. } => egui::CursorIcon::Grabbing,
+            DragOperation::ResizingNotes { .. } => egui::CursorIcon::ResizeHorizontal,
+            DragOperation::Drawing { .. }
+            | DragOperation::Rolling { .. }
+            | DragOperation::SelectionBox { .. } => egui::CursorIcon::Crosshair,
+            DragOperation::MovingAutomationPoint { .. } | DragOperation::DrawingAutomation { .. } => {
+                return;
+            }
+        };
+        ui.ctx().set_cursor_icon(cursor);
+
+        let Some(pos) = ui.ctx().pointer_latest_pos() else {
+            return;
+        };
+        let note_area = egui::Rect::from_min_max(
+            egui::pos2(rect.left() + self.key_width, rect.top()),
+            rect.max,
+        );
+        let store = clip_track(state, clip_id).and_then(|track| midi_store(track, clip_id));
+        let (time_sig, bpm) = (state.project.time_signature, state.project.bpm);
+
+        let label = match dragging {
+            DragOperation::MovingNotes { .. } => {
+                let Some(initial_positions) = &self.drag_initial_positions else {
+                    return;
+                };
+
+                // Outlines where the notes started, so the move reads as a distance
+                if !self.copying_notes {
+                    let positioning = NotePositioning::new(
+                        self.zoom,
+                        self.key_height,
+                        self.scroll_x,
+                        self.scroll_y,
+                        note_area,
+                    );
+                    let painter = ui.painter_at(note_area);
+                    let stroke = egui::Stroke::new(1.0, ui.visuals().weak_text_color());
+                    for (note_id, start_time, key) in initial_positions {
+                        let Some(note) = store.and_then(|store| store.get_note(note_id)) else {
+                            continue;
+                        };
+                        let origin = with_min_width(
+                            positioning.note_to_rect(*start_time, *key, note.duration),
+                            MIN_NOTE_WIDTH,
+                        );
+                        painter.rect_stroke(origin, 4.0, stroke, StrokeKind::Inside);
+                    }
+                }
+
+                let Some((_, start_time, key)) = initial_positions.first() else {
+                    return;
+                };
+                let new_key = self.dragged_key(*key, self.last_applied_delta_pitch);
+                let new_time = (start_time + self.last_applied_delta_time).max(0.0);
+                format!(
+                    "{}  {}  ({:+} st)",
+                    Self::get_note_name(new_key as i32),
+                    self.display_position(new_time, clip_start, state),
+                    new_key as i16 - *key as i16,
+                )
+            }
+            DragOperation::ResizingNotes { edge } => {
+                let Some(note) = self
+                    .selected_notes
+                    .first()
+                    .and_then(|note_id| store?.get_note(note_id))
+                else {
+                    return;
+                };
+                let beats = note.duration / TimeUtils::seconds_per_beat(bpm, time_sig);
+                match edge {
+                    ResizeEdge::Left => format!(
+                        "Start: {}  Length: {:.2} beats",
+                        self.display_position(note.start_time, clip_start, state),
+                        beats
+                    ),
+                    ResizeEdge::Right => format!("Length: {:.2} beats", beats),
+                }
+            }
+            _ => return,
+        };
+
+        ui.painter().text(
+            pos + egui::vec2(12.0, 12.0),
+            egui::Align2::LEFT_TOP,
+            label,
+            FontId::proportional(11.0),
+            ui.visuals().strong_text_color(),
+        );
+    }
+
     fn draw_notes(
         &mut self,
         ui: &mut egui::Ui,
@@ -801,15 +2020,27 @@ impl PianoRoll {
                 continue;
             }
 
-            let note_rect = note_position.note_to_rect(note.start_time, note.key, note.duration);
+            let note_rect = with_min_width(
+                note_position.note_to_rect(note.start_time, note.key, note.duration),
+                MIN_NOTE_WIDTH,
+            );
 
             // Draw base note shape
             let is_selected = self.selected_notes.contains(&note.id);
             let color = if is_selected {
                 ui.visuals().selection.bg_fill
+            } else if self.color_by_velocity {
+                velocity_color(note.velocity)
+            } else if self.color_by_channel {
+                channel_color(note.channel)
             } else {
                 egui::Color32::from_rgb(64, 128, 255)
             };
+            let color = if self.shows_channel(note) {
+                color
+            } else {
+                color.gamma_multiply(0.25)
+            };
 
             ui.painter().rect_filled(note_rect, 4.0, color);
 
@@ -817,11 +2048,47 @@ impl PianoRoll {
             self.draw_velocity_indicator(ui, note_rect, note.velocity);
         }
 
+        // Ghosts of the copies an Alt-drag will drop
+        if self.copying_notes {
+            let ghost_stroke = egui::Stroke::new(1.0, ui.visuals().selection.stroke.color);
+            for note in visible_notes.iter().filter(|note| self.selected_notes.contains(&note.id)) {
+                let pitch = self.dragged_key(note.key, self.last_applied_delta_pitch);
+                let ghost_rect = with_min_width(
+                    note_position.note_to_rect(
+                        note.start_time + self.last_applied_delta_time,
+                        pitch,
+                        note.duration,
+                    ),
+                    MIN_NOTE_WIDTH,
+                );
+                ui.painter()
+                    .rect_stroke(ghost_rect, 4.0, ghost_stroke, StrokeKind::Inside);
+            }
+        }
+
+        // Ghosts of where a pending humanize would move the notes
+        if let Some(preview) = &self.humanize_preview {
+            let ghost_stroke = egui::Stroke::new(1.0, ui.visuals().selection.stroke.color);
+            for note in &visible_notes {
+                let Some((_, start_time)) = preview.targets.iter().find(|(id, _)| id == &note.id) else {
+                    continue;
+                };
+                let ghost_rect = with_min_width(
+                    note_position.note_to_rect(*start_time, note.key, note.duration),
+                    MIN_NOTE_WIDTH,
+                );
+                ui.painter()
+                    .rect_stroke(ghost_rect, 4.0, ghost_stroke, StrokeKind::Inside);
+            }
+        }
+
         // Second pass: Handle interactions and overlays
         // Only handle note interactions if we're not currently drawing
         if !matches!(self.dragging, Some(DragOperation::Drawing { .. })) {
             for note in &visible_notes {
-                if !note_position.is_note_visible(note.start_time, note.key, note.duration) {
+                if !note_position.is_note_visible(note.start_time, note.key, note.duration)
+                    || !self.shows_channel(note)
+                {
                     continue;
                 }
 
@@ -843,7 +2110,8 @@ impl PianoRoll {
         state: &DawState,
         note_response: &egui::Response,
     ) {
-        let handle_width = 6.0;  // Made wider for easier grabbing
+        // Made wider for easier grabbing, but narrow notes keep some body to drag by
+        let handle_width = (note_rect.width() / 4.0).min(6.0);
         const DRAG_THRESHOLD: f32 = 3.0;  // Pixels before resize starts
 
         // Create resize handles
@@ -871,13 +2139,29 @@ impl PianoRoll {
         // Initialize resize state when drag starts
         if left_response.drag_started() || right_response.drag_started() {
             self.resize_initial_values = Some((note.start_time, note.duration));
-            self.drag_accumulator = 0.0;
+            self.selected_notes.retain(|id| id != &note.id);
+            self.selected_notes.insert(0, note.id.clone());
+            if self.dragging.is_none() {
+                self.dragging = Some(DragOperation::ResizingNotes {
+                    edge: if left_response.drag_started() {
+                        ResizeEdge::Left
+                    } else {
+                        ResizeEdge::Right
+                    },
+                });
+            }
+            self.resize_snap_handler.reset();
+            self.resize_snap_handler
+                .set_threshold(state.settings.snap_threshold);
         }
 
         // Reset state when drag stops
         if left_response.drag_stopped() || right_response.drag_stopped() {
             self.resize_initial_values = None;
-            self.drag_accumulator = 0.0;
+            self.resize_snap_handler.reset();
+            if matches!(self.dragging, Some(DragOperation::ResizingNotes { .. })) {
+                self.dragging = None;
+            }
         }
 
         if (left_response.dragged() || right_response.dragged())
@@ -890,13 +2174,14 @@ impl PianoRoll {
             };
 
             // Accumulate drag delta
-            self.drag_accumulator += delta;
+            self.resize_snap_handler.add_delta(delta);
 
             // Only process if we've exceeded the threshold
-            if self.drag_accumulator.abs() >= DRAG_THRESHOLD {
+            if self.resize_snap_handler.get_accumulated().abs() >= DRAG_THRESHOLD {
                 if let Some((initial_start, initial_duration)) = self.resize_initial_values {
                     // Convert accumulated pixel delta to time delta
-                    let accumulated_time_delta = self.drag_accumulator / self.zoom;
+                    let accumulated_time_delta =
+                        self.resize_snap_handler.get_accumulated() / self.zoom;
 
                     // Calculate new times based on the accumulated delta
                     let (new_start_time, new_duration) = match edge {
@@ -904,35 +2189,31 @@ impl PianoRoll {
                             let note_end = initial_start + initial_duration;
                             let proposed_start = initial_start - accumulated_time_delta as f64;
 
-                            // Apply snapping less aggressively
-                            let new_start = if self.grid_snap && self.drag_accumulator.abs() > 10.0 {
-                                TimeUtils::snap_time(
-                                    proposed_start.max(0.0).min(note_end - 0.1),
-                                    state.project.bpm,
-                                    state.snap_mode,
-                                )
-                            } else {
-                                proposed_start.max(0.0).min(note_end - 0.1)
-                            };
+                            let new_start = self.resize_snap_handler.resize_start(
+                                proposed_start,
+                                note_end,
+                                MIN_NOTE_DURATION,
+                                state.project.bpm,
+                                state.project.time_signature,
+                                self.snap_mode(state),
+                                self.snaps(ui.ctx()),
+                            );
 
                             let new_duration = note_end - new_start;
                             (new_start, new_duration)
                         }
                         ResizeEdge::Right => {
-                            let proposed_duration = initial_duration + accumulated_time_delta as f64;
-
-                            // Apply snapping less aggressively
-                            let new_duration = if self.grid_snap && self.drag_accumulator.abs() > 10.0 {
-                                let end_time = initial_start + proposed_duration;
-                                let snapped_end = TimeUtils::snap_time(
-                                    end_time.max(initial_start + 0.1),
-                                    state.project.bpm,
-                                    state.snap_mode,
-                                );
-                                snapped_end - initial_start
-                            } else {
-                                proposed_duration.max(0.1)
-                            };
+                            let proposed_end =
+                                initial_start + initial_duration + accumulated_time_delta as f64;
+                            let new_duration = self.resize_snap_handler.resize_end(
+                                initial_start,
+                                proposed_end,
+                                MIN_NOTE_DURATION,
+                                state.project.bpm,
+                                state.project.time_signature,
+                                self.snap_mode(state),
+                                self.snaps(ui.ctx()),
+                            ) - initial_start;
 
                             (initial_start, new_duration)
                         }
@@ -954,6 +2235,38 @@ impl PianoRoll {
         }
     }
 
+    /// Alt+wheel over a note nudges its velocity, or every selected note's if it is selected
+    fn handle_velocity_wheel(&mut self, ui: &egui::Ui, note: &Note, clip_id: &str, state: &DawState) {
+        self.velocity_wheel_accumulator +=
+            ui.input(|i| i.raw_scroll_delta.y) / WHEEL_PIXELS_PER_VELOCITY;
+        let steps = self.velocity_wheel_accumulator.trunc();
+        if steps == 0.0 {
+            return;
+        }
+        self.velocity_wheel_accumulator -= steps;
+
+        let Some(store) = clip_track(state, clip_id).and_then(|track| midi_store(track, clip_id)) else {
+            return;
+        };
+        let commands = store
+            .get_notes()
+            .filter(|other| {
+                if self.selected_notes.contains(&note.id) {
+                    self.selected_notes.contains(&other.id)
+                } else {
+                    other.id == note.id
+                }
+            })
+            .map(|other| DawCommand::UpdateNoteVelocity {
+                clip_id: clip_id.to_string(),
+                note_id: other.id.clone(),
+                velocity: (other.velocity as f32 + steps).clamp(1.0, 127.0) as u8,
+            })
+            .collect();
+        self.command_collector
+            .add_command(DawCommand::Batch { commands });
+    }
+
     fn handle_note_interaction(
         &mut self,
         ui: &mut egui::Ui,
@@ -962,7 +2275,14 @@ impl PianoRoll {
         clip_id: &str,
         state: &DawState,
     ) {
+        let note_rect = with_min_width(note_rect, MIN_NOTE_HIT_WIDTH);
         let response = ui.allocate_rect(note_rect, egui::Sense::click_and_drag());
+        if response.hovered() && ui.input(|i| i.modifiers.alt) {
+            self.handle_velocity_wheel(ui, note, clip_id, state);
+            response
+                .clone()
+                .on_hover_text(format!("Velocity {} (Alt+wheel to change)", note.velocity));
+        }
 
         // Handle selection
         if response.clicked() {
@@ -1011,7 +2331,7 @@ impl PianoRoll {
                             self.selected_notes.clear();
                             for n in &all_notes {
                                 if n.start_time >= min_time && n.start_time <= max_time &&
-                                   n.key >= min_pitch && n.key <= max_pitch {
+                                   n.key >= min_pitch && n.key <= max_pitch && self.shows_channel(n) {
                                     self.selected_notes.push(n.id.clone());
                                 }
                             }
@@ -1054,6 +2374,7 @@ impl PianoRoll {
         rect: egui::Rect,
         clip_start: f64,
         current_time: f64,
+        recording: bool,
     ) {
         let grid_rect = egui::Rect::from_min_max(
             egui::pos2(rect.left() + self.key_width, rect.top()),
@@ -1068,14 +2389,18 @@ impl PianoRoll {
 
         // Only draw if playhead is within view
         if playhead_x >= grid_rect.left() && playhead_x <= grid_rect.right() {
-            // Draw playhead line - use same soft red color as timeline
-            let playhead_color = egui::Color32::from_rgb(220, 80, 80);
+            // Draw playhead line - same soft red as the timeline, stronger while recording
+            let (width, playhead_color) = if recording {
+                (3.0, egui::Color32::from_rgb(255, 40, 40))
+            } else {
+                (2.0, egui::Color32::from_rgb(220, 80, 80))
+            };
             ui.painter().line_segment(
                 [
                     egui::pos2(playhead_x, grid_rect.top()),
                     egui::pos2(playhead_x, grid_rect.bottom()),
                 ],
-                (2.0, playhead_color),
+                (width, playhead_color),
             );
         }
     }
@@ -1185,29 +2510,52 @@ impl PianoRoll {
                                 let pitch = pitch_float.floor() as u8;
                                 
                                 // Snap time to grid if enabled
-                                let snapped_time = if self.grid_snap {
-                                    TimeUtils::snap_time(time, state.project.bpm, state.snap_mode)
+                                let snapped_time = if self.snaps(ui.ctx()) {
+                                    TimeUtils::snap_time(
+                                        time,
+                                        state.project.bpm,
+                                        state.project.time_signature,
+                                        self.snap_mode(state),
+                                    )
                                 } else {
                                     time
                                 };
                                 
-                                // Calculate default duration (quarter note = 1 beat)
-                                let beat_duration = 60.0 / state.project.bpm;
-                                let default_duration = beat_duration; // Quarter note
+                                // Default duration is one beat
+                                let default_duration = TimeUtils::seconds_per_beat(
+                                    state.project.bpm,
+                                    state.project.time_signature,
+                                );
                                 
-                                // Create the note
-                                self.command_collector.add_command(DawCommand::AddNote {
-                                    clip_id: clip_id.to_string(),
-                                    start_time: snapped_time,
-                                    duration: default_duration,
+                                // Create the note, making room among notes it lands on
+                                let velocity = self.input_velocity(DEFAULT_NOTE_VELOCITY, state, clip_id);
+                                let commands = add_note_commands(
+                                    state,
+                                    clip_id,
+                                    snapped_time,
+                                    default_duration,
                                     pitch,
-                                    velocity: 100, // Default velocity
-                                });
+                                    velocity,
+                                );
+                                self.command_collector
+                                    .add_command(DawCommand::Batch { commands });
                             }
                         }
                     }
                 }
                 
+                // Start a roll on drag with the roll tool
+                if response.drag_started()
+                    && self.tool == PianoRollTool::Roll
+                    && self.snap_mode(state) != SnapMode::None
+                    && self.dragging.is_none()
+                    && !ui.input(|i| i.modifiers.shift)
+                {
+                    let time = ((pos.x - note_area.left() + self.scroll_x) / self.zoom) as f64;
+                    let pitch = ((rect.bottom() - pos.y + self.scroll_y) / self.key_height).floor() as u8;
+                    self.dragging = Some(DragOperation::Rolling { start_time: time, pitch });
+                }
+
                 // Start selection box on drag with shift
                 if response.drag_started() && ui.input(|i| i.modifiers.shift) {
                     if self.dragging.is_none() {
@@ -1224,6 +2572,30 @@ impl PianoRoll {
                         Some(DragOperation::SelectionBox { start_x, start_y }) => {
                             // Selection box visual is drawn in the main draw code
                         }
+                        Some(DragOperation::Rolling { start_time, pitch }) => {
+                            // Preview the notes the roll will insert
+                            let time = ((pos.x - note_area.left() + self.scroll_x) / self.zoom) as f64;
+                            let step = self
+                                .snap_mode(state)
+                                .get_division(state.project.bpm, state.project.time_signature);
+                            let positioning = NotePositioning::new(
+                                self.zoom,
+                                self.key_height,
+                                self.scroll_x,
+                                self.scroll_y,
+                                note_area,
+                            );
+                            let painter = ui.painter_at(note_area);
+                            for (note_time, velocity) in self.roll_notes(start_time, time, state) {
+                                let note_rect = positioning.note_to_rect(note_time, pitch, step).shrink(1.0);
+                                let alpha = 60 + (velocity as f32 / 127.0 * 120.0) as u8;
+                                painter.rect_filled(
+                                    note_rect,
+                                    2.0,
+                                    egui::Color32::from_rgba_unmultiplied(100, 150, 255, alpha),
+                                );
+                            }
+                        }
                         _ => {}
                     }
                 }
@@ -1253,6 +2625,7 @@ impl PianoRoll {
                                 let notes_in_box = self.get_visible_notes(note_area, track_id, clip_id, state)
                                     .into_iter()
                                     .filter(|note| {
+                                        self.shows_channel(note) &&
                                         note.start_time < end_time && 
                                         (note.start_time + note.duration) > start_time &&
                                         note.key >= bottom_pitch && 
@@ -1269,6 +2642,25 @@ impl PianoRoll {
                                 }
                             }
                         }
+                        Some(DragOperation::Rolling { start_time, pitch }) => {
+                            let time = ((pos.x - note_area.left() + self.scroll_x) / self.zoom) as f64;
+                            let step = self
+                                .snap_mode(state)
+                                .get_division(state.project.bpm, state.project.time_signature);
+                            let commands = self
+                                .roll_notes(start_time, time, state)
+                                .into_iter()
+                                .map(|(note_time, velocity)| DawCommand::AddNote {
+                                    clip_id: clip_id.to_string(),
+                                    start_time: note_time,
+                                    duration: step,
+                                    pitch,
+                                    velocity: self.input_velocity(velocity, state, clip_id),
+                                })
+                                .collect();
+                            self.command_collector
+                                .add_command(DawCommand::Batch { commands });
+                        }
                         _ => {}
                     }
                     self.dragging = None;
@@ -1287,17 +2679,93 @@ impl PianoRoll {
         format!("{}{}", note_names[note as usize], octave)
     }
 
-    fn center_on_middle_c(&mut self, viewport_height: f32) {
-        // Only center if we haven't initialized the scroll position yet
-        if self.viewport_height != viewport_height {
-            self.viewport_height = viewport_height;
-            let total_height = self.get_total_height();
-            let middle_c_position = (MIDDLE_C as f32) * self.key_height;
-            self.scroll_y = middle_c_position - (viewport_height / 2.0);
+    /// Take over the scroll and zoom a clip was last shown with
+    fn restore_view(&mut self, view: &EditorView) {
+        if let EditorView::PianoRoll {
+            scroll_position,
+            vertical_scroll,
+            horizontal_zoom,
+            vertical_zoom,
+            ..
+        } = view
+        {
+            self.scroll_x = *scroll_position;
+            self.zoom = *horizontal_zoom;
+            self.key_height = DEFAULT_KEY_HEIGHT * vertical_zoom;
+            match vertical_scroll {
+                Some(scroll_y) => {
+                    self.scroll_y = *scroll_y;
+                    self.center_pending = false;
+                }
+                None => self.center_pending = true,
+            }
+        }
+    }
+
+    /// Write the current scroll and zoom back into the view, and remember them for the clip
+    fn store_view(&self, state: &mut DawState) {
+        let EditorView::PianoRoll {
+            clip_id,
+            scroll_position,
+            vertical_scroll,
+            horizontal_zoom,
+            vertical_zoom,
+            ..
+        } = &mut state.current_view
+        else {
+            return;
+        };
+        *scroll_position = self.scroll_x;
+        *vertical_scroll = Some(self.scroll_y);
+        *horizontal_zoom = self.zoom;
+        *vertical_zoom = self.key_height / DEFAULT_KEY_HEIGHT;
+
+        let clip_id = clip_id.clone();
+        state
+            .piano_roll_views
+            .insert(clip_id, state.current_view.clone());
+    }
+
+    /// Scroll to the first note of a clip and aim the vertical centring at the middle
+    /// of its pitch range; an empty clip is centred on middle C
+    fn frame_content(&mut self, state: &DawState, clip_id: &str) {
+        let notes: Vec<&Note> = clip_track(state, clip_id)
+            .and_then(|track| midi_store(track, clip_id))
+            .map(|store| store.get_notes().collect())
+            .unwrap_or_default();
+        let (Some(lowest), Some(highest)) = (
+            notes.iter().map(|n| n.key).min(),
+            notes.iter().map(|n| n.key).max(),
+        ) else {
+            self.center_key = MIDDLE_C as f32;
+            return;
+        };
+
+        self.center_key = (lowest as f32 + highest as f32 + 1.0) / 2.0;
+        let first_start = notes
+            .iter()
+            .map(|n| n.start_time)
+            .fold(f64::INFINITY, f64::min);
+        self.scroll_x = (first_start as f32 * self.zoom - FRAME_MARGIN).max(0.0);
+    }
+
+    fn center_view(&mut self, viewport_height: f32) {
+        if self.viewport_height == viewport_height && !self.center_pending {
+            return;
+        }
 
-            // Clamp scroll position to keep piano roll in view
-            self.scroll_y = self.scroll_y.clamp(0.0, total_height - viewport_height);
+        // Only center when a clip is first shown; later size changes (switching views,
+        // toggling the automation panel) keep the current scroll position
+        if self.center_pending {
+            let center_position = self.center_key * self.key_height;
+            self.scroll_y = center_position - (viewport_height / 2.0);
+            self.center_pending = false;
         }
+        self.viewport_height = viewport_height;
+
+        // Clamp scroll position to keep piano roll in view
+        let total_height = self.get_total_height();
+        self.scroll_y = self.scroll_y.clamp(0.0, (total_height - viewport_height).max(0.0));
     }
 
     fn draw_divider(&mut self, ui: &mut egui::Ui, rect: egui::Rect) {
@@ -1332,6 +2800,38 @@ impl PianoRoll {
         }
     }
 
+    /// Drag handle on the key column's right edge; the width is kept in the settings
+    fn draw_key_column_divider(&mut self, ui: &mut egui::Ui, rect: egui::Rect, state: &mut DawState) {
+        let handle = egui::Rect::from_min_size(
+            egui::pos2(rect.left() + self.key_width - 2.0, rect.top()),
+            egui::vec2(4.0, rect.height()),
+        );
+        let response = ui.allocate_rect(handle, egui::Sense::drag());
+
+        if response.hovered() || self.resizing_key_column {
+            ui.painter()
+                .rect_filled(handle, 0.0, ui.visuals().selection.stroke.color);
+            ui.output_mut(|o| o.cursor_icon = egui::CursorIcon::ResizeHorizontal);
+        }
+
+        if response.drag_started() {
+            self.resizing_key_column = true;
+        }
+
+        if self.resizing_key_column {
+            self.key_width = (self.key_width + response.drag_delta().x)
+                .clamp(*KEY_WIDTH_RANGE.start(), *KEY_WIDTH_RANGE.end());
+            state.settings.piano_roll_key_width = self.key_width;
+        }
+
+        if response.drag_stopped() {
+            self.resizing_key_column = false;
+            if let Err(e) = state.settings.save() {
+                state.status.error(format!("Failed to save settings: {}", e));
+            }
+        }
+    }
+
     fn draw_automation_panel(&mut self, ui: &mut egui::Ui, rect: egui::Rect, clip_id: &str, track_id: &str, state: &mut DawState) {
         let header_height = 30.0;
         let lane_gap = 2.0;
@@ -1456,6 +2956,9 @@ impl PianoRoll {
                         });
                 });
                 
+                ui.toggle_value(&mut self.automation_line_tool, "Line")
+                    .on_hover_text("Drag across a lane to draw a straight ramp, replacing the points under it");
+
                 ui.separator();
                 
                 // Quick toggle buttons for existing lanes
@@ -1622,9 +3125,8 @@ impl PianoRoll {
         
         // Draw grid lines (aligned with piano roll)
         let bpm = state.project.bpm;
-        let beat_duration = 60.0 / bpm;
-        let pixels_per_beat = self.zoom;
-        let pixels_per_bar = pixels_per_beat * 4.0;
+        let pixels_per_bar =
+            self.zoom * TimeUtils::seconds_per_bar(bpm, state.project.time_signature) as f32;
         
         let start_bar = (self.scroll_x / pixels_per_bar).floor() as i32;
         let end_bar = ((self.scroll_x + grid_rect.width()) / pixels_per_bar).ceil() as i32;
@@ -1762,6 +3264,7 @@ impl PianoRoll {
                     
                     // Handle drag start - select immediately on mouse down if not already selected
                     if point_response.drag_started() {
+                        self.automation_drag_origin = Some((point.time, point.value, egui::Vec2::ZERO));
                         if !is_selected {
                             if ui.input(|i| i.modifiers.ctrl || i.modifiers.command) {
                                 // Add to selection
@@ -1797,13 +3300,33 @@ impl PianoRoll {
                         let is_selected_now = self.selected_automation_points.iter()
                             .any(|(lid, pid)| lid == &lane_id && pid == &point_id);
                             
-                        if is_selected_now {
-                            let delta_x = point_response.drag_delta().x / self.zoom;
-                            let delta_y = -point_response.drag_delta().y / rect.height();
-                            
-                            let new_time = (point.time + delta_x as f64).max(0.0);
+                        if let (true, Some((origin_time, origin_value, travel))) =
+                            (is_selected_now, self.automation_drag_origin.as_mut())
+                        {
+                            // Work from the drag's origin so snapping doesn't swallow small moves
+                            *travel += point_response.drag_delta();
+                            let delta_x = travel.x / self.zoom;
+                            let delta_y = -travel.y / rect.height();
+
+                            let new_time = (*origin_time + delta_x as f64).max(0.0);
                             let delta_value = delta_y as f64 * (lane.max_value - lane.min_value);
-                            let new_value = (point.value + delta_value).clamp(lane.min_value, lane.max_value);
+                            let new_value = (*origin_value + delta_value).clamp(lane.min_value, lane.max_value);
+
+                            // Shift drags freely, like the other grid-snapped edits
+                            let (new_time, new_value) = if self.snaps(ui.ctx()) {
+                                (
+                                    TimeUtils::snap_time(
+                                        new_time,
+                                        state.project.bpm,
+                                        state.project.time_signature,
+                                        self.snap_mode(state),
+                                    ),
+                                    lane.snap_value(new_value),
+                                )
+                            } else {
+                                (new_time, new_value)
+                            };
+                            self.draw_automation_value_tip(ui, point_rect.center(), new_value);
                             
                             // Update the point using command
                             self.command_collector.add_command(DawCommand::UpdateAutomationPoint {
@@ -1815,9 +3338,83 @@ impl PianoRoll {
                             });
                         }
                     }
+
+                    if point_response.drag_stopped() {
+                        self.automation_drag_origin = None;
+                    }
+
+                    if point_response.double_clicked() {
+                        self.automation_point_edit = Some(AutomationPointEdit {
+                            lane_id: lane_id.clone(),
+                            point_id: point_id.clone(),
+                            value: format!("{}", point.value),
+                            time: format!("{:.3}", point.time),
+                            focus_requested: false,
+                        });
+                    }
+
+                    point_response.context_menu(|ui| {
+                        if ui.button("Reset Value").clicked() {
+                            self.command_collector.add_command(DawCommand::UpdateAutomationPoint {
+                                clip_id: clip_id.to_string(),
+                                lane_id: lane_id.clone(),
+                                point_id: point_id.clone(),
+                                time: None,
+                                value: Some(lane.default_value),
+                            });
+                            ui.close_menu();
+                        }
+                    });
                 }
             }
         }
+
+        self.draw_automation_point_editor(ui, rect, clip_id, &lane);
+
+        if self.automation_line_tool && !is_velocity_lane {
+            self.handle_automation_line(ui, &response, rect, clip_id, &lane, state);
+        }
+
+        // Velocity lanes show note velocities rather than points, so there's nothing to clear
+        if !is_velocity_lane {
+            response.context_menu(|ui| {
+                if ui
+                    .add_enabled(!lane.points.is_empty(), egui::Button::new("Clear Lane"))
+                    .clicked()
+                {
+                    self.clear_automation_lane(clip_id, &lane);
+                    ui.close_menu();
+                }
+
+                ui.separator();
+                ui.add(
+                    egui::Slider::new(&mut self.thin_tolerance, 0.0..=0.1)
+                        .text("Tolerance")
+                        .fixed_decimals(3),
+                );
+                let redundant = lane.redundant_points(self.thin_tolerance);
+                if ui
+                    .add_enabled(
+                        !redundant.is_empty(),
+                        egui::Button::new(format!("Thin Points ({})", redundant.len())),
+                    )
+                    .on_hover_text("Remove points that sit on the line between their neighbours")
+                    .clicked()
+                {
+                    let points: Vec<_> = redundant
+                        .into_iter()
+                        .map(|point_id| (lane.id.clone(), point_id))
+                        .collect();
+                    self.selected_automation_points
+                        .retain(|selection| !points.contains(selection));
+                    self.command_collector.add_command(DawCommand::DeleteAutomationPoints {
+                        clip_id: clip_id.to_string(),
+                        points,
+                    });
+                    ui.close_menu();
+                }
+            });
+        }
         
         // Handle creating new points
         if response.clicked() && !response.dragged() {
@@ -1844,6 +3441,230 @@ impl PianoRoll {
         self.draw_automation_playhead(ui, rect, state.current_time);
     }
 
+    /// Popup next to the point being edited; Enter applies the typed value and time,
+    /// Escape closes it without changes
+    fn draw_automation_point_editor(
+        &mut self,
+        ui: &egui::Ui,
+        rect: egui::Rect,
+        clip_id: &str,
+        lane: &AutomationLane,
+    ) {
+        let Some(edit) = &mut self.automation_point_edit else {
+            return;
+        };
+        if edit.lane_id != lane.id {
+            return;
+        }
+        let Some(point) = lane.points.iter().find(|p| p.id == edit.point_id) else {
+            self.automation_point_edit = None;
+            return;
+        };
+
+        let range = lane.max_value - lane.min_value;
+        let anchor = egui::pos2(
+            rect.left() + point.time as f32 * self.zoom - self.scroll_x + 8.0,
+            rect.bottom() - ((point.value - lane.min_value) / range) as f32 * rect.height(),
+        );
+
+        let mut submitted = false;
+        egui::Area::new(egui::Id::new("automation_point_edit"))
+            .fixed_pos(anchor)
+            .order(egui::Order::Foreground)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Value:");
+                        let value_edit =
+                            ui.add(egui::TextEdit::singleline(&mut edit.value).desired_width(50.0));
+                        ui.label("Time:");
+                        let time_edit =
+                            ui.add(egui::TextEdit::singleline(&mut edit.time).desired_width(50.0));
+                        ui.label("s");
+
+                        if !edit.focus_requested {
+                            value_edit.request_focus();
+                            edit.focus_requested = true;
+                        }
+                        submitted = (value_edit.lost_focus() || time_edit.lost_focus())
+                            && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    });
+                });
+            });
+
+        if submitted {
+            let value = edit.value.trim().parse::<f64>().ok();
+            let time = edit.time.trim().parse::<f64>().ok().map(|t| t.max(0.0));
+            if value.is_some() || time.is_some() {
+                self.command_collector.add_command(DawCommand::UpdateAutomationPoint {
+                    clip_id: clip_id.to_string(),
+                    lane_id: edit.lane_id.clone(),
+                    point_id: edit.point_id.clone(),
+                    time,
+                    value,
+                });
+            }
+            self.automation_point_edit = None;
+        } else if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.automation_point_edit = None;
+        }
+    }
+
+    /// Line tool: dragging across a lane replaces the points under the drag with a
+    /// straight ramp between its two ends
+    fn handle_automation_line(
+        &mut self,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        rect: egui::Rect,
+        clip_id: &str,
+        lane: &AutomationLane,
+        state: &DawState,
+    ) {
+        if response.drag_started() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                self.dragging = Some(DragOperation::DrawingAutomation {
+                    lane_id: lane.id.clone(),
+                    start_x: pos.x,
+                    start_y: pos.y,
+                });
+            }
+        }
+
+        let Some(DragOperation::DrawingAutomation {
+            lane_id,
+            start_x,
+            start_y,
+        }) = &self.dragging
+        else {
+            return;
+        };
+        if *lane_id != lane.id {
+            return;
+        }
+        let Some(pos) = ui.input(|i| i.pointer.interact_pos()) else {
+            return;
+        };
+
+        let (zoom, scroll_x) = (self.zoom, self.scroll_x);
+        let range = lane.max_value - lane.min_value;
+        let snap = self.snaps(ui.ctx());
+        let to_point = |pos: egui::Pos2| {
+            let time = (((pos.x - rect.left() + scroll_x) / zoom) as f64).max(0.0);
+            let normalized = ((rect.bottom() - pos.y) / rect.height()).clamp(0.0, 1.0) as f64;
+            let value = lane.min_value + normalized * range;
+            if snap {
+                (
+                    TimeUtils::snap_time(
+                        time,
+                        state.project.bpm,
+                        state.project.time_signature,
+                        self.snap_mode(state),
+                    ),
+                    lane.snap_value(value),
+                )
+            } else {
+                (time, value)
+            }
+        };
+        let to_pos = |(time, value): (f64, f64)| {
+            egui::pos2(
+                rect.left() + time as f32 * zoom - scroll_x,
+                rect.bottom() - ((value - lane.min_value) / range) as f32 * rect.height(),
+            )
+        };
+
+        let start = to_point(egui::pos2(*start_x, *start_y));
+        let end = to_point(pos);
+        let ((start_time, start_value), (end_time, end_value)) = if start.0 <= end.0 {
+            (start, end)
+        } else {
+            (end, start)
+        };
+
+        if response.drag_stopped() {
+            self.dragging = None;
+            if end_time <= start_time {
+                return;
+            }
+
+            let replaced: Vec<_> = lane
+                .points
+                .iter()
+                .filter(|p| p.time >= start_time && p.time <= end_time)
+                .map(|p| (lane.id.clone(), p.id.clone()))
+                .collect();
+            let mut commands = Vec::new();
+            if !replaced.is_empty() {
+                self.selected_automation_points
+                    .retain(|selection| !replaced.contains(selection));
+                commands.push(DawCommand::DeleteAutomationPoints {
+                    clip_id: clip_id.to_string(),
+                    points: replaced,
+                });
+            }
+            for (time, value) in [(start_time, start_value), (end_time, end_value)] {
+                commands.push(DawCommand::AddAutomationPoint {
+                    clip_id: clip_id.to_string(),
+                    lane_id: lane.id.clone(),
+                    time,
+                    value,
+                });
+            }
+            self.command_collector
+                .add_command(DawCommand::Batch { commands });
+        } else if response.dragged() {
+            ui.painter().line_segment(
+                [to_pos(start), to_pos(end)],
+                egui::Stroke::new(2.0, ui.visuals().selection.stroke.color),
+            );
+        }
+    }
+
+    /// Value readout shown next to an automation point while it's dragged
+    fn draw_automation_value_tip(&self, ui: &egui::Ui, anchor: egui::Pos2, value: f64) {
+        let painter = ui.ctx().layer_painter(egui::LayerId::new(
+            egui::Order::Tooltip,
+            egui::Id::new("automation_value_tip"),
+        ));
+        let galley = painter.layout_no_wrap(
+            format!("{:.2}", value),
+            egui::FontId::proportional(11.0),
+            ui.visuals().strong_text_color(),
+        );
+        let text_rect = egui::Rect::from_min_size(
+            anchor + egui::vec2(8.0, -galley.size().y - 8.0),
+            galley.size(),
+        );
+        painter.rect_filled(text_rect.expand(3.0), 3.0, ui.visuals().window_fill);
+        painter.galley(text_rect.min, galley, ui.visuals().strong_text_color());
+    }
+
+    /// Remove every point from a lane, leaving a single point at the lane's default value
+    fn clear_automation_lane(&mut self, clip_id: &str, lane: &AutomationLane) {
+        let points = lane
+            .points
+            .iter()
+            .map(|point| (lane.id.clone(), point.id.clone()))
+            .collect();
+        self.command_collector.add_command(DawCommand::Batch {
+            commands: vec![
+                DawCommand::DeleteAutomationPoints {
+                    clip_id: clip_id.to_string(),
+                    points,
+                },
+                DawCommand::AddAutomationPoint {
+                    clip_id: clip_id.to_string(),
+                    lane_id: lane.id.clone(),
+                    time: 0.0,
+                    value: lane.default_value,
+                },
+            ],
+        });
+        self.selected_automation_points
+            .retain(|(lane_id, _)| *lane_id != lane.id);
+    }
+
     fn draw_velocity_bars(&mut self, ui: &mut egui::Ui, rect: egui::Rect, lane_id: &str, clip_id: &str, state: &DawState) {
         // Get the current clip's MIDI data
         if let EditorView::PianoRoll { clip_id, track_id, .. } = &state.current_view {
@@ -1858,13 +3679,19 @@ impl PianoRoll {
                         let notes = store.get_notes_in_range(start_time as f64, end_time as f64);
                         
                         // Draw velocity bar for each note
+                        let mut hovered_note = None;
                         for note in notes {
+                            // A bar being dragged shows the velocity it will be set to on release
+                            let velocity = match &self.velocity_drag {
+                                Some(drag) if drag.note_id == note.id => drag.velocity(),
+                                _ => note.velocity,
+                            };
                             let x_start = rect.left() + (note.start_time as f32 * self.zoom) - self.scroll_x;
                             let x_end = rect.left() + ((note.start_time + note.duration) as f32 * self.zoom) - self.scroll_x;
                             let bar_width = (x_end - x_start).max(2.0);
                             
                             // Calculate bar height based on velocity
-                            let velocity_normalized = note.velocity as f32 / 127.0;
+                            let velocity_normalized = velocity as f32 / 127.0;
                             let bar_height = velocity_normalized * rect.height();
                             
                             // Draw the velocity bar
@@ -1873,14 +3700,7 @@ impl PianoRoll {
                                 egui::vec2(bar_width, bar_height),
                             );
                             
-                            // Color based on velocity
-                            let color = egui::Color32::from_rgb(
-                                (255.0 * velocity_normalized) as u8,
-                                (100.0 + 100.0 * (1.0 - velocity_normalized)) as u8,
-                                (255.0 * (1.0 - velocity_normalized)) as u8,
-                            );
-                            
-                            ui.painter().rect_filled(bar_rect, 2.0, color);
+                            ui.painter().rect_filled(bar_rect, 2.0, velocity_color(velocity));
                             
                             // Draw outline
                             ui.painter().rect_stroke(
@@ -1892,36 +3712,99 @@ impl PianoRoll {
                             
                             // Handle interaction
                             let bar_response = ui.allocate_rect(bar_rect, egui::Sense::drag());
+                            if bar_response.drag_started() {
+                                self.velocity_drag = Some(VelocityDrag::new(note.id.clone(), note.velocity));
+                            }
                             if bar_response.dragged() {
-                                let delta_y = -bar_response.drag_delta().y;
-                                let new_velocity_normalized = ((bar_height + delta_y) / rect.height()).clamp(0.0, 1.0);
-                                let new_velocity = (new_velocity_normalized * 127.0).max(1.0) as u8;
-                                
-                                // Update note velocity through command system
-                                self.command_collector.add_command(DawCommand::UpdateNoteVelocity {
-                                    clip_id: clip_id.clone(),
-                                    note_id: note.id.clone(),
-                                    velocity: new_velocity,
-                                });
+                                if let Some(drag) = &mut self.velocity_drag {
+                                    drag.drag_by(-bar_response.drag_delta().y, rect.height());
+                                }
+                            }
+                            // The whole drag becomes one command, so it undoes in one step
+                            if bar_response.drag_stopped() {
+                                if let Some(command) = self.velocity_drag.take().and_then(|drag| drag.finish(clip_id)) {
+                                    self.command_collector.add_command(command);
+                                }
                             }
                             
+                            if bar_response.hovered() || bar_response.dragged() {
+                                hovered_note = Some(Note {
+                                    velocity,
+                                    ..note.clone()
+                                });
+                            }
+
                             // Show velocity value on hover
                             if bar_response.hovered() {
                                 ui.painter().text(
                                     egui::pos2(x_start + bar_width / 2.0, rect.bottom() - bar_height - 10.0),
                                     egui::Align2::CENTER_BOTTOM,
-                                    format!("{}", note.velocity),
+                                    format!("{}", velocity),
                                     egui::FontId::proportional(10.0),
                                     ui.visuals().text_color(),
                                 );
                             }
                         }
+
+                        // Replay when another bar is hovered or the velocity changes
+                        match hovered_note {
+                            Some(note) if self.audition_velocity => {
+                                let current = (note.id.clone(), note.velocity);
+                                if self.auditioned.as_ref() != Some(&current) {
+                                    self.preview_note(track_id, note.channel, note.key, note.velocity);
+                                    self.auditioned = Some(current);
+                                }
+                            }
+                            _ => self.auditioned = None,
+                        }
                     }
                 }
             }
         }
     }
 
+    /// Queue a short note for the app to play on the track, cutting off any preview
+    /// still sounding
+    fn preview_note(&mut self, track_id: &str, channel: u8, key: u8, velocity: u8) {
+        self.stop_preview();
+        self.preview_events.push((
+            track_id.to_string(),
+            MidiMessage::NoteOn {
+                channel,
+                key,
+                velocity,
+            },
+        ));
+        self.sounding_preview = Some((track_id.to_string(), channel, key, Instant::now()));
+    }
+
+    fn stop_preview(&mut self) {
+        if let Some((track_id, channel, key, _)) = self.sounding_preview.take() {
+            self.preview_events.push((
+                track_id,
+                MidiMessage::NoteOff {
+                    channel,
+                    key,
+                    velocity: 0,
+                },
+            ));
+        }
+    }
+
+    /// Note on/off messages for auditioned notes, to be played on the given tracks.
+    /// Ends a preview once it has sounded long enough.
+    pub fn take_preview_events(&mut self, ctx: &egui::Context) -> Vec<(String, MidiMessage)> {
+        if let Some((_, _, _, started)) = &self.sounding_preview {
+            let elapsed = started.elapsed();
+            if elapsed >= PREVIEW_NOTE_LENGTH {
+                self.stop_preview();
+            } else {
+                ctx.request_repaint_after(PREVIEW_NOTE_LENGTH - elapsed);
+            }
+        }
+        std::mem::take(&mut self.preview_events)
+    }
+
     fn draw_automation_playhead(&self, ui: &mut egui::Ui, rect: egui::Rect, current_time: f64) {
         // Use the same calculation as the piano roll playhead
         let playhead_x = rect.left() + (current_time as f32 * self.zoom) - self.scroll_x;
@@ -1972,3 +3855,154 @@ impl PianoRoll {
         }
     }
 }
+
+/// Widens a note rect to the right so it's at least `min_width` wide
+fn with_min_width(rect: egui::Rect, min_width: f32) -> egui::Rect {
+    if rect.width() >= min_width {
+        return rect;
+    }
+    egui::Rect::from_min_size(rect.min, egui::vec2(min_width, rect.height()))
+}
+
+/// Blue for soft notes through to red for loud ones
+fn velocity_color(velocity: u8) -> egui::Color32 {
+    let velocity_normalized = velocity as f32 / 127.0;
+    egui::Color32::from_rgb(
+        (255.0 * velocity_normalized) as u8,
+        (100.0 + 100.0 * (1.0 - velocity_normalized)) as u8,
+        (255.0 * (1.0 - velocity_normalized)) as u8,
+    )
+}
+
+/// A distinct hue for each of the 16 MIDI channels
+fn channel_color(channel: u8) -> egui::Color32 {
+    let hue = (channel % 16) as f32 / 16.0;
+    egui::ecolor::Hsva::new(hue, 0.6, 0.95, 1.0).into()
+}
+
+fn clip_track<'a>(state: &'a DawState, clip_id: &str) -> Option<&'a Track> {
+    state
+        .project
+        .tracks
+        .iter()
+        .find(|track| track.clips.iter().any(|clip| clip.id() == clip_id))
+}
+
+fn midi_store<'a>(track: &'a Track, clip_id: &str) -> Option<&'a MidiEventStore> {
+    track.clips.iter().find_map(|clip| match clip {
+        Clip::Midi { id, midi_data, .. } if id == clip_id => midi_data.as_ref(),
+        _ => None,
+    })
+}
+
+/// Commands that add a note, first dealing with same-pitch notes it overlaps as the
+/// `note_overlap` setting says. On a monophonic track notes of any pitch make room.
+fn add_note_commands(
+    state: &DawState,
+    clip_id: &str,
+    start_time: f64,
+    duration: f64,
+    pitch: u8,
+    velocity: u8,
+) -> Vec<DawCommand> {
+    let mut commands = Vec::new();
+    let mut duration = duration;
+    let overlap = state.settings.note_overlap;
+
+    let track = clip_track(state, clip_id);
+    let monophonic = track.is_some_and(|track| track.is_monophonic);
+    if let Some(store) = track.and_then(|track| midi_store(track, clip_id)) {
+        let end_time = start_time + duration;
+        let mut deleted = Vec::new();
+        for note in store.get_notes().filter(|note| {
+            (monophonic || (note.key == pitch && overlap != NoteOverlap::Allow))
+                && note.start_time < end_time - MIN_TIME_DELTA
+                && note.start_time + note.duration > start_time + MIN_TIME_DELTA
+        }) {
+            let mode = if note.key == pitch && overlap != NoteOverlap::Allow {
+                overlap
+            } else {
+                NoteOverlap::Shorten
+            };
+            let starts_before = note.start_time < start_time - MIN_TIME_DELTA;
+            let starts_after = note.start_time > start_time + MIN_TIME_DELTA;
+            match mode {
+                NoteOverlap::Shorten if starts_before => {
+                    commands.push(DawCommand::ResizeNote {
+                        clip_id: clip_id.to_string(),
+                        note_id: note.id.clone(),
+                        new_start_time: note.start_time,
+                        new_duration: start_time - note.start_time,
+                    });
+                }
+                NoteOverlap::Shorten if starts_after => {
+                    duration = duration.min(note.start_time - start_time);
+                }
+                // Replacing, or a note at the very same spot that can't be shortened
+                _ => deleted.push(note.id.clone()),
+            }
+        }
+        if !deleted.is_empty() {
+            commands.push(DawCommand::DeleteNotes {
+                clip_id: clip_id.to_string(),
+                note_ids: deleted,
+            });
+        }
+    }
+
+    commands.push(DawCommand::AddNote {
+        clip_id: clip_id.to_string(),
+        start_time,
+        duration,
+        pitch,
+        velocity,
+    });
+    commands
+}
+
+/// On a monophonic track, resizes that end notes overlapping the start of `note_ids`, and
+/// end those notes where the next note starts
+fn monophonic_trims(state: &DawState, clip_id: &str, note_ids: &[EventID]) -> Vec<DawCommand> {
+    let Some(track) = clip_track(state, clip_id).filter(|track| track.is_monophonic) else {
+        return Vec::new();
+    };
+    let Some(store) = midi_store(track, clip_id) else {
+        return Vec::new();
+    };
+
+    // Each trimmed note with the shortest duration any edited note needs of it
+    let mut trims: Vec<(&Note, f64)> = Vec::new();
+
+    for edited in note_ids.iter().filter_map(|id| store.get_note(id)) {
+        for note in store.get_notes().filter(|note| note.id != edited.id) {
+            let note_end = note.start_time + note.duration;
+            let edited_end = edited.start_time + edited.duration;
+            let (trimmed, duration) = if !note_ids.contains(&note.id)
+                && note.start_time < edited.start_time - MIN_TIME_DELTA
+                && note_end > edited.start_time + MIN_TIME_DELTA
+            {
+                (note, edited.start_time - note.start_time)
+            } else if note.start_time > edited.start_time + MIN_TIME_DELTA
+                && note.start_time < edited_end - MIN_TIME_DELTA
+            {
+                (edited, note.start_time - edited.start_time)
+            } else {
+                continue;
+            };
+            match trims.iter_mut().find(|(n, _)| n.id == trimmed.id) {
+                Some((_, shortest)) => *shortest = shortest.min(duration),
+                None => trims.push((trimmed, duration)),
+            }
+        }
+    }
+
+    trims
+        .into_iter()
+        .map(|(note, duration)| DawCommand::ResizeNote {
+            clip_id: clip_id.to_string(),
+            note_id: note.id.clone(),
+            new_start_time: note.start_time,
+            new_duration: duration,
+        })
+        .collect()
+}
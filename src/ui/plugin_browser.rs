@@ -23,10 +23,12 @@ struct PluginInfo {
     format: PluginFormat,
     manufacturer: String,
     is_instrument: bool,
+    builtin: Option<BuiltinInstrument>,
 }
 
 #[derive(Clone, Debug)]
 enum PluginFormat {
+    BuiltIn,
     VST3,
     CLAP,
 }
@@ -88,6 +90,8 @@ impl PluginBrowser {
     }
 
     fn draw_browser_contents(&mut self, ui: &mut egui::Ui, state: &mut DawState) {
+        let mut load_request = None;
+
         ui.vertical(|ui| {
             // Header
             ui.horizontal(|ui| {
@@ -172,18 +176,7 @@ impl PluginBrowser {
                                 }
 
                                 if response.double_clicked() {
-                                    println!("Load Plugin: {:?}", plugin.path);
-
-                                    if let Some(track_id) = &state.selected_track {
-                                        // self.command_collector.add_command(
-                                        //     DawCommand::LoadPlugin {
-                                        //         track_id: track_id.clone(),
-                                        //         path: plugin.path.clone(),
-                                        //     },
-                                        // );
-
-                                        self.visible = false;
-                                    }
+                                    load_request = Some(idx);
                                 }
                             }
                         });
@@ -200,22 +193,18 @@ impl PluginBrowser {
                                     ui.label(format!("Manufacturer: {}", plugin.manufacturer));
                                     ui.label(format!("Category: {}", plugin.category));
                                     ui.label(format!("Format: {:?}", plugin.format));
-                                    ui.label(format!("Path: {}", plugin.path.display()));
+                                    if plugin.builtin.is_none() {
+                                        ui.label(format!("Path: {}", plugin.path.display()));
+                                    }
 
                                     ui.add_space(16.0);
 
+                                    if plugin.is_instrument && state.selected_track.is_none() {
+                                        ui.label("Select a track to load this instrument on");
+                                    }
+
                                     if ui.button("Load Plugin").clicked() {
-                                        println!("Load Plugin: {:?}", plugin.path);
-                                        if let Some(track_id) = &state.selected_track {
-                                            // self.command_collector.add_command(
-                                            //     DawCommand::LoadPlugin {
-                                            //         track_id: track_id.clone(),
-                                            //         path: plugin.path.clone(),
-                                            //     },
-                                            // );
-
-                                            self.visible = false;
-                                        }
+                                        load_request = Some(idx);
                                     }
                                 }
                             } else {
@@ -232,11 +221,56 @@ impl PluginBrowser {
                 ui.label(format!("{} plugins found", self.plugins.len()));
             });
         });
+
+        if let Some(idx) = load_request {
+            self.load_plugin(idx, state);
+        }
+    }
+
+    fn load_plugin(&mut self, idx: usize, state: &mut DawState) {
+        let Some(plugin) = self.plugins.get(idx) else {
+            return;
+        };
+
+        let Some(track_id) = state.selected_track.clone() else {
+            state.status.warning("Select a track to load the plugin on");
+            return;
+        };
+
+        match plugin.builtin {
+            Some(instrument) => {
+                self.command_collector
+                    .add_command(DawCommand::SetTrackInstrument {
+                        track_id,
+                        instrument: Some(instrument),
+                    });
+                self.visible = false;
+            }
+            None => {
+                // TODO: Host external VST3/CLAP plugins
+                state
+                    .status
+                    .error(format!("Loading {:?} plugins is not supported yet", plugin.format));
+            }
+        }
+    }
+
+    fn builtin_plugins() -> impl Iterator<Item = PluginInfo> {
+        BuiltinInstrument::ALL.into_iter().map(|instrument| PluginInfo {
+            name: instrument.name().into(),
+            path: PathBuf::new(),
+            category: "Instrument".into(),
+            format: PluginFormat::BuiltIn,
+            manufacturer: "Supersaw".into(),
+            is_instrument: true,
+            builtin: Some(instrument),
+        })
     }
 
     fn scan_plugins(&mut self) {
         self.is_scanning = true;
         self.plugins.clear();
+        self.plugins.extend(Self::builtin_plugins());
 
         // TODO: Implement actual plugin scanning
         // For now, just add some dummy plugins
@@ -248,6 +282,7 @@ impl PluginBrowser {
                 format: PluginFormat::VST3,
                 manufacturer: "Example Audio".into(),
                 is_instrument: true,
+                builtin: None,
             },
             PluginInfo {
                 name: "Example Reverb".into(),
@@ -256,6 +291,7 @@ impl PluginBrowser {
                 format: PluginFormat::VST3,
                 manufacturer: "Example Audio".into(),
                 is_instrument: false,
+                builtin: None,
             },
         ]);
 
@@ -5,6 +5,42 @@ use crate::core::utils::SnapHandler;
 use crate::core::*;
 use eframe::egui;
 use eframe::epaint::StrokeKind;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Drawn clips shorter than this (in seconds) are treated as a plain click
+const MIN_TIME_DELTA: f64 = 0.001;
+
+/// Shortest a clip can be resized to, in seconds
+const MIN_CLIP_LENGTH: f32 = 0.1;
+
+/// Height of a collapsed track's strip
+const COLLAPSED_TRACK_HEIGHT: f32 = 28.0;
+
+/// Length given to a dropped MIDI clip whose file can't be read up front
+const DROPPED_CLIP_LENGTH: f64 = 10.0;
+
+/// Furthest an audio clip edge moves to reach a zero-crossing, in seconds
+const ZERO_CROSSING_WINDOW: f64 = 0.005;
+
+/// How far the zoom may drift from a cached MIDI preview before it is rebuilt
+/// rather than stretched
+const PREVIEW_RESCALE_TOLERANCE: f32 = 0.1;
+
+/// Height of the strip of section bands between the ruler and the tracks
+const SECTION_LANE_HEIGHT: f32 = 16.0;
+
+/// Width of the grab area at each end of a section band
+const SECTION_EDGE_WIDTH: f32 = 5.0;
+
+/// A MIDI clip's note layout, built once and stretched to the clip's current width
+struct MidiPreview {
+    revision: u64,
+    clip_length: f64,
+    width: f32,
+    height: f32,
+    mesh: egui::Mesh, // Relative to the preview's top left corner
+}
 
 pub struct Timeline {
     pixels_per_second: f32,
@@ -14,6 +50,7 @@ pub struct Timeline {
     track_height: f32,
     track_header_width: f32,
     drag_start: Option<(egui::Pos2, f32)>, // (pointer_pos, clip_start_time)
+    clip_copy_time: Option<f32>, // Where an Alt-drag will drop a copy of the dragged clip
     command_collector: CommandCollector,
     midi_ports: Vec<String>,
     pending_midi_connections: Vec<(String, String)>, // (track_id, device_name)
@@ -22,6 +59,22 @@ pub struct Timeline {
     resize_initial_values: Option<(f32, f32)>, // (start_time, length)
     // Track reordering state
     dragging_track: Option<(usize, f32)>, // (track_index, y_offset)
+    // Clip drawing state
+    drawing_clip: Option<(String, f64)>, // (track_id, snapped time where the drag started)
+    // Marker state
+    ruler_menu_time: f64,                  // Where the ruler context menu was opened
+    marker_rename: Option<(String, String)>, // (marker_id, name being edited)
+    // Section state
+    section_drag_offset: f64, // Pointer time minus section start when a band drag began
+    new_section_start: Option<f64>, // Where a drag in the empty section lane began
+    section_rename: Option<(String, String)>, // (section_id, name being edited)
+    // Cached note layouts, by clip id
+    midi_previews: HashMap<String, MidiPreview>,
+    // MIDI export state
+    midi_export: MidiExportOptions,
+    pending_midi_export: Option<String>, // Clip picked for export, saved once drawing is done
+    // Waveform overviews being read in the background
+    peak_loader: PeakLoader,
 }
 
 impl Default for Timeline {
@@ -34,12 +87,23 @@ impl Default for Timeline {
             track_height: 80.0,
             track_header_width: 200.0,
             drag_start: None,
+            clip_copy_time: None,
             command_collector: CommandCollector::new(),
             midi_ports: Vec::new(),
             pending_midi_connections: Vec::new(),
-            resize_snap_handler: SnapHandler::new(10.0),
+            resize_snap_handler: SnapHandler::new(DEFAULT_SNAP_THRESHOLD),
             resize_initial_values: None,
             dragging_track: None,
+            drawing_clip: None,
+            ruler_menu_time: 0.0,
+            marker_rename: None,
+            section_drag_offset: 0.0,
+            new_section_start: None,
+            section_rename: None,
+            midi_previews: HashMap::new(),
+            midi_export: MidiExportOptions::default(),
+            pending_midi_export: None,
+            peak_loader: PeakLoader::default(),
         }
     }
 }
@@ -74,22 +138,140 @@ impl Timeline {
         ]
     }
 
+    /// A palette entry with a color preview; returns true when clicked
+    fn color_option(ui: &mut egui::Ui, name: &str, color: egui::Color32, is_selected: bool) -> bool {
+        ui.horizontal(|ui| {
+            // Color preview
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(16.0, 16.0), egui::Sense::hover());
+            ui.painter().rect_filled(rect, 2.0, color);
+            ui.painter().rect_stroke(
+                rect,
+                2.0,
+                (1.0, ui.visuals().widgets.noninteractive.bg_stroke.color),
+                StrokeKind::Middle,
+            );
+
+            ui.selectable_label(is_selected, name).clicked()
+        })
+        .inner
+    }
+
+    /// Hand finished waveform overviews to their clips, and start reading the files of
+    /// newly added audio clips. Clips draw a placeholder until theirs arrive.
+    fn update_audio_peaks(&mut self, ctx: &egui::Context, state: &mut DawState) {
+        for (path, e) in self.peak_loader.poll() {
+            state.status.error(format!(
+                "Failed to read audio file {}: {}",
+                path.display(),
+                e
+            ));
+        }
+
+        for clip in state.project.tracks.iter_mut().flat_map(|t| t.clips.iter_mut()) {
+            if let Clip::Audio {
+                file_path,
+                peaks: peaks @ None,
+                ..
+            } = clip
+            {
+                match self.peak_loader.get(file_path) {
+                    Some(loaded) => *peaks = Some(loaded),
+                    None => self.peak_loader.request(file_path),
+                }
+            }
+        }
+
+        if self.peak_loader.is_busy() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+    }
+
+    /// Transpose and velocity settings that change how a MIDI clip plays without
+    /// touching its notes, and the button to write them into the notes
+    fn clip_processors_menu(&mut self, ui: &mut egui::Ui, clip_id: &str, processors: &[ClipProcessor]) {
+        let mut semitones = processors
+            .iter()
+            .find_map(|p| match p {
+                ClipProcessor::Transpose { semitones } => Some(*semitones),
+                _ => None,
+            })
+            .unwrap_or(0);
+        let mut velocity = processors
+            .iter()
+            .find_map(|p| match p {
+                ClipProcessor::VelocityScale { factor } => Some(*factor * 100.0),
+                _ => None,
+            })
+            .unwrap_or(100.0);
+
+        let mut changed = ui
+            .horizontal(|ui| {
+                ui.label("Transpose");
+                ui.add(egui::DragValue::new(&mut semitones).range(-48..=48).suffix(" st"))
+                    .changed()
+            })
+            .inner;
+        changed |= ui
+            .horizontal(|ui| {
+                ui.label("Velocity");
+                ui.add(
+                    egui::DragValue::new(&mut velocity)
+                        .range(10.0..=200.0)
+                        .suffix("%"),
+                )
+                .changed()
+            })
+            .inner;
+        if changed {
+            let mut processors = Vec::new();
+            if semitones != 0 {
+                processors.push(ClipProcessor::Transpose { semitones });
+            }
+            if velocity != 100.0 {
+                processors.push(ClipProcessor::VelocityScale {
+                    factor: velocity / 100.0,
+                });
+            }
+            self.command_collector.add_command(DawCommand::SetClipProcessors {
+                clip_id: clip_id.to_string(),
+                processors,
+            });
+        }
+
+        if ui
+            .add_enabled(!processors.is_empty(), egui::Button::new("Freeze"))
+            .on_hover_text("Write the processed notes into the clip so they can be edited")
+            .clicked()
+        {
+            self.command_collector.add_command(DawCommand::FreezeClip {
+                clip_id: clip_id.to_string(),
+            });
+            ui.close_menu();
+        }
+    }
+
     pub fn take_pending_midi_connections(&mut self) -> Vec<(String, String)> {
         std::mem::take(&mut self.pending_midi_connections)
     }
     pub fn show(&mut self, ui: &mut egui::Ui, state: &mut DawState) -> Vec<DawCommand> {
+        self.update_audio_peaks(ui.ctx(), state);
+        let clips = state.project.tracks.iter().flat_map(|t| t.clips.iter());
+        self.midi_previews
+            .retain(|clip_id, _| clips.clone().any(|c| c.id() == clip_id));
+
         let (full_rect, response) =
             ui.allocate_exact_size(ui.available_size(), egui::Sense::drag());
 
         let ruler_height = 20.0;
+        let top_height = ruler_height + SECTION_LANE_HEIGHT;
 
         // Split into regions
         let header_width = self.track_header_width;
 
-        // Header area (left side, below ruler)
+        // Header area (left side, below ruler and sections)
         let header_rect = egui::Rect::from_min_size(
-            egui::pos2(full_rect.left(), full_rect.top() + ruler_height),
-            egui::vec2(header_width, full_rect.height() - ruler_height),
+            egui::pos2(full_rect.left(), full_rect.top() + top_height),
+            egui::vec2(header_width, full_rect.height() - top_height),
         );
 
         // Timeline area (right side, including ruler)
@@ -98,10 +280,10 @@ impl Timeline {
             egui::vec2(full_rect.width() - header_width, full_rect.height()),
         );
 
-        // Tracks area (timeline minus ruler)
+        // Tracks area (timeline minus ruler and sections)
         let tracks_rect = egui::Rect::from_min_size(
-            egui::pos2(timeline_rect.left(), timeline_rect.top() + ruler_height),
-            egui::vec2(timeline_rect.width(), timeline_rect.height() - ruler_height),
+            egui::pos2(timeline_rect.left(), timeline_rect.top() + top_height),
+            egui::vec2(timeline_rect.width(), timeline_rect.height() - top_height),
         );
 
         // Ruler area (only above timeline)
@@ -110,21 +292,35 @@ impl Timeline {
             egui::vec2(timeline_rect.width(), ruler_height),
         );
 
+        // Section lane (between ruler and tracks)
+        let sections_rect = egui::Rect::from_min_size(
+            egui::pos2(timeline_rect.left(), ruler_rect.bottom()),
+            egui::vec2(timeline_rect.width(), SECTION_LANE_HEIGHT),
+        );
+
         // Draw timeline background and grid
         self.draw_background(ui, tracks_rect);
         self.draw_grid(ui, tracks_rect, state);
 
         // Handle interactions
+        self.handle_reveal_request(tracks_rect, state);
         self.handle_zooming(ui, timeline_rect);
         self.handle_scrolling(ui, &response);
-        self.handle_file_drops(ui, state);
+        self.handle_file_drops(ui, tracks_rect, state);
         self.handle_delete_clip(ui, state);
+        self.handle_clip_mute_key(ui, state);
+        self.handle_marker_key(ui, state);
+        self.handle_snap_clip_key(ui, state);
         self.handle_escape_key(ui);
+        self.handle_open_editor_key(ui, state);
+        self.handle_track_selection_keys(ui, tracks_rect, state);
 
         // Draw components
         self.draw_track_headers(ui, header_rect, state);
         self.draw_tracks(ui, tracks_rect, state);
+        self.handle_midi_export(state);
         self.draw_ruler(ui, ruler_rect, state);
+        self.draw_sections(ui, sections_rect, state);
         self.handle_loop_region(ui, tracks_rect, state);
         self.draw_playhead(ui, timeline_rect, state);
 
@@ -138,11 +334,13 @@ impl Timeline {
 
     fn draw_grid(&self, ui: &mut egui::Ui, rect: egui::Rect, state: &DawState) {
         let bpm = state.project.bpm;
-        let beat_duration = 60.0 / bpm;
-        let bar_duration = beat_duration * 4.0;
+        let time_sig = state.project.time_signature;
+        let beats_per_bar = time_sig.0 as i32;
+        let beat_duration = TimeUtils::seconds_per_beat(bpm, time_sig);
+        let bar_duration = TimeUtils::seconds_per_bar(bpm, time_sig);
 
         let pixels_per_beat = self.pixels_per_second * beat_duration as f32;
-        let pixels_per_bar = pixels_per_beat * 4.0;
+        let pixels_per_bar = self.pixels_per_second * bar_duration as f32;
 
         let start_time = self.scroll_offset / self.pixels_per_second;
         let end_time = (self.scroll_offset + rect.width()) / self.pixels_per_second;
@@ -150,8 +348,9 @@ impl Timeline {
         let start_bar = ((start_time as f64) / bar_duration).floor() as i32;
         let end_bar = ((end_time as f64) / bar_duration).ceil() as i32;
 
-        let division = state.snap_mode.get_division(bpm);
-        let subdivisions_per_beat = (beat_duration / division).round() as i32; // How many subdivision lines per beat
+        let division = state.snap_mode.get_division(bpm, time_sig);
+        // How many subdivision lines per beat; bar snap has none, and never zero
+        let subdivisions_per_beat = (beat_duration / division).round().max(1.0) as i32;
         let pixels_per_division = pixels_per_beat / subdivisions_per_beat as f32;
 
         for bar in start_bar..=end_bar {
@@ -176,7 +375,7 @@ impl Timeline {
             );
 
             // Draw beat and subdivision lines
-            for beat in 0..4 {
+            for beat in 0..beats_per_bar {
                 let beat_x = x + (beat as f32 * pixels_per_beat);
                 let beat_line_color = ui.visuals().window_stroke.color.linear_multiply(0.8);
                 ui.painter().line_segment(
@@ -251,58 +450,116 @@ impl Timeline {
         });
     }
 
-    fn handle_file_drops(&mut self, ui: &mut egui::Ui, state: &mut DawState) {
-        let mut files = ui.input(|i| i.raw.dropped_files.clone());
-        if let Some(file) = files.pop() {
-            println!("Dropping files");
-            if let Some(path) = file.path {
-                println!("Dropping file: {:?}", path);
-
-                if let Some(pos) = ui.input(|i| i.pointer.hover_pos()) {
-                    // TODO: Wrong, use util and cleanup
-                    let time = (pos.x + self.scroll_offset) / self.pixels_per_second;
-
-                    println!("Dropping file at time: {}", time);
-
-                    if let Some(track_id) = &state.selected_track {
-                        println!("Dropping file on track: {}", track_id);
-
-                        let extension = path
-                            .extension()
-                            .and_then(|e| e.to_str())
-                            .unwrap_or("")
-                            .to_lowercase();
-                        let is_midi = extension == "mid" || extension == "midi";
-                        let is_audio = extension == "wav" || extension == "mp3";
-
-                        println!(
-                            "name : {}, extension: {}, is_midi: {}, is_audio: {}",
-                            path.display(),
-                            extension,
-                            is_midi,
-                            is_audio
-                        );
-
-                        if let Some(track) = state.project.tracks.iter().find(|t| &t.id == track_id)
-                        {
-                            let can_add = match &track.track_type {
-                                TrackType::Midi { .. } => is_midi,
-                                // TODO: Handle audio tracks
-                                _ => false,
-                            };
-                            if can_add {
-                                self.command_collector.add_command(DawCommand::AddClip {
-                                    track_id: track_id.clone(),
-                                    start_time: time as f64,
-                                    length: 10.0,
-                                    file_path: path,
-                                });
-                            }
-                        }
+    fn handle_file_drops(&mut self, ui: &mut egui::Ui, rect: egui::Rect, state: &mut DawState) {
+        let files = ui.input(|i| i.raw.dropped_files.clone());
+        if files.is_empty() {
+            return;
+        }
+        let Some(pos) = ui.input(|i| i.pointer.hover_pos()) else {
+            return;
+        };
+        let time =
+            ((pos.x - rect.left() + self.scroll_offset) / self.pixels_per_second).max(0.0) as f64;
+
+        let paths: Vec<PathBuf> = files.into_iter().filter_map(|file| file.path).collect();
+        let (midi_paths, skipped): (Vec<_>, Vec<_>) =
+            paths.into_iter().partition(|path| is_midi_file(path));
+        if midi_paths.is_empty() {
+            // TODO: Handle audio files
+            state.status.warning("Only MIDI files can be dropped on the timeline");
+            return;
+        }
+
+        let mut imported = 0;
+        match self.track_at(rect, pos.y, state) {
+            // Dropped on a MIDI track: the files follow each other from the drop time
+            Some(track) if matches!(track.track_type, TrackType::Midi { .. }) => {
+                let track_id = track.id.clone();
+                let mut start_time = time;
+                let mut commands = Vec::new();
+                for path in midi_paths {
+                    let length = MidiEventStore::load_from_file(&path)
+                        .ok()
+                        .and_then(|store| store.get_last_event_time())
+                        .filter(|length| *length > 0.0)
+                        .unwrap_or(DROPPED_CLIP_LENGTH);
+                    commands.push(DawCommand::AddClip {
+                        track_id: track_id.clone(),
+                        start_time,
+                        length,
+                        file_path: path,
+                    });
+                    start_time += length;
+                    imported += 1;
+                }
+                self.command_collector
+                    .add_command(DawCommand::Batch { commands });
+            }
+            Some(_) => {
+                state.status.warning("MIDI files can only be dropped on MIDI tracks");
+                return;
+            }
+            // Dropped on empty space below the tracks: every file becomes a track of its own,
+            // added in one undo step
+            None => {
+                let mut commands = Vec::new();
+                let mut tracks = Vec::new();
+                for path in midi_paths {
+                    let number = state.project.tracks.len() + tracks.len() + 1;
+                    match state.project.midi_track_from_file_path(&path, time, number) {
+                        Ok(track) => tracks.push(track),
+                        Err(e) => state
+                            .status
+                            .error(format!("Failed to create track from MIDI file: {}", e)),
+                    }
+                }
+
+                if let Some(first) = tracks.first() {
+                    let (bpm, time_signature) = state.project.imported_timing(first);
+                    if let Some(bpm) = bpm {
+                        commands.push(DawCommand::SetBpm { bpm });
+                    }
+                    if let Some(time_signature) = time_signature {
+                        commands.push(DawCommand::SetTimeSignature { time_signature });
+                    }
+                }
+                imported = tracks.len();
+                let first_index = state.project.tracks.len();
+                commands.extend(tracks.into_iter().enumerate().map(|(offset, track)| {
+                    DawCommand::RestoreTrack {
+                        index: first_index + offset,
+                        track: Box::new(track),
                     }
+                }));
+                if !commands.is_empty() {
+                    self.command_collector
+                        .add_command(DawCommand::Batch { commands });
                 }
             }
         }
+
+        if imported > 0 {
+            let mut message = format!(
+                "Imported {} MIDI file{}",
+                imported,
+                if imported == 1 { "" } else { "s" }
+            );
+            if !skipped.is_empty() {
+                message.push_str(&format!(", skipped {} unsupported", skipped.len()));
+            }
+            state.status.success(message);
+        }
+    }
+
+    /// Track whose lane spans `y` in the tracks area, if any
+    fn track_at<'a>(&self, rect: egui::Rect, y: f32, state: &'a DawState) -> Option<&'a Track> {
+        let mut track_top = rect.top() - self.scroll_y;
+        state.project.tracks.iter().find(|track| {
+            let track_bottom = track_top + self.height_of(track);
+            let hit = y >= track_top && y < track_bottom;
+            track_top = track_bottom;
+            hit
+        })
     }
 
     fn handle_delete_clip(&mut self, ui: &mut egui::Ui, state: &mut DawState) {
@@ -323,12 +580,205 @@ impl Timeline {
         }
     }
 
+    /// Toggle mute on the selected clip with 0
+    fn handle_clip_mute_key(&mut self, ui: &mut egui::Ui, state: &DawState) {
+        if ui.memory(|m| m.focused().is_some()) {
+            return;
+        }
+        if ui.input(|i| i.key_pressed(egui::Key::Num0)) {
+            if let Some(clip) = state.selected_clip.as_ref().and_then(|clip_id| {
+                state
+                    .project
+                    .tracks
+                    .iter()
+                    .flat_map(|t| &t.clips)
+                    .find(|c| c.id() == clip_id)
+            }) {
+                self.command_collector.add_command(DawCommand::SetClipMuted {
+                    clip_id: clip.id().to_string(),
+                    muted: !clip.is_muted(),
+                });
+            }
+        }
+    }
+
+    /// Drop a marker at the playhead with M
+    fn handle_marker_key(&mut self, ui: &mut egui::Ui, state: &DawState) {
+        if ui.memory(|m| m.focused().is_some()) {
+            return;
+        }
+        if ui.input(|i| i.key_pressed(egui::Key::M) && i.modifiers.is_none()) {
+            self.command_collector.add_command(DawCommand::AddMarker {
+                marker: state.project.new_marker(state.current_time),
+            });
+        }
+    }
+
+    /// Ask where to save the clip picked in its Export MIDI menu, then write it
+    fn handle_midi_export(&mut self, state: &mut DawState) {
+        let Some(clip_id) = self.pending_midi_export.take() else {
+            return;
+        };
+        let Some((track, clip)) = state.project.tracks.iter().find_map(|track| {
+            let clip = track.clips.iter().find(|c| c.id() == clip_id)?;
+            Some((track, clip))
+        }) else {
+            return;
+        };
+        let (Clip::Midi { file_path, .. }, Some(store)) =
+            (clip, clip.export_store(track.message_channel().unwrap_or(0)))
+        else {
+            return;
+        };
+
+        let name = file_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "clip".to_string());
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Export MIDI")
+            .add_filter("MIDI Files", &["mid", "midi"])
+            .set_file_name(format!("{}.mid", name))
+            .save_file()
+        else {
+            return;
+        };
+
+        match store.save_to_file(&path, &self.midi_export) {
+            Ok(()) => state
+                .status
+                .success(format!("Exported MIDI to {}", path.display())),
+            Err(e) => state.status.error(format!("Failed to export MIDI: {}", e)),
+        }
+    }
+
+    /// Snap the selected clip's start to the grid with G
+    fn handle_snap_clip_key(&mut self, ui: &mut egui::Ui, state: &DawState) {
+        if ui.memory(|m| m.focused().is_some()) {
+            return;
+        }
+        if state.snap_mode == SnapMode::None {
+            return;
+        }
+        if ui.input(|i| i.key_pressed(egui::Key::G) && i.modifiers.is_none()) {
+            if let Some(clip_id) = &state.selected_clip {
+                self.command_collector.add_command(DawCommand::SnapClipToGrid {
+                    clip_id: clip_id.clone(),
+                });
+            }
+        }
+    }
+
     fn handle_escape_key(&mut self, ui: &mut egui::Ui) {
         if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
             self.command_collector.add_command(DawCommand::DeselectAll);
         }
     }
 
+    /// Scroll a clip requested by another view into sight, if it isn't already
+    fn handle_reveal_request(&mut self, rect: egui::Rect, state: &mut DawState) {
+        let Some(clip_id) = state.reveal_clip.take() else {
+            return;
+        };
+        let tracks = &state.project.tracks;
+        let Some((index, clip)) = tracks.iter().enumerate().find_map(|(index, track)| {
+            track
+                .clips
+                .iter()
+                .find(|c| c.id() == clip_id)
+                .map(|clip| (index, clip))
+        }) else {
+            return;
+        };
+
+        let margin = 0.1 * rect.width();
+        let clip_left = clip.start_time() as f32 * self.pixels_per_second;
+        let clip_right = clip_left + clip.length() as f32 * self.pixels_per_second;
+        if clip_left < self.scroll_offset || clip_right > self.scroll_offset + rect.width() {
+            self.scroll_offset = (clip_left - margin).max(0.0);
+        }
+
+        self.scroll_track_into_view(rect, tracks, index);
+    }
+
+    /// Scroll vertically so the track at `index` is fully visible, if it isn't already
+    fn scroll_track_into_view(&mut self, rect: egui::Rect, tracks: &[Track], index: usize) {
+        let track_top = self.track_offset(tracks, index);
+        let track_bottom = track_top + self.height_of(&tracks[index]);
+        if track_top < self.scroll_y {
+            self.scroll_y = track_top;
+        } else if track_bottom > self.scroll_y + rect.height() {
+            self.scroll_y = (track_bottom - rect.height()).max(0.0);
+        }
+    }
+
+    /// Up/Down step through the tracks, Home/End select the first and last one
+    fn handle_track_selection_keys(&mut self, ui: &mut egui::Ui, rect: egui::Rect, state: &DawState) {
+        if ui.memory(|m| m.focused().is_some()) {
+            return;
+        }
+        let tracks = &state.project.tracks;
+        let Some(last) = tracks.len().checked_sub(1) else {
+            return;
+        };
+        let current = state
+            .selected_track
+            .as_ref()
+            .and_then(|track_id| tracks.iter().position(|t| t.id == *track_id));
+
+        let target = ui.input(|i| {
+            if !i.modifiers.is_none() {
+                None
+            } else if i.key_pressed(egui::Key::ArrowUp) {
+                Some(current.map_or(0, |index| index.saturating_sub(1)))
+            } else if i.key_pressed(egui::Key::ArrowDown) {
+                Some(current.map_or(0, |index| (index + 1).min(last)))
+            } else if i.key_pressed(egui::Key::Home) {
+                Some(0)
+            } else if i.key_pressed(egui::Key::End) {
+                Some(last)
+            } else {
+                None
+            }
+        });
+        let Some(index) = target else {
+            return;
+        };
+
+        if current != Some(index) {
+            self.command_collector.add_command(DawCommand::SelectTrack {
+                track_id: tracks[index].id.clone(),
+            });
+        }
+        self.scroll_track_into_view(rect, tracks, index);
+    }
+
+    /// Enter opens the piano roll for the selected MIDI clip
+    fn handle_open_editor_key(&mut self, ui: &mut egui::Ui, state: &DawState) {
+        if ui.memory(|m| m.focused().is_some()) {
+            return;
+        }
+        if !ui.input(|i| i.key_pressed(egui::Key::Enter) && i.modifiers.is_none()) {
+            return;
+        }
+        let Some(clip_id) = &state.selected_clip else {
+            return;
+        };
+        let selected = state.project.tracks.iter().find_map(|track| {
+            track
+                .clips
+                .iter()
+                .find(|c| matches!(c, Clip::Midi { id, .. } if id == clip_id))
+                .map(|_| track.id.clone())
+        });
+        if let Some(track_id) = selected {
+            self.command_collector.add_command(DawCommand::OpenPianoRoll {
+                clip_id: clip_id.clone(),
+                track_id,
+            });
+        }
+    }
+
     fn handle_loop_region(&mut self, ui: &mut egui::Ui, rect: egui::Rect, state: &mut DawState) {
         if state.loop_enabled {
             let loop_start_x =
@@ -387,10 +837,11 @@ impl Timeline {
             if start_response.dragged() {
                 let delta = start_response.drag_delta().x / self.pixels_per_second;
 
-                let new_start_snap = if self.snap_enabled {
+                let new_start_snap = if self.snap_enabled && !snap_bypassed(ui.ctx()) {
                     TimeUtils::snap_time(
                         (state.loop_start + delta as f64).max(0.0),
                         state.project.bpm,
+                        state.project.time_signature,
                         state.snap_mode,
                     )
                 } else {
@@ -403,10 +854,11 @@ impl Timeline {
             // Handle end handle dragging
             if end_response.dragged() {
                 let delta = end_response.drag_delta().x / self.pixels_per_second;
-                let new_end_snap = if self.snap_enabled {
+                let new_end_snap = if self.snap_enabled && !snap_bypassed(ui.ctx()) {
                     TimeUtils::snap_time(
                         (state.loop_end + delta as f64).max(state.loop_start + 0.1),
                         state.project.bpm,
+                        state.project.time_signature,
                         state.snap_mode,
                     )
                 } else {
@@ -469,10 +921,45 @@ impl Timeline {
             }
         }
 
+        if response.secondary_clicked() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                self.ruler_menu_time =
+                    ((pos.x - rect.left() + self.scroll_offset) / self.pixels_per_second) as f64;
+            }
+        }
+        response.context_menu(|ui| {
+            if ui.button("Add Marker Here").clicked() {
+                self.command_collector.add_command(DawCommand::AddMarker {
+                    marker: state.project.new_marker(self.ruler_menu_time),
+                });
+                ui.close_menu();
+            }
+            if ui.button("Add Section from Loop").clicked() {
+                self.command_collector.add_command(DawCommand::AddSection {
+                    section: state.project.new_section(state.loop_start, state.loop_end),
+                });
+                ui.close_menu();
+            }
+        });
+
         if response.hovered() {
             ui.output_mut(|o| o.cursor_icon = egui::CursorIcon::PointingHand);
         }
 
+        // Show the musical position under the cursor
+        if let Some(pos) = response.hover_pos() {
+            let hover_time = (pos.x - rect.left() + self.scroll_offset) / self.pixels_per_second;
+            let position = TimeUtils::time_to_bars_beats(
+                hover_time as f64,
+                state.project.bpm,
+                state.project.time_signature,
+            );
+            response.clone().on_hover_text_at_pointer(format!(
+                "{} ({:.2}s)",
+                position, hover_time
+            ));
+        }
+
         // Draw time markers
         let start_time = (self.scroll_offset / self.pixels_per_second).floor() as i32;
         let end_time = ((self.scroll_offset + rect.width()) / self.pixels_per_second).ceil() as i32;
@@ -502,8 +989,375 @@ impl Timeline {
             );
         }
 
-        // Restore original clip rect
-        ui.set_clip_rect(original_clip_rect);
+        self.draw_markers(ui, rect, state);
+
+        // Restore original clip rect
+        ui.set_clip_rect(original_clip_rect);
+    }
+
+    /// Marker flags along the bottom of the ruler: click to seek, drag to move,
+    /// right-click to rename or delete
+    fn draw_markers(&mut self, ui: &mut egui::Ui, rect: egui::Rect, state: &DawState) {
+        let flag_height = 11.0;
+        let flag_color = egui::Color32::from_rgb(250, 170, 60);
+
+        for marker in &state.project.markers {
+            let x = rect.left() + marker.time as f32 * self.pixels_per_second - self.scroll_offset;
+            let galley = ui.painter().layout_no_wrap(
+                marker.name.clone(),
+                egui::FontId::proportional(10.0),
+                egui::Color32::BLACK,
+            );
+            let flag_rect = egui::Rect::from_min_size(
+                egui::pos2(x, rect.bottom() - flag_height),
+                egui::vec2(galley.size().x + 6.0, flag_height),
+            );
+            if flag_rect.right() < rect.left() || flag_rect.left() > rect.right() {
+                continue;
+            }
+
+            ui.painter().line_segment(
+                [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                (1.0, flag_color),
+            );
+            ui.painter().rect_filled(flag_rect, 2.0, flag_color);
+            ui.painter().galley(
+                egui::pos2(
+                    flag_rect.left() + 3.0,
+                    flag_rect.center().y - galley.size().y / 2.0,
+                ),
+                galley,
+                egui::Color32::BLACK,
+            );
+
+            let response = ui.interact(
+                flag_rect,
+                ui.id().with(("marker", &marker.id)),
+                egui::Sense::click_and_drag(),
+            );
+
+            if response.clicked() {
+                self.command_collector
+                    .add_command(DawCommand::SeekTime { time: marker.time });
+            }
+
+            if response.dragged() {
+                if let Some(pos) = response.interact_pointer_pos() {
+                    let time = ((pos.x - rect.left() + self.scroll_offset)
+                        / self.pixels_per_second)
+                        .max(0.0) as f64;
+                    let snap = self.snap_enabled && !snap_bypassed(ui.ctx());
+                    let time = if snap {
+                        TimeUtils::snap_time(
+                            time,
+                            state.project.bpm,
+                            state.project.time_signature,
+                            state.snap_mode,
+                        )
+                    } else {
+                        time
+                    };
+                    self.command_collector.add_command(DawCommand::MoveMarker {
+                        marker_id: marker.id.clone(),
+                        time,
+                    });
+                }
+            }
+
+            if response.secondary_clicked() {
+                self.marker_rename = Some((marker.id.clone(), marker.name.clone()));
+            }
+            response.context_menu(|ui| {
+                if let Some((marker_id, name)) = &mut self.marker_rename {
+                    let edit = ui.text_edit_singleline(name);
+                    let submitted = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if ui.button("Rename").clicked() || submitted {
+                        self.command_collector.add_command(DawCommand::RenameMarker {
+                            marker_id: marker_id.clone(),
+                            name: name.clone(),
+                        });
+                        ui.close_menu();
+                    }
+                }
+                if ui.button("Delete Marker").clicked() {
+                    self.command_collector.add_command(DawCommand::DeleteMarker {
+                        marker_id: marker.id.clone(),
+                    });
+                    ui.close_menu();
+                }
+            });
+
+            response.on_hover_text(format!("{} ({:.2}s)", marker.name, marker.time));
+        }
+    }
+
+    /// Section bands: click to loop, drag to move, drag an end to resize,
+    /// drag in the empty lane to add one, right-click for more
+    fn draw_sections(&mut self, ui: &mut egui::Ui, rect: egui::Rect, state: &mut DawState) {
+        ui.painter().rect_filled(rect, 0.0, ui.visuals().faint_bg_color);
+
+        let original_clip_rect = ui.clip_rect();
+        ui.set_clip_rect(rect);
+
+        let snap = self.snap_enabled && !snap_bypassed(ui.ctx());
+        let (scroll_offset, pixels_per_second) = (self.scroll_offset, self.pixels_per_second);
+        let (bpm, time_sig, snap_mode) = (
+            state.project.bpm,
+            state.project.time_signature,
+            state.snap_mode,
+        );
+        let snap_time = |time: f64| {
+            if snap {
+                TimeUtils::snap_time(time, bpm, time_sig, snap_mode)
+            } else {
+                time
+            }
+        };
+        let pointer_time = |pos: egui::Pos2| {
+            snap_time(((pos.x - rect.left() + scroll_offset) / pixels_per_second).max(0.0) as f64)
+        };
+
+        // Dragging across the empty lane adds a section
+        let lane_response = ui.interact(rect, ui.id().with("section_lane"), egui::Sense::drag());
+        if lane_response.drag_started() {
+            self.new_section_start = lane_response.interact_pointer_pos().map(pointer_time);
+        }
+        if let (Some(start), Some(pos)) =
+            (self.new_section_start, lane_response.interact_pointer_pos())
+        {
+            let end = pointer_time(pos);
+            let x = |time: f64| rect.left() + time as f32 * pixels_per_second - scroll_offset;
+            let preview = egui::Rect::from_x_y_ranges(
+                x(start.min(end))..=x(start.max(end)),
+                rect.y_range(),
+            );
+            ui.painter().rect_filled(
+                preview,
+                2.0,
+                ui.visuals().selection.bg_fill.linear_multiply(0.5),
+            );
+            if lane_response.drag_stopped() {
+                if (end - start).abs() > MIN_TIME_DELTA {
+                    self.command_collector.add_command(DawCommand::AddSection {
+                        section: state.project.new_section(start, end),
+                    });
+                }
+                self.new_section_start = None;
+            }
+        }
+
+        for section in state.project.sections.clone() {
+            let start_x =
+                rect.left() + section.start as f32 * self.pixels_per_second - self.scroll_offset;
+            let end_x =
+                rect.left() + section.end as f32 * self.pixels_per_second - self.scroll_offset;
+            let band_rect = egui::Rect::from_min_max(
+                egui::pos2(start_x, rect.top() + 1.0),
+                egui::pos2(end_x, rect.bottom() - 1.0),
+            );
+            if band_rect.right() < rect.left() || band_rect.left() > rect.right() {
+                continue;
+            }
+
+            let color = hex_to_color32(&section.color).unwrap_or(egui::Color32::LIGHT_BLUE);
+            let is_looped = state.loop_enabled
+                && (state.loop_start - section.start).abs() < MIN_TIME_DELTA
+                && (state.loop_end - section.end).abs() < MIN_TIME_DELTA;
+            ui.painter().rect_filled(band_rect, 2.0, color);
+            if is_looped {
+                ui.painter().rect_stroke(
+                    band_rect,
+                    2.0,
+                    (1.5, ui.visuals().selection.stroke.color),
+                    StrokeKind::Inside,
+                );
+            }
+            ui.painter().text(
+                egui::pos2(band_rect.left() + 4.0, band_rect.center().y),
+                egui::Align2::LEFT_CENTER,
+                &section.name,
+                egui::FontId::proportional(10.0),
+                egui::Color32::BLACK,
+            );
+
+            let body_response = ui.interact(
+                band_rect,
+                ui.id().with(("section", &section.id)),
+                egui::Sense::click_and_drag(),
+            );
+            let start_handle = egui::Rect::from_x_y_ranges(
+                start_x..=start_x + SECTION_EDGE_WIDTH,
+                band_rect.y_range(),
+            );
+            let end_handle = egui::Rect::from_x_y_ranges(
+                end_x - SECTION_EDGE_WIDTH..=end_x,
+                band_rect.y_range(),
+            );
+            let start_response = ui.interact(
+                start_handle,
+                ui.id().with(("section_start", &section.id)),
+                egui::Sense::drag(),
+            );
+            let end_response = ui.interact(
+                end_handle,
+                ui.id().with(("section_end", &section.id)),
+                egui::Sense::drag(),
+            );
+
+            if start_response.hovered() || end_response.hovered() {
+                ui.output_mut(|o| o.cursor_icon = egui::CursorIcon::ResizeHorizontal);
+            }
+
+            if start_response.dragged() {
+                if let Some(pos) = start_response.interact_pointer_pos() {
+                    self.command_collector.add_command(DawCommand::SetSectionRange {
+                        section_id: section.id.clone(),
+                        start: pointer_time(pos).min(section.end - MIN_TIME_DELTA),
+                        end: section.end,
+                    });
+                }
+            } else if end_response.dragged() {
+                if let Some(pos) = end_response.interact_pointer_pos() {
+                    self.command_collector.add_command(DawCommand::SetSectionRange {
+                        section_id: section.id.clone(),
+                        start: section.start,
+                        end: pointer_time(pos).max(section.start + MIN_TIME_DELTA),
+                    });
+                }
+            } else if body_response.drag_started() {
+                if let Some(pos) = body_response.interact_pointer_pos() {
+                    let time = ((pos.x - rect.left() + self.scroll_offset)
+                        / self.pixels_per_second) as f64;
+                    self.section_drag_offset = time - section.start;
+                }
+            } else if body_response.dragged() {
+                if let Some(pos) = body_response.interact_pointer_pos() {
+                    let time = ((pos.x - rect.left() + self.scroll_offset)
+                        / self.pixels_per_second) as f64;
+                    let start = snap_time((time - self.section_drag_offset).max(0.0));
+                    self.command_collector.add_command(DawCommand::SetSectionRange {
+                        section_id: section.id.clone(),
+                        start,
+                        end: start + section.length(),
+                    });
+                }
+            }
+
+            if body_response.clicked() {
+                Self::loop_section(&section, state);
+            }
+
+            if body_response.secondary_clicked() {
+                self.section_rename = Some((section.id.clone(), section.name.clone()));
+            }
+            body_response.context_menu(|ui| {
+                if let Some((section_id, name)) = &mut self.section_rename {
+                    let edit = ui.text_edit_singleline(name);
+                    let submitted = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if ui.button("Rename").clicked() || submitted {
+                        self.command_collector.add_command(DawCommand::RenameSection {
+                            section_id: section_id.clone(),
+                            name: name.clone(),
+                        });
+                        ui.close_menu();
+                    }
+                }
+                ui.separator();
+                if ui.button("Loop Section").clicked() {
+                    Self::loop_section(&section, state);
+                    ui.close_menu();
+                }
+                if ui.button("Move Section to Playhead").clicked() {
+                    self.command_collector.add_command(Self::section_clips_command(
+                        &section,
+                        state.current_time,
+                        false,
+                        state,
+                    ));
+                    ui.close_menu();
+                }
+                if ui.button("Duplicate Section").clicked() {
+                    self.command_collector.add_command(Self::section_clips_command(
+                        &section,
+                        section.end,
+                        true,
+                        state,
+                    ));
+                    ui.close_menu();
+                }
+                if ui.button("Copy Section to Playhead").clicked() {
+                    self.command_collector.add_command(Self::section_clips_command(
+                        &section,
+                        state.current_time,
+                        true,
+                        state,
+                    ));
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Delete Section").clicked() {
+                    self.command_collector.add_command(DawCommand::DeleteSection {
+                        section_id: section.id.clone(),
+                    });
+                    ui.close_menu();
+                }
+            });
+
+            body_response.on_hover_text(format!(
+                "{} ({:.2}s - {:.2}s)",
+                section.name, section.start, section.end
+            ));
+        }
+
+        ui.set_clip_rect(original_clip_rect);
+    }
+
+    /// Set the loop region to a section and turn looping on
+    fn loop_section(section: &Section, state: &mut DawState) {
+        state.loop_start = section.start;
+        state.loop_end = section.end;
+        state.loop_enabled = true;
+    }
+
+    /// One undo step that moves (or copies) every clip starting inside `section`
+    /// so the section begins at `target`; moving also moves the section itself,
+    /// copying selects the first copy
+    fn section_clips_command(section: &Section, target: f64, copy: bool, state: &DawState) -> DawCommand {
+        let shift = target - section.start;
+        let clips = state.project.clips_starting_in(section.start, section.end);
+        let mut first_copy = None;
+        let mut commands: Vec<DawCommand> = clips
+            .into_iter()
+            .map(|(track_id, clip)| {
+                let new_start = clip.start_time() + shift;
+                if copy {
+                    let copy = clip.duplicate(new_start);
+                    first_copy.get_or_insert_with(|| copy.id().to_string());
+                    DawCommand::RestoreClip {
+                        track_id: track_id.to_string(),
+                        index: usize::MAX, // Appended after the track's existing clips
+                        clip: Box::new(copy),
+                    }
+                } else {
+                    DawCommand::MoveClip {
+                        clip_id: clip.id().to_string(),
+                        track_id: track_id.to_string(),
+                        new_start_time: new_start,
+                    }
+                }
+            })
+            .collect();
+        if let Some(clip_id) = first_copy {
+            commands.push(DawCommand::SelectClip { clip_id });
+        }
+        if !copy {
+            commands.push(DawCommand::SetSectionRange {
+                section_id: section.id.clone(),
+                start: target,
+                end: target + section.length(),
+            });
+        }
+        DawCommand::Batch { commands }
     }
 
     fn draw_track_headers(&mut self, ui: &mut egui::Ui, rect: egui::Rect, state: &mut DawState) {
@@ -521,12 +1375,13 @@ impl Timeline {
         }).collect();
         
         // Draw track headers manually with scroll offset
+        let mut track_top = rect.top() - self.scroll_y;
         for (track_idx, track) in tracks {
-            let track_top = rect.top() + (track_idx as f32 * self.track_height) - self.scroll_y;
             let track_rect = egui::Rect::from_min_size(
                 egui::pos2(rect.left(), track_top),
-                egui::vec2(rect.width(), self.track_height),
+                egui::vec2(rect.width(), self.height_of(&track)),
             );
+            track_top += track_rect.height();
 
             // Skip if not visible
             if track_rect.bottom() < rect.top() || track_rect.top() > rect.bottom() {
@@ -538,7 +1393,7 @@ impl Timeline {
         }
 
         // Draw "Add Track" button at the bottom
-        let total_height = state.project.tracks.len() as f32 * self.track_height;
+        let total_height = self.track_offset(&state.project.tracks, state.project.tracks.len());
         let add_track_y = rect.top() + total_height - self.scroll_y;
 
         // Always show the button, even when there are no tracks
@@ -580,14 +1435,14 @@ impl Timeline {
         
         // Draw drop indicator when dragging
         if let Some((from_index, offset)) = self.dragging_track {
-            let tracks_moved = (offset / self.track_height).round() as i32;
-            let target_index = (from_index as i32 + tracks_moved).max(0) as usize;
-            let target_index = target_index.min(state.project.tracks.len().saturating_sub(1));
+            let tracks = &state.project.tracks;
+            let target_index = self.reorder_target(tracks, from_index, offset);
             
             if target_index != from_index {
-                let indicator_y = rect.top() + (target_index as f32 * self.track_height) - self.scroll_y;
+                let indicator_y =
+                    rect.top() + self.track_offset(tracks, target_index) - self.scroll_y;
                 let indicator_y = if target_index > from_index {
-                    indicator_y + self.track_height // Show below the target track
+                    indicator_y + self.height_of(&tracks[target_index]) // Show below the target track
                 } else {
                     indicator_y // Show above the target track
                 };
@@ -660,9 +1515,7 @@ impl Timeline {
         if drag_response.drag_stopped() {
             if let Some((from_index, offset)) = self.dragging_track {
                 // Calculate target index based on drag offset
-                let tracks_moved = (offset / self.track_height).round() as i32;
-                let to_index = (from_index as i32 + tracks_moved).max(0) as usize;
-                let to_index = to_index.min(state.project.tracks.len().saturating_sub(1));
+                let to_index = self.reorder_target(&state.project.tracks, from_index, offset);
                 
                 if from_index != to_index {
                     self.command_collector.add_command(DawCommand::ReorderTracks {
@@ -680,10 +1533,18 @@ impl Timeline {
             ui.ctx().set_cursor_icon(egui::CursorIcon::Grab);
         }
 
+        // Tracks silenced by another track's solo are drawn faded
+        let silenced = Self::is_silenced_by_solo(track, state);
+
         // Draw track color stripe on the left (like Bitwig)
         let stripe_rect = egui::Rect::from_min_size(rect.min, egui::vec2(4.0, rect.height()));
         let track_color =
             hex_to_color32(&track.color).unwrap_or(egui::Color32::from_rgb(253, 224, 71)); // Default yellow
+        let track_color = if silenced {
+            track_color.gamma_multiply(0.4)
+        } else {
+            track_color
+        };
         ui.painter().rect_filled(stripe_rect, 0.0, track_color);
 
         // Draw separator line at bottom
@@ -699,9 +1560,10 @@ impl Timeline {
         );
 
         // Content area with padding (accounting for color stripe)
+        let padding_y = if track.is_collapsed { 4.0 } else { 6.0 };
         let content_rect = egui::Rect::from_min_size(
-            rect.min + egui::vec2(10.0, 6.0),
-            egui::vec2(rect.width() - 16.0, rect.height() - 12.0),
+            rect.min + egui::vec2(10.0, padding_y),
+            egui::vec2(rect.width() - 16.0, rect.height() - 2.0 * padding_y),
         );
 
         ui.allocate_new_ui(egui::UiBuilder::new().max_rect(content_rect), |ui| {
@@ -712,11 +1574,39 @@ impl Timeline {
                 ui.horizontal(|ui| {
                     ui.spacing_mut().item_spacing.x = 6.0;
 
+                    // Collapse/expand toggle
+                    let toggle_icon = if track.is_collapsed { "⏵" } else { "⏷" };
+                    let toggle_hint = if track.is_collapsed { "Expand Track" } else { "Collapse Track" };
+                    if ui
+                        .add(egui::Button::new(toggle_icon).small().frame(false))
+                        .on_hover_text(toggle_hint)
+                        .clicked()
+                    {
+                        self.command_collector.add_command(DawCommand::SetTrackCollapsed {
+                            track_id: track.id.clone(),
+                            collapsed: !track.is_collapsed,
+                        });
+                    }
+
                     // Track name (clickable for selection)
-                    let name_response = ui.add(
-                        egui::Label::new(egui::RichText::new(&track.name).size(13.0))
-                            .sense(egui::Sense::click()),
-                    );
+                    let name_text = egui::RichText::new(&track.name).size(13.0);
+                    let name_text = if silenced {
+                        name_text.color(ui.visuals().weak_text_color())
+                    } else {
+                        name_text
+                    };
+                    let name_response =
+                        ui.add(egui::Label::new(name_text).sense(egui::Sense::click()));
+
+                    let name_response = match track.instrument {
+                        Some(instrument) => name_response.on_hover_text(instrument.name()),
+                        None => name_response,
+                    };
+                    let name_response = if silenced {
+                        name_response.on_hover_text("Silent while another track is soloed")
+                    } else {
+                        name_response
+                    };
 
                     if name_response.clicked() {
                         self.command_collector.add_command(DawCommand::SelectTrack {
@@ -737,6 +1627,59 @@ impl Timeline {
                                     });
                                     ui.close_menu();
                                 }
+                                if matches!(track.track_type, TrackType::Midi { .. })
+                                    && ui
+                                        .selectable_label(track.is_monophonic, "Monophonic")
+                                        .on_hover_text(
+                                            "Trim overlapping notes so only one note plays at a time",
+                                        )
+                                        .clicked()
+                                {
+                                    self.command_collector.add_command(
+                                        DawCommand::SetTrackMonophonic {
+                                            track_id: track.id.clone(),
+                                            monophonic: !track.is_monophonic,
+                                        },
+                                    );
+                                    ui.close_menu();
+                                }
+                                if matches!(track.track_type, TrackType::Midi { .. }) {
+                                    ui.menu_button("Input Velocity", |ui| {
+                                        for curve in VelocityCurve::ALL {
+                                            if ui
+                                                .selectable_label(
+                                                    track.input_velocity_curve == curve,
+                                                    curve.display_name(),
+                                                )
+                                                .clicked()
+                                            {
+                                                self.command_collector.add_command(
+                                                    DawCommand::SetTrackInputVelocityCurve {
+                                                        track_id: track.id.clone(),
+                                                        curve,
+                                                    },
+                                                );
+                                                ui.close_menu();
+                                            }
+                                        }
+                                    })
+                                    .response
+                                    .on_hover_text("How the velocities of notes entered in the piano roll are shaped");
+                                }
+                                if let Some(instrument) = track.instrument {
+                                    if ui
+                                        .button(format!("Remove {}", instrument.name()))
+                                        .clicked()
+                                    {
+                                        self.command_collector.add_command(
+                                            DawCommand::SetTrackInstrument {
+                                                track_id: track.id.clone(),
+                                                instrument: None,
+                                            },
+                                        );
+                                        ui.close_menu();
+                                    }
+                                }
                             })
                             .response
                             .on_hover_text("Track Menu");
@@ -819,34 +1762,16 @@ impl Timeline {
                                 let palette = Self::get_color_palette();
                                 for (name, hex) in palette {
                                     let color = hex_to_color32(hex).unwrap();
-                                    ui.horizontal(|ui| {
-                                        // Color preview
-                                        let (rect, _) = ui.allocate_exact_size(
-                                            egui::vec2(16.0, 16.0),
-                                            egui::Sense::hover(),
-                                        );
-                                        ui.painter().rect_filled(rect, 2.0, color);
-                                        ui.painter().rect_stroke(
-                                            rect,
-                                            2.0,
-                                            (
-                                                1.0,
-                                                ui.visuals().widgets.noninteractive.bg_stroke.color,
-                                            ),
-                                            StrokeKind::Middle,
+                                    let is_selected = track.color == hex;
+                                    if Self::color_option(ui, name, color, is_selected) {
+                                        self.command_collector.add_command(
+                                            DawCommand::SetTrackColor {
+                                                track_id: track.id.clone(),
+                                                color: hex.to_string(),
+                                            },
                                         );
-
-                                        let is_selected = track.color == hex;
-                                        if ui.selectable_label(is_selected, name).clicked() {
-                                            self.command_collector.add_command(
-                                                DawCommand::SetTrackColor {
-                                                    track_id: track.id.clone(),
-                                                    color: hex.to_string(),
-                                                },
-                                            );
-                                            ui.close_menu();
-                                        }
-                                    });
+                                        ui.close_menu();
+                                    }
                                 }
                             });
 
@@ -862,7 +1787,7 @@ impl Timeline {
                 });
 
                 // Second row for MIDI settings if track is tall enough
-                if self.track_height > 70.0 {
+                if rect.height() > 70.0 {
                     match &track.track_type {
                         TrackType::Midi {
                             channel,
@@ -948,12 +1873,13 @@ impl Timeline {
         let end_time = (self.scroll_offset + rect.width()) / self.pixels_per_second;
 
         // Apply vertical scroll offset to tracks
+        let mut track_top = rect.top() - self.scroll_y;
         for (track_idx, track) in state.project.tracks.iter().enumerate() {
-            let track_top = rect.top() + track_idx as f32 * self.track_height - self.scroll_y;
             let track_rect = egui::Rect::from_min_max(
                 egui::pos2(rect.left(), track_top),
-                egui::pos2(rect.right(), track_top + self.track_height),
+                egui::pos2(rect.right(), track_top + self.height_of(track)),
             );
+            track_top = track_rect.bottom();
 
             // Skip if track is not visible
             if track_rect.bottom() < rect.top() || track_rect.top() > rect.bottom() {
@@ -979,12 +1905,27 @@ impl Timeline {
                 (1.0, ui.visuals().window_stroke.color),
             );
 
-            // Handle click on empty track area for deselection
+            // Collapsed tracks only hint at where their clips are
+            if track.is_collapsed {
+                self.draw_collapsed_clips(ui, track_rect, track, state);
+                continue;
+            }
+
+            // Handle click on empty track area for deselection, and drags on MIDI tracks
+            // for drawing new clips
+            let is_midi = matches!(track.track_type, TrackType::Midi { .. });
             let response = ui.interact(
                 track_rect,
                 ui.id().with(format!("track_{}", track_idx)),
-                egui::Sense::click(),
+                if is_midi {
+                    egui::Sense::click_and_drag()
+                } else {
+                    egui::Sense::click()
+                },
             );
+            if is_midi {
+                self.handle_clip_drawing(ui, &response, track_rect, track, state);
+            }
             if response.clicked() {
                 // Check if click was on empty area (not on a clip)
                 let click_pos = response.hover_pos().unwrap_or_default();
@@ -1010,7 +1951,7 @@ impl Timeline {
 
             // Draw clips
             for clip in &track.clips {
-                self.draw_clip(ui, track_rect, clip, state);
+                self.draw_clip(ui, track_rect, track, clip, state);
             }
         }
 
@@ -1018,10 +1959,188 @@ impl Timeline {
         ui.set_clip_rect(original_clip_rect);
     }
 
+    fn height_of(&self, track: &Track) -> f32 {
+        if track.is_collapsed {
+            COLLAPSED_TRACK_HEIGHT
+        } else {
+            self.track_height
+        }
+    }
+
+    /// Distance from the top of the first track to the top of the track at `index`
+    fn track_offset(&self, tracks: &[Track], index: usize) -> f32 {
+        tracks.iter().take(index).map(|t| self.height_of(t)).sum()
+    }
+
+    /// Index a track dragged `offset` pixels from `from_index` would be dropped at
+    fn reorder_target(&self, tracks: &[Track], from_index: usize, offset: f32) -> usize {
+        let Some(dragged) = tracks.get(from_index) else {
+            return from_index;
+        };
+        let center = self.track_offset(tracks, from_index) + self.height_of(dragged) / 2.0 + offset;
+
+        let mut bottom = 0.0;
+        for (index, track) in tracks.iter().enumerate() {
+            bottom += self.height_of(track);
+            if center < bottom {
+                return index;
+            }
+        }
+        tracks.len() - 1
+    }
+
+    /// Thin, non-interactive bars marking the clips of a collapsed track
+    fn draw_collapsed_clips(
+        &self,
+        ui: &egui::Ui,
+        track_rect: egui::Rect,
+        track: &Track,
+        state: &DawState,
+    ) {
+        let viewport_pos =
+            ViewportPosition::new(self.pixels_per_second, self.scroll_offset, track_rect);
+        for clip in &track.clips {
+            let clip_rect = egui::Rect::from_min_size(
+                egui::pos2(viewport_pos.time_to_x(clip.start_time()), track_rect.top() + 8.0),
+                egui::vec2(
+                    viewport_pos.duration_to_width(clip.length()),
+                    track_rect.height() - 16.0,
+                ),
+            );
+            ui.painter()
+                .rect_filled(clip_rect, 2.0, Self::clip_color(track, clip, state));
+        }
+    }
+
+    /// Clip color, then track color, then a default per clip type; dimmed when the clip
+    /// is muted or another track's solo silences it
+    fn clip_color(track: &Track, clip: &Clip, state: &DawState) -> egui::Color32 {
+        let color = clip
+            .color()
+            .and_then(hex_to_color32)
+            .or_else(|| hex_to_color32(&track.color))
+            .unwrap_or(match clip {
+                Clip::Midi { .. } => egui::Color32::from_rgb(64, 128, 255),
+                Clip::Audio { .. } => egui::Color32::from_rgb(128, 255, 64),
+            });
+        let color = if clip.is_muted() {
+            color.gamma_multiply(0.35)
+        } else {
+            color
+        };
+        if Self::is_silenced_by_solo(track, state) {
+            color.gamma_multiply(0.5)
+        } else {
+            color
+        }
+    }
+
+    /// Whether the track only stays quiet because some other track is soloed
+    fn is_silenced_by_solo(track: &Track, state: &DawState) -> bool {
+        !track.is_soloed && state.project.any_track_soloed()
+    }
+
+    /// Click-dragging on an empty part of a MIDI track lays out a new empty clip
+    /// spanning the (snapped) drag
+    fn handle_clip_drawing(
+        &mut self,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        track_rect: egui::Rect,
+        track: &Track,
+        state: &DawState,
+    ) {
+        let snap = self.snap_enabled && !snap_bypassed(ui.ctx());
+        let time_at = |pos: egui::Pos2| {
+            let time = ((pos.x - track_rect.left() + self.scroll_offset) / self.pixels_per_second)
+                .max(0.0) as f64;
+            if snap {
+                TimeUtils::snap_time(
+                    time,
+                    state.project.bpm,
+                    state.project.time_signature,
+                    state.snap_mode,
+                )
+            } else {
+                time
+            }
+        };
+
+        if response.drag_started() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                self.drawing_clip = Some((track.id.clone(), time_at(pos)));
+            }
+        }
+
+        let Some((track_id, anchor)) = &self.drawing_clip else {
+            return;
+        };
+        if *track_id != track.id {
+            return;
+        }
+        let Some(pos) = ui.input(|i| i.pointer.interact_pos()) else {
+            return;
+        };
+        let current = time_at(pos);
+        let (start, end) = (anchor.min(current), anchor.max(current));
+
+        if response.drag_stopped() {
+            if end - start > MIN_TIME_DELTA {
+                self.command_collector.add_command(DawCommand::AddClip {
+                    track_id: track.id.clone(),
+                    start_time: start,
+                    length: end - start,
+                    file_path: PathBuf::new(),
+                });
+            }
+            self.drawing_clip = None;
+        } else if response.dragged() {
+            let preview = egui::Rect::from_min_max(
+                egui::pos2(
+                    track_rect.left() + start as f32 * self.pixels_per_second - self.scroll_offset,
+                    track_rect.top() + 2.0,
+                ),
+                egui::pos2(
+                    track_rect.left() + end as f32 * self.pixels_per_second - self.scroll_offset,
+                    track_rect.bottom() - 2.0,
+                ),
+            );
+            let color = ui.visuals().selection.stroke.color;
+            ui.painter()
+                .rect_filled(preview, 2.0, color.gamma_multiply(0.25));
+            ui.painter().rect_stroke(
+                preview,
+                2.0,
+                egui::Stroke::new(1.5, color),
+                StrokeKind::Inside,
+            );
+        }
+    }
+
+    /// Diagonal stripes across a muted clip
+    fn draw_mute_hatch(&self, ui: &egui::Ui, clip_rect: egui::Rect) {
+        let spacing = 8.0;
+        let stroke = egui::Stroke::new(1.0, egui::Color32::from_black_alpha(90));
+        let painter = ui.painter().with_clip_rect(clip_rect.intersect(ui.clip_rect()));
+
+        let mut x = clip_rect.left() - clip_rect.height();
+        while x < clip_rect.right() {
+            painter.line_segment(
+                [
+                    egui::pos2(x, clip_rect.bottom()),
+                    egui::pos2(x + clip_rect.height(), clip_rect.top()),
+                ],
+                stroke,
+            );
+            x += spacing;
+        }
+    }
+
     fn draw_clip(
         &mut self,
         ui: &mut egui::Ui,
         track_rect: egui::Rect,
+        track: &Track,
         clip: &Clip,
         state: &DawState,
     ) {
@@ -1051,6 +2170,8 @@ impl Timeline {
         if response.drag_started() {
             // Store the initial drag position and clip start time
             self.drag_start = Some((response.hover_pos().unwrap(), start_time));
+            // Alt-dragging leaves the clip in place and drops a copy instead
+            self.clip_copy_time = ui.input(|i| i.modifiers.alt).then_some(start_time);
         }
 
         if response.dragged() {
@@ -1062,31 +2183,66 @@ impl Timeline {
                 let new_start_time = (clip_start_time + time_delta).max(0.0);
 
                 // Snap to grid if enabled (disable with Shift key)
-                let snap = self.snap_enabled && !ui.input(|i| i.modifiers.shift);
+                let snap = self.snap_enabled && !snap_bypassed(ui.ctx());
                 let snapped_time = if snap {
-                    TimeUtils::snap_time(new_start_time as f64, state.project.bpm, state.snap_mode)
-                        as f32
+                    TimeUtils::snap_time_with_bias(
+                        new_start_time as f64,
+                        state.project.bpm,
+                        state.project.time_signature,
+                        state.snap_mode,
+                        SnapBias::Floor,
+                    ) as f32
                 } else {
                     new_start_time
                 };
 
-                self.command_collector.add_command(DawCommand::MoveClip {
-                    clip_id: match clip {
-                        Clip::Midi { id, .. } | Clip::Audio { id, .. } => id.clone(),
-                    },
-                    track_id: state
-                        .project
-                        .tracks
-                        .iter()
-                        .find(|t| t.clips.contains(clip))
-                        .map(|t| t.id.clone())
-                        .unwrap_or_default(),
-                    new_start_time: snapped_time as f64,
-                });
+                if self.clip_copy_time.is_some() {
+                    self.clip_copy_time = Some(snapped_time);
+                    let ghost_rect = clip_rect.translate(egui::vec2(
+                        (snapped_time - start_time) * self.pixels_per_second,
+                        0.0,
+                    ));
+                    ui.painter().rect_stroke(
+                        ghost_rect,
+                        4.0,
+                        egui::Stroke::new(1.5, ui.visuals().selection.stroke.color),
+                        StrokeKind::Inside,
+                    );
+                } else {
+                    self.command_collector.add_command(DawCommand::MoveClip {
+                        clip_id: match clip {
+                            Clip::Midi { id, .. } | Clip::Audio { id, .. } => id.clone(),
+                        },
+                        track_id: state
+                            .project
+                            .tracks
+                            .iter()
+                            .find(|t| t.clips.contains(clip))
+                            .map(|t| t.id.clone())
+                            .unwrap_or_default(),
+                        new_start_time: snapped_time as f64,
+                    });
+                }
             }
         }
 
         if response.drag_stopped() {
+            if let Some(copy_time) = self.clip_copy_time.take() {
+                if copy_time != start_time {
+                    let copy = clip.duplicate(copy_time as f64);
+                    let copy_id = copy.id().to_string();
+                    self.command_collector.add_command(DawCommand::Batch {
+                        commands: vec![
+                            DawCommand::RestoreClip {
+                                track_id: track.id.clone(),
+                                index: track.clips.len(),
+                                clip: Box::new(copy),
+                            },
+                            DawCommand::SelectClip { clip_id: copy_id },
+                        ],
+                    });
+                }
+            }
             self.drag_start = None;
         }
 
@@ -1124,14 +2280,136 @@ impl Timeline {
             };
         }
 
+        response.context_menu(|ui| {
+            let label = if clip.is_muted() { "Unmute Clip" } else { "Mute Clip" };
+            if ui.button(label).clicked() {
+                self.command_collector.add_command(DawCommand::SetClipMuted {
+                    clip_id: clip.id().to_string(),
+                    muted: !clip.is_muted(),
+                });
+                ui.close_menu();
+            }
+
+            // Audio clips only heal back together when they were cut from one piece
+            let next_clip = track
+                .next_adjacent_clip(clip.id())
+                .filter(|next| matches!(clip, Clip::Midi { .. }) || clip.is_split_from(next));
+            let join_label = match clip {
+                Clip::Midi { .. } => "Join with Next Clip",
+                Clip::Audio { .. } => "Heal Split",
+            };
+            if ui
+                .add_enabled(next_clip.is_some(), egui::Button::new(join_label))
+                .clicked()
+            {
+                if let Some(next) = next_clip {
+                    self.command_collector.add_command(DawCommand::JoinClips {
+                        track_id: track.id.clone(),
+                        clip_id: clip.id().to_string(),
+                        next_clip_id: next.id().to_string(),
+                    });
+                }
+                ui.close_menu();
+            }
+
+            let snapped = TimeUtils::snap_time(
+                clip.start_time(),
+                state.project.bpm,
+                state.project.time_signature,
+                state.snap_mode,
+            );
+            let on_grid = (snapped - clip.start_time()).abs() < 1e-9;
+            if ui
+                .add_enabled(
+                    state.snap_mode != SnapMode::None && !on_grid,
+                    egui::Button::new("Snap Start to Grid"),
+                )
+                .on_hover_text("Move the clip start to the nearest grid line (G)")
+                .clicked()
+            {
+                self.command_collector.add_command(DawCommand::SnapClipToGrid {
+                    clip_id: clip.id().to_string(),
+                });
+                ui.close_menu();
+            }
+
+            if let Clip::Midi { processors, .. } = clip {
+                if ui
+                    .button("Clear Clip")
+                    .on_hover_text("Remove all notes and automation, keeping the clip")
+                    .clicked()
+                {
+                    self.command_collector.add_command(DawCommand::ClearClip {
+                        clip_id: clip.id().to_string(),
+                    });
+                    ui.close_menu();
+                }
+
+                ui.menu_button("Transpose", |ui| {
+                    for (label, semitones) in [
+                        ("Octave Up", 12),
+                        ("Semitone Up", 1),
+                        ("Semitone Down", -1),
+                        ("Octave Down", -12),
+                    ] {
+                        if ui.button(label).clicked() {
+                            self.command_collector.add_command(DawCommand::TransposeClip {
+                                clip_id: clip.id().to_string(),
+                                semitones,
+                            });
+                            ui.close_menu();
+                        }
+                    }
+                });
+
+                ui.menu_button("Processors", |ui| {
+                    self.clip_processors_menu(ui, clip.id(), processors);
+                });
+
+                ui.menu_button("Export MIDI", |ui| {
+                    ui.checkbox(&mut self.midi_export.split_channels, "One track per channel")
+                        .on_hover_text("Write a Format 1 file with each MIDI channel on its own track");
+                    ui.checkbox(&mut self.midi_export.include_controllers, "Include controllers")
+                        .on_hover_text("Keep CC and pitch bend data, including the clip's CC lanes");
+                    if ui.button("Save MIDI File...").clicked() {
+                        self.pending_midi_export = Some(clip.id().to_string());
+                        ui.close_menu();
+                    }
+                });
+            }
+
+            ui.menu_button("Color", |ui| {
+                let track_color = hex_to_color32(&track.color).unwrap_or(egui::Color32::GRAY);
+                if Self::color_option(ui, "Track Color", track_color, clip.color().is_none()) {
+                    self.command_collector.add_command(DawCommand::SetClipColor {
+                        clip_id: clip.id().to_string(),
+                        color: None,
+                    });
+                    ui.close_menu();
+                }
+                ui.separator();
+                for (name, hex) in Self::get_color_palette() {
+                    let color = hex_to_color32(hex).unwrap();
+                    if Self::color_option(ui, name, color, clip.color() == Some(hex)) {
+                        self.command_collector.add_command(DawCommand::SetClipColor {
+                            clip_id: clip.id().to_string(),
+                            color: Some(hex.to_string()),
+                        });
+                        ui.close_menu();
+                    }
+                }
+            });
+        });
+
         // Draw clip background
-        let clip_color = match clip {
-            Clip::Midi { .. } => egui::Color32::from_rgb(64, 128, 255),
-            Clip::Audio { .. } => egui::Color32::from_rgb(128, 255, 64),
-        };
+        let clip_color = Self::clip_color(track, clip, state);
 
         ui.painter().rect_filled(clip_rect, 2.0, clip_color);
 
+        if clip.is_muted() {
+            self.draw_mute_hatch(ui, clip_rect);
+        }
+
         // Draw clip border
         let is_selected = match clip {
             Clip::Midi { id, .. } | Clip::Audio { id, .. } => {
@@ -1178,8 +2456,27 @@ impl Timeline {
         } = clip
         {
             if let Some(midi_store) = midi_data {
-                self.draw_midi_preview(ui, clip_rect, midi_store, *clip_start, *clip_length);
+                self.draw_midi_preview(ui, clip_rect, clip.id(), midi_store, *clip_length);
+            }
+        }
+
+        // Draw waveform for audio clips, or a placeholder while it is still being read
+        match clip {
+            Clip::Audio {
+                peaks: Some(peaks),
+                start_offset,
+                ..
+            } => self.draw_waveform(ui, clip_rect, peaks, *start_offset),
+            Clip::Audio { peaks: None, .. } => {
+                ui.painter().with_clip_rect(clip_rect).text(
+                    clip_rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    "Loading waveform…",
+                    egui::FontId::proportional(11.0),
+                    ui.visuals().weak_text_color(),
+                );
             }
+            Clip::Midi { .. } => {}
         }
 
         // Handle clip dragging
@@ -1217,6 +2514,8 @@ impl Timeline {
         if left_response.drag_started() {
             self.resize_initial_values = Some((start_time, length));
             self.resize_snap_handler.reset();
+            self.resize_snap_handler
+                .set_threshold(state.settings.snap_threshold);
         }
 
         if left_response.dragged() {
@@ -1228,38 +2527,51 @@ impl Timeline {
                     self.resize_snap_handler.get_accumulated() / self.pixels_per_second;
 
                 // Apply snapping if enabled (disable with Shift key)
-                let snap = self.snap_enabled && !ui.input(|i| i.modifiers.shift);
-                let new_start = self.resize_snap_handler.snap_time_accumulated(
-                    initial_start as f64,
-                    accumulated_time_delta as f64,
+                let snap = self.snap_enabled && !snap_bypassed(ui.ctx());
+                let mut new_start = self.resize_snap_handler.resize_start(
+                    (initial_start + accumulated_time_delta) as f64,
+                    (initial_start + initial_length) as f64,
+                    MIN_CLIP_LENGTH as f64,
                     state.project.bpm,
+                    state.project.time_signature,
                     state.snap_mode,
                     snap,
                 ) as f32;
 
-                let new_length = (initial_length + (initial_start - new_start)).max(0.1);
-
-                // Move the clip
-                self.command_collector.add_command(DawCommand::MoveClip {
-                    clip_id: match clip {
-                        Clip::Midi { id, .. } | Clip::Audio { id, .. } => id.clone(),
-                    },
-                    track_id: state
-                        .project
-                        .tracks
-                        .iter()
-                        .find(|t| t.clips.contains(clip))
-                        .map(|t| t.id.clone())
-                        .unwrap_or_default(),
-                    new_start_time: new_start as f64,
-                });
+                let mut new_length = initial_length + (initial_start - new_start);
+                if let Some(min_length) = min_clip_length(clip, initial_length, state) {
+                    if new_length < min_length {
+                        // Pin the start so the clip still ends where it did
+                        new_start = initial_start + initial_length - min_length;
+                        new_length = min_length;
+                    }
+                }
+                if snap {
+                    // Keep the right edge in place while the length settles on a crossing
+                    let crossing_length = zero_crossing_length(clip, new_length, state);
+                    new_start += new_length - crossing_length;
+                    new_length = crossing_length;
+                }
 
-                // Resize the clip (shrink from left = move start time and change length)
-                self.command_collector.add_command(DawCommand::ResizeClip {
-                    clip_id: match clip {
-                        Clip::Midi { id, .. } | Clip::Audio { id, .. } => id.clone(),
-                    },
-                    new_length: new_length as f64,
+                // Move and resize together (shrink from left = move start time and change length)
+                self.command_collector.add_command(DawCommand::Batch {
+                    commands: vec![
+                        DawCommand::MoveClip {
+                            clip_id: clip.id().to_string(),
+                            track_id: state
+                                .project
+                                .tracks
+                                .iter()
+                                .find(|t| t.clips.contains(clip))
+                                .map(|t| t.id.clone())
+                                .unwrap_or_default(),
+                            new_start_time: new_start as f64,
+                        },
+                        DawCommand::ResizeClip {
+                            clip_id: clip.id().to_string(),
+                            new_length: new_length as f64,
+                        },
+                    ],
                 });
             }
         }
@@ -1275,6 +2587,8 @@ impl Timeline {
         if right_response.drag_started() {
             self.resize_initial_values = Some((start_time, length));
             self.resize_snap_handler.reset();
+            self.resize_snap_handler
+                .set_threshold(state.settings.snap_threshold);
         }
 
         if right_response.dragged() {
@@ -1284,18 +2598,27 @@ impl Timeline {
                     .add_delta(right_response.drag_delta().x);
                 let accumulated_time_delta =
                     self.resize_snap_handler.get_accumulated() / self.pixels_per_second;
-                let proposed_length = (initial_length + accumulated_time_delta).max(0.1);
 
                 // Apply snapping if enabled (disable with Shift key)
-                let snap = self.snap_enabled && !ui.input(|i| i.modifiers.shift);
-                let new_length = if snap && self.resize_snap_handler.should_snap() {
-                    let end_time = initial_start + proposed_length;
-                    let snapped_end =
-                        TimeUtils::snap_time(end_time as f64, state.project.bpm, state.snap_mode)
-                            as f32;
-                    (snapped_end - initial_start).max(0.1)
+                let snap = self.snap_enabled && !snap_bypassed(ui.ctx());
+                let new_end = self.resize_snap_handler.resize_end(
+                    initial_start as f64,
+                    (initial_start + initial_length + accumulated_time_delta) as f64,
+                    MIN_CLIP_LENGTH as f64,
+                    state.project.bpm,
+                    state.project.time_signature,
+                    state.snap_mode,
+                    snap,
+                ) as f32;
+                let new_length = new_end - initial_start;
+                let new_length = match min_clip_length(clip, initial_length, state) {
+                    Some(min_length) => new_length.max(min_length),
+                    None => new_length,
+                };
+                let new_length = if snap {
+                    zero_crossing_length(clip, new_length, state)
                 } else {
-                    proposed_length
+                    new_length
                 };
 
                 self.command_collector.add_command(DawCommand::ResizeClip {
@@ -1318,12 +2641,47 @@ impl Timeline {
         }
     }
 
-    fn draw_midi_preview(
+    fn draw_waveform(
         &self,
         ui: &mut egui::Ui,
         clip_rect: egui::Rect,
+        peaks: &AudioPeaks,
+        start_offset: f64,
+    ) {
+        let visible_rect = clip_rect.intersect(ui.clip_rect());
+        if peaks.buckets.is_empty() || visible_rect.width() <= 0.0 {
+            return;
+        }
+
+        let center_y = clip_rect.center().y;
+        let half_height = clip_rect.height() * 0.4;
+        let seconds_per_pixel = 1.0 / self.pixels_per_second as f64;
+        let color = egui::Color32::from_black_alpha(140);
+        let painter = ui.painter_at(visible_rect);
+
+        // One line per visible pixel column, merging cached buckets as needed
+        let mut x = visible_rect.left().floor();
+        while x < visible_rect.right() {
+            let time = start_offset + (x - clip_rect.left()) as f64 * seconds_per_pixel;
+            if let Some((min, max)) = peaks.range(time, time + seconds_per_pixel) {
+                painter.line_segment(
+                    [
+                        egui::pos2(x, center_y - max.clamp(-1.0, 1.0) * half_height),
+                        egui::pos2(x, center_y - min.clamp(-1.0, 1.0) * half_height + 1.0),
+                    ],
+                    egui::Stroke::new(1.0, color),
+                );
+            }
+            x += 1.0;
+        }
+    }
+
+    fn draw_midi_preview(
+        &mut self,
+        ui: &mut egui::Ui,
+        clip_rect: egui::Rect,
+        clip_id: &str,
         midi_store: &MidiEventStore,
-        clip_start_time: f64,
         clip_length: f64,
     ) {
         // Create a content area below the clip name with padding
@@ -1350,9 +2708,7 @@ impl Timeline {
             egui::Color32::from_rgba_unmultiplied(0, 0, 0, 30),
         );
 
-        // Get all notes
-        let notes: Vec<_> = midi_store.get_notes().collect();
-        if notes.is_empty() {
+        if midi_store.get_notes().next().is_none() {
             // Draw "Empty" text if no notes
             ui.painter().text(
                 preview_rect.center(),
@@ -1364,49 +2720,33 @@ impl Timeline {
             return;
         }
 
-        // Find the pitch range
-        let min_pitch = notes.iter().map(|n| n.key).min().unwrap_or(60);
-        let max_pitch = notes.iter().map(|n| n.key).max().unwrap_or(72);
-        let pitch_range = (max_pitch - min_pitch).max(12) as f32;
-
-        // Draw notes as small rectangles
-        // Use a lighter color that contrasts with the clip background
-        let note_color = egui::Color32::from_rgba_unmultiplied(255, 255, 255, 100);
-        let pixels_per_second = clip_rect.width() as f64 / clip_length;
-
-        for note in notes {
-            // Calculate note position within the clip
-            let note_x = preview_rect.left() + (note.start_time * pixels_per_second) as f32;
-            let note_width = (note.duration * pixels_per_second) as f32;
-
-            // Skip notes outside the visible clip area
-            if note_x + note_width < preview_rect.left() || note_x > preview_rect.right() {
-                continue;
-            }
-
-            // Calculate vertical position (inverted so higher pitches are at top)
-            let pitch_normalized = (note.key - min_pitch) as f32 / pitch_range;
-            let available_height = preview_rect.height();
-            let note_y = preview_rect.bottom() - (pitch_normalized * available_height);
-            let note_height = (available_height / pitch_range).max(1.0).min(3.0);
-
-            let note_rect = egui::Rect::from_min_size(
-                egui::pos2(note_x.max(preview_rect.left()), note_y - note_height / 2.0),
-                egui::vec2(
-                    note_width.min(preview_rect.right() - note_x).max(1.0),
-                    note_height,
-                ),
+        let is_current = self.midi_previews.get(clip_id).is_some_and(|preview| {
+            preview.revision == midi_store.revision()
+                && preview.clip_length == clip_length
+                && preview.height == preview_rect.height()
+                && (preview_rect.width() / preview.width - 1.0).abs()
+                    <= PREVIEW_RESCALE_TOLERANCE
+        });
+        if !is_current {
+            self.midi_previews.insert(
+                clip_id.to_string(),
+                build_midi_preview(midi_store, preview_rect.size(), clip_length),
             );
+        }
+        let Some(preview) = self.midi_previews.get(clip_id) else {
+            return;
+        };
 
-            // Only draw if the note rect is within the preview area
-            if note_rect.intersects(preview_rect) {
-                // Draw note with velocity-based opacity
-                let opacity = (note.velocity as f32 / 127.0 * 150.0 + 50.0) as u8;
-                let velocity_color = egui::Color32::from_rgba_unmultiplied(255, 255, 255, opacity);
-
-                ui.painter().rect_filled(note_rect, 0.5, velocity_color);
-            }
+        // Stretch the cached layout to the clip's current width
+        let scale_x = preview_rect.width() / preview.width;
+        let mut mesh = preview.mesh.clone();
+        for vertex in &mut mesh.vertices {
+            vertex.pos.x = preview_rect.left() + vertex.pos.x * scale_x;
+            vertex.pos.y += preview_rect.top();
         }
+        ui.painter()
+            .with_clip_rect(preview_rect.intersect(ui.clip_rect()))
+            .add(egui::Shape::mesh(mesh));
     }
 
     fn draw_playhead(&mut self, ui: &mut egui::Ui, rect: egui::Rect, state: &DawState) {
@@ -1420,7 +2760,7 @@ impl Timeline {
 
         let playhead_position = playhead_x - self.scroll_offset as f64;
 
-        if state.playing {
+        if state.playing && state.settings.follow_playhead {
             if playhead_position > visible_width * 0.8 {
                 self.scroll_offset = (playhead_x - visible_width_threshold) as f32;
             } else if playhead_position < visible_width_threshold {
@@ -1430,18 +2770,115 @@ impl Timeline {
 
         let playhead_x = rect.left() as f64 + playhead_x - self.scroll_offset as f64;
         
-        // Use a soft red color for the playhead
-        let playhead_color = egui::Color32::from_rgb(220, 80, 80);
-        
+        // Use a soft red color for the playhead, and a stronger red while recording
+        let (width, playhead_color) = if state.recording {
+            (3.0, egui::Color32::from_rgb(255, 40, 40))
+        } else {
+            (2.0, egui::Color32::from_rgb(220, 80, 80))
+        };
+
         ui.painter().line_segment(
             [
                 egui::pos2(playhead_x as f32, rect.top()),
                 egui::pos2(playhead_x as f32, rect.bottom()),
             ],
-            (2.0, playhead_color),
+            (width, playhead_color),
         );
         
         // Restore original clip rect
         ui.set_clip_rect(original_clip_rect);
     }
 }
+
+fn is_midi_file(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("mid") || e.eq_ignore_ascii_case("midi"))
+}
+
+/// Shortest length a resize may give `clip` without cutting off notes, unless the
+/// settings allow trimming content. A clip that already cuts notes off may keep doing so.
+fn min_clip_length(clip: &Clip, initial_length: f32, state: &DawState) -> Option<f32> {
+    if state.settings.trim_clip_content {
+        return None;
+    }
+    clip.content_end().map(|end| (end as f32).min(initial_length))
+}
+
+/// `length` adjusted so an audio clip ends on a nearby zero-crossing, when that is enabled
+/// and the clip's waveform has one close enough
+fn zero_crossing_length(clip: &Clip, length: f32, state: &DawState) -> f32 {
+    if !state.settings.snap_to_zero_crossing {
+        return length;
+    }
+    let Clip::Audio {
+        peaks: Some(peaks),
+        start_offset,
+        ..
+    } = clip
+    else {
+        return length;
+    };
+    peaks
+        .nearest_zero_crossing(start_offset + length as f64, ZERO_CROSSING_WINDOW)
+        .map(|crossing| (crossing - start_offset) as f32)
+        .filter(|crossing_length| *crossing_length > 0.0)
+        .unwrap_or(length)
+}
+
+/// Lay out a clip's notes as small rectangles in a `size` preview area, scaled so the
+/// clip's length spans the full width
+fn build_midi_preview(
+    midi_store: &MidiEventStore,
+    size: egui::Vec2,
+    clip_length: f64,
+) -> MidiPreview {
+    let notes: Vec<_> = midi_store.get_notes().collect();
+
+    // Find the pitch range
+    let min_pitch = notes.iter().map(|n| n.key).min().unwrap_or(60);
+    let max_pitch = notes.iter().map(|n| n.key).max().unwrap_or(72);
+    let pitch_range = (max_pitch - min_pitch).max(12) as f32;
+
+    let pixels_per_second = size.x as f64 / clip_length;
+    let note_height = (size.y / pitch_range).clamp(1.0, 3.0);
+    let mut mesh = egui::Mesh::default();
+
+    for note in notes {
+        let note_x = (note.start_time * pixels_per_second) as f32;
+        let note_width = (note.duration * pixels_per_second) as f32;
+
+        // Skip notes outside the clip
+        if note_x + note_width < 0.0 || note_x > size.x {
+            continue;
+        }
+
+        // Calculate vertical position (inverted so higher pitches are at top)
+        let pitch_normalized = (note.key - min_pitch) as f32 / pitch_range;
+        let note_y = size.y - pitch_normalized * size.y;
+
+        let note_rect = egui::Rect::from_min_size(
+            egui::pos2(note_x.max(0.0), note_y - note_height / 2.0),
+            egui::vec2(note_width.min(size.x - note_x).max(1.0), note_height),
+        );
+
+        // Velocity-based opacity, in a light color that contrasts with the clip background.
+        // Notes cut off by the clip end are faded, since they won't play in full
+        let mut opacity = (note.velocity as f32 / 127.0 * 150.0 + 50.0) as u8;
+        if note.start_time + note.duration > clip_length {
+            opacity /= 3;
+        }
+        mesh.add_colored_rect(
+            note_rect,
+            egui::Color32::from_rgba_unmultiplied(255, 255, 255, opacity),
+        );
+    }
+
+    MidiPreview {
+        revision: midi_store.revision(),
+        clip_length,
+        width: size.x,
+        height: size.y,
+        mesh,
+    }
+}